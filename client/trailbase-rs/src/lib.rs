@@ -9,13 +9,19 @@
 
 use eventsource_stream::Eventsource;
 pub use futures::Stream;
-use futures::StreamExt;
+use futures::channel::{mpsc, oneshot};
+use futures::{SinkExt, StreamExt};
 use parking_lot::RwLock;
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use reqwest::{Method, StatusCode};
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
 use thiserror::Error;
+use tokio_tungstenite::tungstenite::{self, Message, client::IntoClientRequest};
 use tracing::*;
 
 use serde::de::DeserializeOwned;
@@ -43,6 +49,19 @@ pub enum Error {
   // NOTE: This error is leaky but comprehensively unpacking reqwest is unsustainable.
   #[error("Reqwest: {0}")]
   OtherReqwest(reqwest::Error),
+
+  // NOTE: Leaky for the same reasons as above.
+  #[error("WebSocket: {0}")]
+  WebSocket(Box<tungstenite::Error>),
+
+  #[error("Subscription: {0}")]
+  Subscription(String),
+}
+
+impl From<tungstenite::Error> for Error {
+  fn from(err: tungstenite::Error) -> Self {
+    return Self::WebSocket(Box::new(err));
+  }
 }
 
 impl From<reqwest::Error> for Error {
@@ -104,7 +123,11 @@ pub enum DbEvent {
   Update(Option<serde_json::Value>),
   Insert(Option<serde_json::Value>),
   Delete(Option<serde_json::Value>),
+  /// Record matching at the time of subscribing, when requesting a snapshot.
+  Snapshot(Option<serde_json::Value>),
   Error(String),
+  /// Missed changes could not be replayed after resuming a subscription.
+  Reset(String),
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -257,6 +280,34 @@ pub struct ListArguments<'a> {
   filters: Option<ValueOrFilterGroup>,
   expand: Option<Vec<&'a str>>,
   count: bool,
+  nearest: Option<Nearest<'a>>,
+}
+
+/// Distance metric for vector similarity searches.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VectorMetric {
+  #[default]
+  Cosine,
+  L2,
+  L1,
+}
+
+impl VectorMetric {
+  fn format(&self) -> &'static str {
+    return match self {
+      Self::Cosine => "cosine",
+      Self::L2 => "l2",
+      Self::L1 => "l1",
+    };
+  }
+}
+
+/// Lists records by similarity of their vector `column` to a query vector.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nearest<'a> {
+  pub column: &'a str,
+  pub vector: Vec<f32>,
+  pub metric: VectorMetric,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -268,7 +319,25 @@ pub enum CompareOp {
   LessThanEqual,
   LessThan,
   Like,
+  /// Case-insensitive `Like`.
+  ILike,
   Regexp,
+  /// Full-text search, requires a FTS5 index on the table.
+  Match,
+  /// Column value is one of `Filter::values`.
+  In,
+  /// Column value is none of `Filter::values`.
+  NotIn,
+  /// Column value is within the inclusive range given by the two `Filter::values`.
+  Between,
+  /// Column is NULL for a value of "true" and NOT NULL for "false".
+  Null,
+  /// Point given by a `lat,lng` column pair is within the radius in meters around a center, i.e.
+  /// `Filter::values` are latitude, longitude and radius.
+  Near,
+  /// Point given by a `lat,lng` column pair is within the bounding box given by the
+  /// `Filter::values` `min_lat, min_lng, max_lat, max_lng`.
+  Within,
 }
 
 impl CompareOp {
@@ -281,7 +350,15 @@ impl CompareOp {
       Self::LessThanEqual => "$lte",
       Self::LessThan => "$lt",
       Self::Like => "$like",
+      Self::ILike => "$ilike",
       Self::Regexp => "$re",
+      Self::Match => "$match",
+      Self::In => "$in",
+      Self::NotIn => "$nin",
+      Self::Between => "$between",
+      Self::Null => "$null",
+      Self::Near => "$near",
+      Self::Within => "$within",
     };
   }
 }
@@ -291,6 +368,9 @@ pub struct Filter {
   pub column: String,
   pub op: Option<CompareOp>,
   pub value: String,
+  /// Values of list operators, i.e. `In`, `NotIn`, `Between`, `Near` and `Within`, which ignore
+  /// `value`.
+  pub values: Vec<String>,
 }
 
 impl Filter {
@@ -299,8 +379,76 @@ impl Filter {
       column: column.into(),
       op: Some(op),
       value: value.into(),
+      values: vec![],
     };
   }
+
+  /// Filter for a list operator, e.g. `CompareOp::In`.
+  pub fn new_list<V: Into<String>>(
+    column: impl Into<String>,
+    op: CompareOp,
+    values: impl IntoIterator<Item = V>,
+  ) -> Self {
+    return Self {
+      column: column.into(),
+      op: Some(op),
+      value: String::new(),
+      values: values.into_iter().map(|v| v.into()).collect(),
+    };
+  }
+
+  pub fn is_in<V: Into<String>>(
+    column: impl Into<String>,
+    values: impl IntoIterator<Item = V>,
+  ) -> Self {
+    return Self::new_list(column, CompareOp::In, values);
+  }
+
+  pub fn not_in<V: Into<String>>(
+    column: impl Into<String>,
+    values: impl IntoIterator<Item = V>,
+  ) -> Self {
+    return Self::new_list(column, CompareOp::NotIn, values);
+  }
+
+  pub fn between(
+    column: impl Into<String>,
+    lower: impl Into<String>,
+    upper: impl Into<String>,
+  ) -> Self {
+    return Self::new_list(column, CompareOp::Between, [lower.into(), upper.into()]);
+  }
+
+  pub fn is_null(column: impl Into<String>) -> Self {
+    return Self::new(column, CompareOp::Null, "true");
+  }
+
+  pub fn is_not_null(column: impl Into<String>) -> Self {
+    return Self::new(column, CompareOp::Null, "false");
+  }
+
+  /// Points within `radius` meters around the given center.
+  pub fn near(lat_column: &str, lng_column: &str, lat: f64, lng: f64, radius: f64) -> Self {
+    return Self::new_list(
+      format!("{lat_column},{lng_column}"),
+      CompareOp::Near,
+      [lat, lng, radius].map(|v| v.to_string()),
+    );
+  }
+
+  /// Points within the bounding box given by its south-west and north-east corners.
+  pub fn within(
+    lat_column: &str,
+    lng_column: &str,
+    (min_lat, min_lng): (f64, f64),
+    (max_lat, max_lng): (f64, f64),
+  ) -> Self {
+    return Self::new_list(
+      format!("{lat_column},{lng_column}"),
+      CompareOp::Within,
+      [min_lat, min_lng, max_lat, max_lng].map(|v| v.to_string()),
+    );
+  }
 }
 
 impl From<Filter> for ValueOrFilterGroup {
@@ -360,6 +508,11 @@ impl<'a> ListArguments<'a> {
     self.count = count;
     return self;
   }
+
+  pub fn with_nearest(mut self, nearest: Nearest<'a>) -> Self {
+    self.nearest = Some(nearest);
+    return self;
+  }
 }
 
 impl RecordApi {
@@ -367,8 +520,7 @@ impl RecordApi {
     &self,
     args: ListArguments<'_>,
   ) -> Result<ListResponse<T>, Error> {
-    type Param = (Cow<'static, str>, Cow<'static, str>);
-    let mut params: Vec<Param> = vec![];
+    let mut params: Vec<QueryParam> = vec![];
     if let Some(cursor) = args.pagination.cursor {
       params.push((Cow::Borrowed("cursor"), Cow::Owned(cursor)));
     }
@@ -398,36 +550,25 @@ impl RecordApi {
       params.push((Cow::Borrowed("count"), Cow::Borrowed("true")));
     }
 
-    fn traverse_filters(params: &mut Vec<Param>, path: String, filter: ValueOrFilterGroup) {
-      match filter {
-        ValueOrFilterGroup::Filter(filter) => {
-          if let Some(op) = filter.op {
-            params.push((
-              Cow::Owned(format!(
-                "{path}[{col}][{op}]",
-                col = filter.column,
-                op = op.format()
-              )),
-              Cow::Owned(filter.value),
-            ));
-          } else {
-            params.push((
-              Cow::Owned(format!("{path}[{col}]", col = filter.column)),
-              Cow::Owned(filter.value),
-            ));
-          }
-        }
-        ValueOrFilterGroup::And(vec) => {
-          for (i, f) in vec.into_iter().enumerate() {
-            traverse_filters(params, format!("{path}[$and][{i}]"), f);
-          }
-        }
-        ValueOrFilterGroup::Or(vec) => {
-          for (i, f) in vec.into_iter().enumerate() {
-            traverse_filters(params, format!("{path}[$or][{i}]"), f);
-          }
-        }
-      }
+    if let Some(nearest) = args.nearest {
+      let vector = nearest
+        .vector
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+      params.push((
+        Cow::Borrowed("nearest[column]"),
+        Cow::Owned(nearest.column.to_string()),
+      ));
+      params.push((
+        Cow::Borrowed("nearest[vector]"),
+        Cow::Owned(format!("[{vector}]")),
+      ));
+      params.push((
+        Cow::Borrowed("nearest[metric]"),
+        Cow::Borrowed(nearest.metric.format()),
+      ));
     }
 
     if let Some(filters) = args.filters {
@@ -543,6 +684,27 @@ impl RecordApi {
     &self,
     id: T,
   ) -> Result<impl Stream<Item = DbEvent> + use<T>, Error> {
+    return self.subscribe_impl(id, None).await;
+  }
+
+  /// Subscribes to changes of records in the entire table matching the given filters.
+  pub async fn subscribe_with_filters<F: Into<ValueOrFilterGroup>>(
+    &self,
+    filters: F,
+  ) -> Result<impl Stream<Item = DbEvent> + use<F>, Error> {
+    return self.subscribe_impl("*", Some(filters.into())).await;
+  }
+
+  async fn subscribe_impl<'a, T: RecordId<'a>>(
+    &self,
+    id: T,
+    filters: Option<ValueOrFilterGroup>,
+  ) -> Result<impl Stream<Item = DbEvent> + use<T>, Error> {
+    let mut params: Vec<QueryParam> = vec![];
+    if let Some(filters) = filters {
+      traverse_filters(&mut params, "filter".to_string(), filters);
+    }
+
     // TODO: Might have to add HeaderValue::from_static("text/event-stream").
     let response = self
       .client
@@ -554,7 +716,7 @@ impl RecordApi {
         ),
         Method::GET,
         None::<&()>,
-        None,
+        (!params.is_empty()).then_some(params.as_slice()),
       )
       .await?;
 
@@ -574,6 +736,302 @@ impl RecordApi {
   }
 }
 
+type QueryParam = (Cow<'static, str>, Cow<'static, str>);
+
+fn traverse_filters(params: &mut Vec<QueryParam>, path: String, filter: ValueOrFilterGroup) {
+  match filter {
+    ValueOrFilterGroup::Filter(filter) => {
+      if let Some(
+        op @ (CompareOp::In
+        | CompareOp::NotIn
+        | CompareOp::Between
+        | CompareOp::Near
+        | CompareOp::Within),
+      ) = filter.op
+      {
+        for (i, value) in filter.values.into_iter().enumerate() {
+          params.push((
+            Cow::Owned(format!(
+              "{path}[{col}][{op}][{i}]",
+              col = filter.column,
+              op = op.format()
+            )),
+            Cow::Owned(value),
+          ));
+        }
+      } else if let Some(op) = filter.op {
+        params.push((
+          Cow::Owned(format!(
+            "{path}[{col}][{op}]",
+            col = filter.column,
+            op = op.format()
+          )),
+          Cow::Owned(filter.value),
+        ));
+      } else {
+        params.push((
+          Cow::Owned(format!("{path}[{col}]", col = filter.column)),
+          Cow::Owned(filter.value),
+        ));
+      }
+    }
+    ValueOrFilterGroup::And(vec) => {
+      for (i, f) in vec.into_iter().enumerate() {
+        traverse_filters(params, format!("{path}[$and][{i}]"), f);
+      }
+    }
+    ValueOrFilterGroup::Or(vec) => {
+      for (i, f) in vec.into_iter().enumerate() {
+        traverse_filters(params, format!("{path}[$or][{i}]"), f);
+      }
+    }
+  }
+}
+
+/// Record subscriptions multiplexed over a single WebSocket connection.
+///
+/// Subscriptions end when their streams are dropped. The connection is closed once all handles
+/// and streams are dropped.
+#[derive(Clone)]
+pub struct Subscriptions {
+  state: Arc<SubscriptionsState>,
+}
+
+struct SubscriptionsState {
+  commands: mpsc::UnboundedSender<Command>,
+  next_id: AtomicU64,
+}
+
+impl Subscriptions {
+  /// Subscribes to changes of a specific record or, with `id` being "*", the entire table of
+  /// the given API.
+  pub async fn subscribe<'a>(
+    &self,
+    api: &str,
+    id: impl RecordId<'a>,
+  ) -> Result<SubscriptionStream, Error> {
+    return self
+      .subscribe_impl(api, id.serialized_id().into_owned(), None)
+      .await;
+  }
+
+  /// Subscribes to changes of records in the entire table of the given API matching the given
+  /// filters.
+  pub async fn subscribe_with_filters(
+    &self,
+    api: &str,
+    filters: impl Into<ValueOrFilterGroup>,
+  ) -> Result<SubscriptionStream, Error> {
+    let mut params: Vec<QueryParam> = vec![];
+    traverse_filters(&mut params, "filter".to_string(), filters.into());
+
+    let query = url::form_urlencoded::Serializer::new(String::new())
+      .extend_pairs(params)
+      .finish();
+
+    return self.subscribe_impl(api, "*".to_string(), Some(query)).await;
+  }
+
+  async fn subscribe_impl(
+    &self,
+    api: &str,
+    record: String,
+    query: Option<String>,
+  ) -> Result<SubscriptionStream, Error> {
+    let id = self.state.next_id.fetch_add(1, Ordering::SeqCst);
+    let (sender, events) = mpsc::unbounded::<DbEvent>();
+    let (ack, ack_receiver) = oneshot::channel::<Result<(), Error>>();
+
+    let closed = || Error::Subscription("Connection closed".to_string());
+
+    self
+      .state
+      .commands
+      .unbounded_send(Command::Subscribe {
+        id,
+        api: api.to_string(),
+        record,
+        query,
+        events: sender,
+        ack,
+      })
+      .map_err(|_err| closed())?;
+
+    // Only construct the stream after sending the command to unsubscribe on drop.
+    let stream = SubscriptionStream {
+      id,
+      commands: self.state.commands.clone(),
+      events,
+    };
+
+    ack_receiver.await.map_err(|_err| closed())??;
+
+    return Ok(stream);
+  }
+}
+
+/// Stream of change events of a multiplexed subscription. Dropping it unsubscribes.
+pub struct SubscriptionStream {
+  id: u64,
+  commands: mpsc::UnboundedSender<Command>,
+  events: mpsc::UnboundedReceiver<DbEvent>,
+}
+
+impl Stream for SubscriptionStream {
+  type Item = DbEvent;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    return self.events.poll_next_unpin(cx);
+  }
+}
+
+impl Drop for SubscriptionStream {
+  fn drop(&mut self) {
+    let _ = self
+      .commands
+      .unbounded_send(Command::Unsubscribe { id: self.id });
+  }
+}
+
+enum Command {
+  Subscribe {
+    id: u64,
+    api: String,
+    record: String,
+    query: Option<String>,
+    events: mpsc::UnboundedSender<DbEvent>,
+    ack: oneshot::Sender<Result<(), Error>>,
+  },
+  Unsubscribe {
+    id: u64,
+  },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SubscriptionRequest<'a> {
+  Subscribe {
+    id: u64,
+    api: &'a str,
+    record: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<&'a str>,
+  },
+  Unsubscribe {
+    id: u64,
+  },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SubscriptionResponse {
+  Subscribed { id: u64 },
+  Event { id: u64, event: DbEvent },
+  Unsubscribed { id: u64 },
+  Error { id: Option<u64>, message: String },
+}
+
+/// Drives a multiplexed subscription connection: forwards commands to the server and dispatches
+/// its responses to the respective subscriptions.
+async fn run_subscriptions(
+  socket: tokio_tungstenite::WebSocketStream<
+    tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+  >,
+  mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+  let (mut sink, mut stream) = socket.split();
+
+  let mut pending: HashMap<u64, oneshot::Sender<Result<(), Error>>> = HashMap::new();
+  let mut subscriptions: HashMap<u64, mpsc::UnboundedSender<DbEvent>> = HashMap::new();
+
+  loop {
+    tokio::select! {
+      command = commands.next() => {
+        // All handles and streams are gone.
+        let Some(command) = command else {
+          break;
+        };
+
+        let request = match command {
+          Command::Subscribe { id, api, record, query, events, ack } => {
+            let request = serde_json::to_string(&SubscriptionRequest::Subscribe {
+              id,
+              api: &api,
+              record: &record,
+              query: query.as_deref(),
+            });
+            pending.insert(id, ack);
+            subscriptions.insert(id, events);
+            request
+          }
+          Command::Unsubscribe { id } => {
+            pending.remove(&id);
+            if subscriptions.remove(&id).is_none() {
+              continue;
+            }
+            serde_json::to_string(&SubscriptionRequest::Unsubscribe { id })
+          }
+        };
+
+        match request {
+          Ok(request) => {
+            if let Err(err) = sink.send(Message::text(request)).await {
+              warn!("Failed to send subscription request: {err}");
+              break;
+            }
+          }
+          Err(err) => {
+            warn!("Failed to encode subscription request: {err}");
+          }
+        }
+      }
+      message = stream.next() => {
+        let text = match message {
+          Some(Ok(Message::Text(text))) => text,
+          Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+          Some(Ok(_)) => continue,
+        };
+
+        let response = match serde_json::from_str::<SubscriptionResponse>(text.as_str()) {
+          Ok(response) => response,
+          Err(err) => {
+            warn!("Failed to decode subscription response: {err}");
+            continue;
+          }
+        };
+
+        match response {
+          SubscriptionResponse::Subscribed { id } => {
+            if let Some(ack) = pending.remove(&id) {
+              let _ = ack.send(Ok(()));
+            }
+          }
+          SubscriptionResponse::Event { id, event } => {
+            if let Some(events) = subscriptions.get(&id) {
+              let _ = events.unbounded_send(event);
+            }
+          }
+          SubscriptionResponse::Unsubscribed { id } => {
+            // Ends the stream.
+            subscriptions.remove(&id);
+          }
+          SubscriptionResponse::Error { id: Some(id), message } => {
+            subscriptions.remove(&id);
+            if let Some(ack) = pending.remove(&id) {
+              let _ = ack.send(Err(Error::Subscription(message)));
+            }
+          }
+          SubscriptionResponse::Error { id: None, message } => {
+            warn!("Subscription error: {message}");
+          }
+        }
+      }
+    }
+  }
+
+  // Dropping pending acks and senders fails pending subscribes and ends all streams.
+}
+
 #[derive(Clone, Debug)]
 struct TokenState {
   state: Option<(Tokens, JwtTokenClaims)>,
@@ -611,13 +1069,7 @@ impl ClientState {
     body: Option<&T>,
     query_params: Option<&[(Cow<'static, str>, Cow<'static, str>)]>,
   ) -> Result<reqwest::Response, Error> {
-    let (mut headers, refresh_token) = self.extract_headers_and_refresh_token_if_exp();
-    if let Some(refresh_token) = refresh_token {
-      let new_tokens = ClientState::refresh_tokens(&self.client, headers, refresh_token).await?;
-
-      headers = new_tokens.headers.clone();
-      *self.tokens.write() = new_tokens;
-    }
+    let headers = self.headers().await?;
 
     return Ok(
       self
@@ -628,6 +1080,19 @@ impl ClientState {
     );
   }
 
+  /// Returns the headers for authenticating requests, refreshing expiring tokens first.
+  async fn headers(&self) -> Result<HeaderMap, Error> {
+    let (headers, refresh_token) = self.extract_headers_and_refresh_token_if_exp();
+    let Some(refresh_token) = refresh_token else {
+      return Ok(headers);
+    };
+
+    let new_tokens = ClientState::refresh_tokens(&self.client, headers, refresh_token).await?;
+    let headers = new_tokens.headers.clone();
+    *self.tokens.write() = new_tokens;
+    return Ok(headers);
+  }
+
   #[inline]
   fn extract_headers_and_refresh_token_if_exp(&self) -> (HeaderMap, Option<String>) {
     #[inline]
@@ -734,6 +1199,37 @@ impl Client {
     };
   }
 
+  /// Opens a WebSocket connection, which multiplexes many record subscriptions.
+  pub async fn subscriptions(&self) -> Result<Subscriptions, Error> {
+    let headers = self.state.headers().await?;
+
+    let url = self
+      .state
+      .client
+      .url
+      .join(&format!("/{RECORD_API}/_subscribe"))
+      .map_err(Error::InvalidUrl)?;
+    let url = match url.scheme() {
+      "https" => url.as_str().replacen("https", "wss", 1),
+      _ => url.as_str().replacen("http", "ws", 1),
+    };
+
+    let mut request = url.into_client_request()?;
+    request.headers_mut().extend(headers);
+
+    let (socket, _response) = tokio_tungstenite::connect_async(request).await?;
+
+    let (commands, receiver) = mpsc::unbounded::<Command>();
+    tokio::spawn(run_subscriptions(socket, receiver));
+
+    return Ok(Subscriptions {
+      state: Arc::new(SubscriptionsState {
+        commands,
+        next_id: AtomicU64::new(0),
+      }),
+    });
+  }
+
   pub async fn refresh(&self) -> Result<(), Error> {
     let Some((headers, refresh_token)) = self.state.extract_headers_refresh_token() else {
       return Err(Error::MissingRefreshToken);
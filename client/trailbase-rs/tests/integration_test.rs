@@ -200,6 +200,7 @@ async fn records_test() {
       column: "text_not_null".to_string(),
       op: Some(CompareOp::Like),
       value: format!("% =?&{now}"),
+      ..Default::default()
     };
     let records_ascending: ListResponse<SimpleStrict> = api
       .list(
@@ -336,6 +337,17 @@ async fn subscription_test() {
 
   let now = now();
   let create_message = format!("rust client realtime test 0: =?&{now}");
+  let updated_message = format!("rust client updated realtime test 0: =?&{now}");
+
+  let filtered_table_stream = api
+    .subscribe_with_filters(Filter::new(
+      "text_not_null",
+      CompareOp::Equal,
+      updated_message.clone(),
+    ))
+    .await
+    .unwrap();
+
   let id = api
     .create(json!({"text_not_null": create_message}))
     .await
@@ -343,7 +355,6 @@ async fn subscription_test() {
 
   let record_stream = api.subscribe(&id).await.unwrap();
 
-  let updated_message = format!("rust client updated realtime test 0: =?&{now}");
   api
     .update(&id, json!({"text_not_null": updated_message}))
     .await
@@ -388,6 +399,99 @@ async fn subscription_test() {
       msg => panic!("Unexpected event: {msg:?}"),
     };
   }
+
+  {
+    // The insert doesn't match the filter.
+    let filtered_table_events = filtered_table_stream.take(2).collect::<Vec<_>>().await;
+    match &filtered_table_events[0] {
+      DbEvent::Update(Some(serde_json::Value::Object(obj))) => {
+        assert_eq!(obj["text_not_null"], updated_message);
+      }
+      msg => panic!("Unexpected event: {msg:?}"),
+    };
+    match &filtered_table_events[1] {
+      DbEvent::Delete(Some(serde_json::Value::Object(obj))) => {
+        assert_eq!(obj["text_not_null"], updated_message);
+      }
+      msg => panic!("Unexpected event: {msg:?}"),
+    };
+  }
+}
+
+async fn multiplexed_subscription_test() {
+  let client = connect().await;
+  let api = client.records("simple_strict_table");
+
+  let subscriptions = client.subscriptions().await.unwrap();
+  let table_stream = subscriptions
+    .subscribe("simple_strict_table", "*")
+    .await
+    .unwrap();
+
+  let now = now();
+  let create_message = format!("rust client multiplexed realtime test 0: =?&{now}");
+  let updated_message = format!("rust client updated multiplexed realtime test 0: =?&{now}");
+
+  let filtered_table_stream = subscriptions
+    .subscribe_with_filters(
+      "simple_strict_table",
+      Filter::new("text_not_null", CompareOp::Equal, updated_message.clone()),
+    )
+    .await
+    .unwrap();
+
+  let id = api
+    .create(json!({"text_not_null": create_message}))
+    .await
+    .unwrap();
+
+  let record_stream = subscriptions
+    .subscribe("simple_strict_table", &id)
+    .await
+    .unwrap();
+
+  // Subscribing to missing APIs fails without affecting other subscriptions.
+  assert!(subscriptions.subscribe("missing_api", "*").await.is_err());
+
+  api
+    .update(&id, json!({"text_not_null": updated_message}))
+    .await
+    .unwrap();
+
+  api.delete(&id).await.unwrap();
+
+  {
+    // The record subscription ends with the deletion of the record.
+    let record_events = record_stream.collect::<Vec<_>>().await;
+    assert_eq!(2, record_events.len(), "{record_events:?}");
+    match &record_events[1] {
+      DbEvent::Delete(Some(serde_json::Value::Object(obj))) => {
+        assert_eq!(obj["text_not_null"], updated_message);
+      }
+      msg => panic!("Unexpected event: {msg:?}"),
+    };
+  }
+
+  {
+    let table_events = table_stream.take(3).collect::<Vec<_>>().await;
+    match &table_events[0] {
+      DbEvent::Insert(Some(serde_json::Value::Object(obj))) => {
+        assert_eq!(obj["text_not_null"], create_message);
+      }
+      msg => panic!("Unexpected event: {msg:?}"),
+    };
+  }
+
+  {
+    // The insert doesn't match the filter.
+    let filtered_table_events = filtered_table_stream.take(2).collect::<Vec<_>>().await;
+    match &filtered_table_events[0] {
+      DbEvent::Update(Some(serde_json::Value::Object(obj))) => {
+        assert_eq!(obj["text_not_null"], updated_message);
+      }
+      msg => panic!("Unexpected event: {msg:?}"),
+    };
+  }
 }
 
 #[test]
@@ -410,6 +514,9 @@ fn integration_test() {
 
   runtime.block_on(subscription_test());
   println!("Ran subscription tests");
+
+  runtime.block_on(multiplexed_subscription_test());
+  println!("Ran multiplexed subscription tests");
 }
 
 fn now() -> u64 {
@@ -1,4 +1,4 @@
-use trailbase_client::{Client, DbEvent, RecordId, Stream};
+use trailbase_client::{Client, CompareOp, DbEvent, Filter, RecordId, Stream};
 
 pub async fn subscribe(
   client: &Client,
@@ -10,3 +10,12 @@ pub async fn subscribe(
 pub async fn subscribe_all(client: &Client) -> anyhow::Result<impl Stream<Item = DbEvent>> {
   Ok(client.records("simple_strict_table").subscribe("*").await?)
 }
+
+pub async fn subscribe_filtered(client: &Client) -> anyhow::Result<impl Stream<Item = DbEvent>> {
+  Ok(
+    client
+      .records("simple_strict_table")
+      .subscribe_with_filters([Filter::new("text_not_null", CompareOp::Like, "%test%")])
+      .await?,
+  )
+}
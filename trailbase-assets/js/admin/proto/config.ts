@@ -86,6 +86,8 @@ export enum SystemJobId {
   AUTH_CLEANER = 4,
   QUERY_OPTIMIZER = 5,
   FILE_DELETIONS = 6,
+  SOFT_DELETE_PURGE = 7,
+  CHANGE_LOG_PRUNE = 8,
   UNRECOGNIZED = -1,
 }
 
@@ -112,6 +114,12 @@ export function systemJobIdFromJSON(object: any): SystemJobId {
     case 6:
     case "FILE_DELETIONS":
       return SystemJobId.FILE_DELETIONS;
+    case 7:
+    case "SOFT_DELETE_PURGE":
+      return SystemJobId.SOFT_DELETE_PURGE;
+    case 8:
+    case "CHANGE_LOG_PRUNE":
+      return SystemJobId.CHANGE_LOG_PRUNE;
     case -1:
     case "UNRECOGNIZED":
     default:
@@ -135,6 +143,10 @@ export function systemJobIdToJSON(object: SystemJobId): string {
       return "QUERY_OPTIMIZER";
     case SystemJobId.FILE_DELETIONS:
       return "FILE_DELETIONS";
+    case SystemJobId.SOFT_DELETE_PURGE:
+      return "SOFT_DELETE_PURGE";
+    case SystemJobId.CHANGE_LOG_PRUNE:
+      return "CHANGE_LOG_PRUNE";
     case SystemJobId.UNRECOGNIZED:
     default:
       return "UNRECOGNIZED";
@@ -411,6 +423,57 @@ export interface JobsConfig {
   systemJobs: SystemJob[];
 }
 
+/**
+ * / Access rules for an individual column, e.g. to limit access to sensitive
+ * / columns such as `salary` to the record's owner. Rules are evaluated with
+ * / the same `_USER_`, `_ROW_` and `_REQ_` tables as row-level access rules.
+ */
+export interface ColumnAccessRule {
+  /** / Name of the column. */
+  column?:
+    | string
+    | undefined;
+  /**
+   * / Rule deciding whether the column's value is visible on read, list, in
+   * / subscriptions and record history. Withheld values are returned as NULL.
+   * / Has access to `_USER_` and `_ROW_`.
+   */
+  readRule?:
+    | string
+    | undefined;
+  /**
+   * / Rule deciding whether the column may be set on create and update. Has
+   * / access to `_USER_` and `_REQ_` as well as `_ROW_` for updates.
+   */
+  writeRule?: string | undefined;
+}
+
+/**
+ * / Record API governing access to records of a related table, see
+ * / `RecordApiConfig.related_apis`.
+ */
+export interface RelatedRecordApi {
+  /**
+   * / Name of the related table. Foreign keys cannot cross databases, i.e. the
+   * / table is in the same database as the API's table.
+   */
+  tableName?:
+    | string
+    | undefined;
+  /** / Name of the Record API exposing the related table. */
+  apiName?: string | undefined;
+}
+
+/**
+ * / Permissions granted to members of a role, e.g. "editor" or "moderator".
+ * / Role memberships are managed by admins.
+ */
+export interface RoleAcl {
+  /** / Name of the role. */
+  role?: string | undefined;
+  acl: PermissionFlag[];
+}
+
 export interface RecordApiConfig {
   /** / API name, i.e. unique name used to access data via HTTP. */
   name?:
@@ -446,6 +509,8 @@ export interface RecordApiConfig {
   /** / Access control lists. */
   aclWorld: PermissionFlag[];
   aclAuthenticated: PermissionFlag[];
+  /** / Access control lists for authenticated members of the given roles. */
+  aclRoles: RoleAcl[];
   /**
    * / Columns excluded from this API.
    * /
@@ -482,10 +547,83 @@ export interface RecordApiConfig {
    * / the foreign record will be inlined into the response. By default nothing
    * / is expanded.
    * /
+   * / Entries may be nested paths, e.g. "author.org", and contain reverse
+   * / relations "<table>!<column>", e.g. "comment!post", inlining the records of
+   * / <table> referencing the parent via <column>.
+   * /
    * / Only columns and foreign tables with names not starting with "_", i.e. are
-   * / allowed to be expanded.
+   * / allowed to be expanded. Expanded tables must be exposed by a Record API,
+   * / whose access rules apply to the expanded records, see `related_apis`.
    */
   expand: string[];
+  /**
+   * / Record APIs governing access to records of related tables, i.e. expanded
+   * / records and filters on related columns. Only required for tables exposed
+   * / by more than one API, otherwise the only API exposing a table is used.
+   * / Relations to tables not exposed by any API cannot be expanded.
+   */
+  relatedApis: RelatedRecordApi[];
+  /**
+   * / Column driving record versions for optimistic concurrency control, e.g.
+   * / an `updated` timestamp or a counter incremented by a trigger. Records
+   * / carry an ETag derived from this column, or a hash of the entire record if
+   * / unset. Updates and deletes honor `If-Match` and reads `If-None-Match`.
+   */
+  versionColumn?: string | undefined;
+  /**
+   * / Nullable column marking records as soft-deleted, e.g. `deleted_at`. If
+   * / set, deletes will store the current UNIX timestamp instead of removing
+   * / records. Soft-deleted records are hidden from reads, lists and
+   * / subscriptions and can be restored until they're purged.
+   */
+  softDeleteColumn?:
+    | string
+    | undefined;
+  /**
+   * / Retention of soft-deleted records before they're purged for good.
+   * / Defaults to 30 days.
+   */
+  softDeleteRetentionSec?:
+    | number
+    | undefined;
+  /**
+   * / Record the history of inserts, updates and deletes including old and new
+   * / values, the acting user and a timestamp. Requires a table in the main
+   * / database.
+   */
+  enableHistory?:
+    | boolean
+    | undefined;
+  /** / Column-level access rules in addition to the above row-level rules. */
+  columnAccessRules: ColumnAccessRule[];
+}
+
+/**
+ * / Ephemeral, in-memory pub/sub channel, e.g. for cursors, typing indicators or
+ * / presence. Messages are relayed to current subscribers only and never
+ * / persisted.
+ */
+export interface BroadcastChannelConfig {
+  /** / Channel name, i.e. unique name used to access the channel via HTTP. */
+  name?:
+    | string
+    | undefined;
+  /**
+   * / Access rules evaluated when subscribing and publishing, respectively.
+   * / Expected to be valid SQL expression over `_USER_`, e.g.:
+   * /
+   * /   _USER_.id IS NOT NULL
+   * /
+   * / NOTE: Channels without a rule cannot be subscribed or published to by
+   * / clients, respectively. Use `TRUE` to grant access to anyone, including
+   * / unauthenticated users.
+   */
+  subscribeAccessRule?: string | undefined;
+  publishAccessRule?:
+    | string
+    | undefined;
+  /** / Track subscribers and notify them when others join or leave. */
+  enablePresence?: boolean | undefined;
 }
 
 export interface JsonSchemaConfig {
@@ -505,6 +643,7 @@ export interface Config {
   jobs: JobsConfig | undefined;
   recordApis: RecordApiConfig[];
   schemas: JsonSchemaConfig[];
+  broadcastChannels: BroadcastChannelConfig[];
 }
 
 function createBaseEmailTemplate(): EmailTemplate {
@@ -1625,8 +1764,272 @@ export const JobsConfig: MessageFns<JobsConfig> = {
   },
 };
 
+function createBaseColumnAccessRule(): ColumnAccessRule {
+  return {};
+}
+
+export const ColumnAccessRule: MessageFns<ColumnAccessRule> = {
+  encode(message: ColumnAccessRule, writer: BinaryWriter = new BinaryWriter()): BinaryWriter {
+    if (message.column !== undefined && message.column !== "") {
+      writer.uint32(10).string(message.column);
+    }
+    if (message.readRule !== undefined && message.readRule !== "") {
+      writer.uint32(18).string(message.readRule);
+    }
+    if (message.writeRule !== undefined && message.writeRule !== "") {
+      writer.uint32(26).string(message.writeRule);
+    }
+    return writer;
+  },
+
+  decode(input: BinaryReader | Uint8Array, length?: number): ColumnAccessRule {
+    const reader = input instanceof BinaryReader ? input : new BinaryReader(input);
+    let end = length === undefined ? reader.len : reader.pos + length;
+    const message = createBaseColumnAccessRule();
+    while (reader.pos < end) {
+      const tag = reader.uint32();
+      switch (tag >>> 3) {
+        case 1: {
+          if (tag !== 10) {
+            break;
+          }
+
+          message.column = reader.string();
+          continue;
+        }
+        case 2: {
+          if (tag !== 18) {
+            break;
+          }
+
+          message.readRule = reader.string();
+          continue;
+        }
+        case 3: {
+          if (tag !== 26) {
+            break;
+          }
+
+          message.writeRule = reader.string();
+          continue;
+        }
+      }
+      if ((tag & 7) === 4 || tag === 0) {
+        break;
+      }
+      reader.skip(tag & 7);
+    }
+    return message;
+  },
+
+  fromJSON(object: any): ColumnAccessRule {
+    return {
+      column: isSet(object.column) ? globalThis.String(object.column) : undefined,
+      readRule: isSet(object.readRule) ? globalThis.String(object.readRule) : undefined,
+      writeRule: isSet(object.writeRule) ? globalThis.String(object.writeRule) : undefined,
+    };
+  },
+
+  toJSON(message: ColumnAccessRule): unknown {
+    const obj: any = {};
+    if (message.column !== undefined && message.column !== "") {
+      obj.column = message.column;
+    }
+    if (message.readRule !== undefined && message.readRule !== "") {
+      obj.readRule = message.readRule;
+    }
+    if (message.writeRule !== undefined && message.writeRule !== "") {
+      obj.writeRule = message.writeRule;
+    }
+    return obj;
+  },
+
+  create<I extends Exact<DeepPartial<ColumnAccessRule>, I>>(base?: I): ColumnAccessRule {
+    return ColumnAccessRule.fromPartial(base ?? ({} as any));
+  },
+  fromPartial<I extends Exact<DeepPartial<ColumnAccessRule>, I>>(object: I): ColumnAccessRule {
+    const message = createBaseColumnAccessRule();
+    message.column = object.column ?? "";
+    message.readRule = object.readRule ?? "";
+    message.writeRule = object.writeRule ?? "";
+    return message;
+  },
+};
+
+function createBaseRelatedRecordApi(): RelatedRecordApi {
+  return {};
+}
+
+export const RelatedRecordApi: MessageFns<RelatedRecordApi> = {
+  encode(message: RelatedRecordApi, writer: BinaryWriter = new BinaryWriter()): BinaryWriter {
+    if (message.tableName !== undefined && message.tableName !== "") {
+      writer.uint32(10).string(message.tableName);
+    }
+    if (message.apiName !== undefined && message.apiName !== "") {
+      writer.uint32(18).string(message.apiName);
+    }
+    return writer;
+  },
+
+  decode(input: BinaryReader | Uint8Array, length?: number): RelatedRecordApi {
+    const reader = input instanceof BinaryReader ? input : new BinaryReader(input);
+    let end = length === undefined ? reader.len : reader.pos + length;
+    const message = createBaseRelatedRecordApi();
+    while (reader.pos < end) {
+      const tag = reader.uint32();
+      switch (tag >>> 3) {
+        case 1: {
+          if (tag !== 10) {
+            break;
+          }
+
+          message.tableName = reader.string();
+          continue;
+        }
+        case 2: {
+          if (tag !== 18) {
+            break;
+          }
+
+          message.apiName = reader.string();
+          continue;
+        }
+      }
+      if ((tag & 7) === 4 || tag === 0) {
+        break;
+      }
+      reader.skip(tag & 7);
+    }
+    return message;
+  },
+
+  fromJSON(object: any): RelatedRecordApi {
+    return {
+      tableName: isSet(object.tableName) ? globalThis.String(object.tableName) : undefined,
+      apiName: isSet(object.apiName) ? globalThis.String(object.apiName) : undefined,
+    };
+  },
+
+  toJSON(message: RelatedRecordApi): unknown {
+    const obj: any = {};
+    if (message.tableName !== undefined && message.tableName !== "") {
+      obj.tableName = message.tableName;
+    }
+    if (message.apiName !== undefined && message.apiName !== "") {
+      obj.apiName = message.apiName;
+    }
+    return obj;
+  },
+
+  create<I extends Exact<DeepPartial<RelatedRecordApi>, I>>(base?: I): RelatedRecordApi {
+    return RelatedRecordApi.fromPartial(base ?? ({} as any));
+  },
+  fromPartial<I extends Exact<DeepPartial<RelatedRecordApi>, I>>(object: I): RelatedRecordApi {
+    const message = createBaseRelatedRecordApi();
+    message.tableName = object.tableName ?? "";
+    message.apiName = object.apiName ?? "";
+    return message;
+  },
+};
+
+function createBaseRoleAcl(): RoleAcl {
+  return { acl: [] };
+}
+
+export const RoleAcl: MessageFns<RoleAcl> = {
+  encode(message: RoleAcl, writer: BinaryWriter = new BinaryWriter()): BinaryWriter {
+    if (message.role !== undefined && message.role !== "") {
+      writer.uint32(10).string(message.role);
+    }
+    writer.uint32(18).fork();
+    for (const v of message.acl) {
+      writer.int32(v);
+    }
+    writer.join();
+    return writer;
+  },
+
+  decode(input: BinaryReader | Uint8Array, length?: number): RoleAcl {
+    const reader = input instanceof BinaryReader ? input : new BinaryReader(input);
+    let end = length === undefined ? reader.len : reader.pos + length;
+    const message = createBaseRoleAcl();
+    while (reader.pos < end) {
+      const tag = reader.uint32();
+      switch (tag >>> 3) {
+        case 1: {
+          if (tag !== 10) {
+            break;
+          }
+
+          message.role = reader.string();
+          continue;
+        }
+        case 2: {
+          if (tag === 16) {
+            message.acl.push(reader.int32() as any);
+
+            continue;
+          }
+
+          if (tag === 18) {
+            const end2 = reader.uint32() + reader.pos;
+            while (reader.pos < end2) {
+              message.acl.push(reader.int32() as any);
+            }
+
+            continue;
+          }
+
+          break;
+        }
+      }
+      if ((tag & 7) === 4 || tag === 0) {
+        break;
+      }
+      reader.skip(tag & 7);
+    }
+    return message;
+  },
+
+  fromJSON(object: any): RoleAcl {
+    return {
+      role: isSet(object.role) ? globalThis.String(object.role) : undefined,
+      acl: globalThis.Array.isArray(object?.acl) ? object.acl.map((e: any) => permissionFlagFromJSON(e)) : [],
+    };
+  },
+
+  toJSON(message: RoleAcl): unknown {
+    const obj: any = {};
+    if (message.role !== undefined && message.role !== "") {
+      obj.role = message.role;
+    }
+    if (message.acl?.length) {
+      obj.acl = message.acl.map((e) => permissionFlagToJSON(e));
+    }
+    return obj;
+  },
+
+  create<I extends Exact<DeepPartial<RoleAcl>, I>>(base?: I): RoleAcl {
+    return RoleAcl.fromPartial(base ?? ({} as any));
+  },
+  fromPartial<I extends Exact<DeepPartial<RoleAcl>, I>>(object: I): RoleAcl {
+    const message = createBaseRoleAcl();
+    message.role = object.role ?? "";
+    message.acl = object.acl?.map((e) => e) || [];
+    return message;
+  },
+};
+
 function createBaseRecordApiConfig(): RecordApiConfig {
-  return { aclWorld: [], aclAuthenticated: [], excludedColumns: [], expand: [] };
+  return {
+    aclWorld: [],
+    aclAuthenticated: [],
+    aclRoles: [],
+    excludedColumns: [],
+    expand: [],
+    relatedApis: [],
+    columnAccessRules: [],
+  };
 }
 
 export const RecordApiConfig: MessageFns<RecordApiConfig> = {
@@ -1656,6 +2059,9 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
       writer.int32(v);
     }
     writer.join();
+    for (const v of message.aclRoles) {
+      RoleAcl.encode(v!, writer.uint32(218).fork()).join();
+    }
     for (const v of message.excludedColumns) {
       writer.uint32(82).string(v!);
     }
@@ -1677,6 +2083,24 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
     for (const v of message.expand) {
       writer.uint32(170).string(v!);
     }
+    for (const v of message.relatedApis) {
+      RelatedRecordApi.encode(v!, writer.uint32(226).fork()).join();
+    }
+    if (message.versionColumn !== undefined && message.versionColumn !== "") {
+      writer.uint32(178).string(message.versionColumn);
+    }
+    if (message.softDeleteColumn !== undefined && message.softDeleteColumn !== "") {
+      writer.uint32(186).string(message.softDeleteColumn);
+    }
+    if (message.softDeleteRetentionSec !== undefined && message.softDeleteRetentionSec !== 0) {
+      writer.uint32(192).int64(message.softDeleteRetentionSec);
+    }
+    if (message.enableHistory !== undefined && message.enableHistory !== false) {
+      writer.uint32(200).bool(message.enableHistory);
+    }
+    for (const v of message.columnAccessRules) {
+      ColumnAccessRule.encode(v!, writer.uint32(210).fork()).join();
+    }
     return writer;
   },
 
@@ -1763,6 +2187,14 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
 
           break;
         }
+        case 27: {
+          if (tag !== 218) {
+            break;
+          }
+
+          message.aclRoles.push(RoleAcl.decode(reader, reader.uint32()));
+          continue;
+        }
         case 10: {
           if (tag !== 82) {
             break;
@@ -1819,6 +2251,54 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
           message.expand.push(reader.string());
           continue;
         }
+        case 28: {
+          if (tag !== 226) {
+            break;
+          }
+
+          message.relatedApis.push(RelatedRecordApi.decode(reader, reader.uint32()));
+          continue;
+        }
+        case 22: {
+          if (tag !== 178) {
+            break;
+          }
+
+          message.versionColumn = reader.string();
+          continue;
+        }
+        case 23: {
+          if (tag !== 186) {
+            break;
+          }
+
+          message.softDeleteColumn = reader.string();
+          continue;
+        }
+        case 24: {
+          if (tag !== 192) {
+            break;
+          }
+
+          message.softDeleteRetentionSec = longToNumber(reader.int64());
+          continue;
+        }
+        case 25: {
+          if (tag !== 200) {
+            break;
+          }
+
+          message.enableHistory = reader.bool();
+          continue;
+        }
+        case 26: {
+          if (tag !== 210) {
+            break;
+          }
+
+          message.columnAccessRules.push(ColumnAccessRule.decode(reader, reader.uint32()));
+          continue;
+        }
       }
       if ((tag & 7) === 4 || tag === 0) {
         break;
@@ -1847,6 +2327,7 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
       aclAuthenticated: globalThis.Array.isArray(object?.aclAuthenticated)
         ? object.aclAuthenticated.map((e: any) => permissionFlagFromJSON(e))
         : [],
+      aclRoles: globalThis.Array.isArray(object?.aclRoles) ? object.aclRoles.map((e: any) => RoleAcl.fromJSON(e)) : [],
       excludedColumns: globalThis.Array.isArray(object?.excludedColumns)
         ? object.excludedColumns.map((e: any) => globalThis.String(e))
         : [],
@@ -1856,6 +2337,18 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
       deleteAccessRule: isSet(object.deleteAccessRule) ? globalThis.String(object.deleteAccessRule) : undefined,
       schemaAccessRule: isSet(object.schemaAccessRule) ? globalThis.String(object.schemaAccessRule) : undefined,
       expand: globalThis.Array.isArray(object?.expand) ? object.expand.map((e: any) => globalThis.String(e)) : [],
+      relatedApis: globalThis.Array.isArray(object?.relatedApis)
+        ? object.relatedApis.map((e: any) => RelatedRecordApi.fromJSON(e))
+        : [],
+      versionColumn: isSet(object.versionColumn) ? globalThis.String(object.versionColumn) : undefined,
+      softDeleteColumn: isSet(object.softDeleteColumn) ? globalThis.String(object.softDeleteColumn) : undefined,
+      softDeleteRetentionSec: isSet(object.softDeleteRetentionSec)
+        ? globalThis.Number(object.softDeleteRetentionSec)
+        : undefined,
+      enableHistory: isSet(object.enableHistory) ? globalThis.Boolean(object.enableHistory) : undefined,
+      columnAccessRules: globalThis.Array.isArray(object?.columnAccessRules)
+        ? object.columnAccessRules.map((e: any) => ColumnAccessRule.fromJSON(e))
+        : [],
     };
   },
 
@@ -1882,6 +2375,9 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
     if (message.aclAuthenticated?.length) {
       obj.aclAuthenticated = message.aclAuthenticated.map((e) => permissionFlagToJSON(e));
     }
+    if (message.aclRoles?.length) {
+      obj.aclRoles = message.aclRoles.map((e) => RoleAcl.toJSON(e));
+    }
     if (message.excludedColumns?.length) {
       obj.excludedColumns = message.excludedColumns;
     }
@@ -1903,6 +2399,24 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
     if (message.expand?.length) {
       obj.expand = message.expand;
     }
+    if (message.relatedApis?.length) {
+      obj.relatedApis = message.relatedApis.map((e) => RelatedRecordApi.toJSON(e));
+    }
+    if (message.versionColumn !== undefined && message.versionColumn !== "") {
+      obj.versionColumn = message.versionColumn;
+    }
+    if (message.softDeleteColumn !== undefined && message.softDeleteColumn !== "") {
+      obj.softDeleteColumn = message.softDeleteColumn;
+    }
+    if (message.softDeleteRetentionSec !== undefined && message.softDeleteRetentionSec !== 0) {
+      obj.softDeleteRetentionSec = Math.round(message.softDeleteRetentionSec);
+    }
+    if (message.enableHistory !== undefined && message.enableHistory !== false) {
+      obj.enableHistory = message.enableHistory;
+    }
+    if (message.columnAccessRules?.length) {
+      obj.columnAccessRules = message.columnAccessRules.map((e) => ColumnAccessRule.toJSON(e));
+    }
     return obj;
   },
 
@@ -1918,6 +2432,7 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
     message.enableSubscriptions = object.enableSubscriptions ?? false;
     message.aclWorld = object.aclWorld?.map((e) => e) || [];
     message.aclAuthenticated = object.aclAuthenticated?.map((e) => e) || [];
+    message.aclRoles = object.aclRoles?.map((e) => RoleAcl.fromPartial(e)) || [];
     message.excludedColumns = object.excludedColumns?.map((e) => e) || [];
     message.createAccessRule = object.createAccessRule ?? "";
     message.readAccessRule = object.readAccessRule ?? "";
@@ -1925,6 +2440,122 @@ export const RecordApiConfig: MessageFns<RecordApiConfig> = {
     message.deleteAccessRule = object.deleteAccessRule ?? "";
     message.schemaAccessRule = object.schemaAccessRule ?? "";
     message.expand = object.expand?.map((e) => e) || [];
+    message.relatedApis = object.relatedApis?.map((e) => RelatedRecordApi.fromPartial(e)) || [];
+    message.versionColumn = object.versionColumn ?? "";
+    message.softDeleteColumn = object.softDeleteColumn ?? "";
+    message.softDeleteRetentionSec = object.softDeleteRetentionSec ?? 0;
+    message.enableHistory = object.enableHistory ?? false;
+    message.columnAccessRules = object.columnAccessRules?.map((e) => ColumnAccessRule.fromPartial(e)) || [];
+    return message;
+  },
+};
+
+function createBaseBroadcastChannelConfig(): BroadcastChannelConfig {
+  return {};
+}
+
+export const BroadcastChannelConfig: MessageFns<BroadcastChannelConfig> = {
+  encode(message: BroadcastChannelConfig, writer: BinaryWriter = new BinaryWriter()): BinaryWriter {
+    if (message.name !== undefined && message.name !== "") {
+      writer.uint32(10).string(message.name);
+    }
+    if (message.subscribeAccessRule !== undefined && message.subscribeAccessRule !== "") {
+      writer.uint32(18).string(message.subscribeAccessRule);
+    }
+    if (message.publishAccessRule !== undefined && message.publishAccessRule !== "") {
+      writer.uint32(26).string(message.publishAccessRule);
+    }
+    if (message.enablePresence !== undefined && message.enablePresence !== false) {
+      writer.uint32(32).bool(message.enablePresence);
+    }
+    return writer;
+  },
+
+  decode(input: BinaryReader | Uint8Array, length?: number): BroadcastChannelConfig {
+    const reader = input instanceof BinaryReader ? input : new BinaryReader(input);
+    let end = length === undefined ? reader.len : reader.pos + length;
+    const message = createBaseBroadcastChannelConfig();
+    while (reader.pos < end) {
+      const tag = reader.uint32();
+      switch (tag >>> 3) {
+        case 1: {
+          if (tag !== 10) {
+            break;
+          }
+
+          message.name = reader.string();
+          continue;
+        }
+        case 2: {
+          if (tag !== 18) {
+            break;
+          }
+
+          message.subscribeAccessRule = reader.string();
+          continue;
+        }
+        case 3: {
+          if (tag !== 26) {
+            break;
+          }
+
+          message.publishAccessRule = reader.string();
+          continue;
+        }
+        case 4: {
+          if (tag !== 32) {
+            break;
+          }
+
+          message.enablePresence = reader.bool();
+          continue;
+        }
+      }
+      if ((tag & 7) === 4 || tag === 0) {
+        break;
+      }
+      reader.skip(tag & 7);
+    }
+    return message;
+  },
+
+  fromJSON(object: any): BroadcastChannelConfig {
+    return {
+      name: isSet(object.name) ? globalThis.String(object.name) : undefined,
+      subscribeAccessRule: isSet(object.subscribeAccessRule)
+        ? globalThis.String(object.subscribeAccessRule)
+        : undefined,
+      publishAccessRule: isSet(object.publishAccessRule) ? globalThis.String(object.publishAccessRule) : undefined,
+      enablePresence: isSet(object.enablePresence) ? globalThis.Boolean(object.enablePresence) : undefined,
+    };
+  },
+
+  toJSON(message: BroadcastChannelConfig): unknown {
+    const obj: any = {};
+    if (message.name !== undefined && message.name !== "") {
+      obj.name = message.name;
+    }
+    if (message.subscribeAccessRule !== undefined && message.subscribeAccessRule !== "") {
+      obj.subscribeAccessRule = message.subscribeAccessRule;
+    }
+    if (message.publishAccessRule !== undefined && message.publishAccessRule !== "") {
+      obj.publishAccessRule = message.publishAccessRule;
+    }
+    if (message.enablePresence !== undefined && message.enablePresence !== false) {
+      obj.enablePresence = message.enablePresence;
+    }
+    return obj;
+  },
+
+  create<I extends Exact<DeepPartial<BroadcastChannelConfig>, I>>(base?: I): BroadcastChannelConfig {
+    return BroadcastChannelConfig.fromPartial(base ?? ({} as any));
+  },
+  fromPartial<I extends Exact<DeepPartial<BroadcastChannelConfig>, I>>(object: I): BroadcastChannelConfig {
+    const message = createBaseBroadcastChannelConfig();
+    message.name = object.name ?? "";
+    message.subscribeAccessRule = object.subscribeAccessRule ?? "";
+    message.publishAccessRule = object.publishAccessRule ?? "";
+    message.enablePresence = object.enablePresence ?? false;
     return message;
   },
 };
@@ -2006,7 +2637,15 @@ export const JsonSchemaConfig: MessageFns<JsonSchemaConfig> = {
 };
 
 function createBaseConfig(): Config {
-  return { email: undefined, server: undefined, auth: undefined, jobs: undefined, recordApis: [], schemas: [] };
+  return {
+    email: undefined,
+    server: undefined,
+    auth: undefined,
+    jobs: undefined,
+    recordApis: [],
+    schemas: [],
+    broadcastChannels: [],
+  };
 }
 
 export const Config: MessageFns<Config> = {
@@ -2029,6 +2668,9 @@ export const Config: MessageFns<Config> = {
     for (const v of message.schemas) {
       JsonSchemaConfig.encode(v!, writer.uint32(170).fork()).join();
     }
+    for (const v of message.broadcastChannels) {
+      BroadcastChannelConfig.encode(v!, writer.uint32(178).fork()).join();
+    }
     return writer;
   },
 
@@ -2087,6 +2729,14 @@ export const Config: MessageFns<Config> = {
           message.schemas.push(JsonSchemaConfig.decode(reader, reader.uint32()));
           continue;
         }
+        case 22: {
+          if (tag !== 178) {
+            break;
+          }
+
+          message.broadcastChannels.push(BroadcastChannelConfig.decode(reader, reader.uint32()));
+          continue;
+        }
       }
       if ((tag & 7) === 4 || tag === 0) {
         break;
@@ -2108,6 +2758,9 @@ export const Config: MessageFns<Config> = {
       schemas: globalThis.Array.isArray(object?.schemas)
         ? object.schemas.map((e: any) => JsonSchemaConfig.fromJSON(e))
         : [],
+      broadcastChannels: globalThis.Array.isArray(object?.broadcastChannels)
+        ? object.broadcastChannels.map((e: any) => BroadcastChannelConfig.fromJSON(e))
+        : [],
     };
   },
 
@@ -2131,6 +2784,9 @@ export const Config: MessageFns<Config> = {
     if (message.schemas?.length) {
       obj.schemas = message.schemas.map((e) => JsonSchemaConfig.toJSON(e));
     }
+    if (message.broadcastChannels?.length) {
+      obj.broadcastChannels = message.broadcastChannels.map((e) => BroadcastChannelConfig.toJSON(e));
+    }
     return obj;
   },
 
@@ -2153,6 +2809,7 @@ export const Config: MessageFns<Config> = {
       : undefined;
     message.recordApis = object.recordApis?.map((e) => RecordApiConfig.fromPartial(e)) || [];
     message.schemas = object.schemas?.map((e) => JsonSchemaConfig.fromPartial(e)) || [];
+    message.broadcastChannels = object.broadcastChannels?.map((e) => BroadcastChannelConfig.fromPartial(e)) || [];
     return message;
   },
 };
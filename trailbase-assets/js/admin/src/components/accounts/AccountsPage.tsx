@@ -164,6 +164,7 @@ function EditSheetContent(props: {
       email: props.user.email,
       password: null,
       verified: props.user.verified,
+      roles: null,
     } as UpdateUserRequest,
     onSubmit: async ({ value }) => {
       updateUser(value)
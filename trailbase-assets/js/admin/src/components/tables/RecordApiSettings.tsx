@@ -413,8 +413,11 @@ export function RecordApiSettingsForm(props: {
           tableName: tableName,
           aclWorld: [],
           aclAuthenticated: [],
+          aclRoles: [],
           excludedColumns: [],
           expand: [],
+          relatedApis: [],
+          columnAccessRules: [],
         } as RecordApiConfig),
       onSubmit: async ({ value }: { value: RecordApiConfig }) => {
         console.debug("Add record api config:", value);
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RevertRowRequest = { 
+/**
+ * Id of the revision recorded by a Record API with history enabled, which the row shall be
+ * reverted to.
+ */
+revision_id: bigint, };
@@ -1,3 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type UpdateUserRequest = { id: string, email: string | null, password: string | null, verified: boolean | null, };
+export type UpdateUserRequest = { id: string, email: string | null, password: string | null, verified: boolean | null, 
+/**
+ * Replaces the user's role memberships, if present. Changes take effect once the user's auth
+ * token is refreshed.
+ */
+roles: Array<string> | null, };
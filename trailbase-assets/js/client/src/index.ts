@@ -36,6 +36,7 @@ type TokenClaims = {
   iat: number;
   exp: number;
   email: string;
+  roles?: string[];
   csrf_token: string;
 };
 
@@ -51,7 +52,9 @@ export type Event =
   | { Insert: object }
   | { Update: object }
   | { Delete: object }
-  | { Error: string };
+  | { Snapshot: object }
+  | { Error: string }
+  | { Reset: string };
 
 function buildTokenState(tokens?: Tokens): TokenState {
   return {
@@ -147,7 +150,8 @@ type CompareOp =
   | "greaterThan"
   | "greaterThanEqual"
   | "like"
-  | "regexp";
+  | "regexp"
+  | "match";
 
 function formatCompareOp(op: CompareOp): string {
   switch (op) {
@@ -167,6 +171,8 @@ function formatCompareOp(op: CompareOp): string {
       return "$like";
     case "regexp":
       return "$re";
+    case "match":
+      return "$match";
   }
 }
 
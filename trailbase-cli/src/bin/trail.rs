@@ -227,10 +227,12 @@ async fn async_main() -> Result<(), BoxError> {
             warn!("User '{email}' not verified");
           }
 
+          let roles = api::user_roles(&conn, &user.uuid()).await?;
           let claims = TokenClaims::new(
             user.verified,
             user.uuid(),
             user.email,
+            roles,
             chrono::Duration::hours(12),
           );
           let token = jwt.encode(&claims)?;
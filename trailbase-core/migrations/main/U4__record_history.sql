@@ -0,0 +1,30 @@
+-- Record history, i.e. an audit trail of inserts, updates and deletes.
+--
+-- Triggers are being used to populate the history of tables exposed by Record
+-- APIs with history enabled.
+CREATE TABLE _record_history (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  created                      INTEGER NOT NULL DEFAULT (UNIXEPOCH()),
+
+  -- Which record changed.
+  table_name                   TEXT NOT NULL,
+  record_id                    ANY NOT NULL,
+
+  -- One of: 'insert', 'update', 'delete'.
+  action                       TEXT NOT NULL,
+  -- JSON-encoded record before and after the change, respectively.
+  old                          TEXT,
+  new                          TEXT,
+
+  -- Acting user, if any.
+  user                         BLOB
+) STRICT;
+
+CREATE INDEX __record_history__record_index ON _record_history (table_name, record_id);
+
+-- Acting user attributed to history entries recorded within the current
+-- transaction. Only ever populated for the duration of a transaction.
+CREATE TABLE _record_history_actor (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  user                         BLOB
+) STRICT;
@@ -0,0 +1,10 @@
+-- Role memberships, e.g. "editor" or "moderator".
+--
+-- Roles are embedded in auth tokens and can be granted permissions on Record
+-- APIs via `acl_roles` or be checked in access rules via `_USER_.roles`.
+CREATE TABLE _user_role (
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  role                         TEXT NOT NULL CHECK(length(role) > 0),
+
+  PRIMARY KEY (user, role)
+) STRICT;
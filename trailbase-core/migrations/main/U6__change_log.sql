@@ -0,0 +1,20 @@
+-- Bounded log of changes to tables exposed by Record APIs with subscriptions
+-- enabled, allowing subscribers to catch up on changes they missed while
+-- disconnected.
+--
+-- Temporary triggers on the writer connection are being used to populate the
+-- log. Only changed records are identified, their values are read when
+-- brokering. The log is pruned periodically to retain the most recent entries.
+CREATE TABLE _change_log (
+  -- Monotonically increasing sequence number, never reused. Doubles as the
+  -- id of the emitted events.
+  seq                          INTEGER PRIMARY KEY AUTOINCREMENT,
+
+  -- Which record changed.
+  database_schema              TEXT NOT NULL,
+  table_name                   TEXT NOT NULL,
+  row_id                       INTEGER NOT NULL,
+
+  -- One of: 'insert', 'update', 'delete'.
+  action                       TEXT NOT NULL
+) STRICT;
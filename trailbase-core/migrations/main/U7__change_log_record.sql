@@ -0,0 +1,4 @@
+-- JSON-encoded snapshot of the changed record, i.e. its values after inserts
+-- and updates or before deletions. Allows replaying changes, including
+-- deletions, with the values as of the change.
+ALTER TABLE _change_log ADD COLUMN record TEXT;
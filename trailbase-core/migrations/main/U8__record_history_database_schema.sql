@@ -0,0 +1,5 @@
+-- Distinguish the history of equally named tables in different databases.
+ALTER TABLE _record_history ADD COLUMN database_schema TEXT NOT NULL DEFAULT 'main';
+
+DROP INDEX __record_history__record_index;
+CREATE INDEX __record_history__record_index ON _record_history (database_schema, table_name, record_id);
@@ -33,6 +33,7 @@ pub fn router() -> Router<AppState> {
     .route("/table/{table_name}", patch(rows::update_row_handler))
     .route("/table/{table_name}", post(rows::insert_row_handler))
     .route("/table/{table_name}", delete(rows::delete_row_handler))
+    .route("/table/{table_name}/revert", post(rows::revert_row_handler))
     // Index actions.
     .route("/index", post(table::create_index_handler))
     .route("/index", patch(table::alter_index_handler))
@@ -64,7 +64,10 @@ pub(crate) async fn delete_row(
     &QualifiedNameEscaped::from(&schema_metadata.schema.name),
     pk_col,
     simple_json_value_to_param(column.data_type, value)?,
+    None,
     schema_metadata.json_metadata.has_file_columns(),
+    None,
+    None,
   )
   .await?;
 
@@ -166,6 +169,7 @@ mod tests {
           checks: vec![],
           virtual_table: false,
           temporary: false,
+          virtual_table_module: None,
         },
         dry_run: Some(false),
       }),
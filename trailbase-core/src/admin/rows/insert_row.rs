@@ -43,6 +43,7 @@ pub(crate) async fn insert_row(
     "_rowid_",
     schema_metadata.json_metadata.has_file_columns(),
     Params::from(&*schema_metadata, json_row, None)?,
+    None,
   )
   .await?;
 
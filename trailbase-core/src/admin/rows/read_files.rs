@@ -71,6 +71,8 @@ pub async fn read_files_handler(
       file_col_json_metadata,
       &request.pk_column,
       pk_value,
+      None,
+      None,
     )
     .await?;
 
@@ -87,6 +89,8 @@ pub async fn read_files_handler(
       file_col_json_metadata,
       &request.pk_column,
       pk_value,
+      None,
+      None,
     )
     .await?;
 
@@ -0,0 +1,46 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use serde::{Deserialize, Serialize};
+use trailbase_schema::QualifiedName;
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::records::RecordError;
+use crate::records::history::revert_record;
+
+#[derive(Debug, Serialize, Deserialize, Default, TS)]
+#[ts(export)]
+pub struct RevertRowRequest {
+  /// Id of the revision recorded by a Record API with history enabled, which the row shall be
+  /// reverted to.
+  pub revision_id: i64,
+}
+
+pub async fn revert_row_handler(
+  State(state): State<AppState>,
+  Path(table_name): Path<String>,
+  user: User,
+  Json(request): Json<RevertRowRequest>,
+) -> Result<(), Error> {
+  if state.demo_mode() && table_name.starts_with("_") {
+    return Err(Error::Precondition("Disallowed in demo".into()));
+  }
+
+  let table_name = QualifiedName::parse(&table_name)?;
+  revert_record(&state, &table_name, request.revision_id, Some(&user))
+    .await
+    .map_err(|err| match err {
+      RecordError::RecordNotFound => Error::Precondition(format!(
+        "Revision {} not found for table {table_name:?}",
+        request.revision_id
+      )),
+      RecordError::ApiRequiresTable => {
+        Error::Precondition(format!("Table {table_name:?} not found"))
+      }
+      err => Error::Internal(err.into()),
+    })?;
+
+  return Ok(());
+}
@@ -69,8 +69,11 @@ pub async fn update_row_handler(
     &state,
     &QualifiedNameEscaped::new(&schema_metadata.schema.name),
     &column.name,
+    None,
     schema_metadata.json_metadata.has_file_columns(),
     Params::from(&*schema_metadata, row, None)?,
+    None,
+    None,
   )
   .await?;
 
@@ -71,5 +71,7 @@ pub async fn alter_index_handler(
     debug!("Migration report: {report:?}");
   }
 
+  state.schema_metadata().invalidate_all().await?;
+
   return Ok((StatusCode::OK, "altered index").into_response());
 }
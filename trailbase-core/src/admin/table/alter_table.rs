@@ -186,6 +186,7 @@ mod tests {
         checks: vec![],
         virtual_table: false,
         temporary: false,
+        virtual_table_module: None,
       },
       dry_run: Some(false),
     };
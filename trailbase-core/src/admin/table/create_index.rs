@@ -52,6 +52,8 @@ pub async fn create_index_handler(
         .apply_as_migration(conn, migration_path, &filename)
         .await?;
     }
+
+    state.schema_metadata().invalidate_all().await?;
   }
 
   return Ok(Json(CreateIndexResponse {
@@ -55,5 +55,7 @@ pub async fn drop_index_handler(
       .await?;
   }
 
+  state.schema_metadata().invalidate_all().await?;
+
   return Ok((StatusCode::OK, "").into_response());
 }
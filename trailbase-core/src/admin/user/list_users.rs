@@ -2,9 +2,11 @@ use axum::{
   Json,
   extract::{RawQuery, State},
 };
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use serde::Serialize;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use trailbase_qs::{Cursor, Order, OrderPrecedent, Query};
 use trailbase_schema::QualifiedName;
 use ts_rs::TS;
@@ -13,7 +15,7 @@ use uuid::Uuid;
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
 use crate::auth::user::DbUser;
-use crate::constants::USER_TABLE;
+use crate::constants::{USER_ROLE_TABLE, USER_TABLE};
 use crate::listing::{WhereClause, build_filter_where_clause, cursor_to_value, limit_or_default};
 use crate::util::id_to_b64;
 
@@ -29,6 +31,9 @@ pub struct UserJson {
   pub provider_user_id: Option<String>,
 
   pub email_verification_code: String,
+
+  /// Names of the roles the user is a member of.
+  pub roles: Vec<String>,
 }
 
 impl From<DbUser> for UserJson {
@@ -41,6 +46,7 @@ impl From<DbUser> for UserJson {
       provider_id: value.provider_id,
       provider_user_id: value.provider_user_id,
       email_verification_code: value.email_verification_code.unwrap_or_default(),
+      roles: vec![],
     }
   }
 }
@@ -117,16 +123,53 @@ pub async fn list_users_handler(
   )
   .await?;
 
+  let mut roles = fetch_roles(conn, &users).await?;
+
   return Ok(Json(ListUsersResponse {
     total_row_count,
     cursor: users.last().map(|user| id_to_b64(&user.id)),
     users: users
       .into_iter()
-      .map(|user| user.into())
+      .map(|user| {
+        let user_roles = roles.remove(&user.id).unwrap_or_default();
+        return UserJson {
+          roles: user_roles,
+          ..UserJson::from(user)
+        };
+      })
       .collect::<Vec<UserJson>>(),
   }));
 }
 
+async fn fetch_roles(
+  conn: &trailbase_sqlite::Connection,
+  users: &[DbUser],
+) -> Result<HashMap<[u8; 16], Vec<String>>, Error> {
+  if users.is_empty() {
+    return Ok(HashMap::new());
+  }
+
+  let placeholders = (1..=users.len()).map(|i| format!("${i}")).join(", ");
+  let rows = conn
+    .read_query_rows(
+      format!(
+        "SELECT user, role FROM {USER_ROLE_TABLE} WHERE user IN ({placeholders}) ORDER BY role"
+      ),
+      users
+        .iter()
+        .map(|user| trailbase_sqlite::Value::Blob(user.id.to_vec()))
+        .collect::<Vec<_>>(),
+    )
+    .await?;
+
+  let mut roles = HashMap::<[u8; 16], Vec<String>>::new();
+  for row in rows.iter() {
+    let user: [u8; 16] = row.get(0)?;
+    roles.entry(user).or_default().push(row.get(1)?);
+  }
+  return Ok(roles);
+}
+
 async fn fetch_users(
   conn: &trailbase_sqlite::Connection,
   filter_where_clause: WhereClause,
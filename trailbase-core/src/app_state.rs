@@ -6,12 +6,15 @@ use trailbase_schema::QualifiedName;
 
 use crate::auth::jwt::JwtHelper;
 use crate::auth::options::AuthOptions;
+use crate::broadcast::BroadcastManager;
 use crate::config::proto::{Config, RecordApiConfig, S3StorageConfig, hash_config};
 use crate::config::{validate_config, write_config_and_vault_textproto};
 use crate::data_dir::DataDir;
 use crate::email::Mailer;
 use crate::js::{RuntimeHandle, register_database_functions};
 use crate::records::RecordApi;
+use crate::records::change_log::install_change_log_triggers;
+use crate::records::history::install_history_triggers;
 use crate::records::subscribe::SubscriptionManager;
 use crate::scheduler::{JobRegistry, build_job_registry_from_config};
 use crate::schema_metadata::SchemaMetadataCache;
@@ -23,6 +26,7 @@ struct InternalState {
   data_dir: DataDir,
   public_dir: Option<PathBuf>,
   site_url: Computed<url::Url>,
+  cors_allowed_origins: Vec<String>,
   dev: bool,
   demo: bool,
 
@@ -39,6 +43,7 @@ struct InternalState {
 
   schema_metadata: SchemaMetadataCache,
   subscription_manager: SubscriptionManager,
+  broadcast_manager: BroadcastManager,
   object_store: Arc<dyn ObjectStore + Send + Sync>,
 
   runtime: RuntimeHandle,
@@ -52,6 +57,7 @@ pub(crate) struct AppStateArgs {
   pub data_dir: DataDir,
   pub public_dir: Option<PathBuf>,
   pub address: String,
+  pub cors_allowed_origins: Vec<String>,
   pub dev: bool,
   pub demo: bool,
   pub schema_metadata: SchemaMetadataCache,
@@ -79,7 +85,7 @@ impl AppState {
       let conn_clone = args.conn.clone();
 
       Computed::new(&config, move |c| {
-        return c
+        let apis = c
           .record_apis
           .iter()
           .filter_map(|config| {
@@ -92,6 +98,11 @@ impl AppState {
             }
           })
           .collect::<Vec<_>>();
+
+        install_history_triggers(&conn_clone, &schema_metadata_clone, &apis);
+        install_change_log_triggers(&conn_clone, &schema_metadata_clone, &apis);
+
+        return apis;
       })
     };
 
@@ -104,12 +115,14 @@ impl AppState {
     );
 
     let runtime = build_js_runtime(args.conn.clone(), args.js_runtime_threads);
+    let broadcast_manager = BroadcastManager::new(&config);
 
     AppState {
       state: Arc::new(InternalState {
         data_dir: args.data_dir,
         public_dir: args.public_dir,
         site_url,
+        cors_allowed_origins: args.cors_allowed_origins,
         dev: args.dev,
         demo: args.demo,
         auth: Computed::new(&config, |c| AuthOptions::from_config(c.auth.clone())),
@@ -142,6 +155,7 @@ impl AppState {
           args.schema_metadata,
           record_apis,
         ),
+        broadcast_manager,
         object_store,
         runtime,
         #[cfg(test)]
@@ -192,6 +206,10 @@ impl AppState {
     return &self.state.subscription_manager;
   }
 
+  pub(crate) fn broadcast_manager(&self) -> &BroadcastManager {
+    return &self.state.broadcast_manager;
+  }
+
   pub async fn refresh_table_cache(&self) -> Result<(), crate::schema_metadata::SchemaLookupError> {
     self.schema_metadata().invalidate_all().await
   }
@@ -212,6 +230,19 @@ impl AppState {
     return self.state.site_url.load_full();
   }
 
+  /// Whether the request's `Origin` may act on behalf of the user, e.g. for cookie-authenticated
+  /// WebSocket upgrades, which aren't subject to CORS. Any origin is allowed in dev mode.
+  pub(crate) fn is_allowed_origin(&self, headers: &axum::http::HeaderMap) -> bool {
+    if self.dev_mode() {
+      return true;
+    }
+    return crate::util::is_allowed_origin(
+      headers,
+      &self.site_url(),
+      &self.state.cors_allowed_origins,
+    );
+  }
+
   pub(crate) fn mailer(&self) -> Guard<Arc<Mailer>> {
     return self.state.mailer.load();
   }
@@ -229,6 +260,32 @@ impl AppState {
     return None;
   }
 
+  /// Looks up the Record API governing access to records of the related `table_name` when
+  /// expanding or filtering records of `api`, i.e. the API configured in `api`'s `related_apis`
+  /// or otherwise the only API exposing the table. Returns None if the table isn't exposed or
+  /// exposed by multiple APIs none of which is configured.
+  pub(crate) fn lookup_related_record_api(
+    &self,
+    api: &RecordApi,
+    table_name: &QualifiedName,
+  ) -> Option<RecordApi> {
+    let record_apis = self.state.record_apis.load();
+    let mut exposing = record_apis
+      .iter()
+      .filter(|(_name, record_api)| record_api.qualified_name() == table_name);
+
+    if let Some(related_api_name) = api.related_api_name(&table_name.name) {
+      return exposing
+        .find(|(name, _record_api)| name == related_api_name)
+        .map(|(_name, record_api)| record_api.clone());
+    }
+
+    return match (exposing.next(), exposing.next()) {
+      (Some((_name, record_api)), None) => Some(record_api.clone()),
+      _ => None,
+    };
+  }
+
   pub fn get_config(&self) -> Config {
     return (**self.state.config.load()).clone();
   }
@@ -381,7 +438,7 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
   };
 
   let record_apis = Computed::new(&config, move |c| {
-    return c
+    let apis = c
       .record_apis
       .iter()
       .filter_map(|config| {
@@ -395,6 +452,11 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
         return Some((api.api_name().to_string(), api));
       })
       .collect::<Vec<_>>();
+
+    install_history_triggers(&main_conn_clone, &schema_metadata_clone, &apis);
+    install_change_log_triggers(&main_conn_clone, &schema_metadata_clone, &apis);
+
+    return apis;
   });
 
   fn build_mailer(c: &ValueNotifier<Config>, mailer: Option<Mailer>) -> Computed<Mailer> {
@@ -403,12 +465,15 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
     });
   }
 
+  let broadcast_manager = BroadcastManager::new(&config);
+
   let address = "localhost:1234";
   return Ok(AppState {
     state: Arc::new(InternalState {
       data_dir,
       public_dir: None,
       site_url: Computed::new(&config, move |c| build_site_url(c, address)),
+      cors_allowed_origins: vec![],
       dev: true,
       demo: false,
       auth: Computed::new(&config, |c| AuthOptions::from_config(c.auth.clone())),
@@ -421,6 +486,7 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
       jwt: jwt::test_jwt_helper(),
       schema_metadata: schema_metadata.clone(),
       subscription_manager: SubscriptionManager::new(conn.clone(), schema_metadata, record_apis),
+      broadcast_manager,
       object_store,
       runtime: build_js_runtime(conn, None),
       cleanup: vec![Box::new(temp_dir)],
@@ -506,8 +572,10 @@ pub(crate) fn build_objectstore(
     return Ok(Box::new(builder.build()?));
   }
 
+  // Remove directories left empty by deletions, e.g. of generated image variants.
   return Ok(Box::new(
-    object_store::local::LocalFileSystem::new_with_prefix(data_dir.uploads_path())?,
+    object_store::local::LocalFileSystem::new_with_prefix(data_dir.uploads_path())?
+      .with_automatic_cleanup(true),
   ));
 }
 
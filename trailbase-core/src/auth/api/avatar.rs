@@ -43,6 +43,8 @@ pub async fn get_avatar_handler(
     column_json_metadata,
     "user",
     rusqlite::types::Value::Blob(user_id.into()),
+    None,
+    None,
   )
   .await
   .map_err(|err| match err {
@@ -97,6 +99,7 @@ pub async fn create_avatar_handler(
     "user",
     true,
     params,
+    None,
   )
   .await
   .map_err(|err| AuthError::Internal(err.into()))?;
@@ -6,6 +6,7 @@ use ed25519_dalek::pkcs8::{EncodePrivateKey, EncodePublicKey};
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, errors::Error as JwtError};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 use tokio::{
@@ -39,6 +40,10 @@ pub struct TokenClaims {
   /// E-mail address of the [sub].
   pub email: String,
 
+  /// Names of the roles [sub] is a member of at the time of minting.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub roles: Vec<String>,
+
   /// CSRF random token. Requiring that the client echos this random token back on a non-cookie,
   /// non-auto-attach channel can be used to protect from CSRF.
   pub csrf_token: String,
@@ -49,6 +54,7 @@ impl TokenClaims {
     verified: bool,
     user_id: uuid::Uuid,
     email: String,
+    roles: Vec<String>,
     expires_in: chrono::Duration,
   ) -> Self {
     assert!(verified);
@@ -59,6 +65,7 @@ impl TokenClaims {
       exp: (now + expires_in).timestamp(),
       iat: now.timestamp(),
       email,
+      roles,
       csrf_token: generate_random_string(20),
     };
   }
@@ -74,6 +81,9 @@ pub struct JwtHelper {
   // The public key used for validating provided JWTs.
   decoding_key: DecodingKey,
   public_key: String,
+
+  // Secret derived from the private key, see `derive_key`.
+  secret: [u8; 32],
 }
 
 impl JwtHelper {
@@ -84,6 +94,7 @@ impl JwtHelper {
       encoding_key: EncodingKey::from_ed_pem(&private_key)?,
       decoding_key: DecodingKey::from_ed_pem(&public_key)?,
       public_key: String::from_utf8_lossy(&public_key).to_string(),
+      secret: Sha256::digest(&private_key).into(),
     });
   }
 
@@ -117,6 +128,15 @@ impl JwtHelper {
     return self.public_key.clone();
   }
 
+  /// Derives a secret key for the given purpose, which is stable across restarts, e.g. to key
+  /// hashes of otherwise guessable data.
+  pub(crate) fn derive_key(&self, purpose: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(self.secret);
+    hasher.update(purpose.as_bytes());
+    return hasher.finalize().into();
+  }
+
   pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T, JwtError> {
     // Note: we don't need to expose the token headers.
     return jsonwebtoken::decode::<T>(token, &self.decoding_key, &self.validation)
@@ -191,6 +211,7 @@ mod tests {
       true,
       uuid::Uuid::now_v7(),
       "foo@bar.com".to_string(),
+      vec!["editor".to_string()],
       crate::constants::DEFAULT_AUTH_TOKEN_TTL,
     );
     let token = jwt.encode(&claims).unwrap();
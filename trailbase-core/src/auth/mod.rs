@@ -22,6 +22,7 @@ pub use error::AuthError;
 pub use jwt::{JwtHelper, TokenClaims};
 pub(crate) use ui::auth_ui_router;
 pub use user::User;
+pub use util::user_roles;
 
 use crate::constants::AUTH_API_PATH;
 
@@ -90,6 +90,8 @@ pub struct User {
   pub email: String,
   /// Convenience UUID representation of [id] above.
   pub uuid: Uuid,
+  /// Names of the roles the current user is a member of, as included in the auth token claims.
+  pub roles: Vec<String>,
 
   /// The "expected" CSRF token as included in the auth token claims [User] was constructed from.
   pub csrf_token: String,
@@ -112,10 +114,17 @@ impl User {
       id: claims.sub,
       email: claims.email,
       uuid,
+      roles: claims.roles,
       csrf_token: claims.csrf_token,
     });
   }
 
+  /// JSON-encoded array of role names, e.g. `["editor"]`, as exposed to access rules via
+  /// `_USER_.roles`.
+  pub(crate) fn roles_json(&self) -> String {
+    return serde_json::to_string(&self.roles).expect("json array");
+  }
+
   #[cfg(test)]
   pub(crate) fn from_auth_token(state: &AppState, auth_token: &str) -> Option<Self> {
     Some(Self::from_token_claims(state.jwt().decode(auth_token).unwrap()).unwrap())
@@ -127,6 +136,7 @@ impl User {
       id: crate::util::uuid_to_b64(&user_id),
       email: email.to_string(),
       uuid: user_id,
+      roles: vec![],
       csrf_token: crate::rand::generate_random_string(20),
     };
   }
@@ -13,7 +13,8 @@ use crate::AppState;
 use crate::auth::AuthError;
 use crate::auth::user::{DbUser, User};
 use crate::constants::{
-  COOKIE_AUTH_TOKEN, COOKIE_OAUTH_STATE, COOKIE_REFRESH_TOKEN, SESSION_TABLE, USER_TABLE,
+  COOKIE_AUTH_TOKEN, COOKIE_OAUTH_STATE, COOKIE_REFRESH_TOKEN, SESSION_TABLE, USER_ROLE_TABLE,
+  USER_TABLE,
 };
 
 /// Strips plus-addressing, e.g. foo+spam@test.org becomes foo@test.org.
@@ -211,6 +212,29 @@ pub(crate) async fn is_admin(state: &AppState, user: &User) -> bool {
   return row;
 }
 
+/// Returns the names of the roles the given user is a member of.
+pub async fn user_roles(
+  user_conn: &trailbase_sqlite::Connection,
+  user_id: &uuid::Uuid,
+) -> Result<Vec<String>, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"SELECT json_group_array(role) FROM (SELECT role FROM "{USER_ROLE_TABLE}" WHERE user = $1 ORDER BY role)"#
+    );
+  };
+
+  let Some(roles) = user_conn
+    .read_query_row_f(&*QUERY, params!(user_id.into_bytes()), |row| {
+      row.get::<_, String>(0)
+    })
+    .await?
+  else {
+    return Ok(vec![]);
+  };
+
+  return serde_json::from_str(&roles).map_err(|err| AuthError::Internal(err.into()));
+}
+
 pub(crate) async fn delete_all_sessions_for_user(
   state: &AppState,
   user_id: uuid::Uuid,
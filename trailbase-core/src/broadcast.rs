@@ -0,0 +1,855 @@
+use axum::{
+  Json, Router,
+  extract::{
+    Path, State,
+    ws::{Message, WebSocket, WebSocketUpgrade},
+  },
+  http::HeaderMap,
+  response::{
+    Response,
+    sse::{Event, KeepAlive, Sse},
+  },
+  routing::{get, post},
+};
+use futures_util::{Stream, StreamExt};
+use log::*;
+use parking_lot::Mutex;
+use pin_project_lite::pin_project;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower_http::limit::RequestBodyLimitLayer;
+use trailbase_sqlite::NamedParams;
+
+use crate::AppState;
+use crate::auth::user::User;
+use crate::config::{ConfigError, proto};
+use crate::constants::BROADCAST_API_PATH;
+use crate::records::{RecordError, validate_rule};
+use crate::value_notifier::{Computed, ValueNotifier};
+
+static SUBSCRIBER_COUNTER: AtomicI64 = AtomicI64::new(0);
+
+/// Number of events buffered per subscriber. Events for subscribers falling further behind are
+/// dropped, since broadcasts are ephemeral anyway.
+const SUBSCRIBER_BUFFER: usize = 64;
+
+/// Upper bound for the size of published messages in bytes.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+
+/// Upper bound for messages published per WebSocket connection and second.
+const PUBLISH_RATE_LIMIT: usize = 20;
+
+type SseEvent = Result<axum::response::sse::Event, axum::Error>;
+
+/// Subscriber of a channel as seen by other subscribers of channels with presence enabled.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Member {
+  /// Id of the subscription. Unique across all channels.
+  pub id: i64,
+  /// Url-safe Base64 encoded id of the subscribed user, absent for unauthenticated subscribers.
+  pub user: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum BroadcastEvent {
+  /// Message published to the channel. `sender` is the publishing user's id, if any.
+  Message {
+    sender: Option<String>,
+    payload: serde_json::Value,
+  },
+  /// Current members including the new subscriber, sent first when subscribing to channels with
+  /// presence enabled.
+  Presence(Vec<Member>),
+  Join(Member),
+  Leave(Member),
+  Error(String),
+}
+
+/// Channel as configured, with access rules turned into queries.
+struct BroadcastChannel {
+  subscribe_access_query: Option<Arc<str>>,
+  publish_access_query: Option<Arc<str>>,
+  enable_presence: bool,
+}
+
+struct Subscriber {
+  member: Member,
+  sender: async_channel::Sender<Arc<BroadcastEvent>>,
+}
+
+struct ManagerState {
+  channels: Computed<HashMap<String, Arc<BroadcastChannel>>>,
+
+  /// Subscribers by channel name and subscription id. Channels without subscribers are removed.
+  subscribers: Mutex<HashMap<String, BTreeMap<i64, Subscriber>>>,
+}
+
+impl ManagerState {
+  /// Sends the event to all subscribers of the given channel but `skip`. Returns the number of
+  /// receiving subscribers.
+  fn send(&self, channel_name: &str, event: BroadcastEvent, skip: Option<i64>) -> usize {
+    let subscribers = self.subscribers.lock();
+    let Some(subscribers) = subscribers.get(channel_name) else {
+      return 0;
+    };
+
+    let event = Arc::new(event);
+    let mut receivers: usize = 0;
+    for (id, subscriber) in subscribers {
+      if skip == Some(*id) {
+        continue;
+      }
+
+      match subscriber.sender.try_send(event.clone()) {
+        Ok(_) => receivers += 1,
+        Err(async_channel::TrySendError::Full(_)) => {
+          debug!("Dropping broadcast to slow subscriber {id}");
+        }
+        // Removed by the subscription's cleanup.
+        Err(async_channel::TrySendError::Closed(_)) => {}
+      }
+    }
+
+    return receivers;
+  }
+
+  fn remove_subscriber(&self, channel_name: &str, id: i64) -> Option<Subscriber> {
+    let mut channels = self.subscribers.lock();
+    let subscribers = channels.get_mut(channel_name)?;
+    let subscriber = subscribers.remove(&id);
+    if subscribers.is_empty() {
+      channels.remove(channel_name);
+    }
+    return subscriber;
+  }
+}
+
+/// In-memory pub/sub for ephemeral data, e.g. cursors, typing indicators or presence, that
+/// doesn't warrant writing records. Unlike record subscriptions, nothing is persisted and
+/// messages are only relayed to currently connected subscribers.
+#[derive(Clone)]
+pub(crate) struct BroadcastManager {
+  state: Arc<ManagerState>,
+}
+
+impl BroadcastManager {
+  pub(crate) fn new(config: &ValueNotifier<proto::Config>) -> Self {
+    return Self {
+      state: Arc::new(ManagerState {
+        channels: Computed::new(config, |c| {
+          return c
+            .broadcast_channels
+            .iter()
+            .filter_map(|config| {
+              let name = config.name.clone()?;
+              return Some((
+                name,
+                Arc::new(BroadcastChannel {
+                  subscribe_access_query: config
+                    .subscribe_access_rule
+                    .as_deref()
+                    .map(build_access_query),
+                  publish_access_query: config
+                    .publish_access_rule
+                    .as_deref()
+                    .map(build_access_query),
+                  enable_presence: config.enable_presence.unwrap_or(false),
+                }),
+              ));
+            })
+            .collect();
+        }),
+        subscribers: Mutex::new(HashMap::new()),
+      }),
+    };
+  }
+
+  fn lookup_channel(&self, name: &str) -> Option<Arc<BroadcastChannel>> {
+    return self.state.channels.load().get(name).cloned();
+  }
+
+  pub(crate) fn num_subscribers(&self) -> usize {
+    return self
+      .state
+      .subscribers
+      .lock()
+      .values()
+      .map(|s| s.len())
+      .sum();
+  }
+
+  /// Publishes a message to all current subscribers of the given channel but `skip` and returns
+  /// the number of receiving subscribers. Does not check access.
+  pub(crate) fn publish(
+    &self,
+    channel_name: &str,
+    sender: Option<&User>,
+    payload: serde_json::Value,
+    skip: Option<i64>,
+  ) -> Result<usize, RecordError> {
+    if !self.state.channels.load().contains_key(channel_name) {
+      return Err(RecordError::ApiNotFound);
+    }
+
+    return Ok(self.state.send(
+      channel_name,
+      BroadcastEvent::Message {
+        sender: sender.map(|u| u.id.clone()),
+        payload,
+      },
+      skip,
+    ));
+  }
+
+  /// Adds a subscriber to the given channel. Does not check access.
+  fn subscribe(
+    &self,
+    channel_name: &str,
+    channel: &BroadcastChannel,
+    user: Option<&User>,
+  ) -> BroadcastEventStream {
+    let (sender, receiver) = async_channel::bounded::<Arc<BroadcastEvent>>(SUBSCRIBER_BUFFER);
+    let member = Member {
+      id: SUBSCRIBER_COUNTER.fetch_add(1, Ordering::SeqCst),
+      user: user.map(|u| u.id.clone()),
+    };
+
+    {
+      let mut channels = self.state.subscribers.lock();
+      let subscribers = channels.entry(channel_name.to_string()).or_default();
+
+      if channel.enable_presence {
+        let mut members: Vec<Member> = subscribers.values().map(|s| s.member.clone()).collect();
+        members.push(member.clone());
+
+        // The new subscriber's channel is empty and thus has capacity.
+        let _ = sender.try_send(Arc::new(BroadcastEvent::Presence(members)));
+      }
+
+      subscribers.insert(
+        member.id,
+        Subscriber {
+          member: member.clone(),
+          sender,
+        },
+      );
+    }
+
+    if channel.enable_presence {
+      self.state.send(
+        channel_name,
+        BroadcastEvent::Join(member.clone()),
+        Some(member.id),
+      );
+    }
+
+    return BroadcastEventStream {
+      cleanup: Unsubscribe {
+        state: self.state.clone(),
+        channel_name: channel_name.to_string(),
+        id: member.id,
+        enable_presence: channel.enable_presence,
+      },
+      receiver,
+    };
+  }
+}
+
+/// RAII type removing the subscriber and notifying others when the receiving side gets dropped,
+/// e.g. the client disconnects.
+struct Unsubscribe {
+  state: Arc<ManagerState>,
+  channel_name: String,
+  id: i64,
+  enable_presence: bool,
+}
+
+impl Drop for Unsubscribe {
+  fn drop(&mut self) {
+    let Some(subscriber) = self.state.remove_subscriber(&self.channel_name, self.id) else {
+      return;
+    };
+
+    if self.enable_presence {
+      self.state.send(
+        &self.channel_name,
+        BroadcastEvent::Leave(subscriber.member),
+        None,
+      );
+    }
+  }
+}
+
+pin_project! {
+  /// Receiver wrapper that knows how to unsubscribe.
+  #[must_use = "streams do nothing unless polled"]
+  pub(crate) struct BroadcastEventStream {
+    cleanup: Unsubscribe,
+
+    #[pin]
+    receiver: async_channel::Receiver<Arc<BroadcastEvent>>,
+  }
+}
+
+impl BroadcastEventStream {
+  fn id(&self) -> i64 {
+    return self.cleanup.id;
+  }
+}
+
+impl Stream for BroadcastEventStream {
+  type Item = Arc<BroadcastEvent>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let mut this = self.project();
+    this.receiver.as_mut().poll_next(cx)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.receiver.size_hint()
+  }
+}
+
+pub(crate) fn router() -> Router<AppState> {
+  return Router::new()
+    .route(
+      &format!("/{BROADCAST_API_PATH}/{{name}}"),
+      post(publish_handler).layer(RequestBodyLimitLayer::new(MAX_MESSAGE_SIZE)),
+    )
+    .route(
+      &format!("/{BROADCAST_API_PATH}/{{name}}/subscribe"),
+      get(subscribe_sse_handler),
+    )
+    .route(
+      &format!("/{BROADCAST_API_PATH}/{{name}}/ws"),
+      get(broadcast_ws_handler),
+    );
+}
+
+/// Subscribes to the given broadcast channel.
+///
+/// Subscribers of channels with presence enabled first receive the current members and are
+/// subsequently notified when others join or leave.
+pub async fn subscribe_sse_handler(
+  State(state): State<AppState>,
+  Path(channel_name): Path<String>,
+  user: Option<User>,
+) -> Result<Sse<impl Stream<Item = SseEvent>>, RecordError> {
+  let receiver = subscribe(&state, &channel_name, user.as_ref()).await?;
+
+  return Ok(
+    Sse::new(receiver.map(|event| -> SseEvent { Event::default().json_data(&*event) }))
+      .keep_alive(KeepAlive::default()),
+  );
+}
+
+/// Publishes the JSON request body to all current subscribers of the given broadcast channel.
+pub async fn publish_handler(
+  State(state): State<AppState>,
+  Path(channel_name): Path<String>,
+  user: Option<User>,
+  Json(payload): Json<serde_json::Value>,
+) -> Result<(), RecordError> {
+  let Some(channel) = state.broadcast_manager().lookup_channel(&channel_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  check_access(&state, channel.publish_access_query.as_ref(), user.as_ref()).await?;
+
+  state
+    .broadcast_manager()
+    .publish(&channel_name, user.as_ref(), payload, None)?;
+
+  return Ok(());
+}
+
+/// Subscribes to and publishes on the given broadcast channel over a WebSocket.
+///
+/// Clients receive JSON-encoded `BroadcastEvent`s as text messages. Text messages sent by the
+/// client are published as JSON payload to all other subscribers. Publish access is checked on
+/// the first message and again after config changes. Messages are subject to a size and rate
+/// limit. Upgrades from foreign origins are rejected to prevent cross-site WebSocket hijacking.
+pub async fn broadcast_ws_handler(
+  State(state): State<AppState>,
+  Path(channel_name): Path<String>,
+  user: Option<User>,
+  headers: HeaderMap,
+  ws: WebSocketUpgrade,
+) -> Result<Response, RecordError> {
+  if !state.is_allowed_origin(&headers) {
+    return Err(RecordError::Forbidden);
+  }
+
+  let receiver = subscribe(&state, &channel_name, user.as_ref()).await?;
+
+  return Ok(
+    ws.max_message_size(MAX_MESSAGE_SIZE)
+      .on_upgrade(move |socket| {
+        handle_broadcast_socket(state, channel_name, user, receiver, socket)
+      }),
+  );
+}
+
+async fn handle_broadcast_socket(
+  state: AppState,
+  channel_name: String,
+  user: Option<User>,
+  receiver: BroadcastEventStream,
+  mut socket: WebSocket,
+) {
+  let id = receiver.id();
+  let mut receiver = std::pin::pin!(receiver);
+  // Publish access for the channel's current config, which changes on config reloads.
+  let mut may_publish: Option<(Arc<BroadcastChannel>, bool)> = None;
+  let mut rate_limit = RateLimit::new(PUBLISH_RATE_LIMIT, Duration::from_secs(1));
+
+  loop {
+    tokio::select! {
+      msg = socket.recv() => {
+        let text = match msg {
+          Some(Ok(Message::Text(text))) => text,
+          Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+          // Pings are answered automatically.
+          Some(Ok(_)) => continue,
+        };
+
+        let error = match serde_json::from_str::<serde_json::Value>(text.as_str()) {
+          // Dropped rather than delayed, since broadcasts are ephemeral anyway.
+          Ok(_) if !rate_limit.acquire() => Some("Rate limit exceeded".to_string()),
+          Ok(payload) => {
+            match state.broadcast_manager().lookup_channel(&channel_name) {
+              Some(channel) => {
+                let allowed = match may_publish {
+                  Some((ref checked, allowed)) if Arc::ptr_eq(checked, &channel) => allowed,
+                  _ => {
+                    let allowed = check_access(&state, channel.publish_access_query.as_ref(), user.as_ref())
+                      .await
+                      .is_ok();
+                    may_publish = Some((channel, allowed));
+                    allowed
+                  }
+                };
+
+                if allowed {
+                  state
+                    .broadcast_manager()
+                    .publish(&channel_name, user.as_ref(), payload, Some(id))
+                    .err()
+                    .map(|err| err.to_string())
+                } else {
+                  Some(RecordError::Forbidden.to_string())
+                }
+              }
+              None => Some(RecordError::ApiNotFound.to_string()),
+            }
+          }
+          Err(_err) => Some("Invalid payload".to_string()),
+        };
+
+        let Some(error) = error.and_then(|err| encode_event(&BroadcastEvent::Error(err))) else {
+          continue;
+        };
+        if socket.send(Message::Text(error.into())).await.is_err() {
+          break;
+        }
+      }
+      event = receiver.next() => {
+        let Some(event) = event else {
+          break;
+        };
+        let Some(event) = encode_event(&event) else {
+          continue;
+        };
+        if socket.send(Message::Text(event.into())).await.is_err() {
+          break;
+        }
+      }
+    }
+  }
+}
+
+/// Checks subscribe access and adds a subscription, shared by the SSE and WebSocket transports.
+pub(crate) async fn subscribe(
+  state: &AppState,
+  channel_name: &str,
+  user: Option<&User>,
+) -> Result<BroadcastEventStream, RecordError> {
+  let Some(channel) = state.broadcast_manager().lookup_channel(channel_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  check_access(state, channel.subscribe_access_query.as_ref(), user).await?;
+
+  return Ok(
+    state
+      .broadcast_manager()
+      .subscribe(channel_name, &channel, user),
+  );
+}
+
+/// Fixed window rate limit.
+struct RateLimit {
+  limit: usize,
+  window: Duration,
+  window_start: Instant,
+  count: usize,
+}
+
+impl RateLimit {
+  fn new(limit: usize, window: Duration) -> Self {
+    return Self {
+      limit,
+      window,
+      window_start: Instant::now(),
+      count: 0,
+    };
+  }
+
+  /// Returns false if the limit for the current window has been exhausted.
+  fn acquire(&mut self) -> bool {
+    let now = Instant::now();
+    if now.duration_since(self.window_start) >= self.window {
+      self.window_start = now;
+      self.count = 0;
+    }
+
+    if self.count >= self.limit {
+      return false;
+    }
+    self.count += 1;
+    return true;
+  }
+}
+
+/// Evaluates the given access query. Channels without a rule deny access, i.e. subscribing and
+/// publishing have to be enabled explicitly.
+async fn check_access(
+  state: &AppState,
+  access_query: Option<&Arc<str>>,
+  user: Option<&User>,
+) -> Result<(), RecordError> {
+  let Some(access_query) = access_query else {
+    return Err(RecordError::Forbidden);
+  };
+
+  let params: NamedParams = vec![
+    (
+      Cow::Borrowed(":__user_id"),
+      user.map_or(trailbase_sqlite::Value::Null, |u| {
+        trailbase_sqlite::Value::Blob(u.uuid.into())
+      }),
+    ),
+    (
+      Cow::Borrowed(":__user_roles"),
+      user.map_or(trailbase_sqlite::Value::Null, |u| {
+        trailbase_sqlite::Value::Text(u.roles_json())
+      }),
+    ),
+  ];
+
+  let allowed: Option<bool> = state
+    .conn()
+    .read_query_row_f(access_query.clone(), params, |row| row.get(0))
+    .await?;
+
+  if allowed.unwrap_or(false) {
+    return Ok(());
+  }
+  return Err(RecordError::Forbidden);
+}
+
+/// Build access query for subscribing and publishing.
+///
+/// Assumes access_rule is an expression: https://www.sqlite.org/syntax/expr.html
+fn build_access_query(access_rule: &str) -> Arc<str> {
+  return indoc::formatdoc!(
+    r#"
+      SELECT
+        CAST(({access_rule}) AS INTEGER)
+      FROM
+        (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_
+    "#,
+  )
+  .into();
+}
+
+fn encode_event(event: &BroadcastEvent) -> Option<String> {
+  return serde_json::to_string(event)
+    .map_err(|err| warn!("Failed to encode broadcast event: {err}"))
+    .ok();
+}
+
+fn validate_channel_name(name: &str) -> Result<(), ConfigError> {
+  if name.is_empty() {
+    return Err(ConfigError::Invalid(
+      "Invalid channel name: cannot be empty".to_string(),
+    ));
+  }
+
+  if !name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') {
+    return Err(ConfigError::Invalid(format!(
+      "Invalid channel name: {name}. Must only contain alphanumeric characters or '_'."
+    )));
+  }
+
+  Ok(())
+}
+
+pub(crate) fn validate_broadcast_channel_config(
+  config: &proto::BroadcastChannelConfig,
+) -> Result<String, ConfigError> {
+  let Some(ref name) = config.name else {
+    return Err(ConfigError::Invalid(
+      "Broadcast channel config misses name.".to_string(),
+    ));
+  };
+  validate_channel_name(name)?;
+
+  let rules = [&config.subscribe_access_rule, &config.publish_access_rule];
+  for rule in rules.into_iter().flatten() {
+    validate_rule(rule).map_err(ConfigError::Invalid)?;
+  }
+
+  return Ok(name.clone());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::app_state::test_state;
+  use crate::config::proto::BroadcastChannelConfig;
+
+  async fn setup(channels: Vec<BroadcastChannelConfig>) -> AppState {
+    let state = test_state(None).await.unwrap();
+
+    let mut config = state.get_config();
+    config.broadcast_channels = channels;
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    return state;
+  }
+
+  fn test_user(email: &str) -> User {
+    return User::from_unverified(uuid::Uuid::now_v7(), email);
+  }
+
+  async fn next_event(stream: &mut BroadcastEventStream) -> BroadcastEvent {
+    return (*stream.receiver.recv().await.unwrap()).clone();
+  }
+
+  #[tokio::test]
+  async fn broadcast_publish_test() {
+    let state = setup(vec![BroadcastChannelConfig {
+      name: Some("chat".to_string()),
+      subscribe_access_rule: Some("TRUE".to_string()),
+      ..Default::default()
+    }])
+    .await;
+
+    let mut stream0 = subscribe(&state, "chat", None).await.unwrap();
+    let mut stream1 = subscribe(&state, "chat", None).await.unwrap();
+    assert_eq!(2, state.broadcast_manager().num_subscribers());
+
+    assert!(matches!(
+      subscribe(&state, "unknown", None).await,
+      Err(RecordError::ApiNotFound)
+    ));
+
+    let user = test_user("sender@test.org");
+    let payload = serde_json::json!({"text": "hi"});
+    let receivers = state
+      .broadcast_manager()
+      .publish("chat", Some(&user), payload.clone(), Some(stream0.id()))
+      .unwrap();
+    assert_eq!(1, receivers);
+
+    assert_eq!(
+      BroadcastEvent::Message {
+        sender: Some(user.id.clone()),
+        payload,
+      },
+      next_event(&mut stream1).await
+    );
+    assert!(stream0.receiver.is_empty());
+
+    drop(stream0);
+    drop(stream1);
+    assert_eq!(0, state.broadcast_manager().num_subscribers());
+    assert_eq!(
+      0,
+      state
+        .broadcast_manager()
+        .publish("chat", None, serde_json::Value::Null, None)
+        .unwrap()
+    );
+    assert!(matches!(
+      state
+        .broadcast_manager()
+        .publish("unknown", None, serde_json::Value::Null, None),
+      Err(RecordError::ApiNotFound)
+    ));
+  }
+
+  #[tokio::test]
+  async fn broadcast_presence_test() {
+    let state = setup(vec![BroadcastChannelConfig {
+      name: Some("room".to_string()),
+      subscribe_access_rule: Some("TRUE".to_string()),
+      enable_presence: Some(true),
+      ..Default::default()
+    }])
+    .await;
+
+    let alice = test_user("alice@test.org");
+    let bob = test_user("bob@test.org");
+
+    let mut alice_stream = subscribe(&state, "room", Some(&alice)).await.unwrap();
+    let alice_member = Member {
+      id: alice_stream.id(),
+      user: Some(alice.id.clone()),
+    };
+    assert_eq!(
+      BroadcastEvent::Presence(vec![alice_member.clone()]),
+      next_event(&mut alice_stream).await
+    );
+
+    let mut bob_stream = subscribe(&state, "room", Some(&bob)).await.unwrap();
+    let bob_member = Member {
+      id: bob_stream.id(),
+      user: Some(bob.id.clone()),
+    };
+    assert_eq!(
+      BroadcastEvent::Presence(vec![alice_member.clone(), bob_member.clone()]),
+      next_event(&mut bob_stream).await
+    );
+    assert_eq!(
+      BroadcastEvent::Join(bob_member.clone()),
+      next_event(&mut alice_stream).await
+    );
+
+    drop(alice_stream);
+    assert_eq!(
+      BroadcastEvent::Leave(alice_member),
+      next_event(&mut bob_stream).await
+    );
+  }
+
+  #[tokio::test]
+  async fn broadcast_access_test() {
+    let state = setup(vec![BroadcastChannelConfig {
+      name: Some("private".to_string()),
+      subscribe_access_rule: Some("_USER_.id IS NOT NULL".to_string()),
+      publish_access_rule: Some(
+        "EXISTS(SELECT 1 FROM JSON_EACH(_USER_.roles) WHERE value = 'moderator')".to_string(),
+      ),
+      ..Default::default()
+    }])
+    .await;
+
+    let manager = state.broadcast_manager().clone();
+    let channel = manager.lookup_channel("private").unwrap();
+
+    assert!(matches!(
+      subscribe(&state, "private", None).await,
+      Err(RecordError::Forbidden)
+    ));
+
+    let user = test_user("user@test.org");
+    let _stream = subscribe(&state, "private", Some(&user)).await.unwrap();
+
+    assert!(matches!(
+      check_access(&state, channel.publish_access_query.as_ref(), Some(&user)).await,
+      Err(RecordError::Forbidden)
+    ));
+
+    let moderator = User {
+      roles: vec!["moderator".to_string()],
+      ..test_user("moderator@test.org")
+    };
+    check_access(
+      &state,
+      channel.publish_access_query.as_ref(),
+      Some(&moderator),
+    )
+    .await
+    .unwrap();
+  }
+
+  #[tokio::test]
+  async fn broadcast_denied_by_default_test() {
+    let state = setup(vec![BroadcastChannelConfig {
+      name: Some("closed".to_string()),
+      enable_presence: Some(true),
+      ..Default::default()
+    }])
+    .await;
+
+    let channel = state.broadcast_manager().lookup_channel("closed").unwrap();
+
+    // Subscribing and publishing require rules, thus members aren't leaked to anonymous users.
+    let user = test_user("user@test.org");
+    assert!(matches!(
+      subscribe(&state, "closed", None).await,
+      Err(RecordError::Forbidden)
+    ));
+    assert!(matches!(
+      subscribe(&state, "closed", Some(&user)).await,
+      Err(RecordError::Forbidden)
+    ));
+    assert!(matches!(
+      check_access(&state, channel.publish_access_query.as_ref(), Some(&user)).await,
+      Err(RecordError::Forbidden)
+    ));
+  }
+
+  #[test]
+  fn rate_limit_test() {
+    let mut rate_limit = RateLimit::new(2, Duration::from_secs(3600));
+    assert!(rate_limit.acquire());
+    assert!(rate_limit.acquire());
+    assert!(!rate_limit.acquire());
+
+    let mut rate_limit = RateLimit::new(1, Duration::ZERO);
+    assert!(rate_limit.acquire());
+    assert!(rate_limit.acquire());
+  }
+
+  #[test]
+  fn validate_broadcast_channel_config_test() {
+    assert!(validate_broadcast_channel_config(&BroadcastChannelConfig::default()).is_err());
+    assert!(
+      validate_broadcast_channel_config(&BroadcastChannelConfig {
+        name: Some("invalid name".to_string()),
+        ..Default::default()
+      })
+      .is_err()
+    );
+    assert!(
+      validate_broadcast_channel_config(&BroadcastChannelConfig {
+        name: Some("channel".to_string()),
+        publish_access_rule: Some("1, 1".to_string()),
+        ..Default::default()
+      })
+      .is_err()
+    );
+    assert_eq!(
+      "channel",
+      validate_broadcast_channel_config(&BroadcastChannelConfig {
+        name: Some("channel".to_string()),
+        subscribe_access_rule: Some("_USER_.id IS NOT NULL".to_string()),
+        ..Default::default()
+      })
+      .unwrap()
+    );
+  }
+}
@@ -9,10 +9,12 @@ use std::convert::TryFrom;
 use std::str::FromStr;
 use thiserror::Error;
 use tokio::fs;
+use trailbase_schema::QualifiedName;
 use validator::{ValidateEmail, ValidateUrl};
 
 use crate::DESCRIPTOR_POOL;
 use crate::auth::oauth::providers::oauth_provider_registry;
+use crate::broadcast::validate_broadcast_channel_config;
 use crate::data_dir::DataDir;
 use crate::records::validate_record_api_config;
 use crate::schema_metadata::SchemaMetadataCache;
@@ -518,6 +520,50 @@ pub(crate) fn validate_config(
     }
   }
 
+  // Related APIs must expose the related table, which is in the same database as the API's table.
+  for api in &config.record_apis {
+    for related_api in &api.related_apis {
+      let (Some(api_name), Some(table_name)) = (&api.name, &api.table_name) else {
+        continue;
+      };
+      let (Some(related_table), Some(related_api_name)) =
+        (&related_api.table_name, &related_api.api_name)
+      else {
+        continue;
+      };
+
+      let expected = QualifiedName {
+        name: related_table.clone(),
+        database_schema: QualifiedName::parse(table_name)?.database_schema,
+      };
+      let exposes_table = config.record_apis.iter().any(|other| {
+        return other.name.as_ref() == Some(related_api_name)
+          && other
+            .table_name
+            .as_deref()
+            .and_then(|name| QualifiedName::parse(name).ok())
+            .is_some_and(|name| name == expected);
+      });
+      if !exposes_table {
+        return ierr(format!(
+          "Related API '{related_api_name}' in API '{api_name}' does not expose '{related_table}'"
+        ));
+      }
+    }
+  }
+
+  // Check broadcast channels.
+  let mut channel_names = HashSet::<String>::new();
+  for channel in &config.broadcast_channels {
+    let channel_name = validate_broadcast_channel_config(channel)?;
+
+    if !channel_names.insert(channel_name.clone()) {
+      return ierr(format!(
+        "Two or more broadcast channels have the colliding name: '{channel_name}'"
+      ));
+    }
+  }
+
   // Check OAuth.
   for (name, provider) in &config.auth.oauth_providers {
     let provider_id: OAuthProviderId = provider
@@ -5,9 +5,11 @@ pub const USER_TABLE: &str = "_user";
 
 pub(crate) const SESSION_TABLE: &str = "_session";
 pub(crate) const AVATAR_TABLE: &str = "_user_avatar";
+pub(crate) const USER_ROLE_TABLE: &str = "_user_role";
 
 pub(crate) const LOGS_TABLE_ID_COLUMN: &str = "id";
 pub const LOGS_RETENTION_DEFAULT: Duration = Duration::days(7);
+pub const SOFT_DELETE_RETENTION_DEFAULT: Duration = Duration::days(30);
 
 pub const COOKIE_AUTH_TOKEN: &str = "auth_token";
 pub const COOKIE_REFRESH_TOKEN: &str = "refresh_token";
@@ -31,5 +33,6 @@ pub(crate) const REFRESH_TOKEN_LENGTH: usize = 32;
 // Public APIs
 pub const RECORD_API_PATH: &str = "api/records/v1";
 pub const QUERY_API_PATH: &str = "api/query/v1";
+pub const BROADCAST_API_PATH: &str = "api/broadcast/v1";
 pub const AUTH_API_PATH: &str = "api/auth/v1";
 pub const ADMIN_API_PATH: &str = "api/_admin";
@@ -180,6 +180,7 @@ async fn install_routes_and_jobs(
 ) -> Result<Option<Router<AppState>>, AnyError> {
   let runtime_handle = state.script_runtime();
   let jobs = state.jobs();
+  let broadcast_manager = state.broadcast_manager().clone();
 
   // For all the isolates/worker-threads.
   let receivers: Vec<_> = runtime_handle
@@ -190,6 +191,7 @@ async fn install_routes_and_jobs(
       let module = module.clone();
       let runtime_handle = runtime_handle.clone();
       let jobs = jobs.clone();
+      let broadcast_manager = broadcast_manager.clone();
 
       let (router_sender, router_receiver) = kanal::unbounded::<Router<AppState>>();
 
@@ -279,6 +281,20 @@ async fn install_routes_and_jobs(
               )
               .expect("Failed to register 'install_job' function");
 
+            // Register native callback for publishing to broadcast channels.
+            runtime
+              .register_function("broadcast", move |args: &[serde_json::Value]| {
+                let channel: String = get_arg(args, 0)?;
+                let payload: serde_json::Value = get_arg(args, 1)?;
+
+                let receivers = broadcast_manager
+                  .publish(&channel, None, payload, None)
+                  .map_err(|err| LargeRSError::Runtime(err.to_string()))?;
+
+                return Ok(receivers.into());
+              })
+              .expect("Failed to register 'broadcast' function");
+
             return None;
           }),
         ))
@@ -374,3 +390,73 @@ pub fn build_http_dispatch_message(args: DispatchArgs) -> Message {
     args.reply,
   );
 }
+
+#[cfg(test)]
+mod tests {
+  use futures_util::StreamExt;
+  use trailbase_js::runtime::build_call_sync_js_function_message;
+
+  use super::*;
+  use crate::app_state::test_state;
+  use crate::broadcast::{BroadcastEvent, subscribe};
+  use crate::config::proto::BroadcastChannelConfig;
+
+  #[tokio::test]
+  async fn test_js_broadcast() {
+    let state = test_state(None).await.unwrap();
+    let mut config = state.get_config();
+    config.broadcast_channels = vec![BroadcastChannelConfig {
+      name: Some("chat".to_string()),
+      subscribe_access_rule: Some("TRUE".to_string()),
+      ..Default::default()
+    }];
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    let module = Module::new(
+      "broadcast.js",
+      r#"
+        export function test_broadcast(channel) {
+          return rustyscript.functions.broadcast(channel, { x: 1 });
+        }
+      "#,
+    );
+    install_routes_and_jobs(&state, module.clone())
+      .await
+      .unwrap();
+
+    let call = async |channel: &'static str| -> Result<i64, Box<LargeRSError>> {
+      let (sender, receiver) = oneshot::channel::<Result<i64, Box<LargeRSError>>>();
+      state
+        .script_runtime()
+        .state()
+        .first()
+        .unwrap()
+        .send_privately(build_call_sync_js_function_message::<i64>(
+          Some(module.clone()),
+          "test_broadcast",
+          [channel],
+          sender,
+        ))
+        .await
+        .unwrap();
+      return receiver.await.unwrap();
+    };
+
+    // Scripts publish bypassing access rules, i.e. despite the missing publish rule.
+    let stream = subscribe(&state, "chat", None).await.unwrap();
+    let mut stream = std::pin::pin!(stream);
+    assert_eq!(1, call("chat").await.unwrap());
+    assert_eq!(
+      BroadcastEvent::Message {
+        sender: None,
+        payload: serde_json::json!({"x": 1}),
+      },
+      *stream.next().await.unwrap()
+    );
+
+    assert!(call("unknown").await.is_err());
+  }
+}
@@ -11,6 +11,7 @@ pub mod util;
 
 mod admin;
 mod auth;
+mod broadcast;
 mod connection;
 mod data_dir;
 mod email;
@@ -60,7 +61,7 @@ pub mod openapi {
 pub mod api {
   pub use crate::admin::user::{CreateUserRequest, create_user_handler};
   pub use crate::auth::api::login::login_with_password;
-  pub use crate::auth::{JwtHelper, TokenClaims, force_password_reset};
+  pub use crate::auth::{JwtHelper, TokenClaims, force_password_reset, user_roles};
   pub use crate::connection::{Connection, init_main_db};
   pub use crate::email::{Email, EmailError};
   pub use crate::migrations::new_unique_migration_filename;
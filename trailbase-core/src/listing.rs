@@ -1,9 +1,15 @@
 use base64::prelude::*;
 use log::*;
 use std::borrow::Cow;
+use std::cell::RefCell;
 use thiserror::Error;
-use trailbase_qs::{Cursor as QsCursor, Value as QsValue, ValueOrComposite};
-use trailbase_schema::sqlite::Column;
+use trailbase_qs::{
+  Cursor as QsCursor, GeoFilter, RelatedColumn, SqlHooks, Value as QsValue, ValueOrComposite,
+};
+use trailbase_schema::metadata::{
+  FtsMetadata, JsonColumnMetadata, SpatialIndexMetadata, extract_json_metadata,
+};
+use trailbase_schema::sqlite::{Column, ColumnDataType};
 
 #[derive(Debug, Error)]
 pub enum WhereClauseError {
@@ -42,59 +48,325 @@ pub(crate) fn build_filter_where_clause(
     });
   };
 
-  let validator = |column_name: &str| -> Result<(), WhereClauseError> {
-    if column_name.starts_with("_") {
+  let validator =
+    |column_name: &str, json_path: &[String]| validate_column(columns, column_name, json_path);
+
+  let json_params = filter_params.json_path_params();
+  let text_params: Vec<&str> = json_params.iter().map(|p| p.as_str()).collect();
+  let (sql, params) = filter_params.into_sql(Some(table_name), &validator)?;
+
+  return Ok(WhereClause {
+    clause: sql,
+    params: to_sql_params(params, &text_params),
+  });
+}
+
+fn validate_column(
+  columns: &[Column],
+  column_name: &str,
+  json_path: &[String],
+) -> Result<(), WhereClauseError> {
+  if column_name.starts_with("_") {
+    return Err(WhereClauseError::UnrecognizedParam(format!(
+      "Invalid parameter: {column_name}"
+    )));
+  }
+
+  // IMPORTANT: We only include parameters with known columns to avoid building an invalid
+  // query early and forbid injections.
+  let Some(column) = columns.iter().find(|c| c.name == column_name) else {
+    return Err(WhereClauseError::UnrecognizedParam(format!(
+      "Unrecognized parameter: {column_name}"
+    )));
+  };
+
+  if !json_path.is_empty() {
+    validate_json_path(column, json_path)?;
+  }
+
+  return Ok(());
+}
+
+/// Validates filters on JSON paths, e.g. `metadata.tags`, against the column's JSON schema, if
+/// any.
+fn validate_json_path(column: &Column, json_path: &[String]) -> Result<(), WhereClauseError> {
+  let json_metadata = column
+    .options
+    .iter()
+    .find_map(|opt| extract_json_metadata(opt).ok().flatten());
+
+  let schema: serde_json::Value = match json_metadata {
+    Some(JsonColumnMetadata::SchemaName(name)) => {
+      let Some(schema) = trailbase_schema::registry::get_schema(&name) else {
+        return Err(WhereClauseError::UnrecognizedParam(format!(
+          "Missing JSON schema for column: {}",
+          column.name
+        )));
+      };
+      schema.schema
+    }
+    Some(JsonColumnMetadata::Pattern(pattern)) => pattern,
+    None => {
+      return match column.data_type {
+        ColumnDataType::Text
+        | ColumnDataType::Any
+        | ColumnDataType::Blob
+        | ColumnDataType::JSON
+        | ColumnDataType::JSONB => Ok(()),
+        _ => Err(WhereClauseError::UnrecognizedParam(format!(
+          "Not a JSON column: {}",
+          column.name
+        ))),
+      };
+    }
+  };
+
+  let mut schema = &schema;
+  for segment in json_path {
+    let is_index = segment.chars().all(|c| c.is_ascii_digit());
+    let next = match (schema.get("properties"), schema.get("items")) {
+      // Schema doesn't constrain the structure, e.g. a free-form object.
+      (None, None) => return Ok(()),
+      (_, Some(items)) if is_index => Some(items),
+      (Some(properties), _) => match properties.get(segment) {
+        Some(property) => Some(property),
+        // Unknown properties are only acceptable if explicitly allowed.
+        None => match schema.get("additionalProperties") {
+          Some(serde_json::Value::Bool(true)) => return Ok(()),
+          Some(additional) if additional.is_object() => Some(additional),
+          _ => None,
+        },
+      },
+      _ => None,
+    };
+
+    let Some(next) = next else {
       return Err(WhereClauseError::UnrecognizedParam(format!(
-        "Invalid parameter: {column_name}"
+        "JSON path '{path}' not in schema of column: {column}",
+        path = json_path.join("."),
+        column = column.name
       )));
-    }
+    };
+    schema = next;
+  }
+
+  return Ok(());
+}
+
+/// A `$match` full-text filter against a table's FTS5 index.
+#[derive(Debug, Clone)]
+pub struct FtsMatch {
+  /// Index of the matched column within the FTS5 table, e.g. for `snippet()`.
+  pub column_index: usize,
+  /// Name of the query parameter holding the full-text query.
+  pub param: String,
+}
+
+/// A `$near` geospatial filter, e.g. to order results by distance.
+#[derive(Debug, Clone)]
+pub struct GeoNear {
+  pub latitude_column: String,
+  pub longitude_column: String,
+  /// Names of the query parameters holding the center's latitude and longitude.
+  pub center_params: (String, String),
+}
+
+/// Filters, which callers may want to rank or order results by.
+#[derive(Debug, Clone, Default)]
+pub struct FilterMatches {
+  /// `$match` full-text filters.
+  pub fts: Vec<FtsMatch>,
+  /// `$near` geospatial filters.
+  pub near: Vec<GeoNear>,
+}
+
+/// A filter on a column of a related table, e.g. `author.org`.
+#[derive(Debug, Clone)]
+pub struct RelatedFilter {
+  pub sql: RelatedColumn,
+  /// Whether the related column is a BLOB, i.e. values are base64 decoded like for any other
+  /// column rather than compared as text like values within JSON columns.
+  pub blob: bool,
+}
+
+/// Resolves filters on related columns given the column name and path, e.g. `author` and
+/// `["org"]`. Returns `None` for filters on the table's own columns.
+pub(crate) type RelatedColumns<'a> =
+  dyn Fn(&str, &[String]) -> Result<Option<RelatedFilter>, WhereClauseError> + 'a;
 
-    // IMPORTANT: We only include parameters with known columns to avoid building an invalid
-    // query early and forbid injections.
-    if !columns.iter().any(|c| c.name == column_name) {
+/// Returns a condition, which needs to hold for filters on the given column to match, e.g. a
+/// column-level read access rule.
+pub(crate) type ColumnGuards<'a> = dyn Fn(&str) -> Option<String> + 'a;
+
+/// Like `build_filter_where_clause` but maps `$match` filters to sub-queries against the given
+/// FTS5 index, narrows down geospatial filters using the given R*Tree spatial index, maps
+/// filters on `related` columns to sub-queries against their tables and guards filters on
+/// columns using `guards`. Also returns the matches, e.g. to rank results.
+pub(crate) fn build_filter_where_clause_with_fts(
+  table_name: &str,
+  columns: &[Column],
+  fts: Option<&FtsMetadata>,
+  spatial_index: Option<&SpatialIndexMetadata>,
+  related: Option<&RelatedColumns<'_>>,
+  guards: Option<&ColumnGuards<'_>>,
+  filter_params: Option<ValueOrComposite>,
+) -> Result<(WhereClause, FilterMatches), WhereClauseError> {
+  let Some(filter_params) = filter_params else {
+    return Ok((
+      WhereClause {
+        clause: "TRUE".to_string(),
+        params: vec![],
+      },
+      FilterMatches::default(),
+    ));
+  };
+
+  let validator =
+    |column_name: &str, json_path: &[String]| validate_column(columns, column_name, json_path);
+
+  let matches = RefCell::new(Vec::<FtsMatch>::new());
+  let match_sql = |column_name: &str, param: &str| -> Result<String, WhereClauseError> {
+    let Some(fts) = fts else {
+      return Err(WhereClauseError::NotImplemented(
+        "$match requires a full-text index".to_string(),
+      ));
+    };
+    let Some(column_index) = fts.columns.iter().position(|c| c == column_name) else {
       return Err(WhereClauseError::UnrecognizedParam(format!(
-        "Unrecognized parameter: {column_name}"
+        "Column not in full-text index: {column_name}"
       )));
     };
 
-    return Ok(());
+    matches.borrow_mut().push(FtsMatch {
+      column_index,
+      param: param.to_string(),
+    });
+
+    return Ok(format!(
+      r#"{table_name}."{rowid}" IN (SELECT "{fts_name}".rowid FROM {fts_table} WHERE "{fts_name}"."{column_name}" MATCH {param})"#,
+      rowid = fts.content_rowid,
+      fts_name = fts.name.name,
+      fts_table = fts.name.escaped_string(),
+    ));
   };
 
-  let (sql, params) = filter_params.into_sql(Some(table_name), &validator)?;
-  if params.is_empty() {
-    return Ok(WhereClause {
-      clause: "TRUE".to_string(),
-      params: vec![],
+  let near = RefCell::new(Vec::<GeoNear>::new());
+  let geo_sql = |filter: &GeoFilter<'_>| -> Result<Option<String>, WhereClauseError> {
+    if let Some((lat_param, lng_param)) = filter.center {
+      near.borrow_mut().push(GeoNear {
+        latitude_column: filter.latitude_column.to_string(),
+        longitude_column: filter.longitude_column.to_string(),
+        center_params: (lat_param.to_string(), lng_param.to_string()),
+      });
+    }
+
+    let Some(index) = spatial_index else {
+      return Ok(None);
+    };
+    if index.latitude_column != filter.latitude_column
+      || index.longitude_column != filter.longitude_column
+    {
+      return Ok(None);
+    }
+
+    // NOTE: The bounding box consists of validated, finite numbers and is thus safe to inline.
+    let [min_lat, min_lng, max_lat, max_lng] = filter.bbox;
+    let (lat, lng) = (&index.latitude_column, &index.longitude_column);
+    return Ok(Some(format!(
+      r#"{table_name}._rowid_ IN (SELECT "{id}" FROM {rtree} WHERE "max_{lat}" >= {min_lat} AND "min_{lat}" <= {max_lat} AND "max_{lng}" >= {min_lng} AND "min_{lng}" <= {max_lng})"#,
+      id = index.id_column,
+      rtree = index.name.escaped_string(),
+    )));
+  };
+
+  let relation_sql =
+    |column_name: &str, path: &[String]| -> Result<Option<RelatedColumn>, WhereClauseError> {
+      let Some(related) = related else {
+        return Ok(None);
+      };
+      return Ok(related(column_name, path)?.map(|r| r.sql));
+    };
+
+  let guard_sql = |column_name: &str| -> Result<Option<String>, WhereClauseError> {
+    return Ok(guards.and_then(|guards| guards(column_name)));
+  };
+
+  let json_params = filter_params.params_where(|column_name, path| {
+    if path.is_empty() {
+      return false;
+    }
+    let related_blob = related.is_some_and(|related| {
+      matches!(
+        related(column_name, path),
+        Ok(Some(RelatedFilter { blob: true, .. }))
+      )
     });
-  }
+    return !related_blob;
+  });
+  let (sql, params) = filter_params.into_sql_with_hooks(
+    Some(table_name),
+    &validator,
+    &SqlHooks {
+      match_sql: Some(&match_sql),
+      relation_sql: Some(&relation_sql),
+      geo_sql: Some(&geo_sql),
+      guard_sql: Some(&guard_sql),
+    },
+  )?;
+
+  let matches = FilterMatches {
+    fts: matches.into_inner(),
+    near: near.into_inner(),
+  };
+  let text_params: Vec<&str> = matches
+    .fts
+    .iter()
+    .map(|m| m.param.as_str())
+    .chain(json_params.iter().map(|p| p.as_str()))
+    .collect();
+  let params = to_sql_params(params, &text_params);
+
+  return Ok((
+    WhereClause {
+      clause: sql,
+      params,
+    },
+    matches,
+  ));
+}
 
+/// Converts query-string values to SQL values. Strings are interpreted as base64 encoded blobs
+/// where possible, except for parameters in `text_params`, e.g. full-text queries. Numbers are
+/// always bound as such, e.g. to compare against numeric values within JSON columns.
+fn to_sql_params(
+  params: Vec<(String, QsValue)>,
+  text_params: &[&str],
+) -> Vec<(Cow<'static, str>, trailbase_sqlite::Value)> {
   use trailbase_sqlite::Value;
-  type Param = (Cow<'static, str>, Value);
-  let sql_params: Vec<Param> = params
+
+  return params
     .into_iter()
     .map(|(name, value)| {
-      return (
-        Cow::Owned(name),
-        match value {
-          QsValue::String(s) => {
-            if let Ok(b) = BASE64_URL_SAFE.decode(&s) {
-              Value::Blob(b)
-            } else {
-              Value::Text(s)
-            }
+      let value = match value {
+        QsValue::String(s) => {
+          if text_params.contains(&name.as_str()) {
+            Value::Text(s)
+          } else if let Ok(b) = BASE64_URL_SAFE.decode(&s) {
+            Value::Blob(b)
+          } else {
+            Value::Text(s)
           }
-          QsValue::Integer(i) => Value::Integer(i),
-          QsValue::Double(d) => Value::Real(d),
-          QsValue::Bool(b) => Value::Integer(if b { 1 } else { 0 }),
-        },
-      );
+        }
+        QsValue::Integer(i) => Value::Integer(i),
+        QsValue::Double(d) => Value::Real(d),
+        QsValue::Bool(b) => Value::Integer(if b { 1 } else { 0 }),
+        // NOTE: Arrays are flattened into individual parameters by `into_sql`.
+        QsValue::Array(_) => Value::Null,
+      };
+
+      return (Cow::Owned(name), value);
     })
     .collect();
-
-  return Ok(WhereClause {
-    clause: sql,
-    params: sql_params,
-  });
 }
 
 pub fn limit_or_default(limit: Option<usize>) -> Result<usize, &'static str> {
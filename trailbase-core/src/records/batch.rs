@@ -0,0 +1,698 @@
+use axum::extract::{Json, State};
+use serde::{Deserialize, Serialize};
+use trailbase_schema::sqlite::ColumnOption;
+use trailbase_sqlite::Value;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::records::create_record::extract_record_id;
+use crate::records::files::{FileManager, delete_pending_files};
+use crate::records::history;
+use crate::records::params::{FileMetadataContents, JsonRow, Params};
+use crate::records::query_builder::{
+  DeleteQueryBuilder, InsertQueryBuilder, UpdateQueryBuilder, query_row, sql_error,
+};
+use crate::records::{Permission, RecordApi, RecordError};
+use crate::schema_metadata::TableMetadata;
+use crate::util::uuid_to_b64;
+
+/// Either a literal record id or a reference to a record created earlier in the same batch.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum RecordIdOrRef {
+  /// Index of an earlier `Create` operation within the same batch, e.g. `{"$ref": 0}`.
+  Ref {
+    #[serde(rename = "$ref")]
+    index: usize,
+  },
+  Id(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub enum Operation {
+  Create {
+    api_name: String,
+    value: serde_json::Value,
+  },
+  Update {
+    api_name: String,
+    record_id: RecordIdOrRef,
+    value: serde_json::Value,
+  },
+  Delete {
+    api_name: String,
+    record_id: RecordIdOrRef,
+  },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct BatchRequest {
+  /// Operations to be executed atomically in order. Values of foreign key and primary key fields of
+  /// the shape `{"$ref": <index>}` will be substituted with the id of the record created by the
+  /// referenced operation. Other fields, e.g. JSON columns, are taken literally.
+  pub operations: Vec<Operation>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct BatchResponse {
+  /// Safe-url base64 encoded ids of the newly created records in order of the create operations.
+  pub ids: Vec<String>,
+}
+
+enum ResolvedId {
+  Value(Value),
+  Ref(usize),
+}
+
+/// Operation with access-independent pre-processing, i.e. request parsing and param conversion,
+/// already applied.
+enum PreparedOperation {
+  Create {
+    params: Params,
+    refs: Vec<(usize, usize)>,
+  },
+  Update {
+    record_id: ResolvedId,
+    params: Params,
+    refs: Vec<(usize, usize)>,
+  },
+  Delete {
+    record_id: ResolvedId,
+  },
+}
+
+const BATCH_LIMIT: usize = 1024;
+
+/// Atomically execute a batch of create, update and delete operations across record APIs.
+#[utoipa::path(
+  post,
+  path = "/_batch",
+  request_body = BatchRequest,
+  responses(
+    (status = 200, description = "Ids of successfully created records.", body = BatchResponse),
+  )
+)]
+pub async fn batch_handler(
+  State(state): State<AppState>,
+  user: Option<User>,
+  Json(request): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>, RecordError> {
+  let BatchRequest { operations } = request;
+  if operations.is_empty() {
+    return Err(RecordError::BadRequest("no operations provided"));
+  }
+  if operations.len() > BATCH_LIMIT {
+    return Err(RecordError::BadRequest("Batch exceeds limit: 1024"));
+  }
+
+  let is_create: Vec<bool> = operations
+    .iter()
+    .map(|op| matches!(op, Operation::Create { .. }))
+    .collect();
+
+  let mut apis: Vec<RecordApi> = Vec::with_capacity(operations.len());
+  let mut prepared: Vec<PreparedOperation> = Vec::with_capacity(operations.len());
+  let mut files: FileMetadataContents = vec![];
+
+  for (index, operation) in operations.into_iter().enumerate() {
+    let resolve_ref = |r: usize| -> Result<usize, RecordError> {
+      if r >= index || !is_create[r] {
+        return Err(RecordError::BadRequest("Invalid reference"));
+      }
+      return Ok(r);
+    };
+
+    let (api_name, record_id, value, permission) = match operation {
+      Operation::Create { api_name, value } => (api_name, None, Some(value), Permission::Create),
+      Operation::Update {
+        api_name,
+        record_id,
+        value,
+      } => (api_name, Some(record_id), Some(value), Permission::Update),
+      Operation::Delete {
+        api_name,
+        record_id,
+      } => (api_name, Some(record_id), None, Permission::Delete),
+    };
+
+    let Some(api) = state.lookup_record_api(&api_name) else {
+      return Err(RecordError::ApiNotFound);
+    };
+    if !api.is_table() {
+      return Err(RecordError::ApiRequiresTable);
+    }
+
+    // Reject early based on table-level ACLs. Row-level access rules are checked within the
+    // transaction.
+    api.check_table_level_access(permission, user.as_ref())?;
+
+    let record_id = match record_id {
+      Some(RecordIdOrRef::Id(id)) => Some((ResolvedId::Value(api.id_to_sql(&id)?), Some(id))),
+      Some(RecordIdOrRef::Ref { index }) => Some((ResolvedId::Ref(resolve_ref(index)?), None)),
+      None => None,
+    };
+
+    let op = match (value, record_id) {
+      (Some(value), record_id) => {
+        let serde_json::Value::Object(mut record) = value else {
+          return Err(RecordError::BadRequest("Expected single record"));
+        };
+
+        if permission == Permission::Create && api.insert_autofill_missing_user_id_columns() {
+          if let Some(ref user) = user {
+            for column_index in api.user_id_columns() {
+              let col_name = &api.columns()[*column_index].name;
+              if !record.contains_key(col_name) {
+                record.insert(
+                  col_name.to_owned(),
+                  serde_json::Value::String(uuid_to_b64(&user.uuid)),
+                );
+              }
+            }
+          }
+        }
+
+        let Some(table) = state.schema_metadata().get_table(api.qualified_name()) else {
+          return Err(RecordError::ApiRequiresTable);
+        };
+        let mut column_refs = extract_refs(&table, &mut record, resolve_ref)?;
+
+        // For updates, inject the primary key into the request like the update handler does.
+        let record_id = match record_id {
+          Some((record_id, id_str)) => {
+            let pk_name = &api.record_pk_column().1.name;
+            let pk_value = match (&record_id, id_str) {
+              (ResolvedId::Ref(index), _) => {
+                column_refs.push((pk_name.clone(), *index));
+                serde_json::Value::Null
+              }
+              (_, Some(id_str)) => serde_json::Value::String(id_str),
+              (_, None) => unreachable!("id string present for literal ids"),
+            };
+
+            if let Some(existing) = record.insert(pk_name.clone(), pk_value.clone()) {
+              if existing != pk_value && !matches!(record_id, ResolvedId::Ref(_)) {
+                return Err(RecordError::BadRequest("primary key mismatch"));
+              }
+            }
+            Some(record_id)
+          }
+          None => None,
+        };
+
+        let mut params = Params::from(&api, record, None)
+          .map_err(|_| RecordError::BadRequest("Parameter conversion"))?;
+        files.append(&mut params.files);
+
+        // Map column references to param indexes. References to unknown columns are dropped the
+        // same way unknown fields are.
+        let refs: Vec<(usize, usize)> = column_refs
+          .into_iter()
+          .filter_map(|(col_name, r)| {
+            params
+              .column_names
+              .iter()
+              .position(|name| *name == col_name)
+              .map(|param_index| (param_index, r))
+          })
+          .collect();
+
+        match record_id {
+          Some(record_id) => PreparedOperation::Update {
+            record_id,
+            params,
+            refs,
+          },
+          None => PreparedOperation::Create { params, refs },
+        }
+      }
+      (None, Some((record_id, _))) => PreparedOperation::Delete { record_id },
+      (None, None) => unreachable!("delete requires record id"),
+    };
+
+    apis.push(api);
+    prepared.push(op);
+  }
+
+  // We're storing any files to the object store first to make sure the DB entry is valid right
+  // after commit and not racily pointing to soon-to-be-written files.
+  let mut file_manager = if files.is_empty() {
+    FileManager::empty()
+  } else {
+    FileManager::write(&state, files)
+      .await
+      .map_err(|err| RecordError::Internal(err.into()))?
+  };
+
+  let tx_apis = apis.clone();
+  let actor: Option<[u8; 16]> = user
+    .as_ref()
+    .filter(|_| apis.iter().any(|api| api.enable_history()))
+    .map(|user| user.uuid.into_bytes());
+  let result = state
+    .conn()
+    .call(move |conn| {
+      let tx = conn.transaction()?;
+      if actor.is_some() {
+        history::set_actor(&tx, actor.as_ref())?;
+      }
+
+      let result = execute_operations(&tx, &tx_apis, prepared, user.as_ref());
+      if result.is_ok() {
+        if actor.is_some() {
+          history::set_actor(&tx, None)?;
+        }
+        tx.commit()?;
+      }
+
+      return Ok(result);
+    })
+    .await?;
+
+  let BatchResult { created, rowids } = result?;
+
+  // Successful write, do not cleanup written files.
+  file_manager.release();
+
+  for (index, rowid) in rowids {
+    let api = &apis[index];
+    if api.has_file_columns() {
+      delete_pending_files(&state, api.table_name(), rowid)
+        .await
+        .map_err(|err| RecordError::Internal(err.into()))?;
+    }
+  }
+
+  return Ok(Json(BatchResponse {
+    ids: created
+      .into_iter()
+      .map(extract_record_id)
+      .collect::<Result<Vec<_>, _>>()?,
+  }));
+}
+
+struct BatchResult {
+  /// Primary key values of created records.
+  created: Vec<Value>,
+  /// (operation index, rowid) of records whose files may need cleaning up.
+  rowids: Vec<(usize, i64)>,
+}
+
+fn execute_operations(
+  conn: &rusqlite::Connection,
+  apis: &[RecordApi],
+  operations: Vec<PreparedOperation>,
+  user: Option<&User>,
+) -> Result<BatchResult, RecordError> {
+  // Primary keys of created records indexed by operation.
+  let mut pks: Vec<Option<Value>> = Vec::with_capacity(operations.len());
+  let mut result = BatchResult {
+    created: vec![],
+    rowids: vec![],
+  };
+
+  let lookup = |pks: &[Option<Value>], index: usize| -> Result<Value, RecordError> {
+    return pks
+      .get(index)
+      .and_then(|pk| pk.clone())
+      .ok_or(RecordError::BadRequest("Invalid reference"));
+  };
+
+  let resolve = |pks: &[Option<Value>], id: ResolvedId| -> Result<Value, RecordError> {
+    return match id {
+      ResolvedId::Value(value) => Ok(value),
+      ResolvedId::Ref(index) => lookup(pks, index),
+    };
+  };
+
+  for (index, (api, operation)) in std::iter::zip(apis, operations).enumerate() {
+    let (_, pk_column) = api.record_pk_column();
+
+    match operation {
+      PreparedOperation::Create { mut params, refs } => {
+        for (param_index, r) in refs {
+          params.named_params[param_index].1 = lookup(&pks, r)?;
+        }
+
+        api.check_record_level_access_with_conn(
+          conn,
+          Permission::Create,
+          None,
+          Some(&params),
+          user,
+        )?;
+
+        let (query, named_params, _files) = InsertQueryBuilder::build_insert_query(
+          api.table_name(),
+          params,
+          api.insert_conflict_resolution_strategy(),
+          Some(&pk_column.name),
+        )
+        .map_err(|err| RecordError::Internal(err.into()))?;
+
+        let (rowid, pk): (i64, Value) = query_row(conn, &query, named_params, |row| {
+          Ok((row.get(0)?, row.get(1)?))
+        })?
+        .ok_or_else(|| sql_error(rusqlite::Error::QueryReturnedNoRows))?;
+
+        result.created.push(pk.clone());
+        result.rowids.push((index, rowid));
+        pks.push(Some(pk));
+      }
+      PreparedOperation::Update {
+        record_id,
+        mut params,
+        refs,
+      } => {
+        for (param_index, r) in refs {
+          params.named_params[param_index].1 = lookup(&pks, r)?;
+        }
+        let record_id = resolve(&pks, record_id)?;
+
+        api.check_record_level_access_with_conn(
+          conn,
+          Permission::Update,
+          Some(&record_id),
+          Some(&params),
+          user,
+        )?;
+
+        // Only the primary key. Nothing to do.
+        if params.column_names.len() >= 2 {
+          let query = UpdateQueryBuilder::build_update_query(
+            api.table_name(),
+            &params.column_names,
+            &pk_column.name,
+            api.soft_delete_column(),
+          )
+          .map_err(|err| RecordError::Internal(err.into()))?;
+
+          let rowid: i64 = query_row(conn, &query, params.named_params, |row| row.get(0))?
+            .ok_or(RecordError::RecordNotFound)?;
+
+          result.rowids.push((index, rowid));
+        }
+        pks.push(None);
+      }
+      PreparedOperation::Delete { record_id } => {
+        let record_id = resolve(&pks, record_id)?;
+
+        api.check_record_level_access_with_conn(
+          conn,
+          Permission::Delete,
+          Some(&record_id),
+          None,
+          user,
+        )?;
+
+        let rowid: i64 = query_row(
+          conn,
+          &DeleteQueryBuilder::build_delete_query(
+            api.table_name(),
+            &pk_column.name,
+            api.soft_delete_column(),
+          ),
+          vec![(":__record_id", record_id)],
+          |row| row.get(0),
+        )?
+        .ok_or(RecordError::RecordNotFound)?;
+
+        result.rowids.push((index, rowid));
+        pks.push(None);
+      }
+    }
+  }
+
+  return Ok(result);
+}
+
+/// Extracts and removes `{"$ref": <index>}` values from the given record's foreign key and
+/// primary key fields replacing them with NULL placeholders. Only these can hold record ids, i.e.
+/// values of other fields, e.g. JSON objects, are left untouched.
+fn extract_refs(
+  table: &TableMetadata,
+  record: &mut JsonRow,
+  resolve_ref: impl Fn(usize) -> Result<usize, RecordError>,
+) -> Result<Vec<(String, usize)>, RecordError> {
+  let mut refs: Vec<(String, usize)> = vec![];
+  for (key, value) in record.iter_mut() {
+    let serde_json::Value::Object(obj) = value else {
+      continue;
+    };
+    if !is_key_column(table, key) {
+      continue;
+    }
+    if obj.len() != 1 {
+      continue;
+    }
+    let Some(r) = obj.get("$ref") else {
+      continue;
+    };
+    let Some(r) = r.as_u64() else {
+      return Err(RecordError::BadRequest("Invalid reference"));
+    };
+
+    refs.push((key.clone(), resolve_ref(r as usize)?));
+    *value = serde_json::Value::Null;
+  }
+
+  return Ok(refs);
+}
+
+/// Whether the given column may hold ids of records, i.e. is the primary key or a foreign key
+/// expressed either as column or table constraint.
+fn is_key_column(table: &TableMetadata, column_name: &str) -> bool {
+  if table
+    .record_pk_column
+    .is_some_and(|index| table.schema.columns[index].name == column_name)
+  {
+    return true;
+  }
+
+  let column_fk = table.schema.columns.iter().any(|c| {
+    c.name == column_name
+      && c
+        .options
+        .iter()
+        .any(|o| matches!(o, ColumnOption::ForeignKey { .. }))
+  });
+  return column_fk
+    || table
+      .schema
+      .foreign_keys
+      .iter()
+      .any(|fk| fk.columns.iter().any(|c| c == column_name));
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+  use trailbase_schema::QualifiedName;
+
+  use super::*;
+  use crate::admin::user::*;
+  use crate::app_state::*;
+  use crate::auth::api::login::login_with_password;
+  use crate::config::proto::PermissionFlag;
+  use crate::records::test_utils::*;
+  use crate::records::*;
+  use crate::util::id_to_b64;
+
+  #[tokio::test]
+  async fn test_record_api_batch() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    create_chat_message_app_tables(&state).await.unwrap();
+    let password = "Secret!1!!";
+
+    add_record_api(
+      &state,
+      "rooms_api",
+      "room",
+      Acls {
+        authenticated: vec![PermissionFlag::Create, PermissionFlag::Read],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    add_record_api(
+      &state,
+      "messages_api",
+      "message",
+      Acls {
+        authenticated: vec![
+          PermissionFlag::Create,
+          PermissionFlag::Read,
+          PermissionFlag::Update,
+          PermissionFlag::Delete,
+        ],
+        ..Default::default()
+      },
+      AccessRules {
+        // NOTE: The room must exist, which requires seeing rooms created within the same batch.
+        create: Some(
+          "_USER_.id = _REQ_._owner AND EXISTS(SELECT 1 FROM room WHERE rid = _REQ_.room)"
+            .to_string(),
+        ),
+        delete: Some("_ROW_._owner = _USER_.id".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let user_x_email = "user_x@bar.com";
+    let user_x = create_user_for_test(&state, user_x_email, password)
+      .await
+      .unwrap()
+      .into_bytes();
+    let user_x_token = login_with_password(&state, user_x_email, password)
+      .await
+      .unwrap();
+
+    let count = async |table: &str| -> i64 {
+      return conn
+        .read_query_row_f(format!("SELECT COUNT(*) FROM {table}"), (), |row| {
+          row.get(0)
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    };
+
+    {
+      // Create a room and a message referencing it, then update and delete the message.
+      let request: BatchRequest = serde_json::from_value(json!({
+        "operations": [
+          {"Create": {"api_name": "rooms_api", "value": {"name": "room0"}}},
+          {"Create": {"api_name": "messages_api", "value": {
+            "_owner": id_to_b64(&user_x),
+            "room": {"$ref": 0},
+            "data": "first",
+          }}},
+          {"Create": {"api_name": "messages_api", "value": {
+            "_owner": id_to_b64(&user_x),
+            "room": {"$ref": 0},
+            "data": "second",
+          }}},
+          {"Update": {"api_name": "messages_api", "record_id": {"$ref": 1}, "value": {
+            "data": "updated",
+          }}},
+          {"Delete": {"api_name": "messages_api", "record_id": {"$ref": 2}}},
+        ],
+      }))
+      .unwrap();
+
+      let response = batch_handler(
+        State(state.clone()),
+        User::from_auth_token(&state, &user_x_token.auth_token),
+        Json(request),
+      )
+      .await
+      .unwrap()
+      .0;
+
+      assert_eq!(3, response.ids.len());
+      assert_eq!(1, count("room").await);
+      assert_eq!(1, count("message").await);
+
+      let data: String = conn
+        .read_query_row_f("SELECT data FROM message", (), |row| row.get(0))
+        .await
+        .unwrap()
+        .unwrap();
+      assert_eq!("updated", data);
+    }
+
+    {
+      // The second create is rejected by the access rule, thus the entire batch is rolled back.
+      let request: BatchRequest = serde_json::from_value(json!({
+        "operations": [
+          {"Create": {"api_name": "rooms_api", "value": {"name": "room1"}}},
+          {"Create": {"api_name": "messages_api", "value": {
+            "_owner": id_to_b64(&uuid::Uuid::now_v7().into_bytes()),
+            "room": {"$ref": 0},
+          }}},
+        ],
+      }))
+      .unwrap();
+
+      let response = batch_handler(
+        State(state.clone()),
+        User::from_auth_token(&state, &user_x_token.auth_token),
+        Json(request),
+      )
+      .await;
+
+      assert!(
+        matches!(response, Err(RecordError::Forbidden)),
+        "{response:?}"
+      );
+      assert_eq!(1, count("room").await);
+      assert_eq!(1, count("message").await);
+    }
+
+    {
+      // Forward references are invalid.
+      let request: BatchRequest = serde_json::from_value(json!({
+        "operations": [
+          {"Delete": {"api_name": "messages_api", "record_id": {"$ref": 1}}},
+          {"Create": {"api_name": "rooms_api", "value": {"name": "room2"}}},
+        ],
+      }))
+      .unwrap();
+
+      let response = batch_handler(
+        State(state.clone()),
+        User::from_auth_token(&state, &user_x_token.auth_token),
+        Json(request),
+      )
+      .await;
+
+      assert!(
+        matches!(response, Err(RecordError::BadRequest(_))),
+        "{response:?}"
+      );
+    }
+
+    {
+      // References are only resolved for key columns, other values are taken literally.
+      let table = state
+        .schema_metadata()
+        .get_table(&QualifiedName::parse("message").unwrap())
+        .unwrap();
+      let mut record: JsonRow = serde_json::from_value(json!({
+        "room": {"$ref": 0},
+        "data": {"$ref": 0},
+      }))
+      .unwrap();
+
+      let refs = extract_refs(&table, &mut record, Ok).unwrap();
+      assert_eq!(vec![("room".to_string(), 0)], refs);
+      assert_eq!(serde_json::Value::Null, record["room"]);
+      assert_eq!(json!({"$ref": 0}), record["data"]);
+    }
+
+    {
+      // Unauthenticated users lack table-level access.
+      let request: BatchRequest = serde_json::from_value(json!({
+        "operations": [
+          {"Create": {"api_name": "rooms_api", "value": {"name": "room3"}}},
+        ],
+      }))
+      .unwrap();
+
+      let response = batch_handler(State(state.clone()), None, Json(request)).await;
+      assert!(
+        matches!(response, Err(RecordError::Forbidden)),
+        "{response:?}"
+      );
+      assert_eq!(1, count("room").await);
+    }
+  }
+}
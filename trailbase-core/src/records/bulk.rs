@@ -0,0 +1,387 @@
+use axum::extract::{Json, Path, RawQuery, State};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use trailbase_qs::Query;
+use trailbase_sqlite::{NamedParams, Value};
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::extract::Either;
+use crate::listing::{WhereClause, build_filter_where_clause_with_fts};
+use crate::records::params::{JsonRow, Params};
+use crate::records::query_builder::{DeleteQueryBuilder, UpdateQueryBuilder};
+use crate::records::{Permission, RecordApi, RecordError};
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct BulkResponse {
+  /// Number of records updated or deleted.
+  pub affected_rows: usize,
+}
+
+/// Update all records matching the given filters.
+///
+/// Accepts the same `filter` parameters as listing. Only records for which the update access rule
+/// holds are updated.
+#[utoipa::path(
+  patch,
+  path = "/:name",
+  request_body = serde_json::Value,
+  responses(
+    (status = 200, description = "Number of updated records.", body = BulkResponse)
+  )
+)]
+pub async fn bulk_update_records_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  RawQuery(raw_url_query): RawQuery,
+  user: Option<User>,
+  either_request: Either<JsonRow>,
+) -> Result<Json<BulkResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  if !api.is_table() {
+    return Err(RecordError::ApiRequiresTable);
+  }
+
+  api.check_table_level_access(Permission::Update, user.as_ref())?;
+
+  let request = match either_request {
+    Either::Json(value) => value,
+    Either::Multipart(_value, _files) => {
+      return Err(RecordError::BadRequest(
+        "Bulk updates do not support file uploads",
+      ));
+    }
+    Either::Form(value) => value,
+  };
+
+  let (_index, pk_column) = api.record_pk_column();
+  if request.contains_key(&pk_column.name) {
+    return Err(RecordError::BadRequest(
+      "Bulk updates cannot change primary keys",
+    ));
+  }
+
+  let params = Params::from(&api, request, None)
+    .map_err(|_| RecordError::BadRequest("Parameter conversion"))?;
+  if params.column_names.is_empty() {
+    return Err(RecordError::BadRequest("Nothing to update"));
+  }
+  if !params.files.is_empty() {
+    return Err(RecordError::BadRequest(
+      "Bulk updates do not support file uploads",
+    ));
+  }
+
+  let WhereClause {
+    clause: filter_clause,
+    params: filter_params,
+  } = build_bulk_filter(&api, raw_url_query.as_deref())?;
+
+  // NOTE: Binds all columns, NULL if absent, for `_REQ_` as well as `_USER_` and `_REQ_FIELDS_`.
+  let mut named_params: NamedParams =
+    api.build_named_params(Permission::Update, None, Some(&params), user.as_ref())?;
+  named_params.extend(filter_params);
+
+  let affected_rows = UpdateQueryBuilder::run_bulk(
+    &state,
+    api.table_name(),
+    api.columns(),
+    api.has_file_columns(),
+    &params.column_names,
+    api.update_access_rule().unwrap_or("TRUE"),
+    &filter_clause,
+    named_params,
+    api.history_actor(user.as_ref()),
+  )
+  .await
+  .map_err(|err| RecordError::Internal(err.into()))?;
+
+  return Ok(Json(BulkResponse { affected_rows }));
+}
+
+/// Delete all records matching the given filters.
+///
+/// Accepts the same `filter` parameters as listing. Only records for which the delete access rule
+/// holds are deleted.
+#[utoipa::path(
+  delete,
+  path = "/:name",
+  responses(
+    (status = 200, description = "Number of deleted records.", body = BulkResponse)
+  )
+)]
+pub async fn bulk_delete_records_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  RawQuery(raw_url_query): RawQuery,
+  user: Option<User>,
+) -> Result<Json<BulkResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  if !api.is_table() {
+    return Err(RecordError::ApiRequiresTable);
+  }
+
+  api.check_table_level_access(Permission::Delete, user.as_ref())?;
+
+  let WhereClause {
+    clause: filter_clause,
+    mut params,
+  } = build_bulk_filter(&api, raw_url_query.as_deref())?;
+
+  params.push((
+    Cow::Borrowed(":__user_id"),
+    user
+      .as_ref()
+      .map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+  ));
+  params.push((
+    Cow::Borrowed(":__user_roles"),
+    user
+      .as_ref()
+      .map_or(Value::Null, |u| Value::Text(u.roles_json())),
+  ));
+
+  let affected_rows = DeleteQueryBuilder::run_bulk(
+    &state,
+    api.table_name(),
+    api.soft_delete_column(),
+    api.has_file_columns(),
+    api.delete_access_rule().unwrap_or("TRUE"),
+    &filter_clause,
+    params,
+    api.history_actor(user.as_ref()),
+  )
+  .await
+  .map_err(|err| RecordError::Internal(err.into()))?;
+
+  return Ok(Json(BulkResponse { affected_rows }));
+}
+
+fn build_bulk_filter(
+  api: &RecordApi,
+  raw_url_query: Option<&str>,
+) -> Result<WhereClause, RecordError> {
+  let Query { filter, .. } = raw_url_query
+    .map_or_else(|| Ok(Query::default()), Query::parse)
+    .map_err(|_err| RecordError::BadRequest("Invalid query"))?;
+
+  // Guard against accidentally updating or deleting all records.
+  if filter.is_none() {
+    return Err(RecordError::BadRequest("Missing filter"));
+  }
+
+  // Filters on values withheld by column-level read rules never match.
+  let guards = |column_name: &str| api.column_read_rule(column_name).map(|r| format!("({r})"));
+  // NOTE: This will also drop any filters for unknown columns, thus avoiding SQL injections.
+  let (WhereClause { clause, params }, _matches) = build_filter_where_clause_with_fts(
+    "_ROW_",
+    api.columns(),
+    None,
+    None,
+    None,
+    Some(&guards),
+    filter,
+  )
+  .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
+
+  // Soft-deleted records are neither updated nor deleted again.
+  return Ok(WhereClause {
+    clause: match api.soft_delete_filter("_ROW_") {
+      Some(soft_delete_filter) => format!("({clause}) AND {soft_delete_filter}"),
+      None => clause,
+    },
+    params,
+  });
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+
+  use super::*;
+  use crate::admin::user::*;
+  use crate::app_state::*;
+  use crate::auth::api::login::login_with_password;
+  use crate::config::proto::PermissionFlag;
+  use crate::records::test_utils::*;
+  use crate::records::*;
+  use crate::util::id_to_b64;
+
+  #[tokio::test]
+  async fn test_record_api_bulk_update_and_delete() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    create_chat_message_app_tables(&state).await.unwrap();
+    let room0 = add_room(conn, "room0").await.unwrap();
+    let room1 = add_room(conn, "room1").await.unwrap();
+    let password = "Secret!1!!";
+
+    add_record_api(
+      &state,
+      "messages_api",
+      "message",
+      Acls {
+        authenticated: vec![
+          PermissionFlag::Read,
+          PermissionFlag::Update,
+          PermissionFlag::Delete,
+        ],
+        ..Default::default()
+      },
+      AccessRules {
+        // Only owners can update and delete and never to "forbidden".
+        update: Some("_ROW_._owner = _USER_.id AND _REQ_.data != 'forbidden'".to_string()),
+        delete: Some("_ROW_._owner = _USER_.id".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let user_x_email = "user_x@test.com";
+    let user_x = create_user_for_test(&state, user_x_email, password)
+      .await
+      .unwrap()
+      .into_bytes();
+    let user_x_token = login_with_password(&state, user_x_email, password)
+      .await
+      .unwrap();
+
+    let user_y_email = "user_y@test.com";
+    let user_y = create_user_for_test(&state, user_y_email, password)
+      .await
+      .unwrap()
+      .into_bytes();
+
+    for (user, room) in [
+      (user_x, room0),
+      (user_x, room0),
+      (user_x, room1),
+      (user_y, room0),
+    ] {
+      send_message(conn, user, room, "msg").await.unwrap();
+    }
+
+    let count = async |filter: &str| -> i64 {
+      return conn
+        .read_query_row_f(
+          format!("SELECT COUNT(*) FROM message WHERE {filter}"),
+          (),
+          |row| row.get(0),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    };
+
+    let room0_filter = Some(format!("filter[room]={}", id_to_b64(&room0)));
+    let user_x = || User::from_auth_token(&state, &user_x_token.auth_token);
+
+    {
+      // Only user X's messages in room0 are updated.
+      let response = bulk_update_records_handler(
+        State(state.clone()),
+        Path("messages_api".to_string()),
+        RawQuery(room0_filter.clone()),
+        user_x(),
+        Either::Json(json_row_from_value(json!({"data": "updated"})).unwrap()),
+      )
+      .await
+      .unwrap();
+
+      assert_eq!(2, response.affected_rows);
+      assert_eq!(2, count("data = 'updated'").await);
+
+      // The update access rule is evaluated against the request.
+      let response = bulk_update_records_handler(
+        State(state.clone()),
+        Path("messages_api".to_string()),
+        RawQuery(room0_filter.clone()),
+        user_x(),
+        Either::Json(json_row_from_value(json!({"data": "forbidden"})).unwrap()),
+      )
+      .await
+      .unwrap();
+
+      assert_eq!(0, response.affected_rows);
+      assert_eq!(0, count("data = 'forbidden'").await);
+    }
+
+    {
+      // Missing filters and primary keys are rejected.
+      assert!(
+        bulk_update_records_handler(
+          State(state.clone()),
+          Path("messages_api".to_string()),
+          RawQuery(None),
+          user_x(),
+          Either::Json(json_row_from_value(json!({"data": "all"})).unwrap()),
+        )
+        .await
+        .is_err()
+      );
+
+      assert!(
+        bulk_update_records_handler(
+          State(state.clone()),
+          Path("messages_api".to_string()),
+          RawQuery(room0_filter.clone()),
+          user_x(),
+          Either::Json(json_row_from_value(json!({"mid": "invalid"})).unwrap()),
+        )
+        .await
+        .is_err()
+      );
+
+      assert!(
+        bulk_delete_records_handler(
+          State(state.clone()),
+          Path("messages_api".to_string()),
+          RawQuery(None),
+          user_x(),
+        )
+        .await
+        .is_err()
+      );
+    }
+
+    {
+      // Anonymous users lack table-level access.
+      assert!(
+        bulk_delete_records_handler(
+          State(state.clone()),
+          Path("messages_api".to_string()),
+          RawQuery(room0_filter.clone()),
+          None,
+        )
+        .await
+        .is_err()
+      );
+
+      // Only user X's messages in room0 are deleted.
+      let response = bulk_delete_records_handler(
+        State(state.clone()),
+        Path("messages_api".to_string()),
+        RawQuery(room0_filter.clone()),
+        user_x(),
+      )
+      .await
+      .unwrap();
+
+      assert_eq!(2, response.affected_rows);
+      assert_eq!(2, count("TRUE").await);
+      assert_eq!(
+        1,
+        count("data = 'msg' AND room = (SELECT rid FROM room WHERE name = 'room1')").await
+      );
+    }
+  }
+}
@@ -0,0 +1,289 @@
+use itertools::Itertools;
+use log::*;
+use trailbase_schema::QualifiedName;
+use trailbase_sqlite::Connection;
+
+use crate::records::RecordApi;
+use crate::records::history::{record_snapshot_expr, snapshot_value_expr};
+use crate::records::subscribe::RecordAction;
+use crate::schema_metadata::{SchemaMetadataCache, TableMetadata};
+
+pub(crate) const CHANGE_LOG_TABLE: &str = "_change_log";
+
+/// Number of most recent changes retained for subscribers to catch up on. The log is pruned
+/// periodically, i.e. it may temporarily hold more entries.
+const CHANGE_LOG_CAPACITY: i64 = 10_000;
+
+/// Change to a record of a table exposed by a Record API with subscriptions enabled.
+#[derive(Debug)]
+pub(crate) struct ChangeLogEntry {
+  pub seq: i64,
+  pub table_name: QualifiedName,
+  pub row_id: i64,
+  pub action: RecordAction,
+  /// JSON-encoded snapshot of the record after the change or, for deletions, before, see
+  /// `snapshot_values`.
+  pub record: Option<String>,
+}
+
+/// (Re-)installs the triggers logging changes to tables exposed by Record APIs with subscriptions
+/// enabled and removes stale ones.
+///
+/// NOTE: The triggers are temporary, i.e. only log changes made through the writer connection,
+/// which allows logging changes to tables of attached databases.
+pub(crate) fn install_change_log_triggers(
+  conn: &Connection,
+  schema_metadata: &SchemaMetadataCache,
+  apis: &[(String, RecordApi)],
+) {
+  let mut sql: Vec<String> = vec![];
+  let mut tables: Vec<&QualifiedName> = vec![];
+  for (_api_name, api) in apis {
+    if !api.enable_subscriptions() || !api.is_table() || tables.contains(&api.qualified_name()) {
+      continue;
+    }
+    let Some(metadata) = schema_metadata.get_table(api.qualified_name()) else {
+      continue;
+    };
+    let column_names: Vec<&str> = metadata
+      .schema
+      .columns
+      .iter()
+      .map(|c| c.name.as_str())
+      .collect();
+
+    sql.push(build_change_log_triggers(
+      api.qualified_name(),
+      &column_names,
+    ));
+    tables.push(api.qualified_name());
+  }
+
+  conn.call_and_forget(move |conn| {
+    let install = || -> Result<(), rusqlite::Error> {
+      let tx = conn.unchecked_transaction()?;
+
+      let stale: Vec<String> = tx
+        .prepare(
+          "SELECT name FROM temp.sqlite_master WHERE type = 'trigger' AND name GLOB '__*__change_log_*_trigger'",
+        )?
+        .query_map((), |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+      for name in stale {
+        tx.execute(&format!(r#"DROP TRIGGER IF EXISTS temp."{name}""#), ())?;
+      }
+
+      for triggers in sql {
+        tx.execute_batch(&triggers)?;
+      }
+
+      return tx.commit();
+    };
+
+    if let Err(err) = install() {
+      warn!("Failed to install change log triggers: {err}");
+    }
+  });
+}
+
+fn build_change_log_triggers(table_name: &QualifiedName, column_names: &[&str]) -> String {
+  let db = table_name.database_schema.as_deref().unwrap_or("main");
+  let name = &table_name.name;
+
+  // Names are used both as string literals and as quoted identifiers.
+  let (db_literal, name_literal) = (db.replace('\'', "''"), name.replace('\'', "''"));
+  let (db_ident, name_ident) = (db.replace('"', "\"\""), name.replace('"', "\"\""));
+
+  // NOTE: Tables modified within triggers cannot be qualified. For TEMP triggers, unqualified
+  // names resolve to main unless shadowed by a temporary table.
+  let log = |action: &str, alias: &str| -> String {
+    return format!(
+      "INSERT INTO {CHANGE_LOG_TABLE} (database_schema, table_name, row_id, action, record) VALUES ('{db_literal}', '{name_literal}', {alias}._rowid_, '{action}', {record});",
+      record = record_snapshot_expr(alias, column_names),
+    );
+  };
+
+  return indoc::formatdoc!(
+    r#"
+    CREATE TEMP TRIGGER "__{db_ident}_{name_ident}__change_log_insert_trigger" AFTER INSERT ON "{db_ident}"."{name_ident}"
+      BEGIN
+        {insert}
+      END;
+
+    CREATE TEMP TRIGGER "__{db_ident}_{name_ident}__change_log_update_trigger" AFTER UPDATE ON "{db_ident}"."{name_ident}"
+      BEGIN
+        {update}
+      END;
+
+    CREATE TEMP TRIGGER "__{db_ident}_{name_ident}__change_log_delete_trigger" AFTER DELETE ON "{db_ident}"."{name_ident}"
+      BEGIN
+        {delete}
+      END;
+    "#,
+    insert = log("insert", "NEW"),
+    update = log("update", "NEW"),
+    delete = log("delete", "OLD"),
+  );
+}
+
+/// Deletes all but the most recent `CHANGE_LOG_CAPACITY` changes. Runs periodically rather than
+/// on every logged change to keep writes cheap.
+pub(crate) async fn prune_change_log(conn: &Connection) -> Result<(), trailbase_sqlite::Error> {
+  conn
+    .execute(
+      format!(
+        "DELETE FROM {CHANGE_LOG_TABLE} WHERE seq <= (SELECT MAX(seq) FROM {CHANGE_LOG_TABLE}) - {CHANGE_LOG_CAPACITY}"
+      ),
+      (),
+    )
+    .await?;
+  return Ok(());
+}
+
+/// Sequence number of the most recently logged change or zero.
+pub(crate) fn last_seq(conn: &rusqlite::Connection) -> Result<i64, rusqlite::Error> {
+  return conn.query_row(
+    &format!(
+      "SELECT COALESCE((SELECT seq FROM main.sqlite_sequence WHERE name = '{CHANGE_LOG_TABLE}'), 0)"
+    ),
+    (),
+    |row| row.get(0),
+  );
+}
+
+/// Whether changes logged after `seq` have already been truncated, i.e. cannot be replayed.
+pub(crate) fn truncated_after(
+  conn: &rusqlite::Connection,
+  seq: i64,
+  last_seq: i64,
+) -> Result<bool, rusqlite::Error> {
+  if seq > last_seq {
+    // Unknown position, e.g. from before the database got reset.
+    return Ok(true);
+  }
+
+  let first_seq: i64 = conn.query_row(
+    &format!("SELECT COALESCE(MIN(seq), $1 + 1) FROM main.{CHANGE_LOG_TABLE}"),
+    [last_seq],
+    |row| row.get(0),
+  )?;
+  return Ok(first_seq > seq + 1);
+}
+
+/// Reads changes logged after `after_seq` up to and including `until_seq` in order, optionally
+/// limited to the given table.
+pub(crate) fn read_change_log(
+  conn: &rusqlite::Connection,
+  after_seq: i64,
+  until_seq: i64,
+  table_name: Option<&QualifiedName>,
+) -> Result<Vec<ChangeLogEntry>, rusqlite::Error> {
+  let mut stmt = conn.prepare_cached(&format!(
+    "SELECT seq, database_schema, table_name, row_id, action, record FROM main.{CHANGE_LOG_TABLE} WHERE seq > $1 AND seq <= $2 AND ($3 IS NULL OR (database_schema = $3 AND table_name = $4)) ORDER BY seq"
+  ))?;
+
+  let (db, name) = match table_name {
+    Some(table_name) => (
+      Some(table_name.database_schema.as_deref().unwrap_or("main")),
+      Some(table_name.name.as_str()),
+    ),
+    None => (None, None),
+  };
+
+  let mut entries: Vec<ChangeLogEntry> = vec![];
+  let mut rows = stmt.query((after_seq, until_seq, db, name))?;
+  while let Some(row) = rows.next()? {
+    let action: String = row.get(4)?;
+    let action = match action.as_str() {
+      "insert" => RecordAction::Insert,
+      "update" => RecordAction::Update,
+      "delete" => RecordAction::Delete,
+      action => {
+        warn!("Unknown change log action: {action}");
+        continue;
+      }
+    };
+
+    entries.push(ChangeLogEntry {
+      seq: row.get(0)?,
+      table_name: QualifiedName {
+        name: row.get(2)?,
+        database_schema: Some(row.get(1)?),
+      },
+      row_id: row.get(3)?,
+      action,
+      record: row.get(5)?,
+    });
+  }
+
+  return Ok(entries);
+}
+
+/// Decodes the values of a logged record snapshot in the order of the table's columns. Columns
+/// missing from the snapshot, e.g. added since, are NULL.
+pub(crate) fn snapshot_values(
+  conn: &rusqlite::Connection,
+  metadata: &TableMetadata,
+  snapshot: &str,
+) -> Result<Vec<rusqlite::types::Value>, rusqlite::Error> {
+  let columns = &metadata.schema.columns;
+  let mut stmt = conn.prepare_cached(&format!(
+    "SELECT {}",
+    columns
+      .iter()
+      .map(|c| snapshot_value_expr(":__snapshot", &c.name))
+      .join(", "),
+  ))?;
+
+  return stmt.query_row(rusqlite::named_params! {":__snapshot": snapshot}, |row| {
+    return (0..columns.len()).map(|idx| row.get(idx)).collect();
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_change_log_triggers_escape_names() {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    conn
+      .execute_batch(&format!(
+        r#"
+        CREATE TABLE {CHANGE_LOG_TABLE} (
+          seq INTEGER PRIMARY KEY AUTOINCREMENT,
+          database_schema TEXT NOT NULL,
+          table_name TEXT NOT NULL,
+          row_id INTEGER NOT NULL,
+          action TEXT NOT NULL,
+          record TEXT
+        );
+        CREATE TABLE "it's ""quoted""" (id INTEGER PRIMARY KEY, text TEXT);
+      "#
+      ))
+      .unwrap();
+
+    let table_name = QualifiedName {
+      name: r#"it's "quoted""#.to_string(),
+      database_schema: None,
+    };
+    conn
+      .execute_batch(&build_change_log_triggers(&table_name, &["id", "text"]))
+      .unwrap();
+
+    conn
+      .execute_batch(
+        r#"
+        INSERT INTO "it's ""quoted""" (id, text) VALUES (1, 'a');
+        DELETE FROM "it's ""quoted""";
+      "#,
+      )
+      .unwrap();
+
+    let entries = read_change_log(&conn, 0, last_seq(&conn).unwrap(), Some(&table_name)).unwrap();
+    assert_eq!(2, entries.len());
+    assert_eq!(RecordAction::Delete, entries[1].action);
+    // Deletions retain the deleted record.
+    assert_eq!(Some(r#"{"id":1,"text":"a"}"#), entries[1].record.as_deref());
+  }
+}
@@ -71,7 +71,9 @@ pub(crate) fn extract_records(
 }
 
 #[inline]
-fn extract_record_id(value: rusqlite::types::Value) -> Result<String, trailbase_sqlite::Error> {
+pub(crate) fn extract_record_id(
+  value: rusqlite::types::Value,
+) -> Result<String, trailbase_sqlite::Error> {
   return match value {
     rusqlite::types::Value::Blob(blob) => Ok(BASE64_URL_SAFE.encode(blob)),
     rusqlite::types::Value::Text(text) => Ok(text),
@@ -161,6 +163,7 @@ pub async fn create_record_handler(
         &pk_column.name,
         api.has_file_columns(),
         params_list.swap_remove(0),
+        api.history_actor(user.as_ref()),
       )
       .await
       .map_err(|err| RecordError::Internal(err.into()))?;
@@ -175,6 +178,7 @@ pub async fn create_record_handler(
         &pk_column.name,
         api.has_file_columns(),
         params_list,
+        api.history_actor(user.as_ref()),
       )
       .await
       .map_err(|err| RecordError::Internal(err.into()))?;
@@ -1,12 +1,13 @@
 use axum::{
   extract::{Path, State},
-  http::StatusCode,
+  http::{HeaderMap, StatusCode},
   response::{IntoResponse, Response},
 };
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
-use crate::records::query_builder::DeleteQueryBuilder;
+use crate::records::etag::IfMatch;
+use crate::records::query_builder::{DeleteQueryBuilder, QueryError};
 use crate::records::{Permission, RecordError};
 
 /// Delete record.
@@ -14,13 +15,15 @@ use crate::records::{Permission, RecordError};
   delete,
   path = "/:name/:record",
   responses(
-    (status = 200, description = "Successful deletion.")
+    (status = 200, description = "Successful deletion."),
+    (status = 412, description = "Record changed, i.e. `If-Match` precondition failed.")
   )
 )]
 pub async fn delete_record_handler(
   State(state): State<AppState>,
   Path((api_name, record)): Path<(String, String)>,
   user: Option<User>,
+  headers: HeaderMap,
 ) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
@@ -36,16 +39,24 @@ pub async fn delete_record_handler(
     .await?;
 
   let (_index, pk_column) = api.record_pk_column();
+  let if_match = IfMatch::from_headers(&state, &api, &record_id, user.as_ref(), &headers)?;
 
   DeleteQueryBuilder::run(
     &state,
     api.table_name(),
     &pk_column.name,
     record_id,
+    api.soft_delete_column(),
     api.has_file_columns(),
+    if_match,
+    api.history_actor(user.as_ref()),
   )
   .await
-  .map_err(|err| RecordError::Internal(err.into()))?;
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    QueryError::RecordChanged => RecordError::PreconditionFailed,
+    err => RecordError::Internal(err.into()),
+  })?;
 
   return Ok((StatusCode::OK, "deleted").into_response());
 }
@@ -53,6 +64,7 @@ pub async fn delete_record_handler(
 #[cfg(test)]
 mod test {
   use axum::extract::Query;
+  use axum::http::{HeaderValue, header};
   use trailbase_sqlite::params;
 
   use super::*;
@@ -147,6 +159,40 @@ mod test {
       assert!(response.is_err());
       assert_eq!(message_exists(conn, &id).await, true);
     }
+
+    {
+      // Deletes with a stale `If-Match` precondition fail.
+      let id = add_message(&state, &user_x, &user_x_token.auth_token, &room)
+        .await
+        .unwrap();
+      let delete = async |if_match: &'static str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_MATCH, HeaderValue::from_static(if_match));
+
+        return delete_record_handler(
+          State(state.clone()),
+          Path(("messages_api".to_string(), id_to_b64(&id))),
+          User::from_auth_token(&state, &user_x_token.auth_token),
+          headers,
+        )
+        .await;
+      };
+
+      assert!(matches!(
+        delete("\"stale\"").await,
+        Err(RecordError::PreconditionFailed)
+      ));
+      assert_eq!(message_exists(conn, &id).await, true);
+
+      delete("*").await.unwrap();
+      assert_eq!(message_exists(conn, &id).await, false);
+
+      // Missing records never match.
+      assert!(matches!(
+        delete("*").await,
+        Err(RecordError::PreconditionFailed)
+      ));
+    }
   }
 
   async fn message_exists(conn: &trailbase_sqlite::Connection, id: &[u8; 16]) -> bool {
@@ -202,6 +248,7 @@ mod test {
       State(state.clone()),
       Path(("messages_api".to_string(), id_to_b64(&id))),
       User::from_auth_token(state, auth_token),
+      HeaderMap::new(),
     )
     .await?;
     return Ok(());
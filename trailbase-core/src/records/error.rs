@@ -21,6 +21,10 @@ pub enum RecordError {
   Forbidden,
   #[error("Bad request: {0}")]
   BadRequest(&'static str),
+  #[error("Precondition Failed")]
+  PreconditionFailed,
+  #[error("Conflict: {0}")]
+  Conflict(&'static str),
   #[error("Internal: {0}")]
   Internal(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -69,6 +73,8 @@ impl IntoResponse for RecordError {
       Self::RecordNotFound => (StatusCode::NOT_FOUND, None),
       Self::Forbidden => (StatusCode::FORBIDDEN, None),
       Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, Some(msg.to_string())),
+      Self::PreconditionFailed => (StatusCode::PRECONDITION_FAILED, None),
+      Self::Conflict(msg) => (StatusCode::CONFLICT, Some(msg.to_string())),
       Self::Internal(err) if cfg!(debug_assertions) => {
         (StatusCode::INTERNAL_SERVER_ERROR, Some(err.to_string()))
       }
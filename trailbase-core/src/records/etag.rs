@@ -0,0 +1,164 @@
+use axum::http::{HeaderMap, header};
+use base64::prelude::*;
+use sha2::{Digest, Sha256};
+use trailbase_sqlite::{NamedParams, Params as _, Value};
+
+use crate::AppState;
+use crate::auth::user::User;
+use crate::records::{Permission, RecordApi, RecordError};
+
+/// Key for hashing record versions into ETags.
+///
+/// NOTE: Versions may include columns withheld by column-level read rules. Keying the hash with a
+/// server secret prevents brute-forcing low-entropy values from the ETag.
+pub(crate) fn etag_key(state: &AppState) -> [u8; 32] {
+  return state.jwt().derive_key("record etag");
+}
+
+/// Derives a strong ETag from the record's version text as computed by `RecordApi::etag_expr`.
+///
+/// Projections, i.e. explicitly selected `fields`, yield distinct representations and thus
+/// distinct ETags. Only ETags of complete records can be used with `If-Match`.
+pub(crate) fn etag_from_value(
+  key: &[u8; 32],
+  value: &Value,
+  fields: Option<&[&str]>,
+) -> Result<String, RecordError> {
+  let Value::Text(version) = value else {
+    return Err(RecordError::Internal(
+      format!("expected version text, got {value:?}").into(),
+    ));
+  };
+
+  return Ok(etag_from_version(key, version, fields));
+}
+
+fn etag_from_version(key: &[u8; 32], version: &str, fields: Option<&[&str]>) -> String {
+  let mut hasher = Sha256::new();
+  hasher.update(key);
+  hasher.update(version.as_bytes());
+  if let Some(fields) = fields {
+    hasher.update(b"\0");
+    for field in fields {
+      hasher.update(field.as_bytes());
+      hasher.update(b",");
+    }
+  }
+  let digest = hasher.finalize();
+
+  return format!("\"{}\"", BASE64_URL_SAFE_NO_PAD.encode(&digest[..16]));
+}
+
+/// Checks whether `etag` matches any entity tag listed in `If-None-Match`, i.e. whether a cached
+/// representation is still valid. Uses weak comparison as per RFC 9110.
+pub(crate) fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+  return headers
+    .get_all(header::IF_NONE_MATCH)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .map(|tag| tag.trim())
+    .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+}
+
+/// `If-Match` precondition for a write to the given record.
+///
+/// To avoid lost updates, the precondition must be checked atomically with the write, i.e. on the
+/// writer within the same transaction, see `UpdateQueryBuilder::run` and `DeleteQueryBuilder::run`.
+pub(crate) struct IfMatch {
+  tags: Vec<String>,
+  query: String,
+  params: NamedParams,
+  key: [u8; 32],
+}
+
+impl IfMatch {
+  /// Returns the precondition for the given record if the request carries an `If-Match` header.
+  pub(crate) fn from_headers(
+    state: &AppState,
+    api: &RecordApi,
+    record_id: &Value,
+    user: Option<&User>,
+    headers: &HeaderMap,
+  ) -> Result<Option<Self>, RecordError> {
+    let tags: Vec<String> = headers
+      .get_all(header::IF_MATCH)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(','))
+      .map(|tag| tag.trim().to_string())
+      .collect();
+
+    if tags.is_empty() {
+      return Ok(None);
+    }
+
+    let (_index, pk_column) = api.record_pk_column();
+    let column_names: Vec<&str> = api.columns().iter().map(|c| c.name.as_str()).collect();
+    return Ok(Some(Self {
+      tags,
+      query: format!(
+        r#"SELECT {etag_expr} FROM (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_, {table_name} AS _ROW_ WHERE _ROW_."{pk}" = :__record_id"#,
+        etag_expr = api.etag_expr(&column_names),
+        table_name = api.table_name(),
+        pk = pk_column.name,
+      ),
+      params: api.build_named_params(Permission::Read, Some(record_id), None, user)?,
+      key: etag_key(state),
+    }));
+  }
+
+  /// Checks whether the record's current ETag matches any of the listed entity tags. Missing
+  /// records never match.
+  pub(crate) fn matches(&self, conn: &rusqlite::Connection) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached(&self.query)?;
+    self.params.clone().bind(&mut stmt)?;
+    let mut rows = stmt.raw_query();
+    let Some(row) = rows.next()? else {
+      return Ok(false);
+    };
+
+    let etag = etag_from_version(&self.key, &row.get::<_, String>(0)?, None);
+
+    // NOTE: If-Match requires strong comparison, i.e. weak tags never match.
+    return Ok(self.tags.iter().any(|tag| tag == "*" || *tag == etag));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::http::HeaderValue;
+
+  use super::*;
+
+  #[test]
+  fn test_etag_matching() {
+    let key = [0; 32];
+    let etag = etag_from_value(&key, &Value::Text("1".to_string()), None).unwrap();
+    assert_ne!(
+      etag,
+      etag_from_value(&key, &Value::Text("2".to_string()), None).unwrap()
+    );
+    assert_ne!(
+      etag,
+      etag_from_value(&[1; 32], &Value::Text("1".to_string()), None).unwrap()
+    );
+    assert_ne!(
+      etag,
+      etag_from_value(&key, &Value::Text("1".to_string()), Some(&["id"])).unwrap()
+    );
+    assert!(etag_from_value(&key, &Value::Integer(1), None).is_err());
+
+    let mut headers = HeaderMap::new();
+    assert!(!if_none_match(&headers, &etag));
+
+    headers.insert(
+      header::IF_NONE_MATCH,
+      HeaderValue::from_str(&format!("\"other\", W/{etag}")).unwrap(),
+    );
+    assert!(if_none_match(&headers, &etag));
+
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+    assert!(!if_none_match(&headers, &etag));
+  }
+}
@@ -0,0 +1,643 @@
+use askama::Template;
+use futures_util::future::BoxFuture;
+use itertools::Itertools;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use trailbase_qs::RelatedColumn;
+use trailbase_schema::sqlite::{Column, ColumnDataType, ColumnOption};
+use trailbase_schema::{QualifiedName, QualifiedNameEscaped};
+use trailbase_sqlite::{NamedParams, Row, Value};
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::listing::{RelatedFilter, WhereClauseError};
+use crate::records::projection::{Projection, forward_expansion_columns, split_fields};
+use crate::records::sql_to_json::row_to_json_expand;
+use crate::records::{Permission, RecordApi, RecordError};
+use crate::schema_metadata::{JsonColumnMetadata, TableMetadata};
+
+/// Max number of nested expansions, e.g. `author.org` has a depth of 2.
+pub(crate) const MAX_EXPANSION_DEPTH: usize = 3;
+
+/// Max number of records inlined per parent record for reverse, i.e. one-to-many, expansions.
+pub(crate) const MAX_REVERSE_EXPANSION_RECORDS: usize = 25;
+
+/// A single segment of an expansion path.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Relation {
+  /// Foreign key `column` on the parent referencing another table, e.g. `author`.
+  Forward { column: String },
+  /// Records in `table` whose foreign key `column` references the parent, e.g. `comment!post`.
+  Reverse { table: String, column: String },
+}
+
+impl Relation {
+  pub(crate) fn parse(segment: &str) -> Self {
+    return match segment.split_once('!') {
+      Some((table, column)) => Self::Reverse {
+        table: table.to_string(),
+        column: column.to_string(),
+      },
+      None => Self::Forward {
+        column: segment.to_string(),
+      },
+    };
+  }
+}
+
+/// Whether `path` is allowed by the `configured` expansion paths, i.e. it's either configured
+/// itself or a prefix of a configured path, e.g. `author` for `author.org`.
+pub(crate) fn is_expandable(configured: &[String], path: &str) -> bool {
+  return configured.iter().any(|c| {
+    c == path
+      || c
+        .strip_prefix(path)
+        .is_some_and(|rest| rest.starts_with('.'))
+  });
+}
+
+/// The table a record expands into, relative to its parent.
+pub(crate) struct ExpandNode {
+  relation: Relation,
+  table: Arc<TableMetadata>,
+  /// The Record API exposing `table`, whose access rules apply to expanded records.
+  api: RecordApi,
+  /// Column of `table` matched against the parent's key, i.e. the column referred to by forward
+  /// expansions or the foreign key column of reverse expansions.
+  key_column_name: String,
+  /// Map pre-filled with `Value::Null` for all configured nested forward expansions as used by
+  /// `row_to_json_expand`, i.e. their foreign keys are rendered as `{"id": <key>}`.
+  prefill: Option<HashMap<String, serde_json::Value>>,
+  /// Requested subset of columns, if any.
+  projection: Option<Projection>,
+  children: Vec<ExpandNode>,
+}
+
+impl ExpandNode {
+  fn columns(&self) -> &[Column] {
+    if let Some(ref projection) = self.projection {
+      return projection.columns();
+    }
+    return self.api.columns();
+  }
+
+  fn json_column_metadata(&self) -> &[Option<JsonColumnMetadata>] {
+    if let Some(ref projection) = self.projection {
+      return projection.json_column_metadata();
+    }
+    return self.api.json_column_metadata();
+  }
+
+  fn pk_column_name(&self) -> Result<&str, RecordError> {
+    let Some(index) = self.table.record_pk_column else {
+      return Err(RecordError::Internal("invalid PK".into()));
+    };
+    return Ok(&self.table.schema.columns[index].name);
+  }
+}
+
+/// The table being expanded from.
+pub(crate) struct ExpandParent<'a> {
+  pub table_name: &'a QualifiedName,
+  pub columns: &'a [Column],
+  pub pk_column_name: &'a str,
+}
+
+fn split_first_segment<T: AsRef<str>>(paths: &[T]) -> Vec<(&str, Option<&str>)> {
+  return paths
+    .iter()
+    .map(|path| {
+      let path = path.as_ref();
+      return match path.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (path, None),
+      };
+    })
+    .collect();
+}
+
+fn nested_paths<'a>(paths: &[(&str, Option<&'a str>)], segment: &str) -> Vec<&'a str> {
+  return paths
+    .iter()
+    .filter_map(|(first, rest)| if *first == segment { *rest } else { None })
+    .collect();
+}
+
+/// Resolves the requested expansion `paths` of records of `api` into a tree of tables to expand.
+///
+/// The `paths` must have been validated against the `configured` paths, e.g. using
+/// [is_expandable]. The `fields` of expanded records are keyed by the first segment of their
+/// expansion path, see [split_fields]. Only tables exposed by a Record API can be expanded, see
+/// `AppState::lookup_related_record_api`.
+pub(crate) fn build_expand_tree<T: AsRef<str>, C: AsRef<str>>(
+  state: &AppState,
+  api: &RecordApi,
+  parent: &ExpandParent<'_>,
+  paths: &[T],
+  configured: &[C],
+  fields: &[(&str, &str)],
+) -> Result<Vec<ExpandNode>, RecordError> {
+  let mut nodes: Vec<ExpandNode> = vec![];
+
+  let paths = split_first_segment(paths);
+  let configured = split_first_segment(configured);
+
+  // Fields can only be selected for records that are actually expanded.
+  for (first, _) in fields {
+    if !paths.iter().any(|(segment, _)| segment == first) {
+      return Err(RecordError::BadRequest("Invalid field"));
+    }
+  }
+
+  for segment in paths.iter().map(|(first, _)| *first).unique() {
+    if segment.is_empty() {
+      return Err(RecordError::BadRequest("Invalid expansion"));
+    }
+
+    let relation = Relation::parse(segment);
+    let (table, key_column_name) = match relation {
+      Relation::Forward { ref column } => {
+        let Some(column) = parent.columns.iter().find(|c| c.name == *column) else {
+          return Err(RecordError::BadRequest("Invalid expansion"));
+        };
+
+        // FIXME: This only expand FKs expressed as column constraints missing table constraints.
+        let Some(ColumnOption::ForeignKey {
+          foreign_table,
+          referred_columns,
+          ..
+        }) = column
+          .options
+          .iter()
+          .find(|o| matches!(o, ColumnOption::ForeignKey { .. }))
+        else {
+          return Err(RecordError::Internal("not a foreign key".into()));
+        };
+
+        let table = lookup_table(state, parent.table_name, foreign_table)?;
+        // Foreign keys without explicit columns refer to the primary key.
+        let key_column_name = match referred_columns.first() {
+          Some(referred_column) => referred_column.clone(),
+          None => {
+            let Some(index) = table.record_pk_column else {
+              return Err(RecordError::Internal("invalid PK".into()));
+            };
+            table.schema.columns[index].name.clone()
+          }
+        };
+
+        (table, key_column_name)
+      }
+      Relation::Reverse {
+        table: ref table_name,
+        ref column,
+      } => {
+        let table = lookup_table(state, parent.table_name, table_name)?;
+
+        let references_parent = table.schema.columns.iter().any(|c| {
+          return c.name == *column
+            && c.options.iter().any(|o| match o {
+              ColumnOption::ForeignKey {
+                foreign_table,
+                referred_columns,
+                ..
+              } => {
+                *foreign_table == parent.table_name.name
+                  && (referred_columns.is_empty() || referred_columns[0] == parent.pk_column_name)
+              }
+              _ => false,
+            });
+        });
+        if !references_parent {
+          return Err(RecordError::Internal("not a reverse foreign key".into()));
+        }
+
+        (table, column.clone())
+      }
+    };
+
+    let configured_nested = nested_paths(&configured, segment);
+    let prefill: HashMap<String, serde_json::Value> = configured_nested
+      .iter()
+      .map(|path| path.split('.').next().unwrap_or(path))
+      .filter(|first| !first.contains('!'))
+      .map(|column_name| (column_name.to_string(), serde_json::Value::Null))
+      .collect();
+
+    let Some(related_api) = state.lookup_related_record_api(api, table.name()) else {
+      return Err(RecordError::BadRequest("Expanded table not exposed"));
+    };
+    let mut node = ExpandNode {
+      relation,
+      table,
+      api: related_api,
+      key_column_name,
+      prefill: if prefill.is_empty() {
+        None
+      } else {
+        Some(prefill)
+      },
+      projection: None,
+      children: vec![],
+    };
+
+    let nested = nested_paths(&paths, segment);
+
+    let node_fields: Vec<&str> = fields
+      .iter()
+      .filter_map(|(first, rest)| if *first == segment { Some(*rest) } else { None })
+      .collect();
+    let (columns, nested_fields) = split_fields(&node_fields);
+    if !columns.is_empty() {
+      let mut required = vec![node.pk_column_name()?];
+      required.extend(forward_expansion_columns(&nested));
+
+      let projection = Projection::new(
+        node.columns(),
+        node.json_column_metadata(),
+        &columns,
+        &required,
+      )?;
+      node.projection = Some(projection);
+    }
+
+    if !nested.is_empty() || !nested_fields.is_empty() {
+      let children = build_expand_tree(
+        state,
+        api,
+        &ExpandParent {
+          table_name: node.table.name(),
+          columns: node.columns(),
+          pk_column_name: node.pk_column_name()?,
+        },
+        &nested,
+        &configured_nested,
+        &nested_fields,
+      )?;
+      node.children = children;
+    }
+
+    nodes.push(node);
+  }
+
+  return Ok(nodes);
+}
+
+fn lookup_table(
+  state: &AppState,
+  parent_table_name: &QualifiedName,
+  table_name: &str,
+) -> Result<Arc<TableMetadata>, RecordError> {
+  if table_name.starts_with("_") {
+    return Err(RecordError::BadRequest("Invalid expansion"));
+  }
+
+  // NOTE: Foreign keys cannot cross database boundaries.
+  let Some(table) = state.schema_metadata().get_table(&QualifiedName {
+    name: table_name.to_string(),
+    database_schema: parent_table_name.database_schema.clone(),
+  }) else {
+    return Err(RecordError::ApiRequiresTable);
+  };
+
+  if table.record_pk_column.is_none() {
+    return Err(RecordError::Internal("invalid PK".into()));
+  }
+
+  return Ok(table);
+}
+
+/// Resolves filters on columns of related tables, e.g. `author.org`, where `author` is a foreign
+/// key column of the parent table aliased as `parent_alias`. Returns `None` if `column_name`
+/// isn't a foreign key, e.g. for filters on JSON columns.
+///
+/// Only relations the parent's Record API allows to expand can be filtered on. Related records
+/// are subject to the read access rule of the Record API exposing the related table, same as
+/// expanded records.
+pub(crate) fn related_column_filter(
+  state: &AppState,
+  api: &RecordApi,
+  user: Option<&User>,
+  parent_alias: &str,
+  column_name: &str,
+  path: &[String],
+) -> Result<Option<RelatedFilter>, WhereClauseError> {
+  let unrecognized = |msg: &str| {
+    return Err(WhereClauseError::UnrecognizedParam(format!(
+      "{msg}: {column_name}.{path}",
+      path = path.join(".")
+    )));
+  };
+
+  let Some(column) = api.columns().iter().find(|c| c.name == column_name) else {
+    return Ok(None);
+  };
+  let Some(ColumnOption::ForeignKey {
+    foreign_table,
+    referred_columns,
+    ..
+  }) = column
+    .options
+    .iter()
+    .find(|o| matches!(o, ColumnOption::ForeignKey { .. }))
+  else {
+    return Ok(None);
+  };
+
+  if !is_expandable(api.expand_paths(), column_name) {
+    return unrecognized("Relation not expandable");
+  }
+
+  let [related_column_name] = path else {
+    return unrecognized("Nested relations cannot be filtered");
+  };
+  if related_column_name.starts_with("_") {
+    return unrecognized("Invalid related column");
+  }
+
+  let Ok(table) = lookup_table(state, api.qualified_name(), foreign_table) else {
+    return unrecognized("Invalid relation");
+  };
+  let Some(related_api) = state.lookup_related_record_api(api, table.name()) else {
+    return unrecognized("Related table not exposed");
+  };
+  if related_api
+    .check_table_level_access(Permission::Read, user)
+    .is_err()
+  {
+    return unrecognized("Forbidden relation");
+  }
+
+  let Some(related_column) = related_api
+    .columns()
+    .iter()
+    .find(|c| c.name == *related_column_name)
+  else {
+    return unrecognized("Unrecognized related column");
+  };
+
+  let key_column_name = match referred_columns.first() {
+    Some(referred_column) => referred_column.as_str(),
+    None => {
+      let Some(index) = table.record_pk_column else {
+        return unrecognized("Invalid relation");
+      };
+      table.schema.columns[index].name.as_str()
+    }
+  };
+
+  let read_access_rule = related_api.read_access_rule().unwrap_or("TRUE");
+  // Soft-deleted records are treated like inaccessible ones.
+  let read_access_clause = match related_api.soft_delete_filter("_ROW_") {
+    Some(soft_delete_filter) => format!("({read_access_rule}) AND {soft_delete_filter}"),
+    None => read_access_rule.to_string(),
+  };
+  // Filters on withheld values never match.
+  let read_access_clause = match related_api.column_read_rule(related_column_name) {
+    Some(column_read_rule) => format!("{read_access_clause} AND ({column_read_rule})"),
+    None => read_access_clause,
+  };
+
+  // NOTE: Access rules refer to the related record as `_ROW_`, thus related records are
+  // filtered in a nested scope before being matched against the parent.
+  return Ok(Some(RelatedFilter {
+    sql: RelatedColumn {
+      column: format!(r#"_REL_."{related_column_name}""#),
+      prefix: format!(
+        r#"EXISTS (SELECT 1 FROM (SELECT _ROW_.* FROM (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_, {table_name} AS _ROW_ WHERE {read_access_clause}) AS _REL_ WHERE _REL_."{key_column_name}" = {parent_alias}."{column_name}" AND "#,
+        table_name = table.name().escaped_string(),
+      ),
+      suffix: ")".to_string(),
+    },
+    blob: related_column.data_type == ColumnDataType::Blob,
+  }));
+}
+
+#[derive(Template)]
+#[template(escape = "none", path = "expand_record_query.sql")]
+struct ExpandRecordQueryTemplate<'a> {
+  table_name: &'a QualifiedNameEscaped,
+  column_names: &'a [&'a str],
+  /// Column-level read rules by column, see `RecordApi::column_read_expr`.
+  column_read_rules: &'a [Option<&'a str>],
+  read_access_clause: &'a str,
+  key_column_name: &'a str,
+  pk_column_name: &'a str,
+  num_keys: usize,
+  limit: Option<usize>,
+}
+
+/// Inlines expanded records into the given JSON `records`, which were built from `rows`.
+///
+/// Expanded records are fetched in one batch per expanded table and subject to the read access
+/// rules of the Record API exposing said table. Records the user cannot read are omitted,
+/// i.e. forward expansions will only contain the "id" and reverse expansions will skip them.
+pub(crate) fn expand_records<'a>(
+  state: &'a AppState,
+  user: Option<&'a User>,
+  nodes: &'a [ExpandNode],
+  columns: &'a [Column],
+  pk_column_name: &'a str,
+  rows: &'a [&'a Row],
+  records: &'a mut [serde_json::Value],
+) -> BoxFuture<'a, Result<(), RecordError>> {
+  return Box::pin(async move {
+    assert_eq!(rows.len(), records.len());
+
+    for node in nodes {
+      if node
+        .api
+        .check_table_level_access(Permission::Read, user)
+        .is_err()
+      {
+        continue;
+      }
+
+      // Parent column and expanded table column, whose values are matched.
+      let (parent_key_column_name, limit) = match node.relation {
+        Relation::Forward { ref column } => (column.as_str(), None),
+        Relation::Reverse { .. } => (pk_column_name, Some(MAX_REVERSE_EXPANSION_RECORDS)),
+      };
+      let Some(parent_key_index) = columns
+        .iter()
+        .position(|c| c.name == parent_key_column_name)
+      else {
+        return Err(RecordError::Internal("missing expansion key".into()));
+      };
+
+      let mut keys: Vec<Value> = vec![];
+      for row in rows {
+        let key = &row[parent_key_index];
+        if !matches!(key, Value::Null) && !keys.contains(key) {
+          keys.push(key.clone());
+        }
+      }
+      if keys.is_empty() {
+        continue;
+      }
+
+      let column_names: Vec<&str> = node.columns().iter().map(|c| c.name.as_str()).collect();
+      let column_read_rules: Vec<Option<&str>> = column_names
+        .iter()
+        .map(|name| node.api.column_read_rule(name))
+        .collect();
+      let read_access_rule = node.api.read_access_rule().unwrap_or("TRUE");
+      // Soft-deleted records are treated like inaccessible ones.
+      let read_access_clause = match node.api.soft_delete_filter("_ROW_") {
+        Some(soft_delete_filter) => {
+          Cow::Owned(format!("({read_access_rule}) AND {soft_delete_filter}"))
+        }
+        None => Cow::Borrowed(read_access_rule),
+      };
+      let query = ExpandRecordQueryTemplate {
+        table_name: &QualifiedNameEscaped::new(node.table.name()),
+        column_names: &column_names,
+        column_read_rules: &column_read_rules,
+        read_access_clause: &read_access_clause,
+        key_column_name: &node.key_column_name,
+        pk_column_name: node.pk_column_name()?,
+        num_keys: keys.len(),
+        limit,
+      }
+      .render()
+      .map_err(|err| RecordError::Internal(err.into()))?;
+
+      let mut params: NamedParams = keys
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| (Cow::Owned(format!(":__key{i}")), key))
+        .collect();
+      params.push((
+        Cow::Borrowed(":__user_id"),
+        user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+      ));
+      params.push((
+        Cow::Borrowed(":__user_roles"),
+        user.map_or(Value::Null, |u| Value::Text(u.roles_json())),
+      ));
+
+      let expanded_rows = state.conn().read_query_rows(query, params).await?;
+      let expanded_rows: Vec<&Row> = expanded_rows.iter().collect();
+
+      let mut expanded_records = expanded_rows
+        .iter()
+        .map(|row| {
+          return row_to_json_expand(
+            node.columns(),
+            node.json_column_metadata(),
+            row,
+            column_filter,
+            node.prefill.as_ref(),
+          )
+          .map_err(|err| RecordError::Internal(err.into()));
+        })
+        .collect::<Result<Vec<_>, RecordError>>()?;
+
+      if !node.children.is_empty() {
+        expand_records(
+          state,
+          user,
+          &node.children,
+          node.columns(),
+          node.pk_column_name()?,
+          &expanded_rows,
+          &mut expanded_records,
+        )
+        .await?;
+      }
+
+      // The key is selected right after the regular columns as "_key_".
+      let key_index = column_names.len();
+      for (row, record) in rows.iter().zip(records.iter_mut()) {
+        let serde_json::Value::Object(record) = record else {
+          continue;
+        };
+        let parent_key = &row[parent_key_index];
+
+        match node.relation {
+          Relation::Forward { ref column } => {
+            let Some(index) = expanded_rows
+              .iter()
+              .position(|r| r[key_index] == *parent_key)
+            else {
+              continue;
+            };
+
+            if let Some(serde_json::Value::Object(foreign)) = record.get_mut(column) {
+              foreign.insert("data".to_string(), expanded_records[index].clone());
+            }
+          }
+          Relation::Reverse {
+            ref table,
+            ref column,
+          } => {
+            let children: Vec<serde_json::Value> = expanded_rows
+              .iter()
+              .zip(&expanded_records)
+              .filter(|(r, _)| r[key_index] == *parent_key)
+              .map(|(_, child)| child.clone())
+              .collect();
+
+            record.insert(
+              format!("{table}!{column}"),
+              serde_json::Value::Array(children),
+            );
+          }
+        }
+      }
+    }
+
+    return Ok(());
+  });
+}
+
+fn column_filter(col_name: &str) -> bool {
+  return !col_name.starts_with("_");
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use trailbase_schema::sqlite::sqlite3_parse_into_statement;
+
+  fn sanitize_template(template: &str) {
+    assert!(sqlite3_parse_into_statement(template).is_ok(), "{template}");
+    assert!(!template.contains("\n\n"), "{template}");
+    assert!(!template.contains("   "), "{template}");
+  }
+
+  #[test]
+  fn test_is_expandable() {
+    let configured = vec!["author.org".to_string(), "comment!post".to_string()];
+
+    assert!(is_expandable(&configured, "author"));
+    assert!(is_expandable(&configured, "author.org"));
+    assert!(is_expandable(&configured, "comment!post"));
+    assert!(!is_expandable(&configured, "auth"));
+    assert!(!is_expandable(&configured, "org"));
+    assert!(!is_expandable(&configured, "author.org.owner"));
+  }
+
+  #[test]
+  fn test_expand_record_query_template() {
+    let table_name = QualifiedNameEscaped::new(&QualifiedName::parse("comment").unwrap());
+
+    for limit in [None, Some(MAX_REVERSE_EXPANSION_RECORDS)] {
+      let query = ExpandRecordQueryTemplate {
+        table_name: &table_name,
+        column_names: &["id", "post", "body"],
+        column_read_rules: &[None, None, Some("_USER_.id IS NOT NULL")],
+        read_access_clause: "_USER_.id IS NOT NULL",
+        key_column_name: "post",
+        pk_column_name: "id",
+        num_keys: 3,
+        limit,
+      }
+      .render()
+      .unwrap();
+
+      sanitize_template(&query);
+    }
+  }
+}
@@ -0,0 +1,166 @@
+use axum::http::{HeaderMap, header};
+use itertools::Itertools;
+use std::borrow::Cow;
+use trailbase_qs::Format;
+
+/// Streamable formats for listing records, as opposed to the default JSON `ListResponse`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ExportFormat {
+  Csv,
+  NdJson,
+}
+
+impl ExportFormat {
+  /// Negotiates the format based on the explicit `format` query parameter first and the "Accept"
+  /// header second. Returns `None` for JSON.
+  pub(crate) fn negotiate(format: Option<Format>, headers: &HeaderMap) -> Option<Self> {
+    if let Some(format) = format {
+      return match format {
+        Format::Json => None,
+        Format::Csv => Some(Self::Csv),
+        Format::NdJson => Some(Self::NdJson),
+      };
+    }
+
+    return headers
+      .get_all(header::ACCEPT)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(','))
+      .find_map(|media_type| {
+        // Strip parameters such as quality values, e.g. "text/csv;q=0.9".
+        let media_type = media_type.split(';').next().unwrap_or_default().trim();
+        return match media_type {
+          "application/json" => Some(None),
+          "text/csv" => Some(Some(Self::Csv)),
+          "application/x-ndjson" | "application/ndjson" => Some(Some(Self::NdJson)),
+          _ => None,
+        };
+      })
+      .flatten();
+  }
+
+  pub(crate) fn content_type(&self) -> &'static str {
+    return match self {
+      Self::Csv => "text/csv; charset=utf-8",
+      Self::NdJson => "application/x-ndjson",
+    };
+  }
+
+  /// Header preceding the records, i.e. the column names for CSV.
+  pub(crate) fn header(&self, column_names: &[&str]) -> Option<String> {
+    return match self {
+      Self::Csv => {
+        let mut line = column_names.iter().map(|name| csv_field(name)).join(",");
+        line.push_str("\r\n");
+        Some(line)
+      }
+      Self::NdJson => None,
+    };
+  }
+
+  /// Appends the given JSON record to `out`. For CSV, fields are ordered by `column_names`.
+  pub(crate) fn encode(
+    &self,
+    column_names: &[&str],
+    record: &serde_json::Value,
+    out: &mut String,
+  ) -> Result<(), serde_json::Error> {
+    match self {
+      Self::Csv => {
+        for (i, name) in column_names.iter().enumerate() {
+          if i > 0 {
+            out.push(',');
+          }
+
+          match record.get(name) {
+            None | Some(serde_json::Value::Null) => {}
+            Some(serde_json::Value::String(s)) => out.push_str(&csv_field(s)),
+            // Numbers and booleans never need quoting, nested objects and arrays, e.g. JSON
+            // columns or expanded foreign keys, are serialized as JSON.
+            Some(value) => out.push_str(&csv_field(&serde_json::to_string(value)?)),
+          }
+        }
+        out.push_str("\r\n");
+      }
+      Self::NdJson => {
+        out.push_str(&serde_json::to_string(record)?);
+        out.push('\n');
+      }
+    }
+    return Ok(());
+  }
+}
+
+/// Quotes fields as per RFC 4180 if they contain separators, quotes or line breaks.
+fn csv_field(field: &str) -> Cow<'_, str> {
+  if field.contains([',', '"', '\r', '\n']) {
+    return Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")));
+  }
+  return Cow::Borrowed(field);
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::http::HeaderValue;
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_format_negotiation() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(None, ExportFormat::negotiate(None, &headers));
+
+    headers.insert(
+      header::ACCEPT,
+      HeaderValue::from_static("text/csv;q=0.9, application/json"),
+    );
+    assert_eq!(
+      Some(ExportFormat::Csv),
+      ExportFormat::negotiate(None, &headers)
+    );
+    assert_eq!(
+      Some(ExportFormat::NdJson),
+      ExportFormat::negotiate(Some(Format::NdJson), &headers)
+    );
+    assert_eq!(None, ExportFormat::negotiate(Some(Format::Json), &headers));
+
+    headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+    assert_eq!(None, ExportFormat::negotiate(None, &headers));
+  }
+
+  #[test]
+  fn test_encoding() {
+    let columns = ["id", "text", "num", "obj"];
+    let record = json!({
+      "id": 1,
+      "text": "a \"quoted\", multi\nline text",
+      "obj": {"a": 1},
+    });
+
+    assert_eq!(
+      "id,text,num,obj\r\n",
+      ExportFormat::Csv.header(&columns).unwrap()
+    );
+
+    let mut csv = String::new();
+    ExportFormat::Csv
+      .encode(&columns, &record, &mut csv)
+      .unwrap();
+    assert_eq!(
+      "1,\"a \"\"quoted\"\", multi\nline text\",,\"{\"\"a\"\":1}\"\r\n",
+      csv
+    );
+
+    let mut ndjson = String::new();
+    ExportFormat::NdJson
+      .encode(&columns, &record, &mut ndjson)
+      .unwrap();
+    assert_eq!(ndjson.lines().count(), 1);
+    assert_eq!(
+      record,
+      serde_json::from_str::<serde_json::Value>(&ndjson).unwrap()
+    );
+  }
+}
@@ -9,6 +9,7 @@ use trailbase_schema::{FileUpload, FileUploads, QualifiedNameEscaped};
 use trailbase_sqlite::params;
 
 use crate::app_state::AppState;
+use crate::records::image_transform::delete_file_variants;
 use crate::records::params::FileMetadataContents;
 
 #[derive(Debug, Error)]
@@ -21,6 +22,8 @@ pub enum FileError {
   JsonSerialization(#[from] serde_json::Error),
   #[error("SQL error: {0}")]
   Sql(#[from] trailbase_sqlite::Error),
+  #[error("Image error: {0}")]
+  Image(#[from] image::ImageError),
 }
 
 pub(crate) async fn read_file_into_response(
@@ -85,6 +88,29 @@ pub(crate) async fn delete_pending_files(
   return Ok(());
 }
 
+/// Like `delete_pending_files` but for many records at once, e.g. after bulk updates or deletes.
+pub(crate) async fn delete_pending_files_bulk(
+  state: &AppState,
+  table_name: &QualifiedNameEscaped,
+  rowids: &[i64],
+) -> Result<(), FileError> {
+  if rowids.is_empty() {
+    return Ok(());
+  }
+
+  let rows: Vec<FileDeletionsDb> = state
+    .conn()
+    .read_query_values(
+      "SELECT * FROM main._file_deletions WHERE table_name = ?1 AND record_rowid IN (SELECT value FROM json_each(?2))",
+      params!(table_name.to_string(), serde_json::to_string(rowids)?),
+    )
+    .await?;
+
+  delete_pending_files_impl(state.conn(), state.objectstore(), rows).await?;
+
+  return Ok(());
+}
+
 pub(crate) async fn delete_pending_files_impl(
   conn: &trailbase_sqlite::Connection,
   store: &dyn ObjectStore,
@@ -161,6 +187,9 @@ async fn delete_file(
   store: &dyn ObjectStore,
   file: &FileUpload,
 ) -> Result<(), object_store::Error> {
+  // Delete generated variants, e.g. thumbnails, first, since a missing original ends retries.
+  delete_file_variants(store, file).await?;
+
   return store
     .delete(&object_store::path::Path::from(file.path()))
     .await;
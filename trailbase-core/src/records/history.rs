@@ -0,0 +1,838 @@
+use axum::{
+  Json,
+  extract::{Path, Query, State},
+};
+use base64::prelude::*;
+use itertools::Itertools;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use trailbase_schema::QualifiedName;
+use trailbase_sqlite::{Connection, NamedParams, Params, Value};
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::listing::limit_or_default;
+use crate::records::record_api::withhold_columns;
+use crate::records::{Permission, RecordApi, RecordError};
+use crate::schema_metadata::SchemaMetadataCache;
+use crate::util::uuid_to_b64;
+
+pub(crate) const HISTORY_TABLE: &str = "_record_history";
+const HISTORY_ACTOR_TABLE: &str = "_record_history_actor";
+/// Key of the JSON object tagging hex-encoded BLOBs in record snapshots.
+const BLOB_TAG: &str = "$blob";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RecordHistoryQuery {
+  /// Max number of revisions returned per page.
+  pub limit: Option<usize>,
+  /// Cursor to page, i.e. the `cursor` returned by the previous request.
+  pub cursor: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct RecordRevision {
+  /// Id of the revision, which can be used to revert the record.
+  pub id: i64,
+  /// UNIX timestamp of the change.
+  pub created: i64,
+  /// One of "insert", "update" or "delete".
+  pub action: String,
+  /// Url-safe Base64 encoded id of the acting user, if any.
+  pub user: Option<String>,
+  /// Record before the change. Absent for inserts.
+  pub old: Option<serde_json::Value>,
+  /// Record after the change. Absent for deletes.
+  pub new: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct RecordHistoryResponse {
+  /// Pagination cursor. Round-trip to get the next batch.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cursor: Option<i64>,
+  /// Revisions ordered from newest to oldest.
+  pub revisions: Vec<RecordRevision>,
+}
+
+/// List a record's revisions.
+///
+/// Requires the API to be configured with `enable_history` and read access to the record.
+#[utoipa::path(
+  get,
+  path = "/:name/:record/history",
+  responses(
+    (status = 200, description = "Revisions of the record.", body = RecordHistoryResponse)
+  )
+)]
+pub async fn list_record_history_handler(
+  State(state): State<AppState>,
+  Path((api_name, record)): Path<(String, String)>,
+  Query(query): Query<RecordHistoryQuery>,
+  user: Option<User>,
+) -> Result<Json<RecordHistoryResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  if !api.enable_history() {
+    return Err(RecordError::BadRequest("History not enabled"));
+  }
+
+  let record_id = api.id_to_sql(&record)?;
+
+  api
+    .check_record_level_access(Permission::Read, Some(&record_id), None, user.as_ref())
+    .await?;
+
+  let limit = limit_or_default(query.limit).map_err(RecordError::BadRequest)?;
+
+  // Column-level read rules are evaluated against each snapshot, withholding the values of columns
+  // for which they don't hold.
+  let column_read_rules: Vec<(&str, &str)> = api
+    .columns()
+    .iter()
+    .filter_map(|c| Some((c.name.as_str(), api.column_read_rule(&c.name)?)))
+    .collect();
+  let mut params: NamedParams = vec![
+    (
+      Cow::Borrowed(":__database_schema"),
+      Value::Text(
+        api
+          .qualified_name()
+          .database_schema
+          .clone()
+          .unwrap_or_else(|| "main".to_string()),
+      ),
+    ),
+    (
+      Cow::Borrowed(":__table_name"),
+      Value::Text(api.qualified_name().name.clone()),
+    ),
+    (Cow::Borrowed(":__record_id"), record_id),
+    (
+      Cow::Borrowed(":__cursor"),
+      Value::Integer(query.cursor.unwrap_or(i64::MAX)),
+    ),
+    (Cow::Borrowed(":__limit"), Value::Integer(limit as i64)),
+  ];
+  let (old_allowed, new_allowed) = if column_read_rules.is_empty() {
+    ("NULL".to_string(), "NULL".to_string())
+  } else {
+    params.push((
+      Cow::Borrowed(":__user_id"),
+      user
+        .as_ref()
+        .map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+    ));
+    params.push((
+      Cow::Borrowed(":__user_roles"),
+      user
+        .as_ref()
+        .map_or(Value::Null, |u| Value::Text(u.roles_json())),
+    ));
+    let column_names: Vec<&str> = api.columns().iter().map(|c| c.name.as_str()).collect();
+    (
+      snapshot_read_rules_expr("H.old", &column_names, &column_read_rules),
+      snapshot_read_rules_expr("H.new", &column_names, &column_read_rules),
+    )
+  };
+
+  let rows = state
+    .conn()
+    .read_query_rows(
+      format!(
+        "SELECT H.id, H.created, H.action, H.user, H.old, H.new, {old_allowed}, {new_allowed} FROM {HISTORY_TABLE} AS H WHERE H.database_schema = :__database_schema AND H.table_name = :__table_name AND H.record_id = :__record_id AND H.id < :__cursor ORDER BY H.id DESC LIMIT :__limit"
+      ),
+      params,
+    )
+    .await?;
+
+  let revisions = rows
+    .iter()
+    .map(|row| -> Result<RecordRevision, RecordError> {
+      let get = |index: usize| -> Result<Option<String>, RecordError> {
+        return row
+          .get::<Option<String>>(index)
+          .map_err(|err| RecordError::Internal(err.into()));
+      };
+      let snapshot = |index: usize| -> Result<Option<serde_json::Value>, RecordError> {
+        let Some(json) = get(index)? else {
+          return Ok(None);
+        };
+        let mut snapshot = snapshot_to_json(&api, &json)?;
+
+        if !column_read_rules.is_empty() {
+          // One flag per rule, see `snapshot_read_rules_expr`.
+          let allowed: Vec<i64> = get(index + 2)?
+            .map(|allowed| serde_json::from_str(&allowed))
+            .transpose()
+            .map_err(|err| RecordError::Internal(err.into()))?
+            .unwrap_or_default();
+          let withheld: Vec<&str> = column_read_rules
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| allowed.get(*i) != Some(&1))
+            .map(|(_, (name, _))| *name)
+            .collect();
+          withhold_columns(&mut snapshot, &withheld);
+        }
+
+        return Ok(Some(snapshot));
+      };
+
+      return Ok(RecordRevision {
+        id: row
+          .get(0)
+          .map_err(|err| RecordError::Internal(err.into()))?,
+        created: row
+          .get(1)
+          .map_err(|err| RecordError::Internal(err.into()))?,
+        action: get(2)?.unwrap_or_default(),
+        user: row
+          .get::<Option<[u8; 16]>>(3)
+          .map_err(|err| RecordError::Internal(err.into()))?
+          .map(|id| uuid_to_b64(&uuid::Uuid::from_bytes(id))),
+        old: snapshot(4)?,
+        new: snapshot(5)?,
+      });
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  return Ok(Json(RecordHistoryResponse {
+    cursor: if revisions.len() == limit {
+      revisions.last().map(|r| r.id)
+    } else {
+      None
+    },
+    revisions,
+  }));
+}
+
+/// Reverts the record of the given revision to its state as of said revision, i.e. after the
+/// change. For deletions, the record is restored to its state before the deletion.
+///
+/// The revert itself is recorded as a new revision attributed to `actor`.
+pub(crate) async fn revert_record(
+  state: &AppState,
+  table_name: &QualifiedName,
+  revision_id: i64,
+  actor: Option<&User>,
+) -> Result<(), RecordError> {
+  let Some((old, new)) = state
+    .conn()
+    .read_query_row_f(
+      format!(
+        "SELECT old, new FROM {HISTORY_TABLE} WHERE id = $1 AND database_schema = $2 AND table_name = $3"
+      ),
+      [
+        Value::Integer(revision_id),
+        Value::Text(
+          table_name
+            .database_schema
+            .clone()
+            .unwrap_or_else(|| "main".to_string()),
+        ),
+        Value::Text(table_name.name.clone()),
+      ],
+      |row| -> rusqlite::Result<(Option<String>, Option<String>)> {
+        return Ok((row.get(0)?, row.get(1)?));
+      },
+    )
+    .await?
+  else {
+    return Err(RecordError::RecordNotFound);
+  };
+
+  let Some(snapshot) = new.or(old) else {
+    return Err(RecordError::Internal("empty revision".into()));
+  };
+  let keys: Vec<String> = match serde_json::from_str::<serde_json::Value>(&snapshot) {
+    Ok(serde_json::Value::Object(map)) => map.into_iter().map(|(k, _)| k).collect(),
+    _ => return Err(RecordError::Internal("invalid revision".into())),
+  };
+
+  let Some(metadata) = state.schema_metadata().get_table(table_name) else {
+    return Err(RecordError::ApiRequiresTable);
+  };
+  let Some(pk_index) = metadata.record_pk_column else {
+    return Err(RecordError::ApiRequiresTable);
+  };
+  let pk_column = &metadata.schema.columns[pk_index].name;
+
+  // NOTE: Only restore columns, which still exist. Columns added since retain their defaults.
+  let column_names: Vec<&str> = metadata
+    .schema
+    .columns
+    .iter()
+    .map(|c| c.name.as_str())
+    .filter(|name| keys.iter().any(|k| k == name))
+    .collect();
+  if !column_names.iter().any(|name| name == pk_column) {
+    return Err(RecordError::Internal("revision misses primary key".into()));
+  }
+
+  let query = format!(
+    r#"INSERT INTO {table} ({columns}) VALUES ({values}) ON CONFLICT ("{pk_column}") DO UPDATE SET {updates}"#,
+    table = metadata.schema.name.escaped_string(),
+    columns = column_names.iter().map(|c| format!(r#""{c}""#)).join(", "),
+    values = column_names
+      .iter()
+      .map(|c| snapshot_value_expr(":__snapshot", c))
+      .join(", "),
+    updates = column_names
+      .iter()
+      .map(|c| format!(r#""{c}" = excluded."{c}""#))
+      .join(", "),
+  );
+
+  call_as(state.conn(), actor, move |conn| {
+    conn.execute(&query, rusqlite::named_params! {":__snapshot": snapshot})?;
+    return Ok(());
+  })
+  .await?;
+
+  return Ok(());
+}
+
+/// Runs `f` within a single transaction on the writer, attributing history recorded by the
+/// triggers to `actor`.
+///
+/// NOTE: The actor is set and cleared within the same transaction, i.e. it's never visible to
+/// concurrent writes.
+pub(crate) async fn call_as<T: Send + 'static>(
+  conn: &Connection,
+  actor: Option<&User>,
+  f: impl FnOnce(&rusqlite::Connection) -> Result<T, trailbase_sqlite::Error> + Send + 'static,
+) -> Result<T, trailbase_sqlite::Error> {
+  let actor: Option<[u8; 16]> = actor.map(|user| user.uuid.into_bytes());
+
+  return conn
+    .call(move |conn| {
+      let tx = conn.transaction()?;
+      if let Some(ref actor) = actor {
+        set_actor(&tx, Some(actor))?;
+      }
+      let result = f(&tx)?;
+      if actor.is_some() {
+        set_actor(&tx, None)?;
+      }
+      tx.commit()?;
+
+      return Ok(result);
+    })
+    .await;
+}
+
+/// Like `Connection::query_row_f`, however attributing recorded history to `actor`.
+pub(crate) async fn query_row_f<T: Send + 'static>(
+  conn: &Connection,
+  actor: Option<&User>,
+  sql: String,
+  params: impl Params + Send + 'static,
+  f: impl FnOnce(&rusqlite::Row<'_>) -> rusqlite::Result<T> + Send + 'static,
+) -> Result<Option<T>, trailbase_sqlite::Error> {
+  return call_as(conn, actor, move |conn| {
+    let mut stmt = conn.prepare_cached(&sql)?;
+    params.bind(&mut stmt)?;
+
+    let mut rows = stmt.raw_query();
+    if let Some(row) = rows.next()? {
+      return Ok(Some(f(row)?));
+    }
+    return Ok(None);
+  })
+  .await;
+}
+
+/// Like `Connection::write_query_rows`, however attributing recorded history to `actor`.
+pub(crate) async fn write_query_rows(
+  conn: &Connection,
+  actor: Option<&User>,
+  sql: String,
+  params: impl Params + Send + 'static,
+) -> Result<trailbase_sqlite::Rows, trailbase_sqlite::Error> {
+  return call_as(conn, actor, move |conn| {
+    let mut stmt = conn.prepare_cached(&sql)?;
+    params.bind(&mut stmt)?;
+
+    return Ok(trailbase_sqlite::Rows::from_rows(stmt.raw_query())?);
+  })
+  .await;
+}
+
+/// Sets or clears the acting user attributed to history recorded within the current transaction.
+pub(crate) fn set_actor(
+  conn: &rusqlite::Connection,
+  actor: Option<&[u8; 16]>,
+) -> Result<(), rusqlite::Error> {
+  match actor {
+    Some(actor) => conn.execute(
+      &format!("INSERT OR REPLACE INTO {HISTORY_ACTOR_TABLE} (id, user) VALUES (1, ?1)"),
+      [actor],
+    )?,
+    None => conn.execute(&format!("DELETE FROM {HISTORY_ACTOR_TABLE}"), ())?,
+  };
+  return Ok(());
+}
+
+/// (Re-)installs the triggers recording the history of tables exposed by Record APIs with history
+/// enabled and removes stale ones.
+pub(crate) fn install_history_triggers(
+  conn: &Connection,
+  schema_metadata: &SchemaMetadataCache,
+  apis: &[(String, RecordApi)],
+) {
+  let mut sql: Vec<String> = vec![];
+  let mut tables: Vec<&str> = vec![];
+  for (_api_name, api) in apis {
+    if !api.enable_history() || tables.contains(&api.qualified_name().name.as_str()) {
+      continue;
+    }
+    let Some(metadata) = schema_metadata.get_table(api.qualified_name()) else {
+      continue;
+    };
+
+    let table_name = &api.qualified_name().name;
+    let (_index, pk_column) = api.record_pk_column();
+    let column_names: Vec<&str> = metadata
+      .schema
+      .columns
+      .iter()
+      .map(|c| c.name.as_str())
+      .collect();
+
+    sql.push(build_history_triggers(
+      table_name,
+      &pk_column.name,
+      &column_names,
+    ));
+    tables.push(table_name);
+  }
+
+  conn.call_and_forget(move |conn| {
+    let install = || -> Result<(), rusqlite::Error> {
+      let tx = conn.unchecked_transaction()?;
+
+      let stale: Vec<String> = tx
+        .prepare(
+          "SELECT name FROM main.sqlite_master WHERE type = 'trigger' AND name GLOB '__*__history_*_trigger'",
+        )?
+        .query_map((), |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+      for name in stale {
+        tx.execute(&format!(r#"DROP TRIGGER IF EXISTS main."{name}""#), ())?;
+      }
+
+      for triggers in sql {
+        tx.execute_batch(&triggers)?;
+      }
+
+      return tx.commit();
+    };
+
+    if let Err(err) = install() {
+      warn!("Failed to install history triggers: {err}");
+    }
+  });
+}
+
+fn build_history_triggers(table_name: &str, pk_column: &str, column_names: &[&str]) -> String {
+  let record_json = |alias: &str| record_snapshot_expr(alias, column_names);
+  let actor = format!("(SELECT user FROM {HISTORY_ACTOR_TABLE})");
+
+  // NOTE: History is limited to the main database, see validation.
+
+  return indoc::formatdoc!(
+    r#"
+    CREATE TRIGGER main."__{table_name}__history_insert_trigger" AFTER INSERT ON "{table_name}"
+      BEGIN
+        INSERT INTO {HISTORY_TABLE} (database_schema, table_name, record_id, action, new, user) VALUES
+          ('main', '{table_name}', NEW."{pk_column}", 'insert', {new}, {actor});
+      END;
+
+    CREATE TRIGGER main."__{table_name}__history_update_trigger" AFTER UPDATE ON "{table_name}"
+      BEGIN
+        INSERT INTO {HISTORY_TABLE} (database_schema, table_name, record_id, action, old, new, user) VALUES
+          ('main', '{table_name}', NEW."{pk_column}", 'update', {old}, {new}, {actor});
+      END;
+
+    CREATE TRIGGER main."__{table_name}__history_delete_trigger" AFTER DELETE ON "{table_name}"
+      BEGIN
+        INSERT INTO {HISTORY_TABLE} (database_schema, table_name, record_id, action, old, user) VALUES
+          ('main', '{table_name}', OLD."{pk_column}", 'delete', {old}, {actor});
+      END;
+    "#,
+    old = record_json("OLD"),
+    new = record_json("NEW"),
+  );
+}
+
+/// SQL expression JSON-encoding a trigger's `OLD` or `NEW` record as a snapshot.
+pub(crate) fn record_snapshot_expr(alias: &str, column_names: &[&str]) -> String {
+  return format!(
+    "json_object({})",
+    column_names
+      .iter()
+      .map(|c| {
+        // NOTE: JSON cannot represent BLOBs, thus hex-encode them tagged as `{"$blob": <hex>}`.
+        // Column values lack the JSON subtype, i.e. TEXT is always encoded as a JSON string and
+        // objects are exclusively tagged BLOBs.
+        format!(
+          r#"'{c}', IIF(typeof({alias}."{c}") = 'blob', json_object('{BLOB_TAG}', hex({alias}."{c}")), {alias}."{c}")"#
+        )
+      })
+      .join(", ")
+  );
+}
+
+/// SQL expression extracting the given column's value from a JSON-encoded record snapshot.
+pub(crate) fn snapshot_value_expr(param: &str, column_name: &str) -> String {
+  return format!(
+    r#"IIF(json_type({param}, '$."{column_name}"') = 'object', unhex(json_extract({param}, '$."{column_name}"."{BLOB_TAG}"')), json_extract({param}, '$."{column_name}"'))"#
+  );
+}
+
+/// SQL expression evaluating the given column-level read rules against a JSON-encoded record
+/// snapshot as `_ROW_`, yielding a JSON array with one 0/1 flag per rule. Requires `:__user_id` and
+/// `:__user_roles` to be bound.
+fn snapshot_read_rules_expr(
+  snapshot: &str,
+  column_names: &[&str],
+  column_read_rules: &[(&str, &str)],
+) -> String {
+  let columns = column_names
+    .iter()
+    .map(|name| format!(r#"{} AS "{name}""#, snapshot_value_expr(snapshot, name)))
+    .join(", ");
+  let rules = column_read_rules
+    .iter()
+    .map(|(_, rule)| format!("({rule}) IS TRUE"))
+    .join(", ");
+
+  return format!(
+    "(SELECT json_array({rules}) FROM (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_, (SELECT {columns}) AS _ROW_)"
+  );
+}
+
+/// Converts a JSON-encoded record snapshot into the API's representation, i.e. dropping excluded
+/// columns and encoding BLOBs as url-safe Base64.
+fn snapshot_to_json(api: &RecordApi, snapshot: &str) -> Result<serde_json::Value, RecordError> {
+  let serde_json::Value::Object(map) =
+    serde_json::from_str(snapshot).map_err(|err| RecordError::Internal(err.into()))?
+  else {
+    return Err(RecordError::Internal("invalid revision".into()));
+  };
+
+  return Ok(serde_json::Value::Object(
+    map
+      .into_iter()
+      .filter(|(name, _)| api.columns().iter().any(|c| c.name == *name))
+      .map(|(name, value)| {
+        let value = match value {
+          serde_json::Value::Object(obj) => {
+            let blob = match obj.get(BLOB_TAG) {
+              Some(serde_json::Value::String(hex)) if obj.len() == 1 => decode_hex(hex),
+              _ => None,
+            };
+            let Some(blob) = blob else {
+              return Err(RecordError::Internal(
+                format!("invalid BLOB in revision: {name}").into(),
+              ));
+            };
+            serde_json::Value::String(BASE64_URL_SAFE.encode(blob))
+          }
+          value => value,
+        };
+        return Ok((name, value));
+      })
+      .collect::<Result<_, _>>()?,
+  ));
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+  return (0..hex.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+    .collect();
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::http::HeaderMap;
+  use serde_json::json;
+
+  use super::*;
+  use crate::admin::user::*;
+  use crate::app_state::*;
+  use crate::auth::api::login::login_with_password;
+  use crate::config::proto::{ColumnAccessRule, PermissionFlag, RecordApiConfig};
+  use crate::extract::Either;
+  use crate::records::delete_record::delete_record_handler;
+  use crate::records::test_utils::*;
+  use crate::records::update_record::update_record_handler;
+
+  #[test]
+  fn test_decode_hex() {
+    assert_eq!(Some(vec![0x01, 0xff]), decode_hex("01FF"));
+    assert_eq!(Some(vec![]), decode_hex(""));
+    assert_eq!(None, decode_hex("0"));
+    assert_eq!(None, decode_hex("zz"));
+  }
+
+  #[tokio::test]
+  async fn test_record_history() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+        CREATE TABLE data (
+          id INTEGER PRIMARY KEY,
+          text TEXT NOT NULL,
+          bytes BLOB
+        );
+        INSERT INTO data (id, text, bytes) VALUES (1, 'untracked', X'00');
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("data".to_string()),
+        acl_authenticated: [
+          PermissionFlag::Read as i32,
+          PermissionFlag::Update as i32,
+          PermissionFlag::Delete as i32,
+        ]
+        .into(),
+        enable_history: Some(true),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let email = "user@test.com";
+    let password = "Secret!1!!";
+    let user_id = create_user_for_test(&state, email, password).await.unwrap();
+    let token = login_with_password(&state, email, password).await.unwrap();
+    let user = || User::from_auth_token(&state, &token.auth_token);
+
+    let list = async || -> Vec<RecordRevision> {
+      return list_record_history_handler(
+        State(state.clone()),
+        Path(("api".to_string(), "1".to_string())),
+        Query(RecordHistoryQuery::default()),
+        user(),
+      )
+      .await
+      .unwrap()
+      .0
+      .revisions;
+    };
+
+    // Writes preceding the installation of the triggers aren't tracked.
+    assert!(list().await.is_empty());
+
+    update_record_handler(
+      State(state.clone()),
+      Path(("api".to_string(), "1".to_string())),
+      user(),
+      HeaderMap::new(),
+      Either::Json(json_row_from_value(json!({"text": "updated"})).unwrap()),
+    )
+    .await
+    .unwrap();
+
+    // Writes outside of Record APIs are recorded without user.
+    conn
+      .execute("UPDATE data SET bytes = X'01FF' WHERE id = 1", ())
+      .await
+      .unwrap();
+
+    delete_record_handler(
+      State(state.clone()),
+      Path(("api".to_string(), "1".to_string())),
+      user(),
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+
+    let revisions = list().await;
+    assert_eq!(
+      vec!["delete", "update", "update"],
+      revisions
+        .iter()
+        .map(|r| r.action.as_str())
+        .collect::<Vec<_>>()
+    );
+
+    let update = &revisions[2];
+    assert_eq!(Some(uuid_to_b64(&user_id)), update.user);
+    assert_eq!(
+      Some(json!({"id": 1, "text": "untracked", "bytes": "AA=="})),
+      update.old
+    );
+    assert_eq!(
+      Some(json!({"id": 1, "text": "updated", "bytes": "AA=="})),
+      update.new
+    );
+    assert_eq!(None, revisions[1].user);
+    assert_eq!(None, revisions[0].new);
+
+    // Revert to the state after the first update.
+    revert_record(
+      &state,
+      &QualifiedName::parse("data").unwrap(),
+      update.id,
+      user().as_ref(),
+    )
+    .await
+    .unwrap();
+
+    let (text, bytes): (String, Vec<u8>) = conn
+      .read_query_row_f("SELECT text, bytes FROM data WHERE id = 1", (), |row| {
+        Ok::<_, rusqlite::Error>((row.get(0)?, row.get(1)?))
+      })
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!("updated", text);
+    assert_eq!(vec![0x00], bytes);
+
+    let revisions = list().await;
+    assert_eq!(4, revisions.len());
+    assert_eq!("insert", revisions[0].action);
+    assert_eq!(Some(uuid_to_b64(&user_id)), revisions[0].user);
+
+    // Revisions are keyed by database, too.
+    assert!(matches!(
+      revert_record(
+        &state,
+        &QualifiedName {
+          name: "data".to_string(),
+          database_schema: Some("other".to_string()),
+        },
+        update.id,
+        user().as_ref(),
+      )
+      .await,
+      Err(RecordError::RecordNotFound)
+    ));
+
+    // Text resembling a tagged BLOB is retained as text.
+    let blob_like = format!(r#"{{"{BLOB_TAG}":"00"}}"#);
+    conn
+      .execute(
+        "UPDATE data SET text = $1 WHERE id = 1",
+        [Value::Text(blob_like.clone())],
+      )
+      .await
+      .unwrap();
+    let revisions = list().await;
+    assert_eq!(
+      Some(&json!(blob_like)),
+      revisions[0].new.as_ref().and_then(|new| new.get("text"))
+    );
+
+    // Malformed BLOBs are rejected rather than silently emptied.
+    conn
+      .execute(
+        format!(
+          "UPDATE {HISTORY_TABLE} SET new = json_object('id', 1, 'bytes', json_object('{BLOB_TAG}', 'zz')) WHERE id = (SELECT MAX(id) FROM {HISTORY_TABLE})"
+        ),
+        (),
+      )
+      .await
+      .unwrap();
+    assert!(matches!(
+      list_record_history_handler(
+        State(state.clone()),
+        Path(("api".to_string(), "1".to_string())),
+        Query(RecordHistoryQuery::default()),
+        user(),
+      )
+      .await,
+      Err(RecordError::Internal(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn test_record_history_column_read_rules() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+        CREATE TABLE data (
+          id INTEGER PRIMARY KEY,
+          public INTEGER NOT NULL,
+          secret TEXT
+        );
+        INSERT INTO data (id, public, secret) VALUES (1, 0, 'hidden');
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("data".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        enable_history: Some(true),
+        column_access_rules: vec![ColumnAccessRule {
+          column: Some("secret".to_string()),
+          read_rule: Some("_ROW_.public = 1".to_string()),
+          ..Default::default()
+        }],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    conn
+      .execute(
+        "UPDATE data SET public = 1, secret = 'shown' WHERE id = 1",
+        (),
+      )
+      .await
+      .unwrap();
+
+    let revisions = list_record_history_handler(
+      State(state.clone()),
+      Path(("api".to_string(), "1".to_string())),
+      Query(RecordHistoryQuery::default()),
+      None,
+    )
+    .await
+    .unwrap()
+    .0
+    .revisions;
+
+    // The rule is evaluated against each snapshot rather than the current record.
+    assert_eq!(1, revisions.len());
+    assert_eq!(
+      Some(json!({"id": 1, "public": 0, "secret": null})),
+      revisions[0].old
+    );
+    assert_eq!(
+      Some(json!({"id": 1, "public": 1, "secret": "shown"})),
+      revisions[0].new
+    );
+  }
+}
@@ -0,0 +1,417 @@
+use axum::body::Body;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use object_store::ObjectStore;
+use object_store::path::Path;
+use serde::Deserialize;
+use std::io::Cursor;
+use tokio::sync::Semaphore;
+use trailbase_schema::FileUpload;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::records::RecordError;
+use crate::records::files::FileError;
+
+/// Upper bound for requested dimensions.
+const MAX_DIMENSION: u32 = 4096;
+
+/// Dimensions variants are generated for. Requested dimensions are rounded up to the next one to
+/// bound the number of variants per image.
+const DIMENSIONS: [u32; 22] = [
+  16,
+  32,
+  48,
+  64,
+  96,
+  128,
+  160,
+  192,
+  256,
+  320,
+  384,
+  512,
+  640,
+  768,
+  1024,
+  1280,
+  1536,
+  1920,
+  2048,
+  2560,
+  3072,
+  MAX_DIMENSION,
+];
+
+/// Upper bound for concurrently generated variants, since decoding and resizing are memory and
+/// CPU intensive.
+const MAX_CONCURRENT_TRANSFORMS: usize = 4;
+
+static TRANSFORM_PERMITS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_TRANSFORMS);
+
+/// Upper bound for dimensions of original images to be decoded.
+const MAX_SOURCE_DIMENSION: u32 = 16384;
+
+/// Object store prefix under which generated variants are stored, i.e.
+/// `_variants/<file id>/<variant>`. Variants are deleted together with their original.
+const VARIANTS_PREFIX: &str = "_variants";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+  /// Scale to fit within the given bounds preserving the aspect ratio.
+  #[default]
+  Contain,
+  /// Scale preserving the aspect ratio and crop to fill the given bounds.
+  Cover,
+  /// Stretch to the given bounds.
+  Fill,
+}
+
+impl ImageFit {
+  fn as_str(&self) -> &'static str {
+    return match self {
+      Self::Contain => "contain",
+      Self::Cover => "cover",
+      Self::Fill => "fill",
+    };
+  }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageOutputFormat {
+  Webp,
+  Png,
+  Jpeg,
+}
+
+impl ImageOutputFormat {
+  /// Format matching the original's content type, falling back to PNG.
+  fn from_content_type(content_type: Option<&str>) -> Self {
+    return match content_type {
+      Some("image/webp") => Self::Webp,
+      Some("image/jpeg") => Self::Jpeg,
+      _ => Self::Png,
+    };
+  }
+
+  fn image_format(&self) -> ImageFormat {
+    return match self {
+      Self::Webp => ImageFormat::WebP,
+      Self::Png => ImageFormat::Png,
+      Self::Jpeg => ImageFormat::Jpeg,
+    };
+  }
+
+  fn extension(&self) -> &'static str {
+    return match self {
+      Self::Webp => "webp",
+      Self::Png => "png",
+      Self::Jpeg => "jpg",
+    };
+  }
+
+  fn content_type(&self) -> &'static str {
+    return match self {
+      Self::Webp => "image/webp",
+      Self::Png => "image/png",
+      Self::Jpeg => "image/jpeg",
+    };
+  }
+}
+
+/// Query parameters for serving resized or re-encoded variants of images stored in file columns,
+/// e.g. `?width=128&height=128&fit=cover&format=webp`.
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct ImageTransformQuery {
+  /// Width in pixels. If only one of width and height is given, the aspect ratio is preserved.
+  pub width: Option<u32>,
+  /// Height in pixels.
+  pub height: Option<u32>,
+  /// How to fit the image into the given width and height. Defaults to `contain`.
+  #[param(inline)]
+  pub fit: Option<ImageFit>,
+  /// Output format. Defaults to the original's format, if supported, or PNG otherwise.
+  #[param(inline)]
+  pub format: Option<ImageOutputFormat>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ImageTransform {
+  width: Option<u32>,
+  height: Option<u32>,
+  fit: ImageFit,
+  format: Option<ImageOutputFormat>,
+}
+
+impl ImageTransformQuery {
+  /// Returns the requested transform or None to serve the original.
+  pub(crate) fn transform(&self) -> Result<Option<ImageTransform>, RecordError> {
+    if self.width.is_none() && self.height.is_none() && self.format.is_none() {
+      return Ok(None);
+    }
+
+    if [self.width, self.height]
+      .into_iter()
+      .flatten()
+      .any(|d| d == 0 || d > MAX_DIMENSION)
+    {
+      return Err(RecordError::BadRequest("Invalid image dimensions"));
+    }
+
+    return Ok(Some(ImageTransform {
+      width: self.width.map(snap_dimension),
+      height: self.height.map(snap_dimension),
+      fit: self.fit.unwrap_or_default(),
+      format: self.format,
+    }));
+  }
+}
+
+/// Responds with the requested variant of the given image. Variants are generated on first
+/// request and cached in the object store.
+pub(crate) async fn read_transformed_file_into_response(
+  store: &dyn ObjectStore,
+  file_upload: &FileUpload,
+  transform: ImageTransform,
+) -> Result<Response, FileError> {
+  let format = transform
+    .format
+    .unwrap_or_else(|| ImageOutputFormat::from_content_type(file_upload.content_type()));
+  let path = variant_path(file_upload, &transform, format);
+
+  let contents: bytes::Bytes = match store.get(&path).await {
+    Ok(result) => result.bytes().await?,
+    Err(object_store::Error::NotFound { .. }) => {
+      let _permit = TRANSFORM_PERMITS
+        .acquire()
+        .await
+        .map_err(std::io::Error::other)?;
+
+      let original = store
+        .get(&Path::from(file_upload.path()))
+        .await?
+        .bytes()
+        .await?;
+
+      let contents: bytes::Bytes =
+        tokio::task::spawn_blocking(move || transform_image(&original, &transform, format))
+          .await
+          .map_err(std::io::Error::other)??
+          .into();
+
+      // NOTE: Concurrent first requests may both generate the variant. Last write wins.
+      store.put(&path, contents.clone().into()).await?;
+
+      contents
+    }
+    Err(err) => {
+      return Err(err.into());
+    }
+  };
+
+  return Ok(
+    (
+      [
+        (header::CONTENT_TYPE, format.content_type()),
+        (header::CONTENT_DISPOSITION, "attachment"),
+      ],
+      Body::from(contents),
+    )
+      .into_response(),
+  );
+}
+
+/// Deletes all generated variants of the given file.
+pub(crate) async fn delete_file_variants(
+  store: &dyn ObjectStore,
+  file_upload: &FileUpload,
+) -> Result<(), object_store::Error> {
+  use futures_util::TryStreamExt;
+
+  let prefix = Path::from(format!("{VARIANTS_PREFIX}/{}", file_upload.path()));
+  let variants: Vec<_> = store.list(Some(&prefix)).try_collect().await?;
+  for variant in variants {
+    store.delete(&variant.location).await?;
+  }
+
+  return Ok(());
+}
+
+/// Rounds the given dimension up to the next supported one.
+fn snap_dimension(d: u32) -> u32 {
+  return DIMENSIONS
+    .into_iter()
+    .find(|size| *size >= d)
+    .unwrap_or(MAX_DIMENSION);
+}
+
+fn variant_path(
+  file_upload: &FileUpload,
+  transform: &ImageTransform,
+  format: ImageOutputFormat,
+) -> Path {
+  let dimension = |d: Option<u32>| d.map_or_else(|| "auto".to_string(), |d| d.to_string());
+
+  return Path::from(format!(
+    "{VARIANTS_PREFIX}/{id}/{width}x{height}_{fit}.{extension}",
+    id = file_upload.path(),
+    width = dimension(transform.width),
+    height = dimension(transform.height),
+    fit = transform.fit.as_str(),
+    extension = format.extension(),
+  ));
+}
+
+fn transform_image(
+  data: &[u8],
+  transform: &ImageTransform,
+  format: ImageOutputFormat,
+) -> Result<Vec<u8>, image::ImageError> {
+  const FILTER: FilterType = FilterType::CatmullRom;
+
+  let mut limits = Limits::default();
+  limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+  limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+
+  let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+  reader.limits(limits);
+  let image = reader.decode()?;
+
+  let image = match (transform.width, transform.height) {
+    (Some(width), Some(height)) => match transform.fit {
+      ImageFit::Contain => image.resize(width, height, FILTER),
+      ImageFit::Cover => image.resize_to_fill(width, height, FILTER),
+      ImageFit::Fill => image.resize_exact(width, height, FILTER),
+    },
+    (Some(width), None) => image.resize(width, u32::MAX, FILTER),
+    (None, Some(height)) => image.resize(u32::MAX, height, FILTER),
+    (None, None) => image,
+  };
+
+  // JPEG has no alpha channel and the WebP encoder only supports 8-bit RGB(A).
+  let image = match format {
+    ImageOutputFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+    ImageOutputFormat::Webp if image.color().has_alpha() => {
+      DynamicImage::ImageRgba8(image.to_rgba8())
+    }
+    ImageOutputFormat::Webp => DynamicImage::ImageRgb8(image.to_rgb8()),
+    ImageOutputFormat::Png => image,
+  };
+
+  let mut buffer = Cursor::new(Vec::<u8>::new());
+  image.write_to(&mut buffer, format.image_format())?;
+
+  return Ok(buffer.into_inner());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = DynamicImage::ImageRgba8(image::RgbaImage::new(width, height));
+    let mut buffer = Cursor::new(Vec::<u8>::new());
+    image.write_to(&mut buffer, ImageFormat::Png).unwrap();
+    return buffer.into_inner();
+  }
+
+  fn dimensions(data: &[u8]) -> (u32, u32) {
+    let image = image::load_from_memory(data).unwrap();
+    return (image.width(), image.height());
+  }
+
+  #[test]
+  fn test_transform_query() {
+    assert_eq!(None, ImageTransformQuery::default().transform().unwrap());
+    assert_eq!(
+      None,
+      ImageTransformQuery {
+        fit: Some(ImageFit::Cover),
+        ..Default::default()
+      }
+      .transform()
+      .unwrap()
+    );
+    assert!(
+      ImageTransformQuery {
+        width: Some(0),
+        ..Default::default()
+      }
+      .transform()
+      .is_err()
+    );
+    assert!(
+      ImageTransformQuery {
+        height: Some(MAX_DIMENSION + 1),
+        ..Default::default()
+      }
+      .transform()
+      .is_err()
+    );
+
+    // Dimensions are rounded up to supported ones.
+    let transform = ImageTransformQuery {
+      width: Some(100),
+      height: Some(MAX_DIMENSION),
+      ..Default::default()
+    }
+    .transform()
+    .unwrap()
+    .unwrap();
+    assert_eq!(Some(128), transform.width);
+    assert_eq!(Some(MAX_DIMENSION), transform.height);
+    assert_eq!(16, snap_dimension(1));
+  }
+
+  #[test]
+  fn test_transform_image() {
+    let original = png(40, 20);
+    let transform = |width: Option<u32>,
+                     height: Option<u32>,
+                     fit: ImageFit,
+                     format: ImageOutputFormat|
+     -> (u32, u32) {
+      let data = transform_image(
+        &original,
+        &ImageTransform {
+          width,
+          height,
+          fit,
+          format: Some(format),
+        },
+        format,
+      )
+      .unwrap();
+
+      assert_eq!(Some(format.image_format()), image::guess_format(&data).ok());
+      return dimensions(&data);
+    };
+
+    use ImageFit::*;
+    use ImageOutputFormat::*;
+
+    assert_eq!((20, 10), transform(Some(20), None, Contain, Png));
+    assert_eq!((20, 10), transform(None, Some(10), Contain, Jpeg));
+    assert_eq!((10, 5), transform(Some(10), Some(10), Contain, Webp));
+    assert_eq!((10, 10), transform(Some(10), Some(10), Cover, Png));
+    assert_eq!((10, 10), transform(Some(10), Some(10), Fill, Png));
+    assert_eq!((40, 20), transform(None, None, Contain, Jpeg));
+
+    assert!(
+      transform_image(
+        &[0, 1, 2, 3],
+        &ImageTransform {
+          width: Some(10),
+          height: None,
+          fit: Contain,
+          format: None,
+        },
+        Png
+      )
+      .is_err()
+    );
+  }
+}
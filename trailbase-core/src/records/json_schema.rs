@@ -6,11 +6,15 @@ use trailbase_schema::json_schema::{
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
+use crate::records::projection::{Projection, split_fields};
 use crate::records::{Permission, RecordApi, RecordError};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct JsonSchemaQuery {
   pub mode: Option<JsonSchemaMode>,
+  /// Comma separated list of selected columns, see the `fields` parameter of read and list.
+  /// Only applies to `Select` mode.
+  pub fields: Option<String>,
 }
 
 /// Retrieve json schema associated with given record api.
@@ -35,32 +39,81 @@ pub async fn json_schema_handler(
     .check_record_level_access(Permission::Schema, None, None, user.as_ref())
     .await?;
 
-  return Ok(Json(build_api_json_schema(&state, &api, request.mode)?));
+  let fields: Vec<&str> = match request.fields {
+    Some(ref fields) if !fields.is_empty() => fields.split(",").collect(),
+    Some(_) | None => vec![],
+  };
+
+  return Ok(Json(build_api_json_schema_with_fields(
+    &state,
+    &api,
+    request.mode,
+    &fields,
+  )?));
 }
 
 pub fn build_api_json_schema(
   state: &AppState,
   api: &RecordApi,
   mode: Option<JsonSchemaMode>,
+) -> Result<serde_json::Value, RecordError> {
+  return build_api_json_schema_with_fields(state, api, mode, &[]);
+}
+
+/// Builds the JSON schema for records projected onto the given `fields`, e.g. `["id", "title",
+/// "author.name"]`, with nested fields applying to expanded records.
+pub(crate) fn build_api_json_schema_with_fields(
+  state: &AppState,
+  api: &RecordApi,
+  mode: Option<JsonSchemaMode>,
+  fields: &[&str],
 ) -> Result<serde_json::Value, RecordError> {
   let mode = mode.unwrap_or(JsonSchemaMode::Insert);
 
-  if let (Some(config_expand), JsonSchemaMode::Select) = (api.expand(), mode) {
+  if !matches!(mode, JsonSchemaMode::Select) {
+    let (_schema, json) = build_json_schema(api.api_name(), api.columns(), mode)
+      .map_err(|err| RecordError::Internal(err.into()))?;
+    return Ok(json);
+  }
+
+  // Foreign keys with nested fields are retained, akin to requested forward expansions.
+  let (root_fields, nested_fields) = split_fields(fields);
+  let expanded: Vec<&str> = nested_fields.iter().map(|(segment, _)| *segment).collect();
+  let projection = Projection::from_api(api, &root_fields, &expanded)?;
+  let columns = projection
+    .as_ref()
+    .map_or(api.columns(), |projection| projection.columns());
+
+  if !api.expand_paths().is_empty() {
     let all_tables = state.schema_metadata().tables();
-    let foreign_key_columns = config_expand.keys().map(|k| k.as_str()).collect::<Vec<_>>();
     let expand = Expand {
       tables: &all_tables,
-      foreign_key_columns,
+      paths: api.expand_paths().iter().map(|p| p.as_str()).collect(),
+      fields: fields.to_vec(),
     };
 
-    let (_schema, json) =
-      build_json_schema_expanded(api.api_name(), api.columns(), mode, Some(expand))
+    let (_schema, mut json) =
+      build_json_schema_expanded(api.api_name(), columns, mode, Some(expand))
         .map_err(|err| RecordError::Internal(err.into()))?;
+    unrequire_withheld_columns(api, &mut json);
     return Ok(json);
   }
 
-  let (_schema, json) = build_json_schema(api.api_name(), api.columns(), mode)
+  let (_schema, mut json) = build_json_schema(api.api_name(), columns, mode)
     .map_err(|err| RecordError::Internal(err.into()))?;
+  unrequire_withheld_columns(api, &mut json);
 
   return Ok(json);
 }
+
+/// Values of columns with column-level read rules may be withheld, i.e. returned as NULL, and are
+/// therefore never required.
+fn unrequire_withheld_columns(api: &RecordApi, json: &mut serde_json::Value) {
+  if let Some(serde_json::Value::Array(required)) = json.get_mut("required") {
+    required.retain(|column| {
+      column
+        .as_str()
+        .is_none_or(|column| api.column_read_rule(column).is_none())
+    });
+  }
+}
@@ -3,29 +3,40 @@ use aes_gcm::{Aes256Gcm, Key};
 use askama::Template;
 use axum::{
   Json,
+  body::Body,
   extract::{Path, RawQuery, State},
+  http::{HeaderMap, header},
+  response::{IntoResponse, Response},
 };
 use base64::prelude::*;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use rand::RngCore;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::convert::TryInto;
-use trailbase_qs::{Cursor, CursorType, OrderPrecedent, Query};
+use trailbase_qs::{AggregateFunction, Aggregation, Nearest, OrderPrecedent, Query, VectorMetric};
 use trailbase_schema::QualifiedNameEscaped;
-use trailbase_schema::sqlite::ColumnDataType;
+use trailbase_schema::sqlite::Column;
 use trailbase_sqlite::Value;
+use trailbase_sqlite::rows::value_to_json;
+use utoipa::ToSchema;
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
-use crate::listing::{WhereClause, build_filter_where_clause, limit_or_default};
-use crate::records::query_builder::{ExpandedTable, expand_tables};
-use crate::records::sql_to_json::{row_to_json, row_to_json_expand, rows_to_json_expand};
-use crate::records::{Permission, RecordError};
+use crate::listing::{WhereClause, build_filter_where_clause_with_fts, limit_or_default};
+use crate::records::expand::{
+  ExpandParent, build_expand_tree, expand_records, is_expandable, related_column_filter,
+};
+use crate::records::export::ExportFormat;
+use crate::records::projection::{Projection, split_fields};
+use crate::records::soft_delete::soft_delete_filter;
+use crate::records::sql_to_json::row_to_json_expand;
+use crate::records::{Permission, RecordApi, RecordError};
+use crate::schema_metadata::JsonColumnMetadata;
 
 /// JSON response containing the listed records.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ListResponse {
   /// Pagination cursor. Round-trip to get the next batch.
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -42,16 +53,23 @@ pub struct ListResponse {
 struct ListRecordQueryTemplate<'a> {
   table_name: &'a QualifiedNameEscaped,
   column_names: &'a [&'a str],
+  /// Column-level read rules by column, see `RecordApi::column_read_expr`.
+  column_read_rules: &'a [Option<&'a str>],
+  cursor_columns: &'a [String],
   read_access_clause: &'a str,
   filter_clause: &'a str,
   cursor_clause: Option<&'a str>,
   order_clause: &'a str,
-  expanded_tables: &'a [ExpandedTable],
+  fts_clause: Option<&'a str>,
   count: bool,
   offset: bool,
 }
 
 /// Lists records matching the given filters
+///
+/// Responds with JSON by default. Records can also be streamed as CSV or newline-delimited JSON
+/// by either passing `format=csv|ndjson` or setting the "Accept" header to `text/csv` or
+/// `application/x-ndjson`, respectively.
 #[utoipa::path(
   get,
   path = "/:name",
@@ -64,7 +82,8 @@ pub async fn list_records_handler(
   Path(api_name): Path<String>,
   RawQuery(raw_url_query): RawQuery,
   user: Option<User>,
-) -> Result<Json<ListResponse>, RecordError> {
+  headers: HeaderMap,
+) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
   };
@@ -81,9 +100,16 @@ pub async fn list_records_handler(
     cursor,
     count,
     expand: query_expand,
+    fields,
     order,
     filter: filter_params,
     offset,
+    aggregate,
+    group_by,
+    format,
+    include_deleted,
+    nearest,
+    snapshot: _,
   } = raw_url_query
     .as_ref()
     .map_or_else(|| Ok(Query::default()), |query| Query::parse(query))
@@ -91,139 +117,330 @@ pub async fn list_records_handler(
       return RecordError::BadRequest("Invalid query");
     })?;
 
+  if aggregate.is_some() || group_by.is_some() {
+    return Err(RecordError::BadRequest(
+      "Aggregations are only supported by the aggregate endpoint",
+    ));
+  }
+
   // NOTE: We're using the read access rule to filter accessible rows as opposed to blocking access
   // early as we do for READs.
   let read_access_clause: &str = api.read_access_rule().unwrap_or("TRUE");
 
+  // Filters on related columns, e.g. `author.org`, for configured expansions.
+  let related = |column_name: &str, path: &[String]| {
+    return related_column_filter(&state, &api, user.as_ref(), "_ROW_", column_name, path);
+  };
+  // Filters on withheld values never match.
+  let guards = |column_name: &str| api.column_read_rule(column_name).map(|r| format!("({r})"));
+
   // Where clause contains column filters and cursor depending on what's present.
   // NOTE: This will also drop any filters for unknown columns, thus avoiding SQL injections.
-  let WhereClause {
-    clause: filter_clause,
-    mut params,
-  } = build_filter_where_clause("_ROW_", api.columns(), filter_params)
-    .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
+  let (
+    WhereClause {
+      clause: filter_clause,
+      mut params,
+    },
+    matches,
+  ) = build_filter_where_clause_with_fts(
+    "_ROW_",
+    api.columns(),
+    api.fts(),
+    api.spatial_index(),
+    Some(&related),
+    Some(&guards),
+    filter_params,
+  )
+  .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
+  let filter_clause = match soft_delete_filter(&state, &api, user.as_ref(), include_deleted).await?
+  {
+    Some(soft_delete_filter) => format!("({filter_clause}) AND {soft_delete_filter}"),
+    None => filter_clause,
+  };
+
+  // For vector searches, order by the distance to the query vector. Records without a vector,
+  // including records whose vector is withheld, are skipped.
+  let (filter_clause, vector_distance_expr) = match nearest {
+    Some(nearest) => {
+      let (column_expr, expr, param) = vector_distance(&api, &nearest)?;
+      params.push((Cow::Borrowed(":__nearest"), param));
+      (
+        format!(r#"({filter_clause}) AND {column_expr} IS NOT NULL"#),
+        Some(expr),
+      )
+    }
+    None => (filter_clause, None),
+  };
+
+  // For full-text searches, rank and highlight results based on the first `$match` filter.
+  let (fts_clause, rank_expr): (Option<String>, Option<String>) = match (
+    api.fts(),
+    matches.fts.first(),
+  ) {
+    (Some(fts), Some(fts_match)) => {
+      let sub_query = |expr: &str| -> String {
+        return format!(
+          r#"(SELECT {expr} FROM {fts_table} WHERE "{fts_name}"."{column}" MATCH {param} AND "{fts_name}".rowid = _ROW_."{rowid}")"#,
+          fts_table = fts.name.escaped_string(),
+          fts_name = fts.name.name,
+          column = fts.columns[fts_match.column_index],
+          param = fts_match.param,
+          rowid = fts.content_rowid,
+        );
+      };
+      let (fts_name, index) = (&fts.name.name, fts_match.column_index);
+      let rank_expr = sub_query(&format!(r#"bm25("{fts_name}")"#));
+
+      (
+        Some(format!(
+          "{rank_expr} AS _rank_, {} AS _snippet_, {} AS _highlight_",
+          sub_query(&format!(
+            r#"snippet("{fts_name}", {index}, '<b>', '</b>', '…', 16)"#
+          )),
+          sub_query(&format!(
+            r#"highlight("{fts_name}", {index}, '<b>', '</b>')"#
+          )),
+        )),
+        Some(rank_expr),
+      )
+    }
+    _ => (None, None),
+  };
+
+  // For geospatial searches, order by the distance to the first `$near` filter's center.
+  let distance_expr: Option<String> = matches.near.first().map(|near| {
+    let (center_lat, center_lng) = &near.center_params;
+    return format!(
+      r#"geo_distance(_ROW_."{lat}", _ROW_."{lng}", {center_lat}, {center_lng})"#,
+      lat = near.latitude_column,
+      lng = near.longitude_column,
+    );
+  });
+
+  let export_format = ExportFormat::negotiate(format, &headers);
 
   // User properties
-  params.extend_from_slice(&[
-    (
+  params.push((
+    Cow::Borrowed(":__user_id"),
+    user
+      .as_ref()
+      .map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+  ));
+  params.push((
+    Cow::Borrowed(":__user_roles"),
+    user
+      .as_ref()
+      .map_or(Value::Null, |u| Value::Text(u.roles_json())),
+  ));
+
+  // NOTE: Exports page through all matching records in batches, with an optional `limit`
+  // capping the total.
+  if export_format.is_none() {
+    params.push((
       Cow::Borrowed(":__limit"),
       Value::Integer(limit_or_default(limit).map_err(RecordError::BadRequest)? as i64),
-    ),
-    (
-      Cow::Borrowed(":__user_id"),
-      user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
-    ),
-  ]);
-
-  if let Some(offset) = offset {
-    params.push((
-      Cow::Borrowed(":__offset"),
-      Value::Integer(
-        offset
-          .try_into()
-          .map_err(|_| RecordError::BadRequest("Invalid offset"))?,
-      ),
     ));
   }
 
-  let cursor_clause = if let Some(encrypted_cursor) = cursor {
+  let offset: Option<i64> = offset
+    .map(|offset| offset.try_into())
+    .transpose()
+    .map_err(|_| RecordError::BadRequest("Invalid offset"))?;
+
+  let order: Vec<(String, OrderPrecedent)> = match order {
+    Some(order) => {
+      if rank_expr.is_none() && order.columns.iter().any(|(col, _)| col == FTS_RANK) {
+        return Err(RecordError::BadRequest(
+          "Ordering by rank requires a $match filter",
+        ));
+      }
+      if distance_expr.is_none() && order.columns.iter().any(|(col, _)| col == GEO_DISTANCE) {
+        return Err(RecordError::BadRequest(
+          "Ordering by distance requires a $near filter",
+        ));
+      }
+      if vector_distance_expr.is_none()
+        && order.columns.iter().any(|(col, _)| col == VECTOR_DISTANCE)
+      {
+        return Err(RecordError::BadRequest(
+          "Ordering by vector distance requires a nearest query",
+        ));
+      }
+      order.columns
+    }
+    // Vector searches default to listing the nearest neighbours first.
+    None if vector_distance_expr.is_some() => {
+      vec![(VECTOR_DISTANCE.to_string(), OrderPrecedent::Ascending)]
+    }
+    None => vec![(pk_column.name.clone(), OrderPrecedent::Descending)],
+  };
+
+  let column_expr = |col: &str| -> String {
+    // Full-text search rank, lower is better.
+    if col == FTS_RANK {
+      return rank_expr.clone().unwrap_or_default();
+    }
+    // Distance in meters to a geospatial search's center.
+    if col == GEO_DISTANCE {
+      return distance_expr.clone().unwrap_or_default();
+    }
+    // Distance to a vector search's query vector.
+    if col == VECTOR_DISTANCE {
+      return vector_distance_expr.clone().unwrap_or_default();
+    }
+    return api.column_read_expr(col);
+  };
+
+  // Keyset for cursor pagination: the order columns followed by the primary key to break ties.
+  let mut keyset = order.clone();
+  if !keyset.iter().any(|(col, _)| *col == pk_column.name) {
+    let precedent = keyset
+      .last()
+      .map_or(OrderPrecedent::Descending, |(_, ord)| ord.clone());
+    keyset.push((pk_column.name.clone(), precedent));
+  }
+  let keyset_columns: Vec<String> = keyset.iter().map(|(col, _)| column_expr(col)).collect();
+
+  // NOTE: Ordering by the entire keyset makes ties deterministic, which cursors rely on.
+  let order_clause = keyset
+    .iter()
+    .map(|(col, ord)| {
+      let precedent = match ord {
+        OrderPrecedent::Descending => "DESC",
+        OrderPrecedent::Ascending => "ASC",
+      };
+      if col == FTS_RANK {
+        return format!("_rank_ {precedent}");
+      }
+      return format!("{} {precedent}", column_expr(col));
+    })
+    .join(",");
+
+  let cursor_values: Option<Vec<CursorValue>> = if let Some(encrypted_cursor) = cursor {
     let decrypted_cursor =
       decrypt_cursor(&KEY, api_name.as_bytes(), &encrypted_cursor).map_err(|_err| {
         return RecordError::BadRequest("Bad cursor");
       })?;
 
-    // We always use INTEGER _rowid_.
-    let Cursor::Integer(cursor) = Cursor::parse(&decrypted_cursor, CursorType::Integer)
-      .map_err(|_| RecordError::BadRequest("Invalid integer cursor"))?
-    else {
-      return Err(RecordError::BadRequest("Invalid integer cursor"));
-    };
-
-    let mut pk_order = OrderPrecedent::Descending;
-    if let Some(ref order) = order {
-      if let Some((col, ord)) = order.columns.first() {
-        if *ord == OrderPrecedent::Ascending {
-          if pk_column.data_type != ColumnDataType::Integer || *col != pk_column.name {
-            // NOTE: This relies on the fact that _rowid_ is an alias for integer primary key
-            // columns.
-            return Err(RecordError::BadRequest(
-              "Cannot cursor on queries where the primary order criterion is not an integer primary key",
-            ));
-          }
+    let cursor: KeysetCursor = serde_json::from_str(&decrypted_cursor)
+      .map_err(|_err| RecordError::BadRequest("Bad cursor"))?;
 
-          pk_order = OrderPrecedent::Ascending;
-        }
-      }
+    if cursor.values.len() != keyset.len()
+      || cursor
+        .columns
+        .iter()
+        .zip(&keyset)
+        .any(|(cursor_col, (col, _))| cursor_col != col)
+    {
+      return Err(RecordError::BadRequest(
+        "Cursor does not match the query's order",
+      ));
     }
 
-    params.push((Cow::Borrowed(":cursor"), Value::Integer(cursor)));
-    match pk_order {
-      OrderPrecedent::Descending => Some("_ROW_._rowid_ < :cursor".to_string()),
-      OrderPrecedent::Ascending => Some("_ROW_._rowid_ > :cursor".to_string()),
-    }
+    Some(cursor.values)
   } else {
     None
   };
 
-  fn fmt_order(col: &str, order: OrderPrecedent) -> String {
-    return format!(
-      r#"_ROW_."{col}" {}"#,
-      match order {
-        OrderPrecedent::Descending => "DESC",
-        OrderPrecedent::Ascending => "ASC",
-      }
-    );
+  let query_expand: Vec<String> = query_expand.map_or_else(Vec::new, |e| e.columns);
+  // NOTE: This will reject any unknown expansion path, thus avoiding SQL injections.
+  for path in &query_expand {
+    if !is_expandable(api.expand_paths(), path) {
+      return Err(RecordError::BadRequest("Invalid expansion"));
+    }
   }
 
-  let order_clause = order.map_or_else(
-    || fmt_order(&pk_column.name, OrderPrecedent::Descending),
-    |order| {
-      order
-        .columns
-        .into_iter()
-        .map(|(col, ord)| fmt_order(&col, ord))
-        .join(",")
-    },
-  );
+  let query_fields: Vec<String> = fields.map_or_else(Vec::new, |f| f.columns);
+  let (fields, nested_fields) = split_fields(&query_fields);
+  let projection = Projection::from_api(&api, &fields, &query_expand)?;
+  let (columns, json_metadata) = match projection {
+    Some(ref projection) => (projection.columns(), projection.json_column_metadata()),
+    None => (api.columns(), api.json_column_metadata()),
+  };
 
-  let expanded_tables = match query_expand {
-    Some(ref expand) => {
-      let Some(config_expand) = api.expand() else {
-        return Err(RecordError::BadRequest("Invalid expansion"));
-      };
+  let expand_nodes = if query_expand.is_empty() && nested_fields.is_empty() {
+    vec![]
+  } else {
+    build_expand_tree(
+      &state,
+      &api,
+      &ExpandParent {
+        table_name: api.qualified_name(),
+        columns,
+        pk_column_name: &pk_column.name,
+      },
+      &query_expand,
+      api.expand_paths(),
+      &nested_fields,
+    )?
+  };
 
-      // NOTE: This will drop any unknown expand column, thus avoiding SQL injections.
-      for col_name in &expand.columns {
-        if !config_expand.contains_key(col_name) {
-          return Err(RecordError::BadRequest("Invalid expansion"));
-        }
+  if let Some(export_format) = export_format {
+    if count.is_some() || !expand_nodes.is_empty() {
+      return Err(RecordError::BadRequest(
+        "CSV and NDJSON do not support counts or expansions",
+      ));
+    }
+
+    let export = RecordExport {
+      api: api.clone(),
+      columns: columns.to_vec(),
+      json_metadata: json_metadata.to_vec(),
+      keyset,
+      keyset_columns,
+      filter_clause,
+      order_clause,
+      fts_clause,
+      params,
+      cursor: cursor_values,
+      offset,
+      limit,
+    };
+
+    // Stream batches through a bounded channel, i.e. only fetch more records as the client
+    // consumes them.
+    let (sender, receiver) = async_channel::bounded::<Result<String, RecordError>>(4);
+    tokio::spawn(async move {
+      if let Err(err) = export.run(&state, export_format, &sender).await {
+        // Aborts the response.
+        let _ = sender.send(Err(err)).await;
       }
+    });
 
-      expand_tables(
-        state.schema_metadata(),
-        &api.qualified_name().database_schema,
-        |column_name| {
-          api
-            .column_index_by_name(column_name)
-            .map(|idx| &api.columns()[idx])
-        },
-        &expand.columns,
-      )?
-    }
-    None => vec![],
-  };
+    return Ok(
+      (
+        [(header::CONTENT_TYPE, export_format.content_type())],
+        Body::from_stream(receiver),
+      )
+        .into_response(),
+    );
+  }
+
+  let cursor_clause = cursor_values
+    .map(|values| build_keyset_clause(&keyset, &keyset_columns, values, &mut params))
+    .transpose()?;
+
+  if let Some(offset) = offset {
+    params.push((Cow::Borrowed(":__offset"), Value::Integer(offset)));
+  }
 
   // NOTE: The template relies on load-bearing underscores for "_rowid_" and "_total_count_" to
   // have them be stripped later on by `rows_to_json`.
-  let column_names: Vec<_> = api.columns().iter().map(|c| c.name.as_str()).collect();
+  let column_names: Vec<_> = columns.iter().map(|c| c.name.as_str()).collect();
+  let column_read_rules: Vec<_> = column_names
+    .iter()
+    .map(|name| api.column_read_rule(name))
+    .collect();
   let query = ListRecordQueryTemplate {
     table_name,
     column_names: &column_names,
+    column_read_rules: &column_read_rules,
+    cursor_columns: &keyset_columns,
     read_access_clause,
     filter_clause: &filter_clause,
     cursor_clause: cursor_clause.as_deref(),
     order_clause: &order_clause,
-    expanded_tables: &expanded_tables,
+    fts_clause: fts_clause.as_deref(),
     count: count.unwrap_or(false),
     offset: offset.is_some(),
   }
@@ -234,23 +451,30 @@ pub async fn list_records_handler(
   let rows = state.conn().read_query_rows(query, params).await?;
   let Some(last_row) = rows.last() else {
     // Query result is empty:
-    return Ok(Json(ListResponse {
-      cursor: None,
-      total_count: Some(0),
-      records: vec![],
-    }));
+    return Ok(
+      Json(ListResponse {
+        cursor: None,
+        total_count: Some(0),
+        records: vec![],
+      })
+      .into_response(),
+    );
   };
 
   let cursor: Option<String> = {
-    let rowid_index = last_row.len() - 1;
-    if let Value::Integer(i) = last_row[rowid_index] {
-      Some(
-        encrypt_cursor(&KEY, api_name.as_bytes(), &i.to_string())
-          .map_err(|err| RecordError::Internal(err.into()))?,
-      )
-    } else {
-      None
-    }
+    // The keyset values directly follow the selected columns.
+    let cursor = KeysetCursor {
+      columns: keyset.into_iter().map(|(col, _)| col).collect(),
+      values: (0..keyset_columns.len())
+        .map(|i| CursorValue::from(&last_row[columns.len() + i]))
+        .collect(),
+    };
+    let json = serde_json::to_string(&cursor).map_err(|err| RecordError::Internal(err.into()))?;
+
+    Some(
+      encrypt_cursor(&KEY, api_name.as_bytes(), &json)
+        .map_err(|err| RecordError::Internal(err.into()))?,
+    )
   };
 
   let total_count = if count == Some(true) {
@@ -267,156 +491,672 @@ pub async fn list_records_handler(
     None
   };
 
-  let records = if expanded_tables.is_empty() {
-    rows_to_json_expand(
-      api.columns(),
-      api.json_column_metadata(),
-      rows,
-      column_filter,
-      api.expand(),
+  let fts_results: Option<Vec<serde_json::Value>> = if fts_clause.is_some() {
+    // Rank, snippet and highlight precede the optional total count and the final rowid.
+    let offset = last_row.len() - if count == Some(true) { 5 } else { 4 };
+    Some(
+      rows
+        .iter()
+        .map(|row| {
+          let to_json = |index: usize| {
+            return value_to_json(&row[index]).map_err(|err| RecordError::Internal(err.into()));
+          };
+          return Ok(serde_json::json!({
+            "rank": to_json(offset)?,
+            "snippet": to_json(offset + 1)?,
+            "highlight": to_json(offset + 2)?,
+          }));
+        })
+        .collect::<Result<Vec<_>, RecordError>>()?,
     )
-    .map_err(|err| RecordError::Internal(err.into()))?
   } else {
-    rows
-      .into_iter()
-      .map(|mut row| {
-        // Allocate new empty expansion map.
-        let Some(mut expand) = api.expand().cloned() else {
-          return Err(RecordError::Internal(
-            "Expansion config must be some".into(),
-          ));
-        };
-
-        let mut curr = row.split_off(api.columns().len());
-
-        for expanded in &expanded_tables {
-          let next = curr.split_off(expanded.num_columns);
-
-          let foreign_value = row_to_json(
-            &expanded.metadata.schema.columns,
-            &expanded.metadata.json_metadata.columns,
-            &curr,
-            column_filter,
-          )
-          .map_err(|err| RecordError::Internal(err.into()))?;
+    None
+  };
 
-          let result = expand.insert(expanded.local_column_name.clone(), foreign_value);
-          assert!(result.is_some());
+  let mut records = rows
+    .iter()
+    .map(|row| {
+      return row_to_json_expand(columns, json_metadata, row, column_filter, api.expand())
+        .map_err(|err| RecordError::Internal(err.into()));
+    })
+    .collect::<Result<Vec<_>, RecordError>>()?;
 
-          curr = next;
-        }
+  if !expand_nodes.is_empty() {
+    let rows: Vec<_> = rows.iter().collect();
+    expand_records(
+      &state,
+      user.as_ref(),
+      &expand_nodes,
+      columns,
+      &pk_column.name,
+      &rows,
+      &mut records,
+    )
+    .await?;
+  }
 
-        return row_to_json_expand(
-          api.columns(),
-          api.json_column_metadata(),
-          &row,
-          column_filter,
-          Some(&expand),
-        )
-        .map_err(|err| RecordError::Internal(err.into()));
-      })
-      .collect::<Result<Vec<_>, RecordError>>()?
-  };
+  if let Some(fts_results) = fts_results {
+    for (record, fts_result) in records.iter_mut().zip(fts_results) {
+      if let serde_json::Value::Object(record) = record {
+        record.insert("_fts".to_string(), fts_result);
+      }
+    }
+  }
 
-  return Ok(Json(ListResponse {
-    cursor,
-    total_count,
-    records,
-  }));
+  return Ok(
+    Json(ListResponse {
+      cursor,
+      total_count,
+      records,
+    })
+    .into_response(),
+  );
 }
 
-#[inline]
-fn column_filter(col_name: &str) -> bool {
-  return !col_name.starts_with("_");
+/// JSON response containing aggregated values, one entry per group.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AggregateResponse {
+  pub groups: Vec<AggregateGroup>,
 }
 
-const NONCE_LEN: usize = 12;
-const TAG_LEN: usize = 16;
-// const KEY_LEN: usize = 32;
-
-/// Encrypts the cookie's value with authenticated encryption providing
-/// confidentiality, integrity, and authenticity.
-fn encrypt_cursor(
-  key: &Key<Aes256Gcm>,
-  associated_data: &[u8],
-  cursor: &str,
-) -> Result<String, &'static str> {
-  // Create a vec to hold the [nonce | cookie value | tag].
-  let cursor_bytes = cursor.as_bytes();
-  let mut data = vec![0; NONCE_LEN + cursor_bytes.len() + TAG_LEN];
-
-  // Split data into three: nonce, input/output, tag. Copy input.
-  let (nonce, in_out) = data.split_at_mut(NONCE_LEN);
-  let (in_out, tag) = in_out.split_at_mut(cursor_bytes.len());
-  in_out.copy_from_slice(cursor_bytes);
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AggregateGroup {
+  /// Values of the `group_by` columns identifying this group. Empty if ungrouped.
+  #[schema(value_type = Object)]
+  pub key: serde_json::Map<String, serde_json::Value>,
+  /// Aggregated values keyed by aggregation, e.g. "count" or "sum(col)".
+  #[schema(value_type = Object)]
+  pub values: serde_json::Map<String, serde_json::Value>,
+}
 
-  // Fill nonce piece with random data.
-  let mut rng = rand::rng();
-  rng.fill_bytes(nonce);
-  let nonce = GenericArray::clone_from_slice(nonce);
+#[derive(Template)]
+#[template(escape = "none", path = "aggregate_record_query.sql")]
+struct AggregateRecordQueryTemplate<'a> {
+  table_name: &'a QualifiedNameEscaped,
+  group_by_exprs: &'a [String],
+  aggregations: &'a [String],
+  read_access_clause: &'a str,
+  filter_clause: &'a str,
+  order_clause: &'a str,
+  offset: bool,
+}
 
-  // Perform the actual sealing operation, using the cookie's name as
-  // associated data to prevent value swapping.
-  let aead = Aes256Gcm::new(key);
-  let aad_tag = aead
-    .encrypt_in_place_detached(&nonce, associated_data, in_out)
-    .map_err(|_| "encryption failure!")?;
+/// Computes aggregates, e.g. counts or sums, over records matching the given filters.
+///
+/// Accepts the same filters as listing, plus `aggregate=count,sum(col)` and optionally
+/// `group_by=col`. Defaults to counting if no aggregations are given.
+#[utoipa::path(
+  get,
+  path = "/:name/aggregate",
+  responses(
+    (status = 200, description = "Aggregated values.", body = AggregateResponse)
+  )
+)]
+pub async fn aggregate_records_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  RawQuery(raw_url_query): RawQuery,
+  user: Option<User>,
+) -> Result<Json<AggregateResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
 
-  // Copy the tag into the tag piece.
-  tag.copy_from_slice(&aad_tag);
+  // NOTE: Like for listing, the read access rule is used as a filter, i.e. aggregates only
+  // include accessible records.
+  api.check_table_level_access(Permission::Read, user.as_ref())?;
 
-  // Base64 encode [nonce | encrypted value | tag].
-  return Ok(BASE64_URL_SAFE.encode(&data));
-}
+  let Query {
+    limit,
+    cursor,
+    count,
+    expand,
+    fields,
+    order,
+    filter: filter_params,
+    offset,
+    aggregate,
+    group_by,
+    format: _,
+    include_deleted,
+    nearest,
+    snapshot: _,
+  } = raw_url_query
+    .as_ref()
+    .map_or_else(|| Ok(Query::default()), |query| Query::parse(query))
+    .map_err(|_err| {
+      return RecordError::BadRequest("Invalid query");
+    })?;
 
-fn decrypt_cursor(
-  key: &Key<Aes256Gcm>,
-  associated_data: &[u8],
-  value: &str,
-) -> Result<String, &'static str> {
-  let data = BASE64_URL_SAFE
-    .decode(value)
-    .map_err(|_| "bad base64 value")?;
-  if data.len() <= NONCE_LEN {
-    return Err("length of decoded data is <= NONCE_LEN");
+  if cursor.is_some()
+    || count.is_some()
+    || expand.is_some()
+    || fields.is_some()
+    || nearest.is_some()
+  {
+    return Err(RecordError::BadRequest(
+      "Aggregations do not support cursors, counts, expansions, fields or vector searches",
+    ));
   }
 
-  let (nonce, cipher) = data.split_at(NONCE_LEN);
-  let payload = Payload {
-    msg: cipher,
-    aad: associated_data,
+  // IMPORTANT: Only allow known, non-excluded and non-hidden columns to avoid leaking excluded
+  // data and SQL injections.
+  let is_valid_column = |column_name: &str| -> bool {
+    return column_filter(column_name) && api.column_index_by_name(column_name).is_some();
   };
 
-  let aead = Aes256Gcm::new(GenericArray::from_slice(key));
-  aead
-    .decrypt(GenericArray::from_slice(nonce), payload)
-    .map_err(|_| "invalid key/nonce/value: bad seal")
-    .and_then(|s| String::from_utf8(s).map_err(|_| "bad unsealed utf8"))
-}
+  let group_by_columns = group_by.map_or_else(Vec::new, |g| g.columns);
+  for column_name in &group_by_columns {
+    if !is_valid_column(column_name) {
+      return Err(RecordError::BadRequest("Invalid group_by column"));
+    }
+  }
 
-lazy_static! {
-  static ref KEY: Key<Aes256Gcm> = Aes256Gcm::generate_key(OsRng);
-}
+  let aggregations = aggregate.map_or_else(
+    || {
+      vec![Aggregation {
+        function: AggregateFunction::Count,
+        column: None,
+      }]
+    },
+    |a| a.aggregations,
+  );
+  let aggregation_exprs = aggregations
+    .iter()
+    .map(|aggregation| {
+      let function = aggregation.function.to_sql();
+      return match aggregation.column {
+        Some(ref column_name) => {
+          if !is_valid_column(column_name) {
+            return Err(RecordError::BadRequest("Invalid aggregation column"));
+          }
+          Ok(format!("{function}({})", api.column_read_expr(column_name)))
+        }
+        None => Ok(format!("{function}(*)")),
+      };
+    })
+    .collect::<Result<Vec<_>, RecordError>>()?;
 
-#[cfg(test)]
-mod tests {
-  use aes_gcm::aead::{KeyInit, OsRng};
-  use aes_gcm::{Aes256Gcm, Key};
-  use serde::Deserialize;
-  use std::borrow::Cow;
-  use trailbase_schema::sqlite::{QualifiedName, sqlite3_parse_into_statement};
-  use trailbase_sqlite::Value;
+  // Groups can only be ordered by their keys.
+  let order_clause = match order {
+    Some(order) => order
+      .columns
+      .into_iter()
+      .map(|(col, ord)| {
+        if !group_by_columns.contains(&col) {
+          return Err(RecordError::BadRequest("Order must be on group_by column"));
+        }
+        return Ok(format!(
+          "{} {}",
+          api.column_read_expr(&col),
+          match ord {
+            OrderPrecedent::Descending => "DESC",
+            OrderPrecedent::Ascending => "ASC",
+          }
+        ));
+      })
+      .collect::<Result<Vec<_>, RecordError>>()?
+      .join(","),
+    None => group_by_columns
+      .iter()
+      .map(|col| format!("{} ASC", api.column_read_expr(col)))
+      .join(","),
+  };
+
+  let related = |column_name: &str, path: &[String]| {
+    return related_column_filter(&state, &api, user.as_ref(), "_ROW_", column_name, path);
+  };
+  let guards = |column_name: &str| api.column_read_rule(column_name).map(|r| format!("({r})"));
+  let (
+    WhereClause {
+      clause: filter_clause,
+      mut params,
+    },
+    _matches,
+  ) = build_filter_where_clause_with_fts(
+    "_ROW_",
+    api.columns(),
+    api.fts(),
+    api.spatial_index(),
+    Some(&related),
+    Some(&guards),
+    filter_params,
+  )
+  .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
+  let filter_clause = match soft_delete_filter(&state, &api, user.as_ref(), include_deleted).await?
+  {
+    Some(soft_delete_filter) => format!("({filter_clause}) AND {soft_delete_filter}"),
+    None => filter_clause,
+  };
+
+  params.extend_from_slice(&[
+    (
+      Cow::Borrowed(":__limit"),
+      Value::Integer(limit_or_default(limit).map_err(RecordError::BadRequest)? as i64),
+    ),
+    (
+      Cow::Borrowed(":__user_id"),
+      user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+    ),
+    (
+      Cow::Borrowed(":__user_roles"),
+      user.map_or(Value::Null, |u| Value::Text(u.roles_json())),
+    ),
+  ]);
+
+  if let Some(offset) = offset {
+    params.push((
+      Cow::Borrowed(":__offset"),
+      Value::Integer(
+        offset
+          .try_into()
+          .map_err(|_| RecordError::BadRequest("Invalid offset"))?,
+      ),
+    ));
+  }
+
+  let group_by_exprs: Vec<String> = group_by_columns
+    .iter()
+    .map(|col| api.column_read_expr(col))
+    .collect();
+  let query = AggregateRecordQueryTemplate {
+    table_name: api.table_name(),
+    group_by_exprs: &group_by_exprs,
+    aggregations: &aggregation_exprs,
+    read_access_clause: api.read_access_rule().unwrap_or("TRUE"),
+    filter_clause: &filter_clause,
+    order_clause: &order_clause,
+    offset: offset.is_some(),
+  }
+  .render()
+  .map_err(|err| RecordError::Internal(err.into()))?;
+
+  let rows = state.conn().read_query_rows(query, params).await?;
+
+  let groups = rows
+    .iter()
+    .map(|row| {
+      // Group keys come first followed by the aggregations, see template.
+      let to_json = |index: usize| {
+        return value_to_json(&row[index]).map_err(|err| RecordError::Internal(err.into()));
+      };
+
+      let key = group_by_columns
+        .iter()
+        .enumerate()
+        .map(|(index, column_name)| Ok((column_name.clone(), to_json(index)?)))
+        .collect::<Result<serde_json::Map<_, _>, RecordError>>()?;
+      let values = aggregations
+        .iter()
+        .enumerate()
+        .map(|(index, aggregation)| {
+          Ok((aggregation.name(), to_json(group_by_columns.len() + index)?))
+        })
+        .collect::<Result<serde_json::Map<_, _>, RecordError>>()?;
+
+      return Ok(AggregateGroup { key, values });
+    })
+    .collect::<Result<Vec<_>, RecordError>>()?;
+
+  return Ok(Json(AggregateResponse { groups }));
+}
+
+/// Max number of records fetched at once when streaming exports.
+const EXPORT_BATCH_SIZE: usize = 256;
+
+/// Owned list query, which can be re-run batch by batch to stream CSV or NDJSON exports. Batches
+/// are chained using keyset pagination, same as cursors.
+struct RecordExport {
+  api: RecordApi,
+  columns: Vec<Column>,
+  json_metadata: Vec<Option<JsonColumnMetadata>>,
+  keyset: Vec<(String, OrderPrecedent)>,
+  keyset_columns: Vec<String>,
+  filter_clause: String,
+  order_clause: String,
+  fts_clause: Option<String>,
+  params: Vec<(Cow<'static, str>, Value)>,
+  cursor: Option<Vec<CursorValue>>,
+  offset: Option<i64>,
+  limit: Option<usize>,
+}
+
+impl RecordExport {
+  async fn run(
+    self,
+    state: &AppState,
+    format: ExportFormat,
+    sender: &async_channel::Sender<Result<String, RecordError>>,
+  ) -> Result<(), RecordError> {
+    let column_names: Vec<&str> = self.columns.iter().map(|c| c.name.as_str()).collect();
+    let column_read_rules: Vec<Option<&str>> = column_names
+      .iter()
+      .map(|name| self.api.column_read_rule(name))
+      .collect();
+    let output_columns: Vec<&str> = column_names
+      .iter()
+      .copied()
+      .filter(|name| column_filter(name))
+      .collect();
+
+    if let Some(header) = format.header(&output_columns) {
+      if sender.send(Ok(header)).await.is_err() {
+        return Ok(());
+      }
+    }
+
+    let mut cursor = self.cursor;
+    let mut offset = self.offset;
+    let mut remaining = self.limit;
+    loop {
+      let batch_size = remaining.map_or(EXPORT_BATCH_SIZE, |r| r.min(EXPORT_BATCH_SIZE));
+      if batch_size == 0 {
+        return Ok(());
+      }
+
+      let mut params = self.params.clone();
+      params.push((Cow::Borrowed(":__limit"), Value::Integer(batch_size as i64)));
+      // Subsequent batches continue after the previous batch's keyset.
+      let offset = offset.take();
+      if let Some(offset) = offset {
+        params.push((Cow::Borrowed(":__offset"), Value::Integer(offset)));
+      }
+
+      let cursor_clause = cursor
+        .take()
+        .map(|values| build_keyset_clause(&self.keyset, &self.keyset_columns, values, &mut params))
+        .transpose()?;
+
+      let query = ListRecordQueryTemplate {
+        table_name: self.api.table_name(),
+        column_names: &column_names,
+        column_read_rules: &column_read_rules,
+        cursor_columns: &self.keyset_columns,
+        read_access_clause: self.api.read_access_rule().unwrap_or("TRUE"),
+        filter_clause: &self.filter_clause,
+        cursor_clause: cursor_clause.as_deref(),
+        order_clause: &self.order_clause,
+        fts_clause: self.fts_clause.as_deref(),
+        count: false,
+        offset: offset.is_some(),
+      }
+      .render()
+      .map_err(|err| RecordError::Internal(err.into()))?;
+
+      let rows = state.conn().read_query_rows(query, params).await?;
+
+      let mut chunk = String::new();
+      for row in rows.iter() {
+        let record = row_to_json_expand(
+          &self.columns,
+          &self.json_metadata,
+          row,
+          column_filter,
+          self.api.expand(),
+        )
+        .map_err(|err| RecordError::Internal(err.into()))?;
+
+        format
+          .encode(&output_columns, &record, &mut chunk)
+          .map_err(|err| RecordError::Internal(err.into()))?;
+      }
+
+      if sender.send(Ok(chunk)).await.is_err() {
+        // Client went away.
+        return Ok(());
+      }
+
+      let Some(last_row) = rows.last() else {
+        return Ok(());
+      };
+      if rows.len() < batch_size {
+        return Ok(());
+      }
+
+      remaining = remaining.map(|r| r - rows.len());
+      // The keyset values directly follow the selected columns.
+      cursor = Some(
+        (0..self.keyset_columns.len())
+          .map(|i| CursorValue::from(&last_row[self.columns.len() + i]))
+          .collect(),
+      );
+    }
+  }
+}
+
+/// Pseudo column to order full-text search results by rank, e.g. `order=_rank`.
+const FTS_RANK: &str = "_rank";
+
+/// Pseudo column to order geospatial search results by distance, e.g. `order=_distance`.
+const GEO_DISTANCE: &str = "_distance";
+
+/// Pseudo column to order vector search results by distance, e.g. `order=_vector_distance`.
+const VECTOR_DISTANCE: &str = "_vector_distance";
+
+/// Builds the expression selecting the record's vector, the expression computing its distance to
+/// the query vector using sqlite-vec, as well as the query vector's parameter value.
+///
+/// NOTE: The vector is selected honoring column-level read rules, i.e. withheld vectors are NULL
+/// and thus can neither be probed by ordering nor by filtering.
+fn vector_distance(
+  api: &RecordApi,
+  nearest: &Nearest,
+) -> Result<(String, String, Value), RecordError> {
+  // NOTE: This rejects unknown columns, thus avoiding SQL injections.
+  if !api.columns().iter().any(|c| c.name == nearest.column) {
+    return Err(RecordError::BadRequest("Invalid vector column"));
+  }
+
+  let function = match nearest.metric {
+    VectorMetric::Cosine => "vec_distance_cosine",
+    VectorMetric::L2 => "vec_distance_l2",
+    VectorMetric::L1 => "vec_distance_l1",
+  };
+
+  // Same as sqlite-vec's compact `float32` format.
+  let vector: Vec<u8> = nearest
+    .vector
+    .iter()
+    .flat_map(|x| x.to_le_bytes())
+    .collect();
+
+  let column_expr = api.column_read_expr(&nearest.column);
+  return Ok((
+    column_expr.clone(),
+    format!("{function}({column_expr}, :__nearest)"),
+    Value::Blob(vector),
+  ));
+}
+
+#[inline]
+fn column_filter(col_name: &str) -> bool {
+  return !col_name.starts_with("_");
+}
+
+/// Values of the last returned record's order columns and primary key. Subsequent pages continue
+/// strictly after this key, which keeps pagination stable for arbitrary orderings.
+#[derive(Debug, Serialize, Deserialize)]
+struct KeysetCursor {
+  columns: Vec<String>,
+  values: Vec<CursorValue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum CursorValue {
+  Null,
+  Integer(i64),
+  Real(f64),
+  Text(String),
+  /// Url-safe base64 encoded.
+  Blob(String),
+}
+
+impl From<&Value> for CursorValue {
+  fn from(value: &Value) -> Self {
+    return match value {
+      Value::Null => Self::Null,
+      Value::Integer(i) => Self::Integer(*i),
+      Value::Real(r) => Self::Real(*r),
+      Value::Text(t) => Self::Text(t.clone()),
+      Value::Blob(b) => Self::Blob(BASE64_URL_SAFE.encode(b)),
+    };
+  }
+}
+
+impl TryFrom<CursorValue> for Value {
+  type Error = base64::DecodeError;
+
+  fn try_from(value: CursorValue) -> Result<Self, Self::Error> {
+    return Ok(match value {
+      CursorValue::Null => Value::Null,
+      CursorValue::Integer(i) => Value::Integer(i),
+      CursorValue::Real(r) => Value::Real(r),
+      CursorValue::Text(t) => Value::Text(t),
+      CursorValue::Blob(b) => Value::Blob(BASE64_URL_SAFE.decode(b)?),
+    });
+  }
+}
+
+/// Builds a WHERE clause selecting records strictly after the cursor's key with respect to the
+/// (potentially mixed) ordering, i.e. `(a > :a) OR (a = :a AND b < :b) OR ...`. Accounts for
+/// SQLite sorting NULLs first in ascending and last in descending order.
+fn build_keyset_clause(
+  keyset: &[(String, OrderPrecedent)],
+  keyset_columns: &[String],
+  values: Vec<CursorValue>,
+  params: &mut Vec<(Cow<'static, str>, Value)>,
+) -> Result<String, RecordError> {
+  let mut equal: Vec<String> = vec![];
+  let mut disjunction: Vec<String> = vec![];
+
+  for (i, (((_, ord), expr), value)) in keyset.iter().zip(keyset_columns).zip(values).enumerate() {
+    let value: Value = value
+      .try_into()
+      .map_err(|_err| RecordError::BadRequest("Bad cursor"))?;
+    let param = format!(":__cursor{i}");
+
+    let after = match (ord, &value) {
+      (OrderPrecedent::Ascending, Value::Null) => Some(format!("{expr} IS NOT NULL")),
+      (OrderPrecedent::Ascending, _) => Some(format!("{expr} > {param}")),
+      (OrderPrecedent::Descending, Value::Null) => None,
+      (OrderPrecedent::Descending, _) => Some(format!("({expr} < {param} OR {expr} IS NULL)")),
+    };
+    if let Some(after) = after {
+      disjunction.push(
+        equal
+          .iter()
+          .map(|e| e.as_str())
+          .chain(std::iter::once(after.as_str()))
+          .join(" AND "),
+      );
+    }
+
+    equal.push(match value {
+      Value::Null => format!("{expr} IS NULL"),
+      _ => format!("{expr} = {param}"),
+    });
+    if !matches!(value, Value::Null) {
+      params.push((Cow::Owned(param), value));
+    }
+  }
+
+  if disjunction.is_empty() {
+    return Ok("FALSE".to_string());
+  }
+  return Ok(
+    disjunction
+      .into_iter()
+      .map(|d| format!("({d})"))
+      .join(" OR "),
+  );
+}
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+// const KEY_LEN: usize = 32;
+
+/// Encrypts the cookie's value with authenticated encryption providing
+/// confidentiality, integrity, and authenticity.
+fn encrypt_cursor(
+  key: &Key<Aes256Gcm>,
+  associated_data: &[u8],
+  cursor: &str,
+) -> Result<String, &'static str> {
+  // Create a vec to hold the [nonce | cookie value | tag].
+  let cursor_bytes = cursor.as_bytes();
+  let mut data = vec![0; NONCE_LEN + cursor_bytes.len() + TAG_LEN];
+
+  // Split data into three: nonce, input/output, tag. Copy input.
+  let (nonce, in_out) = data.split_at_mut(NONCE_LEN);
+  let (in_out, tag) = in_out.split_at_mut(cursor_bytes.len());
+  in_out.copy_from_slice(cursor_bytes);
+
+  // Fill nonce piece with random data.
+  let mut rng = rand::rng();
+  rng.fill_bytes(nonce);
+  let nonce = GenericArray::clone_from_slice(nonce);
+
+  // Perform the actual sealing operation, using the cookie's name as
+  // associated data to prevent value swapping.
+  let aead = Aes256Gcm::new(key);
+  let aad_tag = aead
+    .encrypt_in_place_detached(&nonce, associated_data, in_out)
+    .map_err(|_| "encryption failure!")?;
+
+  // Copy the tag into the tag piece.
+  tag.copy_from_slice(&aad_tag);
+
+  // Base64 encode [nonce | encrypted value | tag].
+  return Ok(BASE64_URL_SAFE.encode(&data));
+}
+
+fn decrypt_cursor(
+  key: &Key<Aes256Gcm>,
+  associated_data: &[u8],
+  value: &str,
+) -> Result<String, &'static str> {
+  let data = BASE64_URL_SAFE
+    .decode(value)
+    .map_err(|_| "bad base64 value")?;
+  if data.len() <= NONCE_LEN {
+    return Err("length of decoded data is <= NONCE_LEN");
+  }
+
+  let (nonce, cipher) = data.split_at(NONCE_LEN);
+  let payload = Payload {
+    msg: cipher,
+    aad: associated_data,
+  };
+
+  let aead = Aes256Gcm::new(GenericArray::from_slice(key));
+  aead
+    .decrypt(GenericArray::from_slice(nonce), payload)
+    .map_err(|_| "invalid key/nonce/value: bad seal")
+    .and_then(|s| String::from_utf8(s).map_err(|_| "bad unsealed utf8"))
+}
+
+lazy_static! {
+  static ref KEY: Key<Aes256Gcm> = Aes256Gcm::generate_key(OsRng);
+}
+
+#[cfg(test)]
+mod tests {
+  use aes_gcm::aead::{KeyInit, OsRng};
+  use aes_gcm::{Aes256Gcm, Key};
+  use serde::Deserialize;
+  use std::borrow::Cow;
+  use trailbase_schema::sqlite::{QualifiedName, sqlite3_parse_into_statement};
+  use trailbase_sqlite::Value;
 
   use super::*;
   use crate::admin::user::*;
   use crate::app_state::*;
   use crate::auth::api::login::login_with_password;
   use crate::auth::user::User;
-  use crate::config::proto::PermissionFlag;
+  use crate::config::proto::{ColumnAccessRule, PermissionFlag};
   use crate::records::RecordError;
-  use crate::records::query_builder::expand_tables;
   use crate::records::test_utils::*;
-  use crate::schema_metadata::SchemaMetadataCache;
+  use crate::test::unpack_json_response;
   use crate::util::id_to_b64;
   use crate::util::urlencode;
 
@@ -431,11 +1171,13 @@ mod tests {
       &ListRecordQueryTemplate {
         table_name: &QualifiedName::parse("table").unwrap().into(),
         column_names: &["a", "index"],
+        column_read_rules: &[None, Some("_USER_.id IS NOT NULL")],
+        cursor_columns: &[],
         read_access_clause: "TRUE",
         filter_clause: "TRUE",
         cursor_clause: Some("TRUE"),
         order_clause: "NULL",
-        expanded_tables: &[],
+        fts_clause: None,
         count: false,
         offset: false,
       }
@@ -451,11 +1193,13 @@ mod tests {
         }
         .into(),
         column_names: &["a", "index"],
+        column_read_rules: &[],
+        cursor_columns: &[r#"_ROW_."index""#.to_string(), r#"_ROW_."a""#.to_string()],
         read_access_clause: "_USER_.id IS NOT NULL",
         filter_clause: "a = 'value'",
         cursor_clause: None,
         order_clause: "'index' ASC",
-        expanded_tables: &[],
+        fts_clause: Some("NULL AS _rank_, NULL AS _snippet_, NULL AS _highlight_"),
         count: true,
         offset: true,
       }
@@ -464,123 +1208,794 @@ mod tests {
     );
   }
 
-  #[test]
-  fn test_cursor_encryption() {
-    let api_name = "test".to_string();
+  #[test]
+  fn test_aggregate_records_template() {
+    sanitize_template(
+      &AggregateRecordQueryTemplate {
+        table_name: &QualifiedName::parse("table").unwrap().into(),
+        group_by_exprs: &[],
+        aggregations: &["COUNT(*)".to_string()],
+        read_access_clause: "TRUE",
+        filter_clause: "TRUE",
+        order_clause: "",
+        offset: false,
+      }
+      .render()
+      .unwrap(),
+    );
+
+    sanitize_template(
+      &AggregateRecordQueryTemplate {
+        table_name: &QualifiedName::parse("table").unwrap().into(),
+        group_by_exprs: &[r#"_ROW_."a""#.to_string(), r#"_ROW_."index""#.to_string()],
+        aggregations: &["COUNT(*)".to_string(), r#"SUM(_ROW_."b")"#.to_string()],
+        read_access_clause: "_USER_.id IS NOT NULL",
+        filter_clause: "a = 'value'",
+        order_clause: r#"_ROW_."a" ASC"#,
+        offset: true,
+      }
+      .render()
+      .unwrap(),
+    );
+  }
+
+  #[test]
+  fn test_cursor_encryption() {
+    let api_name = "test".to_string();
+
+    let key: Key<Aes256Gcm> = Aes256Gcm::generate_key(OsRng).into();
+
+    let value = "secret cursor";
+    let encrypted = encrypt_cursor(&key, api_name.as_bytes(), &value).unwrap();
+    let decrypted = decrypt_cursor(&key, api_name.as_bytes(), &encrypted).unwrap();
+
+    assert_eq!(value, decrypted);
+  }
+
+  #[tokio::test]
+  async fn test_list_records_template_with_keyword_columns() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute(
+        r#"CREATE TABLE "other" (
+              "index" INTEGER PRIMARY KEY
+            ) STRICT"#,
+        (),
+      )
+      .await
+      .unwrap();
+
+    conn
+      .execute(
+        r#"CREATE TABLE "table" (
+              tid     INTEGER PRIMARY KEY,
+              "drop"  TEXT,
+              "index" INTEGER REFERENCES "other"("index")
+            ) STRICT"#,
+        (),
+      )
+      .await
+      .unwrap();
+
+    let query = ListRecordQueryTemplate {
+      table_name: &QualifiedName {
+        name: "table".to_string(),
+        database_schema: Some("main".to_string()),
+      }
+      .into(),
+      column_names: &["tid", "drop", "index"],
+      column_read_rules: &[],
+      cursor_columns: &[r#"_ROW_."tid""#.to_string()],
+      read_access_clause: "_USER_.id != X'F000'",
+      filter_clause: "TRUE",
+      cursor_clause: None,
+      order_clause: "tid",
+      fts_clause: None,
+      count: true,
+      offset: false,
+    }
+    .render()
+    .unwrap();
+
+    sanitize_template(&query);
+
+    let params = vec![
+      (Cow::Borrowed(":__limit"), Value::Integer(100)),
+      (
+        Cow::Borrowed(":__user_id"),
+        Value::Blob(uuid::Uuid::now_v7().into()),
+      ),
+      (
+        Cow::Borrowed(":__user_roles"),
+        Value::Text("[]".to_string()),
+      ),
+    ];
+
+    let result = conn.read_query_rows(query, params).await;
+    if let Err(err) = result {
+      panic!("ERROR: {err}");
+    }
+  }
+
+  fn is_auth_err(error: &RecordError) -> bool {
+    return match error {
+      RecordError::Forbidden => true,
+      _ => false,
+    };
+  }
+
+  #[tokio::test]
+  async fn test_record_api_list() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Entry {
+      id: i64,
+      index: String,
+    }
+
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE 'table' (
+          id INTEGER PRIMARY KEY,
+          'index' TEXT NOT NULL DEFAULT ''
+        );
+        INSERT INTO 'table' (id, 'index') VALUES (1, '1'), (2, '2'), (3, '3');
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("table".to_string()),
+        acl_world: [PermissionFlag::Create as i32, PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let response: ListResponse = unpack_json_response(
+      list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(None),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(3, response.records.len());
+
+    let first: Entry = serde_json::from_value(response.records[0].clone()).unwrap();
+
+    let response: ListResponse = unpack_json_response(
+      list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(Some(format!("filter[id]={}", first.id))),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(1, response.records.len());
+    assert_eq!(
+      first,
+      serde_json::from_value(response.records[0].clone()).unwrap()
+    );
+  }
+
+  #[tokio::test]
+  async fn test_record_api_list_export() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE data (
+          id INTEGER PRIMARY KEY,
+          text TEXT NOT NULL,
+          secret TEXT
+        );
+        WITH RECURSIVE seq(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM seq WHERE i < 600)
+        INSERT INTO data (id, text, secret) SELECT i, 'text, ' || i, 'secret' FROM seq;
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("data".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.id % 3 != 0".to_string()),
+        excluded_columns: vec!["secret".to_string()],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let export = async |query: &str, headers: HeaderMap| -> Result<String, RecordError> {
+      let response = list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(Some(query.to_string())),
+        None,
+        headers,
+      )
+      .await?;
+
+      let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+      return Ok(String::from_utf8(body.to_vec()).unwrap());
+    };
+
+    // Streams all matching records across batches.
+    let csv = export("format=csv&order=-id&filter[id][$gt]=100", HeaderMap::new())
+      .await
+      .unwrap();
+    let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(lines[0], "id,text");
+    assert_eq!(lines[1], "599,\"text, 599\"");
+    assert_eq!(lines[lines.len() - 1], "101,\"text, 101\"");
+    // Ids 101 through 600 minus multiples of 3 hidden by the access rule.
+    assert_eq!(lines.len() - 1, 333);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ACCEPT, "application/x-ndjson".parse().unwrap());
+    let ndjson = export("order=id&limit=300", headers).await.unwrap();
+    let records: Vec<serde_json::Value> = ndjson
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+    assert_eq!(records.len(), 300);
+    assert_eq!(records[0], serde_json::json!({"id": 1, "text": "text, 1"}));
+    assert_eq!(records[299]["id"], 449);
+
+    // The offset only applies to the first batch.
+    let csv = export("format=csv&order=id&offset=10", HeaderMap::new())
+      .await
+      .unwrap();
+    let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(lines[1], "16,\"text, 16\"");
+    assert_eq!(lines.len() - 1, 390);
+
+    assert!(
+      export("format=csv&count=true", HeaderMap::new())
+        .await
+        .is_err()
+    );
+  }
+
+  #[tokio::test]
+  async fn test_record_api_list_keyset_cursor() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE data (
+          id   BLOB PRIMARY KEY NOT NULL CHECK(is_uuid_v7(id)) DEFAULT(uuid_v7()),
+          a    INTEGER,
+          b    TEXT
+        );
+        INSERT INTO data (a, b) VALUES
+          (1, 'x'), (NULL, 'y'), (2, NULL), (1, 'y'), (NULL, NULL),
+          (2, 'x'), (1, NULL), (NULL, 'x'), (2, 'y'), (1, 'x');
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("data".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let list = async |query: String| -> ListResponse {
+      return unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("api".to_string()),
+          RawQuery(Some(query)),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
+      )
+      .await
+      .unwrap();
+    };
+
+    // Paging with cursors yields the same records in the same order as a single page, including
+    // NULLs and ties broken by the UUIDv7 primary key.
+    for order in ["a", "-a", "a,b", "-a,b", "a,-b", "-b,-a", "b,id", "-id"] {
+      let expected: Vec<serde_json::Value> = list(format!("order={order}&limit=100")).await.records;
+      assert_eq!(10, expected.len(), "{order}");
+
+      let mut paged: Vec<serde_json::Value> = vec![];
+      let mut cursor: Option<String> = None;
+      loop {
+        let query = match cursor {
+          Some(ref cursor) => format!("order={order}&limit=3&cursor={}", urlencode(cursor)),
+          None => format!("order={order}&limit=3"),
+        };
+        let response = list(query).await;
+        if response.records.is_empty() {
+          break;
+        }
+        assert!(response.records.len() <= 3, "{order}");
+        paged.extend(response.records);
+        cursor = response.cursor;
+      }
+
+      assert_eq!(expected, paged, "{order}");
+    }
+
+    // Cursors are bound to the query's order.
+    let cursor = list("order=a&limit=3".to_string()).await.cursor.unwrap();
+    assert!(
+      list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(Some(format!("order=b&cursor={}", urlencode(&cursor)))),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .is_err()
+    );
+  }
+
+  #[tokio::test]
+  async fn test_record_api_list_fts() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE post (
+          id        INTEGER PRIMARY KEY,
+          title     TEXT NOT NULL,
+          body      TEXT NOT NULL,
+          hidden    INTEGER NOT NULL DEFAULT 0
+        ) STRICT;
+        CREATE VIRTUAL TABLE post_fts USING fts5(title, body, content='post', content_rowid='id');
+
+        INSERT INTO post (id, title, body, hidden) VALUES
+          (1, 'first', 'the quick brown fox', 0),
+          (2, 'second', 'fox and fox and dog', 0),
+          (3, 'third', 'lazy dog', 0),
+          (4, 'fourth', 'hidden fox', 1);
+        INSERT INTO post_fts(post_fts) VALUES('rebuild');
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("post".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.hidden = 0".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let list = async |query: &str| {
+      let response = list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(Some(query.to_string())),
+        None,
+        HeaderMap::new(),
+      )
+      .await?;
+      return Ok::<_, RecordError>(
+        unpack_json_response::<ListResponse>(response)
+          .await
+          .unwrap(),
+      );
+    };
+
+    // The read access rule still applies, i.e. the hidden post is not included.
+    let response = list("filter[body][$match]=fox&order=_rank").await.unwrap();
+    let ids: Vec<i64> = response
+      .records
+      .iter()
+      .map(|r| r["id"].as_i64().unwrap())
+      .collect();
+    assert_eq!(vec![2, 1], ids);
+
+    let fts = &response.records[1]["_fts"];
+    assert!(fts["rank"].is_number(), "{fts:?}");
+    assert_eq!("the quick brown <b>fox</b>", fts["highlight"]);
+    assert_eq!("the quick brown <b>fox</b>", fts["snippet"]);
+
+    // Queries that happen to be valid base64 are still passed as text.
+    let response = list("filter[body][$match]=lazy").await.unwrap();
+    assert_eq!(1, response.records.len());
+
+    // Combined with regular filters.
+    let response = list("filter[body][$match]=dog&filter[title]=third")
+      .await
+      .unwrap();
+    assert_eq!(1, response.records.len());
+
+    // Ordering by rank requires a full-text filter.
+    assert!(matches!(
+      list("order=_rank").await,
+      Err(RecordError::BadRequest(_))
+    ));
+    // Columns must be part of the index.
+    assert!(matches!(
+      list("filter[hidden][$match]=1").await,
+      Err(RecordError::BadRequest(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn test_record_api_list_json_path_filters() {
+    let state = test_state(None).await.unwrap();
+
+    trailbase_schema::registry::set_user_schema(
+      "json_path_filter_test",
+      Some(serde_json::json!({
+        "type": "object",
+        "properties": {
+          "name": { "type": "string" },
+          "age": { "type": "integer" },
+          "tags": { "type": "array", "items": { "type": "string" } },
+        },
+      })),
+    )
+    .unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE data (
+          id INTEGER PRIMARY KEY,
+          profile TEXT CHECK(jsonschema('json_path_filter_test', profile)),
+          metadata TEXT
+        );
+        INSERT INTO data (id, profile, metadata) VALUES
+          (1, '{"name": "test", "age": 17, "tags": ["a"]}', '{"nested": {"x": 1}, "n": 9}'),
+          (2, '{"name": "other", "age": 42, "tags": ["b", "a"]}', '{"nested": {"x": 2}, "n": 10}');
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("data".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let list = async |query: &str| -> Result<Vec<i64>, RecordError> {
+      let response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("api".to_string()),
+          RawQuery(Some(query.to_string())),
+          None,
+          HeaderMap::new(),
+        )
+        .await?,
+      )
+      .await
+      .unwrap();
+
+      return Ok(
+        response
+          .records
+          .iter()
+          .map(|r| r["id"].as_i64().unwrap())
+          .collect(),
+      );
+    };
+
+    // NOTE: "test" is also valid base64, however JSON values are never compared as BLOBs.
+    assert_eq!(vec![1], list("filter[profile.name]=test").await.unwrap());
+    assert_eq!(vec![2], list("filter[profile->age][$gt]=18").await.unwrap());
+    assert_eq!(vec![2], list("filter[profile.tags.0]=b").await.unwrap());
+    // Columns without JSON schema accept any path.
+    assert_eq!(vec![1], list("filter[metadata.nested.x]=1").await.unwrap());
+    // Numbers are compared numerically rather than as text, e.g. 10 > 5 while "10" < "5".
+    assert_eq!(vec![2, 1], list("filter[metadata.n][$gt]=5").await.unwrap());
+    assert_eq!(vec![1], list("filter[metadata.n][$lt]=10").await.unwrap());
+    assert_eq!(vec![2], list("filter[metadata.n][$gte]=9.5").await.unwrap());
+
+    // Paths are validated against the JSON schema.
+    assert!(list("filter[profile.missing]=x").await.is_err());
+    assert!(list("filter[id.x]=1").await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_record_api_list_related_column_filters() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE author (
+          id INTEGER PRIMARY KEY,
+          org TEXT NOT NULL,
+          private INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE post (
+          id INTEGER PRIMARY KEY,
+          author INTEGER REFERENCES author(id),
+          editor INTEGER REFERENCES author(id)
+        );
+        INSERT INTO author (id, org, private) VALUES (1, 'acme', 0), (2, 'other', 0), (3, 'acme', 1);
+        INSERT INTO post (id, author, editor) VALUES (1, 1, 2), (2, 2, 1), (3, 3, 1), (4, NULL, 1);
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("posts".to_string()),
+        table_name: Some("post".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        expand: vec!["author".to_string()],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("authors".to_string()),
+        table_name: Some("author".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.private = 0".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
 
-    let key: Key<Aes256Gcm> = Aes256Gcm::generate_key(OsRng).into();
+    let list = async |query: &str| -> Result<Vec<i64>, RecordError> {
+      let response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("posts".to_string()),
+          RawQuery(Some(query.to_string())),
+          None,
+          HeaderMap::new(),
+        )
+        .await?,
+      )
+      .await
+      .unwrap();
 
-    let value = "secret cursor";
-    let encrypted = encrypt_cursor(&key, api_name.as_bytes(), &value).unwrap();
-    let decrypted = decrypt_cursor(&key, api_name.as_bytes(), &encrypted).unwrap();
+      return Ok(
+        response
+          .records
+          .iter()
+          .map(|r| r["id"].as_i64().unwrap())
+          .collect(),
+      );
+    };
 
-    assert_eq!(value, decrypted);
+    // NOTE: Post 3's author is hidden by the authors' read access rule.
+    assert_eq!(vec![1], list("filter[author.org]=acme").await.unwrap());
+    assert_eq!(
+      vec![2, 1],
+      list("filter[author.org][$in][0]=acme&filter[author.org][$in][1]=other")
+        .await
+        .unwrap()
+    );
+    assert_eq!(
+      vec![2],
+      list("filter[$or][0][author.org]=other&filter[$or][1][id]=-1")
+        .await
+        .unwrap()
+    );
+
+    // Only expandable relations can be filtered on.
+    assert!(list("filter[editor.org]=acme").await.is_err());
+    assert!(list("filter[author.missing]=acme").await.is_err());
+    assert!(list("filter[author.org.x]=acme").await.is_err());
   }
 
   #[tokio::test]
-  async fn test_list_records_template_with_expansions() {
+  async fn test_record_api_list_geo_filters() {
     let state = test_state(None).await.unwrap();
-    let conn = state.conn();
 
-    conn
-      .execute(
-        r#"CREATE TABLE "other" (
-              "index" INTEGER PRIMARY KEY
-            ) STRICT"#,
-        (),
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE place (
+          id INTEGER PRIMARY KEY,
+          lat REAL NOT NULL,
+          lng REAL NOT NULL
+        );
+        CREATE VIRTUAL TABLE place_rtree USING rtree(id, min_lat, max_lat, min_lng, max_lng);
+
+        INSERT INTO place (id, lat, lng) VALUES
+          (1, 52.5200, 13.4050),
+          (2, 52.5219, 13.4132),
+          (3, 52.3906, 13.0645),
+          (4, 48.8566, 2.3522),
+          (5, 52.5201, 13.4051);
+        -- NOTE: Place 5 is deliberately missing from the index.
+        INSERT INTO place_rtree SELECT id, lat, lat, lng, lng FROM place WHERE id < 5;
+      "#,
       )
       .await
       .unwrap();
 
-    conn
-      .execute(
-        r#"CREATE TABLE "table" (
-              tid     INTEGER PRIMARY KEY,
-              "drop"  TEXT,
-              "index" INTEGER REFERENCES "other"("index")
-            ) STRICT"#,
-        (),
-      )
-      .await
-      .unwrap();
+    state.schema_metadata().invalidate_all().await.unwrap();
 
-    let schema_metadata = SchemaMetadataCache::new(conn.clone()).await.unwrap();
-    let table_metadata = schema_metadata
-      .get_table(&QualifiedName::parse("table").unwrap())
-      .unwrap();
-    let expanded_tables = expand_tables(
-      &schema_metadata,
-      &None,
-      |column_name| table_metadata.column_by_name(column_name).map(|(_, c)| c),
-      &["index"],
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("place".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
     )
+    .await
     .unwrap();
 
-    assert_eq!(expanded_tables.len(), 1);
-    assert_eq!(expanded_tables[0].local_column_name, "index");
-    assert_eq!(expanded_tables[0].foreign_table_name, "other");
-    assert_eq!(expanded_tables[0].foreign_column_name, "index");
+    let list = async |query: &str| -> Result<Vec<i64>, RecordError> {
+      let response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("api".to_string()),
+          RawQuery(Some(query.to_string())),
+          None,
+          HeaderMap::new(),
+        )
+        .await?,
+      )
+      .await
+      .unwrap();
 
-    let query = ListRecordQueryTemplate {
-      table_name: &QualifiedName {
-        name: "table".to_string(),
-        database_schema: Some("main".to_string()),
-      }
-      .into(),
-      column_names: &["tid", "drop", "index"],
-      read_access_clause: "_USER_.id != X'F000'",
-      filter_clause: "TRUE",
-      cursor_clause: None,
-      order_clause: "tid",
-      expanded_tables: &expanded_tables,
-      count: true,
-      offset: false,
-    }
-    .render()
-    .unwrap();
+      return Ok(
+        response
+          .records
+          .iter()
+          .map(|r| r["id"].as_i64().unwrap())
+          .collect(),
+      );
+    };
 
-    sanitize_template(&query);
+    const NEAR: &str = "filter[lat,lng][$near][0]=52.52&filter[lat,lng][$near][1]=13.405&filter[lat,lng][$near][2]=5000";
+    assert_eq!(
+      vec![1, 2],
+      list(&format!("{NEAR}&order=_distance")).await.unwrap()
+    );
+    assert_eq!(
+      vec![2, 1],
+      list(&format!("{NEAR}&order=-_distance")).await.unwrap()
+    );
 
-    let params = vec![
-      (Cow::Borrowed(":__limit"), Value::Integer(100)),
-      (
-        Cow::Borrowed(":__user_id"),
-        Value::Blob(uuid::Uuid::now_v7().into()),
-      ),
-    ];
+    // Cursors continue in order of distance.
+    let response: ListResponse = unpack_json_response(
+      list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(Some(format!("{NEAR}&order=_distance&limit=1"))),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(1, response.records[0]["id"]);
+    assert_eq!(
+      vec![2],
+      list(&format!(
+        "{NEAR}&order=_distance&cursor={}",
+        response.cursor.unwrap()
+      ))
+      .await
+      .unwrap()
+    );
 
-    let result = conn.read_query_rows(query, params).await;
-    if let Err(err) = result {
-      panic!("ERROR: {err}");
-    }
-  }
+    assert_eq!(
+      vec![3, 2, 1],
+      list("filter[lat,lng][$within][0]=52&filter[lat,lng][$within][1]=13&filter[lat,lng][$within][2]=53&filter[lat,lng][$within][3]=14")
+        .await
+        .unwrap()
+    );
 
-  fn is_auth_err(error: &RecordError) -> bool {
-    return match error {
-      RecordError::Forbidden => true,
-      _ => false,
-    };
+    assert!(list("order=_distance").await.is_err());
+    assert!(
+      list("filter[lat,x][$near][0]=52&filter[lat,x][$near][1]=13&filter[lat,x][$near][2]=1")
+        .await
+        .is_err()
+    );
   }
 
   #[tokio::test]
-  async fn test_record_api_list() {
-    #[derive(Debug, PartialEq, Deserialize)]
-    struct Entry {
-      id: i64,
-      index: String,
-    }
-
+  async fn test_record_api_list_nearest() {
     let state = test_state(None).await.unwrap();
 
     state
       .conn()
       .execute_batch(
         r#"
-        CREATE TABLE 'table' (
+        CREATE TABLE doc (
           id INTEGER PRIMARY KEY,
-          'index' TEXT NOT NULL DEFAULT ''
+          private INTEGER NOT NULL DEFAULT 0,
+          embedding BLOB
         );
-        INSERT INTO 'table' (id, 'index') VALUES (1, '1'), (2, '2'), (3, '3');
+
+        INSERT INTO doc (id, private, embedding) VALUES
+          (1, 0, vec_f32('[1, 0]')),
+          (2, 0, vec_f32('[0.9, 0.1]')),
+          (3, 0, vec_f32('[0, 1]')),
+          (4, 1, vec_f32('[1, 0]')),
+          (5, 0, NULL);
       "#,
       )
       .await
@@ -592,43 +2007,211 @@ mod tests {
       &state,
       RecordApiConfig {
         name: Some("api".to_string()),
-        table_name: Some("table".to_string()),
-        acl_world: [PermissionFlag::Create as i32, PermissionFlag::Read as i32].into(),
+        table_name: Some("doc".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.private = 0".to_string()),
         ..Default::default()
       },
     )
     .await
     .unwrap();
 
-    let response = list_records_handler(
-      State(state.clone()),
-      Path("api".to_string()),
-      RawQuery(None),
-      None,
+    let list = async |query: &str| -> Result<ListResponse, RecordError> {
+      return Ok(
+        unpack_json_response(
+          list_records_handler(
+            State(state.clone()),
+            Path("api".to_string()),
+            RawQuery(Some(query.to_string())),
+            None,
+            HeaderMap::new(),
+          )
+          .await?,
+        )
+        .await
+        .unwrap(),
+      );
+    };
+    let ids = |response: ListResponse| -> Vec<i64> {
+      return response
+        .records
+        .iter()
+        .map(|r| r["id"].as_i64().unwrap())
+        .collect();
+    };
+
+    // NOTE: Doc 4 is hidden by the read access rule and doc 5 has no embedding.
+    const NEAREST: &str = "nearest[column]=embedding&nearest[vector]=%5B1%2C0%5D";
+    assert_eq!(vec![1, 2, 3], ids(list(NEAREST).await.unwrap()));
+    assert_eq!(
+      vec![1, 2, 3],
+      ids(
+        list(&format!("{NEAREST}&nearest[metric]=l2"))
+          .await
+          .unwrap()
+      )
+    );
+    assert_eq!(
+      vec![3, 2, 1],
+      ids(
+        list(&format!("{NEAREST}&order=-_vector_distance"))
+          .await
+          .unwrap()
+      )
+    );
+
+    // Cursors continue in order of distance.
+    let response = list(&format!("{NEAREST}&limit=2")).await.unwrap();
+    let cursor = response.cursor.clone().unwrap();
+    assert_eq!(vec![1, 2], ids(response));
+    assert_eq!(
+      vec![3],
+      ids(list(&format!("{NEAREST}&cursor={cursor}")).await.unwrap())
+    );
+
+    assert!(list("order=_vector_distance").await.is_err());
+    assert!(
+      list("nearest[column]=missing&nearest[vector]=%5B1%2C0%5D")
+        .await
+        .is_err()
+    );
+
+    // Withheld vectors can neither be probed by ordering nor by filtering.
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("guarded".to_string()),
+        table_name: Some("doc".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        column_access_rules: vec![ColumnAccessRule {
+          column: Some("embedding".to_string()),
+          read_rule: Some("_ROW_.private = 0".to_string()),
+          ..Default::default()
+        }],
+        ..Default::default()
+      },
     )
     .await
-    .unwrap()
-    .0;
+    .unwrap();
 
-    assert_eq!(3, response.records.len());
+    let response: ListResponse = unpack_json_response(
+      list_records_handler(
+        State(state.clone()),
+        Path("guarded".to_string()),
+        RawQuery(Some(NEAREST.to_string())),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(vec![1, 2, 3], ids(response));
+  }
 
-    let first: Entry = serde_json::from_value(response.records[0].clone()).unwrap();
+  #[tokio::test]
+  async fn test_record_api_aggregate() {
+    let state = test_state(None).await.unwrap();
 
-    let response = list_records_handler(
-      State(state.clone()),
-      Path("api".to_string()),
-      RawQuery(Some(format!("filter[id]={}", first.id))),
-      None,
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE sale (
+          id        INTEGER PRIMARY KEY,
+          category  TEXT NOT NULL,
+          price     INTEGER NOT NULL,
+          secret    INTEGER NOT NULL DEFAULT 0,
+          hidden    INTEGER NOT NULL DEFAULT 0
+        ) STRICT;
+        INSERT INTO sale (category, price, hidden) VALUES
+          ('a', 1, 0), ('a', 2, 0), ('b', 10, 0), ('b', 20, 0), ('b', 30, 0), ('b', 1000, 1);
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("sale".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.hidden = 0".to_string()),
+        excluded_columns: vec!["secret".to_string()],
+        ..Default::default()
+      },
     )
     .await
-    .unwrap()
-    .0;
+    .unwrap();
 
-    assert_eq!(1, response.records.len());
+    let aggregate = async |query: &str| {
+      return aggregate_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(Some(query.to_string())),
+        None,
+      )
+      .await
+      .map(|response| response.0);
+    };
+
+    let response = aggregate("aggregate=count,sum(price),max(price)")
+      .await
+      .unwrap();
+    assert_eq!(1, response.groups.len());
+    assert!(response.groups[0].key.is_empty());
     assert_eq!(
-      first,
-      serde_json::from_value(response.records[0].clone()).unwrap()
+      serde_json::json!({
+        "count": 5,
+        "sum(price)": 63,
+        "max(price)": 30,
+      }),
+      serde_json::Value::Object(response.groups[0].values.clone())
+    );
+
+    let response = aggregate("aggregate=count,avg(price)&group_by=category&order=-category")
+      .await
+      .unwrap();
+    assert_eq!(2, response.groups.len());
+    assert_eq!(
+      serde_json::json!({"category": "b"}),
+      serde_json::Value::Object(response.groups[0].key.clone())
+    );
+    assert_eq!(
+      serde_json::json!({"count": 3, "avg(price)": 20.0}),
+      serde_json::Value::Object(response.groups[0].values.clone())
+    );
+
+    let response = aggregate("aggregate=min(price)&filter[category]=a")
+      .await
+      .unwrap();
+    assert_eq!(
+      serde_json::json!({"min(price)": 1}),
+      serde_json::Value::Object(response.groups[0].values.clone())
     );
+
+    // Excluded and unknown columns cannot be aggregated or grouped by.
+    assert!(matches!(
+      aggregate("aggregate=sum(secret)").await,
+      Err(RecordError::BadRequest(_))
+    ));
+    assert!(matches!(
+      aggregate("group_by=secret").await,
+      Err(RecordError::BadRequest(_))
+    ));
+    assert!(matches!(
+      aggregate("aggregate=sum(unknown)").await,
+      Err(RecordError::BadRequest(_))
+    ));
+    // Only group keys can be ordered on.
+    assert!(matches!(
+      aggregate("group_by=category&order=price").await,
+      Err(RecordError::BadRequest(_))
+    ));
   }
 
   #[tokio::test]
@@ -938,27 +2521,25 @@ mod tests {
         to_message(arr_asc[0].clone())
       );
 
-      // NOTE: Ascending ordering by PK column is no longer allowed, since PK might no longer
-      // strictly monotonically increase like the _rowid_ by which we cursor.
-      // let mut cursored_asc = list_records(
-      //   &state,
-      //   Some(&user_y_token.auth_token),
-      //   Some(format!(
-      //     "order={}&cursor={cursor_middle}",
-      //     urlencode("+mid")
-      //   )),
-      // )
-      // .await
-      // .unwrap()
-      // .records;
-      //
-      // assert_eq!(cursored_asc.len(), 1);
-      // assert_eq!(
-      //   to_message(cursored_asc.swap_remove(0)),
-      //   to_message(arr_asc[2].clone())
-      // );
+      let mut cursored_asc = list_records(
+        &state,
+        Some(&user_y_token.auth_token),
+        Some(format!(
+          "order={}&cursor={cursor_middle}",
+          urlencode("+mid")
+        )),
+      )
+      .await
+      .unwrap()
+      .records;
+
+      assert_eq!(cursored_asc.len(), 1);
+      assert_eq!(
+        to_message(cursored_asc.swap_remove(0)),
+        to_message(arr_asc[2].clone())
+      );
 
-      // Ordering and cursor return an error when PK is not primary order cirteria.
+      // Cursors are bound to the order they were minted for.
       assert!(
         list_records(
           &state,
@@ -971,6 +2552,45 @@ mod tests {
         .await
         .is_err()
       );
+
+      // Paginate by a non-unique, non-pk column with ties broken by the primary key.
+      for order in ["+room", "-room", "-room,+mid", "+data"] {
+        let expected = list_records(
+          &state,
+          Some(&user_y_token.auth_token),
+          Some(format!("order={}", urlencode(order))),
+        )
+        .await
+        .unwrap()
+        .records;
+        assert_eq!(expected.len(), 3);
+
+        let mut paginated: Vec<serde_json::Value> = vec![];
+        let mut cursor: Option<String> = None;
+        loop {
+          let resp = list_records(
+            &state,
+            Some(&user_y_token.auth_token),
+            Some(format!(
+              "order={}&limit=1{}",
+              urlencode(order),
+              cursor
+                .as_ref()
+                .map_or(String::new(), |c| format!("&cursor={c}"))
+            )),
+          )
+          .await
+          .unwrap();
+
+          if resp.records.is_empty() {
+            break;
+          }
+          paginated.extend(resp.records);
+          cursor = resp.cursor;
+        }
+
+        assert_eq!(expected, paginated, "{order}");
+      }
     }
 
     {
@@ -1003,15 +2623,15 @@ mod tests {
     auth_token: Option<&str>,
     query: Option<String>,
   ) -> Result<ListResponse, RecordError> {
-    let json_response = list_records_handler(
+    let response = list_records_handler(
       State(state.clone()),
       Path("messages_api".to_string()),
       RawQuery(query),
       auth_token.and_then(|token| User::from_auth_token(&state, token)),
+      HeaderMap::new(),
     )
     .await?;
 
-    let response: ListResponse = json_response.0;
-    return Ok(response);
+    return Ok(unpack_json_response(response).await.unwrap());
   }
 }
@@ -1,27 +1,39 @@
 use axum::{
   Router,
-  routing::{delete, get, patch, post},
+  routing::{delete, get, patch, post, put},
 };
 use utoipa::OpenApi;
 
+pub(crate) mod batch;
+pub(crate) mod bulk;
+pub(crate) mod change_log;
 pub(crate) mod create_record;
 pub(crate) mod delete_record;
 mod error;
+mod etag;
+mod expand;
+mod export;
 pub(crate) mod files;
+pub(crate) mod history;
+mod image_transform;
 pub(crate) mod json_schema;
 pub(crate) mod list_records;
 pub(crate) mod params;
+mod projection;
 pub mod query_builder;
 pub(crate) mod read_record;
 mod record_api;
+mod soft_delete;
 pub mod sql_to_json;
 pub(crate) mod subscribe;
 pub mod test_utils;
 mod update_record;
+mod upsert_record;
 mod validate;
 
 pub(crate) use error::RecordError;
 pub use record_api::RecordApi;
+pub(crate) use record_api::validate_rule;
 pub(crate) use validate::validate_record_api_config;
 
 use crate::AppState;
@@ -35,12 +47,29 @@ use crate::constants::RECORD_API_PATH;
     read_record::get_uploaded_file_from_record_handler,
     read_record::get_uploaded_files_from_record_handler,
     list_records::list_records_handler,
+    list_records::aggregate_records_handler,
     create_record::create_record_handler,
     update_record::update_record_handler,
+    upsert_record::upsert_record_handler,
     delete_record::delete_record_handler,
+    soft_delete::restore_record_handler,
+    history::list_record_history_handler,
+    bulk::bulk_update_records_handler,
+    bulk::bulk_delete_records_handler,
     json_schema::json_schema_handler,
+    batch::batch_handler,
   ),
-  components(schemas(create_record::CreateRecordResponse))
+  components(schemas(
+    create_record::CreateRecordResponse,
+    upsert_record::UpsertRecordResponse,
+    list_records::AggregateResponse,
+    list_records::AggregateGroup,
+    batch::BatchRequest,
+    batch::BatchResponse,
+    bulk::BulkResponse,
+    history::RecordHistoryResponse,
+    history::RecordRevision
+  ))
 )]
 pub(super) struct RecordOpenApi;
 
@@ -54,6 +83,10 @@ pub(crate) fn router() -> Router<AppState> {
       &format!("/{RECORD_API_PATH}/{{name}}"),
       post(create_record::create_record_handler),
     )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}"),
+      put(upsert_record::upsert_record_handler),
+    )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}"),
       patch(update_record::update_record_handler),
@@ -62,6 +95,22 @@ pub(crate) fn router() -> Router<AppState> {
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}"),
       delete(delete_record::delete_record_handler),
     )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/restore"),
+      post(soft_delete::restore_record_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/history"),
+      get(history::list_record_history_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}"),
+      patch(bulk::bulk_update_records_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}"),
+      delete(bulk::bulk_delete_records_handler),
+    )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}"),
       get(list_records::list_records_handler),
@@ -74,6 +123,10 @@ pub(crate) fn router() -> Router<AppState> {
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/files/{{column_name}}/{{file_index}}"),
       get(read_record::get_uploaded_files_from_record_handler),
     )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/aggregate"),
+      get(list_records::aggregate_records_handler),
+    )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}/schema"),
       get(json_schema::json_schema_handler),
@@ -81,6 +134,14 @@ pub(crate) fn router() -> Router<AppState> {
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}/subscribe/{{record}}"),
       get(subscribe::add_subscription_sse_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/_subscribe"),
+      get(subscribe::subscription_ws_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/_batch"),
+      post(batch::batch_handler),
     );
 }
 
@@ -613,7 +613,7 @@ mod tests {
     .unwrap();
     trailbase_extension::jsonschema::get_schema(SCHEMA_NAME).unwrap();
 
-    let metadata = TableMetadata::new(table.clone(), &[table], USER_TABLE);
+    let metadata = TableMetadata::new(table.clone(), &[table], &[], USER_TABLE);
 
     let id: [u8; 16] = uuid::Uuid::now_v7().as_bytes().clone();
     let blob: Vec<u8> = [0; 128].to_vec();
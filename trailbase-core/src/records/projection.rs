@@ -0,0 +1,131 @@
+use trailbase_schema::sqlite::Column;
+
+use crate::records::{RecordApi, RecordError};
+use crate::schema_metadata::JsonColumnMetadata;
+
+/// Subset of a table's columns selected via the `fields` query parameter.
+pub(crate) struct Projection {
+  columns: Vec<Column>,
+  json_metadata: Vec<Option<JsonColumnMetadata>>,
+}
+
+impl Projection {
+  /// Projects `columns` onto the requested `fields`. The `required` columns, e.g. the primary key,
+  /// are always selected.
+  pub(crate) fn new(
+    columns: &[Column],
+    json_metadata: &[Option<JsonColumnMetadata>],
+    fields: &[&str],
+    required: &[&str],
+  ) -> Result<Self, RecordError> {
+    // NOTE: Only accept known, non-hidden columns, thus avoiding SQL injections.
+    for field in fields {
+      if field.starts_with("_") || !columns.iter().any(|c| c.name == *field) {
+        return Err(RecordError::BadRequest("Invalid field"));
+      }
+    }
+
+    let (columns, json_metadata) = columns
+      .iter()
+      .zip(json_metadata)
+      .filter(|(c, _)| {
+        let name = c.name.as_str();
+        return fields.contains(&name) || required.contains(&name);
+      })
+      .map(|(c, m)| (c.clone(), m.clone()))
+      .unzip();
+
+    return Ok(Self {
+      columns,
+      json_metadata,
+    });
+  }
+
+  /// Projects the API's columns onto the requested top-level `fields`. The primary key as well as
+  /// foreign keys of requested forward expansions are always selected.
+  pub(crate) fn from_api<T: AsRef<str>>(
+    api: &RecordApi,
+    fields: &[&str],
+    expand: &[T],
+  ) -> Result<Option<Self>, RecordError> {
+    if fields.is_empty() {
+      return Ok(None);
+    }
+
+    let (_index, pk_column) = api.record_pk_column();
+    let mut required = vec![pk_column.name.as_str()];
+    required.extend(forward_expansion_columns(expand));
+
+    return Ok(Some(Self::new(
+      api.columns(),
+      api.json_column_metadata(),
+      fields,
+      &required,
+    )?));
+  }
+
+  #[inline]
+  pub(crate) fn columns(&self) -> &[Column] {
+    return &self.columns;
+  }
+
+  #[inline]
+  pub(crate) fn json_column_metadata(&self) -> &[Option<JsonColumnMetadata>] {
+    return &self.json_metadata;
+  }
+}
+
+/// Splits `fields` into top-level columns and the fields of expanded records keyed by the first
+/// segment of their expansion path, e.g. `author.org.name` into `("author", "org.name")`.
+pub(crate) fn split_fields<T: AsRef<str>>(fields: &[T]) -> (Vec<&str>, Vec<(&str, &str)>) {
+  let mut columns: Vec<&str> = vec![];
+  let mut nested: Vec<(&str, &str)> = vec![];
+
+  for field in fields {
+    match field.as_ref().split_once('.') {
+      Some(split) => nested.push(split),
+      None => columns.push(field.as_ref()),
+    }
+  }
+
+  return (columns, nested);
+}
+
+/// Foreign key columns of forward expansions, e.g. `author` for `author.org` but not
+/// `comment!post`.
+pub(crate) fn forward_expansion_columns<T: AsRef<str>>(expand: &[T]) -> Vec<&str> {
+  return expand
+    .iter()
+    .map(|path| {
+      let path = path.as_ref();
+      return path.split('.').next().unwrap_or(path);
+    })
+    .filter(|segment| !segment.contains('!'))
+    .collect();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_split_fields() {
+    let (columns, nested) =
+      split_fields(&["id", "author.name", "author.org.name", "comment!post.id"]);
+
+    assert_eq!(vec!["id"], columns);
+    assert_eq!(
+      vec![
+        ("author", "name"),
+        ("author", "org.name"),
+        ("comment!post", "id"),
+      ],
+      nested
+    );
+
+    assert_eq!(
+      vec!["author", "owner"],
+      forward_expansion_columns(&["author.org", "comment!post", "owner"])
+    );
+  }
+}
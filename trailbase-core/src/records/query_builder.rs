@@ -1,18 +1,21 @@
 use askama::Template;
 use itertools::Itertools;
 use log::*;
-use std::sync::Arc;
+use std::borrow::Cow;
 use thiserror::Error;
-use trailbase_schema::sqlite::{Column, ColumnOption};
-use trailbase_schema::{FileUpload, FileUploads, QualifiedName, QualifiedNameEscaped};
+use trailbase_schema::sqlite::Column;
+use trailbase_schema::{FileUpload, FileUploads, QualifiedNameEscaped};
 use trailbase_sqlite::{NamedParams, Params as _, Value};
 
 use crate::AppState;
+use crate::auth::user::User;
 use crate::config::proto::ConflictResolutionStrategy;
 use crate::records::error::RecordError;
-use crate::records::files::{FileManager, delete_pending_files};
+use crate::records::etag::IfMatch;
+use crate::records::files::{FileManager, delete_pending_files, delete_pending_files_bulk};
+use crate::records::history;
 use crate::records::params::{FileMetadataContents, Params};
-use crate::schema_metadata::{JsonColumnMetadata, SchemaMetadataCache, TableMetadata};
+use crate::schema_metadata::JsonColumnMetadata;
 
 #[derive(Debug, Error)]
 pub enum QueryError {
@@ -30,159 +33,49 @@ pub enum QueryError {
   File(#[from] crate::records::files::FileError),
   #[error("Not found")]
   NotFound,
+  #[error("Record changed")]
+  RecordChanged,
+  #[error("Forbidden")]
+  Forbidden,
   #[error("Internal: {0}")]
   Internal(Box<dyn std::error::Error + Send + Sync>),
 }
 
-pub(crate) struct ExpandedTable {
-  pub metadata: Arc<TableMetadata>,
-  pub local_column_name: String,
-  pub num_columns: usize,
-
-  pub foreign_table_name: String,
-  pub foreign_column_name: String,
-}
-
-pub(crate) fn expand_tables<'a, 'b, T: AsRef<str>>(
-  schema_metadata: &SchemaMetadataCache,
-  database_schema: &Option<String>,
-  root_column_by_name: impl Fn(&'a str) -> Option<&'b Column>,
-  expand: &'a [T],
-) -> Result<Vec<ExpandedTable>, RecordError> {
-  let mut expanded_tables = Vec::<ExpandedTable>::with_capacity(expand.len());
-
-  for col_name in expand {
-    let col_name = col_name.as_ref();
-    if col_name.is_empty() {
-      continue;
-    }
-    let Some(column) = root_column_by_name(col_name) else {
-      return Err(RecordError::Internal("Missing column".into()));
-    };
-
-    // FIXME: This only expand FKs expressed as column constraints missing table constraints.
-    let Some(ColumnOption::ForeignKey {
-      foreign_table: foreign_table_name,
-      referred_columns: _,
-      ..
-    }) = column
-      .options
-      .iter()
-      .find_or_first(|o| matches!(o, ColumnOption::ForeignKey { .. }))
-    else {
-      return Err(RecordError::Internal("not a foreign key".into()));
-    };
-
-    let Some(foreign_table) = schema_metadata.get_table(&QualifiedName {
-      name: foreign_table_name.clone(),
-      database_schema: database_schema.clone(),
-    }) else {
-      return Err(RecordError::ApiRequiresTable);
-    };
-
-    let Some(foreign_pk_column_idx) = foreign_table.record_pk_column else {
-      return Err(RecordError::Internal("invalid PK".into()));
-    };
-
-    let foreign_pk_column = &foreign_table.schema.columns[foreign_pk_column_idx].name;
-
-    // TODO: Check that `referred_columns` and foreign_pk_column are the same. It's already
-    // validated as part of config validation.
-
-    let num_columns = foreign_table.schema.columns.len();
-    let foreign_table_name = foreign_table_name.to_string();
-    let foreign_column_name = foreign_pk_column.to_string();
-
-    expanded_tables.push(ExpandedTable {
-      metadata: foreign_table,
-      local_column_name: col_name.to_string(),
-      num_columns,
-      foreign_table_name,
-      foreign_column_name,
-    });
-  }
-
-  return Ok(expanded_tables);
-}
-
-#[derive(Template)]
-#[template(escape = "none", path = "read_record_query_expanded.sql")]
-struct ReadRecordExpandedQueryTemplate<'a> {
-  table_name: &'a QualifiedNameEscaped,
-  column_names: &'a [&'a str],
-  pk_column_name: &'a str,
-  expanded_tables: &'a [ExpandedTable],
-}
-
 #[derive(Template)]
 #[template(escape = "none", path = "read_record_query.sql")]
 struct ReadRecordQueryTemplate<'a> {
   table_name: &'a QualifiedNameEscaped,
-  column_names: &'a [&'a str],
+  column_exprs: &'a [String],
   pk_column_name: &'a str,
+  etag_expr: Option<&'a str>,
+  soft_delete_column: Option<&'a str>,
 }
 
 pub(crate) struct SelectQueryBuilder;
 
-pub(crate) struct ExpandedSelectQueryResult {
-  pub(crate) root: trailbase_sqlite::Row,
-  pub(crate) foreign_rows: Vec<(Arc<TableMetadata>, trailbase_sqlite::Row)>,
-}
-
 impl SelectQueryBuilder {
+  /// Reads the record with the `:__record_id` param as `_ROW_` with `_USER_` in scope, i.e.
+  /// `column_exprs` may evaluate column-level read rules, see `RecordApi::column_read_expr`.
   pub(crate) async fn run(
     conn: &trailbase_sqlite::Connection,
     table_name: &QualifiedNameEscaped,
-    column_names: &[&str],
+    column_exprs: &[String],
     pk_column: &str,
-    pk_value: Value,
+    params: NamedParams,
+    etag_expr: Option<&str>,
+    soft_delete_column: Option<&str>,
   ) -> Result<Option<trailbase_sqlite::Row>, RecordError> {
     let sql = ReadRecordQueryTemplate {
       table_name,
-      column_names,
+      column_exprs,
       pk_column_name: pk_column,
+      etag_expr,
+      soft_delete_column,
     }
     .render()
     .map_err(|err| RecordError::Internal(err.into()))?;
 
-    return Ok(conn.read_query_row(sql, [pk_value]).await?);
-  }
-
-  pub(crate) async fn run_expanded(
-    conn: &trailbase_sqlite::Connection,
-    table_name: &QualifiedNameEscaped,
-    column_names: &[&str],
-    pk_column: &str,
-    pk_value: Value,
-    expanded_tables: &[ExpandedTable],
-  ) -> Result<Option<ExpandedSelectQueryResult>, RecordError> {
-    let sql = ReadRecordExpandedQueryTemplate {
-      table_name,
-      column_names,
-      pk_column_name: pk_column,
-      expanded_tables,
-    }
-    .render()
-    .map_err(|err| RecordError::Internal(err.into()))?;
-
-    let Some(mut row) = conn.read_query_row(sql, [pk_value]).await? else {
-      return Ok(None);
-    };
-
-    let mut foreign_rows: Vec<(Arc<TableMetadata>, trailbase_sqlite::Row)> =
-      Vec::with_capacity(expanded_tables.len());
-
-    let mut curr = row.split_off(column_names.len());
-    for expanded_table in expanded_tables {
-      let next = curr.split_off(expanded_table.num_columns);
-      foreign_rows.push((expanded_table.metadata.clone(), curr));
-      curr = next;
-    }
-
-    return Ok(Some(ExpandedSelectQueryResult {
-      root: row,
-      foreign_rows,
-    }));
+    return Ok(conn.read_query_row(sql, params).await?);
   }
 }
 
@@ -196,23 +89,21 @@ impl GetFileQueryBuilder {
     json_metadata: &JsonColumnMetadata,
     pk_column: &str,
     pk_value: Value,
+    soft_delete_column: Option<&str>,
+    read_rule: Option<(&str, Option<&User>)>,
   ) -> Result<FileUpload, QueryError> {
     return match &json_metadata {
       JsonColumnMetadata::SchemaName(name) if name == "std.FileUpload" => {
-        let column_name = &file_column.name;
-
-        let Some(row) = state
-          .conn()
-          .read_query_row(
-            format!(r#"SELECT "{column_name}" FROM {table_name} WHERE "{pk_column}" = $1"#),
-            [pk_value],
-          )
-          .await?
-        else {
-          return Err(QueryError::NotFound);
-        };
-
-        let json: String = row.get(0)?;
+        let json = read_file_column(
+          state,
+          table_name,
+          &file_column.name,
+          pk_column,
+          pk_value,
+          soft_delete_column,
+          read_rule,
+        )
+        .await?;
         let file_upload: FileUpload = serde_json::from_str(&json)?;
         Ok(file_upload)
       }
@@ -231,23 +122,21 @@ impl GetFilesQueryBuilder {
     json_metadata: &JsonColumnMetadata,
     pk_column: &str,
     pk_value: Value,
+    soft_delete_column: Option<&str>,
+    read_rule: Option<(&str, Option<&User>)>,
   ) -> Result<FileUploads, QueryError> {
     return match &json_metadata {
       JsonColumnMetadata::SchemaName(name) if name == "std.FileUploads" => {
-        let column_name = &file_column.name;
-
-        let Some(row) = state
-          .conn()
-          .read_query_row(
-            format!(r#"SELECT "{column_name}" FROM {table_name} WHERE "{pk_column}" = $1"#),
-            [pk_value],
-          )
-          .await?
-        else {
-          return Err(QueryError::NotFound);
-        };
-
-        let contents: String = row.get(0)?;
+        let contents = read_file_column(
+          state,
+          table_name,
+          &file_column.name,
+          pk_column,
+          pk_value,
+          soft_delete_column,
+          read_rule,
+        )
+        .await?;
         let file_uploads: FileUploads = serde_json::from_str(&contents)?;
         Ok(file_uploads)
       }
@@ -256,6 +145,62 @@ impl GetFilesQueryBuilder {
   }
 }
 
+/// Reads a record's file column, skipping the record if it has been soft-deleted. Fails with
+/// `Forbidden` if the column's read rule, if any, doesn't hold for the given user.
+async fn read_file_column(
+  state: &AppState,
+  table_name: &QualifiedNameEscaped,
+  column_name: &str,
+  pk_column: &str,
+  pk_value: Value,
+  soft_delete_column: Option<&str>,
+  read_rule: Option<(&str, Option<&User>)>,
+) -> Result<String, QueryError> {
+  let soft_delete_filter = soft_delete_column
+    .map(|column| format!(r#" AND _ROW_."{column}" IS NULL"#))
+    .unwrap_or_default();
+
+  let mut params: NamedParams = vec![(Cow::Borrowed(":__record_id"), pk_value)];
+  let (from, allowed) = match read_rule {
+    Some((rule, user)) => {
+      params.push((
+        Cow::Borrowed(":__user_id"),
+        user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+      ));
+      params.push((
+        Cow::Borrowed(":__user_roles"),
+        user.map_or(Value::Null, |u| Value::Text(u.roles_json())),
+      ));
+
+      (
+        format!(
+          "(SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_, {table_name} AS _ROW_"
+        ),
+        format!("CAST(({rule}) AS INTEGER)"),
+      )
+    }
+    None => (format!("{table_name} AS _ROW_"), "1".to_string()),
+  };
+
+  let Some(row) = state
+    .conn()
+    .read_query_row(
+      format!(
+        r#"SELECT _ROW_."{column_name}", {allowed} FROM {from} WHERE _ROW_."{pk_column}" = :__record_id{soft_delete_filter}"#
+      ),
+      params,
+    )
+    .await?
+  else {
+    return Err(QueryError::NotFound);
+  };
+
+  if !row.get::<Option<bool>>(1)?.unwrap_or(false) {
+    return Err(QueryError::Forbidden);
+  }
+  return Ok(row.get(0)?);
+}
+
 #[derive(Template)]
 #[template(escape = "none", path = "create_record_query.sql")]
 struct CreateRecordQueryTemplate<'a> {
@@ -275,6 +220,7 @@ impl InsertQueryBuilder {
     return_column_name: &str,
     has_file_columns: bool,
     params: Params,
+    actor: Option<&User>,
   ) -> Result<rusqlite::types::Value, QueryError> {
     let (query, named_params, files) = Self::build_insert_query(
       table_name,
@@ -291,9 +237,8 @@ impl InsertQueryBuilder {
       FileManager::write(state, files).await?
     };
 
-    let (rowid, return_value): (i64, rusqlite::types::Value) = state
-      .conn()
-      .query_row_f(query, named_params, |row| -> Result<_, rusqlite::Error> {
+    let (rowid, return_value): (i64, rusqlite::types::Value) =
+      history::query_row_f(state.conn(), actor, query, named_params, |row| {
         return Ok((row.get(0)?, row.get(1)?));
       })
       .await?
@@ -316,6 +261,7 @@ impl InsertQueryBuilder {
     return_column_name: &str,
     has_file_columns: bool,
     params_list: Vec<Params>,
+    actor: Option<&User>,
   ) -> Result<Vec<rusqlite::types::Value>, QueryError> {
     let mut all_files: FileMetadataContents = vec![];
     let mut query_and_params: Vec<(String, NamedParams)> = vec![];
@@ -340,12 +286,16 @@ impl InsertQueryBuilder {
       FileManager::write(state, all_files).await?
     };
 
+    let actor: Option<[u8; 16]> = actor.map(|user| user.uuid.into_bytes());
     let result: Vec<(i64, rusqlite::types::Value)> = state
       .conn()
       .call(move |conn| {
         let mut rows = Vec::<(i64, rusqlite::types::Value)>::with_capacity(query_and_params.len());
 
         let tx = conn.transaction()?;
+        if actor.is_some() {
+          history::set_actor(&tx, actor.as_ref())?;
+        }
 
         for (query, named_params) in query_and_params {
           let mut stmt = tx.prepare_cached(&query)?;
@@ -360,6 +310,9 @@ impl InsertQueryBuilder {
           };
         }
 
+        if actor.is_some() {
+          history::set_actor(&tx, None)?;
+        }
         tx.commit()?;
 
         return Ok(rows);
@@ -378,7 +331,7 @@ impl InsertQueryBuilder {
     return Ok(result.into_iter().map(|(_rowid, v)| v).collect());
   }
 
-  fn build_insert_query(
+  pub(crate) fn build_insert_query(
     table_name: &QualifiedNameEscaped,
     params: Params,
     conflict_resolution: Option<ConflictResolutionStrategy>,
@@ -418,18 +371,25 @@ struct UpdateRecordQueryTemplate<'a> {
   table_name: &'a QualifiedNameEscaped,
   column_names: &'a [String],
   pk_column_name: &'a str,
+  soft_delete_column: Option<&'a str>,
   returning: Option<&'a str>,
 }
 
 pub(crate) struct UpdateQueryBuilder;
 
 impl UpdateQueryBuilder {
+  /// Updates the record unless it has been soft-deleted, if `soft_delete_column` is given, or
+  /// the `If-Match` precondition fails.
+  #[allow(clippy::too_many_arguments)]
   pub(crate) async fn run(
     state: &AppState,
     table_name: &QualifiedNameEscaped,
     pk_column: &str,
+    soft_delete_column: Option<&str>,
     has_file_columns: bool,
     mut params: Params,
+    if_match: Option<IfMatch>,
+    actor: Option<&User>,
   ) -> Result<(), QueryError> {
     if params.column_names.len() < 2 {
       // Only the primary key. Nothing to do.
@@ -446,59 +406,389 @@ impl UpdateQueryBuilder {
       FileManager::write(state, files).await?
     };
 
-    let query = UpdateRecordQueryTemplate {
+    let query = Self::build_update_query(
       table_name,
-      column_names: &params.column_names,
+      &params.column_names,
+      pk_column,
+      soft_delete_column,
+    )?;
+
+    let rowid: Option<i64> =
+      write_rowid_if_match(state, actor, if_match, query, params.named_params).await?;
+
+    let Some(rowid) = rowid else {
+      // Nothing was written, i.e. the written files can be cleaned up.
+      return Err(QueryError::NotFound);
+    };
+
+    // Successful write, do not cleanup written files.
+    file_manager.release();
+
+    if has_file_columns {
+      delete_pending_files(state, table_name, rowid).await?;
+    }
+
+    return Ok(());
+  }
+
+  /// Builds an update query for the given columns returning the updated record's `_rowid_`.
+  pub(crate) fn build_update_query(
+    table_name: &QualifiedNameEscaped,
+    column_names: &[String],
+    pk_column: &str,
+    soft_delete_column: Option<&str>,
+  ) -> Result<String, QueryError> {
+    return UpdateRecordQueryTemplate {
+      table_name,
+      column_names,
       pk_column_name: pk_column,
+      soft_delete_column,
       returning: Some("_rowid_"),
     }
     .render()
-    .map_err(|err| QueryError::Internal(err.into()))?;
+    .map_err(|err| QueryError::Internal(err.into()));
+  }
+}
 
-    let rowid: Option<i64> = state
-      .conn()
-      .query_row_f(query, params.named_params, |row| row.get(0))
-      .await?;
+#[derive(Template)]
+#[template(escape = "none", path = "bulk_update_record_query.sql")]
+struct BulkUpdateRecordQueryTemplate<'a> {
+  table_name: &'a QualifiedNameEscaped,
+  column_names: &'a [String],
+  request_column_names: &'a [&'a str],
+  update_access_clause: &'a str,
+  filter_clause: &'a str,
+}
 
-    // Successful write, do not cleanup written files.
-    file_manager.release();
+impl UpdateQueryBuilder {
+  /// Updates all records matching the filter, for which the update access rule holds, with the
+  /// same values. Returns the number of updated records.
+  ///
+  /// Expects `params` to bind `:__user_id`, `:__fields`, all columns to construct `_REQ_` as well
+  /// as the filter's params.
+  #[allow(clippy::too_many_arguments)]
+  pub(crate) async fn run_bulk(
+    state: &AppState,
+    table_name: &QualifiedNameEscaped,
+    columns: &[Column],
+    has_file_columns: bool,
+    column_names: &[String],
+    update_access_clause: &str,
+    filter_clause: &str,
+    params: NamedParams,
+    actor: Option<&User>,
+  ) -> Result<usize, QueryError> {
+    if column_names.is_empty() {
+      return Err(QueryError::Precondition("Nothing to update"));
+    }
+
+    let query = Self::build_bulk_update_query(
+      table_name,
+      columns,
+      column_names,
+      update_access_clause,
+      filter_clause,
+    )?;
+
+    let rows = history::write_query_rows(state.conn(), actor, query, params).await?;
+    let rowids = rowids_from_rows(&rows)?;
 
     if has_file_columns {
-      if let Some(rowid) = rowid {
-        delete_pending_files(state, table_name, rowid).await?;
-      }
+      delete_pending_files_bulk(state, table_name, &rowids).await?;
     }
 
-    return Ok(());
+    return Ok(rowids.len());
+  }
+
+  pub(crate) fn build_bulk_update_query(
+    table_name: &QualifiedNameEscaped,
+    columns: &[Column],
+    column_names: &[String],
+    update_access_clause: &str,
+    filter_clause: &str,
+  ) -> Result<String, QueryError> {
+    let request_column_names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+
+    return BulkUpdateRecordQueryTemplate {
+      table_name,
+      column_names,
+      request_column_names: &request_column_names,
+      update_access_clause,
+      filter_clause,
+    }
+    .render()
+    .map_err(|err| QueryError::Internal(err.into()));
+  }
+}
+
+#[derive(Template)]
+#[template(escape = "none", path = "upsert_record_query.sql")]
+struct UpsertRecordQueryTemplate<'a> {
+  table_name: &'a QualifiedNameEscaped,
+  column_names: &'a [String],
+  conflict_columns: &'a [String],
+  update_column_names: &'a [&'a str],
+  pk_column_name: &'a str,
+}
+
+pub(crate) struct UpsertQueryBuilder;
+
+impl UpsertQueryBuilder {
+  /// Builds an `INSERT ... ON CONFLICT DO UPDATE` query returning the record's `_rowid_` and
+  /// primary key.
+  ///
+  /// All provided columns except for the conflict target, primary key and `insert_only_columns`
+  /// will be updated on conflict.
+  pub(crate) fn build_upsert_query(
+    table_name: &QualifiedNameEscaped,
+    column_names: &[String],
+    conflict_columns: &[String],
+    insert_only_columns: &[String],
+    pk_column: &str,
+  ) -> Result<String, QueryError> {
+    if column_names.is_empty() || conflict_columns.is_empty() {
+      return Err(QueryError::Precondition("Missing columns"));
+    }
+
+    let mut update_column_names: Vec<&str> = column_names
+      .iter()
+      .filter(|name| {
+        *name != pk_column
+          && !conflict_columns.contains(*name)
+          && !insert_only_columns.contains(*name)
+      })
+      .map(|name| name.as_str())
+      .collect();
+
+    // A "DO NOTHING" would not return the conflicting row, thus fall back to a no-op update.
+    if update_column_names.is_empty() {
+      update_column_names.push(&conflict_columns[0]);
+    }
+
+    return UpsertRecordQueryTemplate {
+      table_name,
+      column_names,
+      conflict_columns,
+      update_column_names: &update_column_names,
+      pk_column_name: pk_column,
+    }
+    .render()
+    .map_err(|err| QueryError::Internal(err.into()));
+  }
+
+  /// Builds a query looking up the primary key of the record conflicting on the given columns and
+  /// whether said record has been soft-deleted.
+  pub(crate) fn build_conflict_query(
+    table_name: &QualifiedNameEscaped,
+    conflict_columns: &[String],
+    pk_column: &str,
+    soft_delete_column: Option<&str>,
+  ) -> String {
+    let where_clause = conflict_columns
+      .iter()
+      .map(|name| format!(r#""{name}" = :{name}"#))
+      .join(" AND ");
+    let deleted = soft_delete_column
+      .map(|column| format!(r#""{column}" IS NOT NULL"#))
+      .unwrap_or_else(|| "FALSE".to_string());
+
+    return format!(r#"SELECT "{pk_column}", {deleted} FROM {table_name} WHERE {where_clause}"#);
   }
 }
 
 pub(crate) struct DeleteQueryBuilder;
 
 impl DeleteQueryBuilder {
+  /// Deletes the record or marks it as deleted, if `soft_delete_column` is given, unless the
+  /// `If-Match` precondition fails. Files of soft-deleted records are retained until the record is
+  /// purged.
+  #[allow(clippy::too_many_arguments)]
   pub(crate) async fn run(
     state: &AppState,
     table_name: &QualifiedNameEscaped,
     pk_column: &str,
     pk_value: Value,
+    soft_delete_column: Option<&str>,
     has_file_columns: bool,
+    if_match: Option<IfMatch>,
+    actor: Option<&User>,
   ) -> Result<i64, QueryError> {
-    let rowid: i64 = state
-      .conn()
-      .query_row_f(
-        format!(r#"DELETE FROM {table_name} WHERE "{pk_column}" = $1 RETURNING _rowid_"#),
-        [pk_value],
-        |row| row.get(0),
-      )
-      .await?
-      .ok_or_else(|| QueryError::NotFound)?;
-
-    if has_file_columns {
+    let rowid: i64 = write_rowid_if_match(
+      state,
+      actor,
+      if_match,
+      Self::build_delete_query(table_name, pk_column, soft_delete_column),
+      [(":__record_id", pk_value)],
+    )
+    .await?
+    .ok_or_else(|| QueryError::NotFound)?;
+
+    if has_file_columns && soft_delete_column.is_none() {
       delete_pending_files(state, table_name, rowid).await?;
     }
 
     return Ok(rowid);
   }
+
+  /// Restores a soft-deleted record.
+  pub(crate) async fn run_restore(
+    state: &AppState,
+    table_name: &QualifiedNameEscaped,
+    pk_column: &str,
+    pk_value: Value,
+    soft_delete_column: &str,
+    actor: Option<&User>,
+  ) -> Result<i64, QueryError> {
+    return history::query_row_f(
+      state.conn(),
+      actor,
+      format!(
+        r#"UPDATE {table_name} SET "{soft_delete_column}" = NULL WHERE "{pk_column}" = $1 AND "{soft_delete_column}" IS NOT NULL RETURNING _rowid_"#
+      ),
+      [pk_value],
+      |row| row.get(0),
+    )
+    .await?
+    .ok_or_else(|| QueryError::NotFound);
+  }
+
+  /// Builds a query deleting or soft-deleting the record identified by `:__record_id` returning
+  /// its `_rowid_`.
+  pub(crate) fn build_delete_query(
+    table_name: &QualifiedNameEscaped,
+    pk_column: &str,
+    soft_delete_column: Option<&str>,
+  ) -> String {
+    return match soft_delete_column {
+      Some(column) => format!(
+        r#"UPDATE {table_name} SET "{column}" = UNIXEPOCH() WHERE "{pk_column}" = :__record_id AND "{column}" IS NULL RETURNING _rowid_"#
+      ),
+      None => {
+        format!(r#"DELETE FROM {table_name} WHERE "{pk_column}" = :__record_id RETURNING _rowid_"#)
+      }
+    };
+  }
+}
+
+#[derive(Template)]
+#[template(escape = "none", path = "bulk_delete_record_query.sql")]
+struct BulkDeleteRecordQueryTemplate<'a> {
+  table_name: &'a QualifiedNameEscaped,
+  soft_delete_column: Option<&'a str>,
+  delete_access_clause: &'a str,
+  filter_clause: &'a str,
+}
+
+impl DeleteQueryBuilder {
+  /// Deletes, or soft-deletes if `soft_delete_column` is given, all records matching the filter,
+  /// for which the delete access rule holds. Returns the number of deleted records.
+  #[allow(clippy::too_many_arguments)]
+  pub(crate) async fn run_bulk(
+    state: &AppState,
+    table_name: &QualifiedNameEscaped,
+    soft_delete_column: Option<&str>,
+    has_file_columns: bool,
+    delete_access_clause: &str,
+    filter_clause: &str,
+    params: NamedParams,
+    actor: Option<&User>,
+  ) -> Result<usize, QueryError> {
+    let query = Self::build_bulk_delete_query(
+      table_name,
+      soft_delete_column,
+      delete_access_clause,
+      filter_clause,
+    )?;
+
+    let rows = history::write_query_rows(state.conn(), actor, query, params).await?;
+    let rowids = rowids_from_rows(&rows)?;
+
+    if has_file_columns && soft_delete_column.is_none() {
+      delete_pending_files_bulk(state, table_name, &rowids).await?;
+    }
+
+    return Ok(rowids.len());
+  }
+
+  pub(crate) fn build_bulk_delete_query(
+    table_name: &QualifiedNameEscaped,
+    soft_delete_column: Option<&str>,
+    delete_access_clause: &str,
+    filter_clause: &str,
+  ) -> Result<String, QueryError> {
+    return BulkDeleteRecordQueryTemplate {
+      table_name,
+      soft_delete_column,
+      delete_access_clause,
+      filter_clause,
+    }
+    .render()
+    .map_err(|err| QueryError::Internal(err.into()));
+  }
+}
+
+/// Runs a single-row write returning the affected `_rowid_`, if any. The `If-Match` precondition,
+/// if given, is checked within the same transaction on the writer, i.e. concurrent writes cannot
+/// sneak in between the check and the write.
+async fn write_rowid_if_match(
+  state: &AppState,
+  actor: Option<&User>,
+  if_match: Option<IfMatch>,
+  sql: String,
+  params: impl trailbase_sqlite::Params + Send + 'static,
+) -> Result<Option<i64>, QueryError> {
+  return history::call_as(state.conn(), actor, move |conn| {
+    if let Some(if_match) = if_match {
+      if !if_match.matches(conn)? {
+        return Ok(Err(QueryError::RecordChanged));
+      }
+    }
+
+    let mut stmt = conn.prepare_cached(&sql)?;
+    params.bind(&mut stmt)?;
+
+    let mut rows = stmt.raw_query();
+    return match rows.next()? {
+      Some(row) => Ok(Ok(Some(row.get(0)?))),
+      None => Ok(Ok(None)),
+    };
+  })
+  .await?;
+}
+
+fn rowids_from_rows(rows: &trailbase_sqlite::Rows) -> Result<Vec<i64>, QueryError> {
+  return rows
+    .iter()
+    .map(|row| match row.get_value(0) {
+      Some(Value::Integer(rowid)) => Ok(*rowid),
+      value => Err(QueryError::Internal(
+        format!("expected rowid, got {value:?}").into(),
+      )),
+    })
+    .collect();
+}
+
+/// Runs the given query on a synchronous connection, e.g. within an open transaction, and maps
+/// the first row, if any.
+pub(crate) fn query_row<T>(
+  conn: &rusqlite::Connection,
+  query: &str,
+  params: impl trailbase_sqlite::Params,
+  f: impl FnOnce(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+) -> Result<Option<T>, RecordError> {
+  let mut stmt = conn.prepare_cached(query).map_err(sql_error)?;
+  params.bind(&mut stmt).map_err(sql_error)?;
+
+  let mut rows = stmt.raw_query();
+  return match rows.next().map_err(sql_error)? {
+    Some(row) => Ok(Some(f(row).map_err(sql_error)?)),
+    None => Ok(None),
+  };
+}
+
+#[inline]
+pub(crate) fn sql_error(err: rusqlite::Error) -> RecordError {
+  return trailbase_sqlite::Error::Rusqlite(err).into();
 }
 
 #[cfg(test)]
@@ -557,4 +847,79 @@ mod tests {
       sanitize_template(&query);
     }
   }
+
+  #[test]
+  fn test_upsert_record_template() {
+    let table_name: QualifiedNameEscaped = QualifiedName::parse("table").unwrap().into();
+
+    {
+      let query = UpsertQueryBuilder::build_upsert_query(
+        &table_name,
+        &["id".to_string(), "index".to_string(), "trigger".to_string()],
+        &["index".to_string()],
+        &["trigger".to_string()],
+        "id",
+      )
+      .unwrap();
+
+      sanitize_template(&query);
+    }
+
+    {
+      // Only the conflict target.
+      let query = UpsertQueryBuilder::build_upsert_query(
+        &table_name,
+        &["id".to_string()],
+        &["id".to_string()],
+        &[],
+        "id",
+      )
+      .unwrap();
+
+      sanitize_template(&query);
+    }
+
+    sanitize_template(&UpsertQueryBuilder::build_conflict_query(
+      &table_name,
+      &["index".to_string(), "trigger".to_string()],
+      "id",
+      Some("select"),
+    ));
+  }
+
+  #[test]
+  fn test_bulk_record_templates() {
+    let table_name: QualifiedNameEscaped = QualifiedName::parse("table").unwrap().into();
+    let columns: Vec<Column> = ["id", "index", "trigger"]
+      .into_iter()
+      .map(|name| Column {
+        name: name.to_string(),
+        data_type: trailbase_schema::sqlite::ColumnDataType::Integer,
+        options: vec![],
+      })
+      .collect();
+
+    sanitize_template(
+      &UpdateQueryBuilder::build_bulk_update_query(
+        &table_name,
+        &columns,
+        &["index".to_string(), "trigger".to_string()],
+        r#"_ROW_."index" = _REQ_."index""#,
+        r#"_ROW_."trigger" > :__p0"#,
+      )
+      .unwrap(),
+    );
+
+    for soft_delete_column in [None, Some("deleted")] {
+      sanitize_template(
+        &DeleteQueryBuilder::build_bulk_delete_query(
+          &table_name,
+          soft_delete_column,
+          "TRUE",
+          r#"_ROW_."index" = :__p0"#,
+        )
+        .unwrap(),
+      );
+    }
+  }
 }
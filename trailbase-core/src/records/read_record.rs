@@ -1,26 +1,43 @@
 use axum::{
   Json,
   extract::{Path, Query, State},
-  response::Response,
+  http::{HeaderMap, StatusCode, header},
+  response::{IntoResponse, Response},
 };
 use serde::Deserialize;
+use trailbase_schema::FileUpload;
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
-use crate::records::files::read_file_into_response;
+use crate::records::etag::{etag_from_value, etag_key, if_none_match};
+use crate::records::expand::{ExpandParent, build_expand_tree, expand_records, is_expandable};
+use crate::records::files::{FileError, read_file_into_response};
+use crate::records::image_transform::{ImageTransformQuery, read_transformed_file_into_response};
+use crate::records::projection::{Projection, split_fields};
 use crate::records::query_builder::{
-  ExpandedSelectQueryResult, GetFileQueryBuilder, GetFilesQueryBuilder, SelectQueryBuilder,
-  expand_tables,
+  GetFileQueryBuilder, GetFilesQueryBuilder, QueryError, SelectQueryBuilder,
 };
-use crate::records::sql_to_json::{row_to_json, row_to_json_expand};
+use crate::records::soft_delete::include_deleted;
+use crate::records::sql_to_json::row_to_json_expand;
 use crate::records::{Permission, RecordError};
 
+/// Responses depend on the requesting user, e.g. through column-level read rules.
+const VARY: &str = "Authorization, Cookie";
+
 #[derive(Debug, Default, Deserialize)]
 pub struct ReadRecordQuery {
-  /// Comma separated list of foreign key column names that should be expanded.
+  /// Comma separated list of foreign key columns or reverse relations that should be expanded,
+  /// e.g. `author.org,comment!post`.
   ///
-  /// Requires the API's configuration to explicitly allow expanding said columns.
+  /// Requires the API's configuration to explicitly allow expanding said paths.
   pub expand: Option<String>,
+
+  /// Comma separated list of columns to select, e.g. `id,title`. Fields of expanded records are
+  /// prefixed by their expansion path, e.g. `author.name`. The primary key is always included.
+  pub fields: Option<String>,
+
+  /// Include soft-deleted records (admin only).
+  pub include_deleted: Option<bool>,
 }
 
 /// Read record.
@@ -36,7 +53,8 @@ pub async fn read_record_handler(
   Path((api_name, record)): Path<(String, String)>,
   Query(query): Query<ReadRecordQuery>,
   user: Option<User>,
-) -> Result<Json<serde_json::Value>, RecordError> {
+  headers: HeaderMap,
+) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
   };
@@ -48,96 +66,118 @@ pub async fn read_record_handler(
     .await?;
 
   let (_index, pk_column) = api.record_pk_column();
-  let column_names: Vec<_> = api.columns().iter().map(|c| c.name.as_str()).collect();
-
-  return Ok(Json(match query.expand {
-    Some(query_expand) if !query_expand.is_empty() => {
-      let Some(expand) = api.expand() else {
-        return Err(RecordError::BadRequest("Invalid expansion"));
-      };
-
-      // Input validation, i.e. only accept columns that are also configured.
-      let query_expand: Vec<_> = query_expand.split(",").collect();
-      for col_name in &query_expand {
-        if !query_expand.contains(col_name) {
-          return Err(RecordError::BadRequest("Invalid expansion"));
-        }
-      }
 
-      let expanded_tables = expand_tables(
-        state.schema_metadata(),
-        &api.qualified_name().database_schema,
-        |column_name| {
-          api
-            .column_index_by_name(column_name)
-            .map(|idx| &api.columns()[idx])
-        },
-        &query_expand,
-      )?;
-
-      let Some(ExpandedSelectQueryResult { root, foreign_rows }) =
-        SelectQueryBuilder::run_expanded(
-          state.conn(),
-          api.table_name(),
-          &column_names,
-          &pk_column.name,
-          record_id,
-          &expanded_tables,
-        )
-        .await?
-      else {
-        return Err(RecordError::RecordNotFound);
-      };
-
-      // Alloc a map from column name to value that's pre-filled with with Value::Null for all
-      // expandable columns.
-      let mut expand = expand.clone();
-
-      for (col_name, (metadata, row)) in std::iter::zip(query_expand, foreign_rows) {
-        let foreign_value = row_to_json(
-          &metadata.schema.columns,
-          &metadata.json_metadata.columns,
-          &row,
-          prefix_filter,
-        )
-        .map_err(|err| RecordError::Internal(err.into()))?;
+  let query_expand: Vec<&str> = match query.expand {
+    Some(ref expand) if !expand.is_empty() => expand.split(",").collect(),
+    Some(_) | None => vec![],
+  };
+  // Input validation, i.e. only accept paths that are also configured.
+  for path in &query_expand {
+    if !is_expandable(api.expand_paths(), path) {
+      return Err(RecordError::BadRequest("Invalid expansion"));
+    }
+  }
 
-        let result = expand.insert(col_name.to_string(), foreign_value);
-        assert!(result.is_some());
-      }
+  let query_fields: Vec<&str> = match query.fields {
+    Some(ref fields) if !fields.is_empty() => fields.split(",").collect(),
+    Some(_) | None => vec![],
+  };
+  let (fields, nested_fields) = split_fields(&query_fields);
+  let projection = Projection::from_api(&api, &fields, &query_expand)?;
+  let (columns, json_metadata) = match projection {
+    Some(ref projection) => (projection.columns(), projection.json_column_metadata()),
+    None => (api.columns(), api.json_column_metadata()),
+  };
+  let column_names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+  // NOTE: Column-level read rules are evaluated inline, yielding NULL for withheld values.
+  let column_exprs: Vec<_> = columns
+    .iter()
+    .map(|c| api.column_read_expr(&c.name))
+    .collect();
+
+  let expand_nodes = if query_expand.is_empty() && nested_fields.is_empty() {
+    vec![]
+  } else {
+    build_expand_tree(
+      &state,
+      &api,
+      &ExpandParent {
+        table_name: api.qualified_name(),
+        columns,
+        pk_column_name: &pk_column.name,
+      },
+      &query_expand,
+      api.expand_paths(),
+      &nested_fields,
+    )?
+  };
 
-      row_to_json_expand(
-        api.columns(),
-        api.json_column_metadata(),
-        &root,
-        prefix_filter,
-        Some(&expand),
-      )
-      .map_err(|err| RecordError::Internal(err.into()))?
-    }
-    Some(_) | None => {
-      let Some(row) = SelectQueryBuilder::run(
-        state.conn(),
-        api.table_name(),
-        &column_names,
-        &pk_column.name,
-        record_id,
-      )
-      .await?
-      else {
-        return Err(RecordError::RecordNotFound);
-      };
-
-      row_to_json_expand(
-        api.columns(),
-        api.json_column_metadata(),
-        &row,
-        prefix_filter,
-        api.expand(),
+  let soft_delete_column = if include_deleted(&state, user.as_ref(), query.include_deleted).await? {
+    None
+  } else {
+    api.soft_delete_column()
+  };
+
+  let etag_expr = api.etag_expr(&column_names);
+  let Some(row) = SelectQueryBuilder::run(
+    state.conn(),
+    api.table_name(),
+    &column_exprs,
+    &pk_column.name,
+    api.build_named_params(Permission::Read, Some(&record_id), None, user.as_ref())?,
+    Some(&etag_expr),
+    soft_delete_column,
+  )
+  .await?
+  else {
+    return Err(RecordError::RecordNotFound);
+  };
+
+  // The version directly follows the selected columns.
+  let Some(version) = row.get_value(columns.len()) else {
+    return Err(RecordError::Internal("missing version".into()));
+  };
+  // NOTE: The ETag varies with the projection and the withheld columns, i.e. the representation.
+  let etag = etag_from_value(
+    &etag_key(&state),
+    version,
+    projection.as_ref().map(|_| column_names.as_slice()),
+  )?;
+
+  // NOTE: Expanded records may change independently, thus only plain reads can be not modified.
+  if expand_nodes.is_empty() && if_none_match(&headers, &etag) {
+    return Ok(
+      (
+        StatusCode::NOT_MODIFIED,
+        [(header::ETAG, etag), (header::VARY, VARY.to_string())],
       )
-      .map_err(|err| RecordError::Internal(err.into()))?
-    }
-  }));
+        .into_response(),
+    );
+  }
+
+  let mut record = row_to_json_expand(columns, json_metadata, &row, prefix_filter, api.expand())
+    .map_err(|err| RecordError::Internal(err.into()))?;
+
+  if !expand_nodes.is_empty() {
+    expand_records(
+      &state,
+      user.as_ref(),
+      &expand_nodes,
+      columns,
+      &pk_column.name,
+      &[&row],
+      std::slice::from_mut(&mut record),
+    )
+    .await?;
+  }
+
+  return Ok(
+    (
+      [(header::ETAG, etag), (header::VARY, VARY.to_string())],
+      Json(record),
+    )
+      .into_response(),
+  );
 }
 
 type GetUploadedFileFromRecordPath = Path<(
@@ -147,9 +187,12 @@ type GetUploadedFileFromRecordPath = Path<(
 )>;
 
 /// Read file associated with record.
+///
+/// Images can be resized and re-encoded on the fly, e.g. for thumbnails. Variants are cached.
 #[utoipa::path(
   get,
   path = "/:name/:record/file/:column_name",
+  params(ImageTransformQuery),
   responses(
     (status = 200, description = "File contents.")
   )
@@ -157,6 +200,7 @@ type GetUploadedFileFromRecordPath = Path<(
 pub async fn get_uploaded_file_from_record_handler(
   state: State<AppState>,
   Path((api_name, record, column_name)): GetUploadedFileFromRecordPath,
+  Query(transform_query): Query<ImageTransformQuery>,
   user: Option<User>,
 ) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
@@ -189,13 +233,19 @@ pub async fn get_uploaded_file_from_record_handler(
     column_json_metadata,
     &pk_column.name,
     record_id,
+    api.soft_delete_column(),
+    api
+      .column_read_rule(&column_name)
+      .map(|rule| (rule, user.as_ref())),
   )
   .await
-  .map_err(|err| RecordError::Internal(err.into()))?;
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    QueryError::Forbidden => RecordError::Forbidden,
+    err => RecordError::Internal(err.into()),
+  })?;
 
-  return read_file_into_response(&state, file_upload)
-    .await
-    .map_err(|err| RecordError::Internal(err.into()));
+  return read_file_or_variant_into_response(&state, file_upload, &transform_query).await;
 }
 
 type GetUploadedFilesFromRecordPath = Path<(
@@ -206,9 +256,12 @@ type GetUploadedFilesFromRecordPath = Path<(
 )>;
 
 /// Read single file from list associated with record.
+///
+/// Images can be resized and re-encoded on the fly, e.g. for thumbnails. Variants are cached.
 #[utoipa::path(
   get,
   path = "/:name/:record/files/:column_name/:file_index",
+  params(ImageTransformQuery),
   responses(
     (status = 200, description = "File contents.")
   )
@@ -216,6 +269,7 @@ type GetUploadedFilesFromRecordPath = Path<(
 pub async fn get_uploaded_files_from_record_handler(
   State(state): State<AppState>,
   Path((api_name, record, column_name, file_index)): GetUploadedFilesFromRecordPath,
+  Query(transform_query): Query<ImageTransformQuery>,
   user: Option<User>,
 ) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
@@ -248,17 +302,46 @@ pub async fn get_uploaded_files_from_record_handler(
     column_json_metadata,
     &pk_column.name,
     record_id,
+    api.soft_delete_column(),
+    api
+      .column_read_rule(&column_name)
+      .map(|rule| (rule, user.as_ref())),
   )
   .await
-  .map_err(|err| RecordError::Internal(err.into()))?;
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    QueryError::Forbidden => RecordError::Forbidden,
+    err => RecordError::Internal(err.into()),
+  })?;
 
   if file_index >= file_uploads.0.len() {
     return Err(RecordError::RecordNotFound);
   }
 
-  return read_file_into_response(&state, file_uploads.0.remove(file_index))
-    .await
-    .map_err(|err| RecordError::Internal(err.into()));
+  return read_file_or_variant_into_response(
+    &state,
+    file_uploads.0.remove(file_index),
+    &transform_query,
+  )
+  .await;
+}
+
+async fn read_file_or_variant_into_response(
+  state: &AppState,
+  file_upload: FileUpload,
+  transform_query: &ImageTransformQuery,
+) -> Result<Response, RecordError> {
+  let result = match transform_query.transform()? {
+    Some(transform) => {
+      read_transformed_file_into_response(state.objectstore(), &file_upload, transform).await
+    }
+    None => read_file_into_response(state, file_upload).await,
+  };
+
+  return result.map_err(|err| match err {
+    FileError::Image(_) => RecordError::BadRequest("Unsupported image"),
+    err => RecordError::Internal(err.into()),
+  });
 }
 
 #[inline]
@@ -269,7 +352,7 @@ fn prefix_filter(col_name: &str) -> bool {
 #[cfg(test)]
 mod test {
   use axum::Json;
-  use axum::extract::{Path, Query, State};
+  use axum::extract::{Path, Query, RawQuery, State};
   use serde_json::json;
   use trailbase_schema::{FileUpload, FileUploadInput};
 
@@ -278,13 +361,15 @@ mod test {
   use crate::app_state::*;
   use crate::auth::api::login::login_with_password;
   use crate::auth::user::User;
-  use crate::config::proto::{PermissionFlag, RecordApiConfig};
+  use crate::config::proto::{ColumnAccessRule, PermissionFlag, RecordApiConfig, RoleAcl};
   use crate::constants::USER_TABLE;
   use crate::extract::Either;
   use crate::records::create_record::{
     CreateRecordQuery, CreateRecordResponse, create_record_handler,
   };
   use crate::records::delete_record::delete_record_handler;
+  use crate::records::image_transform::ImageOutputFormat;
+  use crate::records::list_records::{ListResponse, list_records_handler};
   use crate::records::params::JsonRow;
   use crate::records::test_utils::*;
   use crate::test::unpack_json_response;
@@ -385,7 +470,8 @@ mod test {
           State(state.clone()),
           Path(("messages_api".to_string(), id_to_b64(&message_id),)),
           Query(ReadRecordQuery::default()),
-          None
+          None,
+          HeaderMap::new(),
         )
         .await
         .is_err()
@@ -398,6 +484,7 @@ mod test {
           Path(("messages_api".to_string(), id_to_b64(&message_id))),
           Query(ReadRecordQuery::default()),
           User::from_auth_token(&state, &user_x_token.auth_token),
+          HeaderMap::new(),
         )
         .await;
         assert!(response.is_ok(), "{response:?}");
@@ -410,6 +497,7 @@ mod test {
           Path(("messages_api".to_string(), id_to_b64(&message_id))),
           Query(ReadRecordQuery::default()),
           User::from_auth_token(&state, &user_y_token.auth_token),
+          HeaderMap::new(),
         )
         .await;
         assert!(response.is_ok(), "{response:?}");
@@ -428,6 +516,7 @@ mod test {
         Path(("messages_api".to_string(), id_to_b64(&message_id))),
         Query(ReadRecordQuery::default()),
         User::from_auth_token(&state, &user_y_token.auth_token),
+        HeaderMap::new(),
       )
       .await;
       assert!(response.is_err(), "{response:?}");
@@ -496,11 +585,16 @@ mod test {
 
     let record_path = (API_NAME.to_string(), create_response.ids[0].clone());
 
-    let Json(_) = read_record_handler(
-      State(state),
-      Path(record_path),
-      Query(ReadRecordQuery::default()),
-      None,
+    let _: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state),
+        Path(record_path),
+        Query(ReadRecordQuery::default()),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
     )
     .await
     .unwrap();
@@ -535,11 +629,16 @@ mod test {
 
     let record_path = (API_NAME.to_string(), create_response.ids[0].clone());
 
-    let Json(value) = read_record_handler(
-      State(state),
-      Path(record_path),
-      Query(ReadRecordQuery::default()),
-      None,
+    let value: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state),
+        Path(record_path),
+        Query(ReadRecordQuery::default()),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
     )
     .await
     .unwrap();
@@ -589,11 +688,16 @@ mod test {
 
     let record_path = (API_NAME.to_string(), create_response.ids[0].clone());
 
-    let Json(value) = read_record_handler(
-      State(state.clone()),
-      Path(record_path.clone()),
-      Query(ReadRecordQuery::default()),
-      None,
+    let value: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state.clone()),
+        Path(record_path.clone()),
+        Query(ReadRecordQuery::default()),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
     )
     .await
     .unwrap();
@@ -615,6 +719,7 @@ mod test {
     let read_response = get_uploaded_file_from_record_handler(
       State(state.clone()),
       Path(record_file_path.clone()),
+      Query(ImageTransformQuery::default()),
       None,
     )
     .await
@@ -625,9 +730,14 @@ mod test {
       .unwrap();
     assert_eq!(body.to_vec(), bytes);
 
-    let _ = delete_record_handler(State(state.clone()), Path(record_path.clone()), None)
-      .await
-      .unwrap();
+    let _ = delete_record_handler(
+      State(state.clone()),
+      Path(record_path.clone()),
+      None,
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
 
     let mut read_dir = tokio::fs::read_dir(state.data_dir().uploads_path())
       .await
@@ -641,6 +751,7 @@ mod test {
       get_uploaded_file_from_record_handler(
         State(state.clone()),
         Path(record_file_path.clone()),
+        Query(ImageTransformQuery::default()),
         None,
       )
       .await
@@ -648,6 +759,113 @@ mod test {
     );
   }
 
+  #[tokio::test]
+  async fn test_image_variant_e2e() {
+    let state = test_state(None).await.unwrap();
+    const API_NAME: &str = "test_api";
+    create_test_record_api(&state, API_NAME).await;
+
+    let mut png = std::io::Cursor::new(Vec::<u8>::new());
+    image::DynamicImage::ImageRgb8(image::RgbImage::new(40, 20))
+      .write_to(&mut png, image::ImageFormat::Png)
+      .unwrap();
+
+    let create_response: CreateRecordResponse = unpack_json_response(
+      create_record_handler(
+        State(state.clone()),
+        Path(API_NAME.to_string()),
+        Query(CreateRecordQuery::default()),
+        None,
+        Either::Json(
+          json_row_from_value(json!({
+            "file": FileUploadInput {
+              name: None,
+              filename: Some("image.png".to_string()),
+              content_type: Some("image/png".to_string()),
+              data: png.into_inner(),
+            },
+          }))
+          .unwrap()
+          .into(),
+        ),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let record_file_path = Path((
+      API_NAME.to_string(),
+      create_response.ids[0].clone(),
+      "file".to_string(),
+    ));
+    let thumbnail_query = || {
+      Query(ImageTransformQuery {
+        width: Some(10),
+        format: Some(ImageOutputFormat::Jpeg),
+        ..Default::default()
+      })
+    };
+
+    let mut variants: Vec<Vec<u8>> = vec![];
+    for _ in 0..2 {
+      let response = get_uploaded_file_from_record_handler(
+        State(state.clone()),
+        Path(record_file_path.clone()),
+        thumbnail_query(),
+        None,
+      )
+      .await
+      .unwrap();
+      assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "image/jpeg"
+      );
+
+      let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+      let image = image::load_from_memory(&body).unwrap();
+      // Requested dimensions are rounded up to supported ones.
+      assert_eq!((16, 8), (image.width(), image.height()));
+      variants.push(body.to_vec());
+    }
+    // The second request is served from the cached variant.
+    assert_eq!(variants[0], variants[1]);
+
+    assert!(
+      get_uploaded_file_from_record_handler(
+        State(state.clone()),
+        Path(record_file_path.clone()),
+        Query(ImageTransformQuery {
+          width: Some(0),
+          ..Default::default()
+        }),
+        None,
+      )
+      .await
+      .is_err()
+    );
+
+    let _ = delete_record_handler(
+      State(state.clone()),
+      Path((API_NAME.to_string(), create_response.ids[0].clone())),
+      None,
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+
+    // Variants are deleted alongside the original.
+    let mut read_dir = tokio::fs::read_dir(state.data_dir().uploads_path())
+      .await
+      .unwrap();
+    while let Some(entry) = read_dir.next_entry().await.unwrap() {
+      panic!("File should be deleted: {entry:?}");
+    }
+  }
+
   #[tokio::test]
   async fn test_multiple_file_upload_download_e2e() {
     let state = test_state(None).await.unwrap();
@@ -693,11 +911,16 @@ mod test {
 
     let record_path = Path((API_NAME.to_string(), resp.ids[0].clone()));
 
-    let Json(value) = read_record_handler(
-      State(state.clone()),
-      record_path,
-      Query(ReadRecordQuery::default()),
-      None,
+    let value: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state.clone()),
+        record_path,
+        Query(ReadRecordQuery::default()),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
     )
     .await
     .unwrap();
@@ -721,10 +944,14 @@ mod test {
         index,
       ));
 
-      let response =
-        get_uploaded_files_from_record_handler(State(state.clone()), record_file_path, None)
-          .await
-          .unwrap();
+      let response = get_uploaded_files_from_record_handler(
+        State(state.clone()),
+        record_file_path,
+        Query(ImageTransformQuery::default()),
+        None,
+      )
+      .await
+      .unwrap();
 
       let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
@@ -794,6 +1021,7 @@ mod test {
       Path(("messages_api".to_string(), id_to_b64(&message_id))),
       Query(ReadRecordQuery::default()),
       User::from_auth_token(&state, &user_x_token.auth_token),
+      HeaderMap::new(),
     )
     .await;
     assert!(response.is_ok(), "{response:?}");
@@ -856,11 +1084,16 @@ mod test {
 
     assert_eq!(create_response.ids[0], "1");
 
-    let Json(json) = read_record_handler(
-      State(state.clone()),
-      Path((API_NAME.to_string(), create_response.ids[0].clone())),
-      Query(ReadRecordQuery::default()),
-      None,
+    let json: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state.clone()),
+        Path((API_NAME.to_string(), create_response.ids[0].clone())),
+        Query(ReadRecordQuery::default()),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
     )
     .await
     .unwrap();
@@ -974,6 +1207,280 @@ mod test {
     );
   }
 
+  #[tokio::test]
+  async fn test_column_access_rules() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+    conn
+      .execute_batch(
+        r#"
+        CREATE TABLE employee (
+          id           INTEGER PRIMARY KEY NOT NULL,
+          owner        BLOB,
+          name         TEXT NOT NULL,
+          salary       INTEGER
+        ) STRICT;
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("employee".to_string()),
+        acl_authenticated: [PermissionFlag::Create as i32, PermissionFlag::Read as i32].into(),
+        column_access_rules: vec![ColumnAccessRule {
+          column: Some("salary".to_string()),
+          read_rule: Some("_ROW_.owner = _USER_.id".to_string()),
+          write_rule: Some("_REQ_.salary <= 100000".to_string()),
+        }],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let password = "Secret!1!!";
+    let owner = create_user_for_test(&state, "owner@test.com", password)
+      .await
+      .unwrap();
+    let owner_token = login_with_password(&state, "owner@test.com", password)
+      .await
+      .unwrap();
+    let other = create_user_for_test(&state, "other@test.com", password)
+      .await
+      .unwrap();
+    let other_token = login_with_password(&state, "other@test.com", password)
+      .await
+      .unwrap();
+    assert_ne!(owner, other);
+
+    conn
+      .execute(
+        "INSERT INTO employee (id, owner, name, salary) VALUES (1, $1, 'alice', 90000)",
+        trailbase_sqlite::params!(owner.into_bytes()),
+      )
+      .await
+      .unwrap();
+
+    let read = async |token: &str| -> serde_json::Value {
+      return unpack_json_response(
+        read_record_handler(
+          State(state.clone()),
+          Path(("api".to_string(), "1".to_string())),
+          Query(ReadRecordQuery::default()),
+          User::from_auth_token(&state, token),
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
+      )
+      .await
+      .unwrap();
+    };
+    let list = async |token: &str, query: &str| -> ListResponse {
+      return unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("api".to_string()),
+          RawQuery(Some(query.to_string())),
+          User::from_auth_token(&state, token),
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
+      )
+      .await
+      .unwrap();
+    };
+
+    // The salary is only visible to the owner, while the rest of the record is visible to all.
+    let record = read(&owner_token.auth_token).await;
+    assert_eq!(json!(90000), record["salary"]);
+    let record = read(&other_token.auth_token).await;
+    assert_eq!(json!("alice"), record["name"]);
+    assert_eq!(serde_json::Value::Null, record["salary"]);
+
+    // ETags vary with the representation, i.e. the withheld columns and the projection.
+    let etag = async |token: &str, fields: Option<&str>| -> String {
+      let response = read_record_handler(
+        State(state.clone()),
+        Path(("api".to_string(), "1".to_string())),
+        Query(ReadRecordQuery {
+          fields: fields.map(|f| f.to_string()),
+          ..Default::default()
+        }),
+        User::from_auth_token(&state, token),
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap();
+      assert_eq!(
+        "Authorization, Cookie",
+        response.headers().get(header::VARY).unwrap()
+      );
+      return response
+        .headers()
+        .get(header::ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    };
+    let owner_etag = etag(&owner_token.auth_token, None).await;
+    assert_eq!(owner_etag, etag(&owner_token.auth_token, None).await);
+    assert_ne!(owner_etag, etag(&other_token.auth_token, None).await);
+    assert_ne!(
+      owner_etag,
+      etag(&owner_token.auth_token, Some("id,name")).await
+    );
+
+    let response = list(&owner_token.auth_token, "filter[salary][$gt]=80000").await;
+    assert_eq!(1, response.records.len());
+    assert_eq!(json!(90000), response.records[0]["salary"]);
+
+    // Withheld values can neither be observed nor filtered on.
+    let response = list(&other_token.auth_token, "").await;
+    assert_eq!(1, response.records.len());
+    assert_eq!(serde_json::Value::Null, response.records[0]["salary"]);
+    let response = list(&other_token.auth_token, "filter[salary][$gt]=80000").await;
+    assert!(response.records.is_empty());
+
+    // Writes are subject to the column's write rule if, and only if, the column is set.
+    let create = async |value: serde_json::Value| {
+      return create_record_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        Query(CreateRecordQuery::default()),
+        User::from_auth_token(&state, &owner_token.auth_token),
+        Either::Json(json_row_from_value(value).unwrap().into()),
+      )
+      .await;
+    };
+    assert!(create(json!({"name": "bob"})).await.is_ok());
+    assert!(
+      create(json!({"name": "carol", "salary": 50000}))
+        .await
+        .is_ok()
+    );
+    assert!(matches!(
+      create(json!({"name": "dave", "salary": 200000})).await,
+      Err(RecordError::Forbidden)
+    ));
+  }
+
+  #[tokio::test]
+  async fn test_role_acls() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+    conn
+      .execute_batch(
+        r#"
+        CREATE TABLE article (
+          id           INTEGER PRIMARY KEY NOT NULL,
+          title        TEXT NOT NULL,
+          draft        INTEGER NOT NULL DEFAULT FALSE
+        ) STRICT;
+        INSERT INTO article (id, title, draft) VALUES (1, 'published', FALSE), (2, 'draft', TRUE);
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("article".to_string()),
+        acl_roles: vec![RoleAcl {
+          role: Some("editor".to_string()),
+          acl: vec![PermissionFlag::Read as i32],
+        }],
+        read_access_rule: Some(
+          "(NOT _ROW_.draft OR 'admin' IN (SELECT value FROM json_each(_USER_.roles)))".to_string(),
+        ),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let password = "Secret!1!!";
+    let mut tokens = vec![];
+    for (email, roles) in [
+      ("plain@test.com", vec![]),
+      ("editor@test.com", vec!["editor"]),
+      ("admin@test.com", vec!["editor", "admin"]),
+    ] {
+      let user_id = create_user_for_test(&state, email, password).await.unwrap();
+      for role in roles {
+        conn
+          .execute(
+            "INSERT INTO _user_role (user, role) VALUES ($1, $2)",
+            trailbase_sqlite::params!(user_id.into_bytes(), role.to_string()),
+          )
+          .await
+          .unwrap();
+      }
+      tokens.push(
+        login_with_password(&state, email, password)
+          .await
+          .unwrap()
+          .auth_token,
+      );
+    }
+
+    let read = async |token: &str, id: &str| {
+      return read_record_handler(
+        State(state.clone()),
+        Path(("api".to_string(), id.to_string())),
+        Query(ReadRecordQuery::default()),
+        User::from_auth_token(&state, token),
+        HeaderMap::new(),
+      )
+      .await;
+    };
+
+    // Roles are embedded in the auth token.
+    let user = User::from_auth_token(&state, &tokens[2]).unwrap();
+    assert_eq!(vec!["admin".to_string(), "editor".to_string()], user.roles);
+
+    // Only members of the "editor" role have access.
+    assert!(matches!(
+      read(&tokens[0], "1").await,
+      Err(RecordError::Forbidden)
+    ));
+    assert!(read(&tokens[1], "1").await.is_ok());
+
+    // Access rules can refer to the roles via `_USER_.roles`.
+    assert!(matches!(
+      read(&tokens[1], "2").await,
+      Err(RecordError::Forbidden)
+    ));
+    assert!(read(&tokens[2], "2").await.is_ok());
+
+    let response: ListResponse = unpack_json_response(
+      list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(None),
+        User::from_auth_token(&state, &tokens[1]),
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(1, response.records.len());
+  }
+
   #[tokio::test]
   async fn test_expand_fields() {
     let state = test_state(None).await.unwrap();
@@ -1015,6 +1522,18 @@ mod test {
     .await
     .unwrap();
 
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("parent_api".to_string()),
+        table_name: Some("parent".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
     let expected = json!({
       "id": 1,
       "parent": {
@@ -1026,13 +1545,20 @@ mod test {
       },
     });
 
-    let Json(value) = read_record_handler(
-      State(state.clone()),
-      Path(("child_api".to_string(), "1".to_string())),
-      Query(ReadRecordQuery {
-        expand: Some("parent".to_string()),
-      }),
-      None,
+    let value: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state.clone()),
+        Path(("child_api".to_string(), "1".to_string())),
+        Query(ReadRecordQuery {
+          expand: Some("parent".to_string()),
+          fields: None,
+          include_deleted: None,
+        }),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
     )
     .await
     .unwrap();
@@ -1053,17 +1579,101 @@ mod test {
     .await
     .unwrap();
 
-    let Json(value) = read_record_handler(
-      State(state.clone()),
-      Path(("child_view_api".to_string(), "1".to_string())),
-      Query(ReadRecordQuery {
-        expand: Some("parent".to_string()),
-      }),
-      None,
+    let value: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state.clone()),
+        Path(("child_view_api".to_string(), "1".to_string())),
+        Query(ReadRecordQuery {
+          expand: Some("parent".to_string()),
+          fields: None,
+          include_deleted: None,
+        }),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
     )
     .await
     .unwrap();
 
     assert_eq!(value, expected);
   }
+
+  #[tokio::test]
+  async fn test_expand_referred_column() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE parent (
+            id           INTEGER PRIMARY KEY NOT NULL,
+            slug         TEXT UNIQUE NOT NULL
+          ) STRICT;
+          INSERT INTO parent (id, slug) VALUES (1, 'a'), (2, 'b');
+
+          CREATE TABLE child (
+            id           INTEGER PRIMARY KEY NOT NULL,
+            parent       TEXT REFERENCES parent(slug) NOT NULL
+          ) STRICT;
+          INSERT INTO child (id, parent) VALUES (1, 'b');
+       "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    for (name, table_name, expand) in [
+      ("child_api", "child", vec!["parent".to_string()]),
+      ("parent_api", "parent", vec![]),
+    ] {
+      add_record_api_config(
+        &state,
+        RecordApiConfig {
+          name: Some(name.to_string()),
+          table_name: Some(table_name.to_string()),
+          acl_world: [PermissionFlag::Read as i32].into(),
+          expand,
+          ..Default::default()
+        },
+      )
+      .await
+      .unwrap();
+    }
+
+    let value: serde_json::Value = unpack_json_response(
+      read_record_handler(
+        State(state.clone()),
+        Path(("child_api".to_string(), "1".to_string())),
+        Query(ReadRecordQuery {
+          expand: Some("parent".to_string()),
+          ..Default::default()
+        }),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    // Expansions match the foreign key's referred column rather than the primary key.
+    assert_eq!(
+      json!({
+        "id": 1,
+        "parent": {
+          "id": "b",
+          "data": {
+            "id": 2,
+            "slug": "b",
+          },
+        },
+      }),
+      value
+    );
+  }
 }
@@ -1,12 +1,13 @@
 use askama::Template;
+use itertools::Itertools;
 use log::*;
 use rusqlite::types::ToSqlOutput;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
 use trailbase_schema::metadata::{
-  JsonColumnMetadata, TableMetadata, TableOrViewMetadata, ViewMetadata, find_file_column_indexes,
-  find_user_id_foreign_key_columns,
+  FtsMetadata, JsonColumnMetadata, SpatialIndexMetadata, TableMetadata, TableOrViewMetadata,
+  ViewMetadata, find_file_column_indexes, find_user_id_foreign_key_columns,
 };
 use trailbase_schema::sqlite::{Column, ColumnDataType, sqlite3_parse_into_statement};
 use trailbase_schema::{QualifiedName, QualifiedNameEscaped};
@@ -15,7 +16,7 @@ use trailbase_sqlite::{NamedParamRef, NamedParams, Params as _, Value};
 use crate::auth::user::User;
 use crate::config::proto::{ConflictResolutionStrategy, RecordApiConfig};
 use crate::constants::USER_TABLE;
-use crate::records::params::{LazyParams, prefix_colon};
+use crate::records::params::{LazyParams, Params, prefix_colon};
 use crate::records::{Permission, RecordError};
 use crate::util::{assert_uuidv7, b64_to_id};
 
@@ -35,6 +36,12 @@ struct RecordApiSchema {
   json_column_metadata: Vec<Option<JsonColumnMetadata>>,
   has_file_columns: bool,
   user_id_columns: Vec<usize>,
+  /// Sets of columns with uniqueness constraints, e.g. usable as upsert conflict targets.
+  unique_column_sets: Vec<Vec<String>>,
+  /// Associated FTS5 index, if any.
+  fts: Option<FtsMetadata>,
+  /// Associated R*Tree spatial index, if any.
+  spatial_index: Option<SpatialIndexMetadata>,
 
   // Helpers
   column_name_to_index: HashMap<String, usize>,
@@ -69,6 +76,13 @@ impl RecordApiSchema {
         .map(|(index, col)| (col.name.clone(), index)),
     );
 
+    // Only constraints over columns exposed by this API are usable.
+    let unique_column_sets: Vec<Vec<String>> = schema_metadata
+      .unique_column_sets()
+      .into_iter()
+      .filter(|set| set.iter().all(|c| column_name_to_index.contains_key(c)))
+      .collect();
+
     let named_params_template: NamedParams = columns
       .iter()
       .map(|column| {
@@ -88,6 +102,9 @@ impl RecordApiSchema {
       json_column_metadata,
       has_file_columns,
       user_id_columns,
+      unique_column_sets,
+      fts: schema_metadata.fts.clone(),
+      spatial_index: schema_metadata.spatial_index.clone(),
       column_name_to_index,
       named_params_template,
     });
@@ -131,6 +148,9 @@ impl RecordApiSchema {
       json_column_metadata,
       has_file_columns,
       user_id_columns,
+      unique_column_sets: vec![],
+      fts: None,
+      spatial_index: None,
       column_name_to_index,
       named_params_template: NamedParams::new(),
     });
@@ -147,12 +167,18 @@ struct RecordApiState {
   // Below properties are filled from `proto::RecordApiConfig`.
   api_name: String,
   acl: [u8; 2],
+  // Pairs of role name and ACL granted to authenticated members of the role.
+  role_acls: Vec<(String, u8)>,
   insert_conflict_resolution_strategy: Option<ConflictResolutionStrategy>,
   insert_autofill_missing_user_id_columns: bool,
   enable_subscriptions: bool,
 
   // Foreign key expansion configuration. Affects schema.
   expand: Option<HashMap<String, serde_json::Value>>,
+  // All configured expansion paths including nested and reverse expansions.
+  expand_paths: Vec<String>,
+  // Pairs of related table name and name of the API governing access to its records.
+  related_apis: Vec<(String, String)>,
 
   // Open question: right now the read_access rule is also used for listing. It might be nice to
   // allow different permissions, however there's a risk of listing records w/o read access.
@@ -166,6 +192,24 @@ struct RecordApiState {
   update_access_query: Option<Arc<str>>,
   delete_access_query: Option<Arc<str>>,
   schema_access_query: Option<Arc<str>>,
+
+  // The raw update and delete rules are needed to construct bulk, filter-based mutations.
+  update_access_rule: Option<String>,
+  delete_access_rule: Option<String>,
+
+  // Column-level read rules, i.e. pairs of column name and rule. Values of columns, for which the
+  // rule doesn't hold, are withheld. Column-level write rules are folded into the create and
+  // update rules above.
+  column_read_rules: Vec<(String, String)>,
+  subscription_column_read_access_query: Option<String>,
+
+  // Column driving ETags. Records are hashed entirely if absent.
+  version_column: Option<String>,
+
+  // Nullable column marking records as soft-deleted. Records are hard-deleted if absent.
+  soft_delete_column: Option<String>,
+
+  enable_history: bool,
 }
 
 impl RecordApiState {
@@ -220,6 +264,58 @@ impl RecordApi {
       return Err(format!("RecordApi misses name: {config:?}"));
     };
 
+    let mut column_read_rules: Vec<(String, String)> = vec![];
+    let mut column_write_clauses: Vec<String> = vec![];
+    for column_access_rule in &config.column_access_rules {
+      let Some(column_name) = column_access_rule
+        .column
+        .as_ref()
+        .filter(|name| schema.column_name_to_index.contains_key(*name))
+      else {
+        return Err(format!(
+          "Column access rule for unknown column: {column_access_rule:?}"
+        ));
+      };
+
+      if let Some(ref rule) = column_access_rule.read_rule {
+        column_read_rules.push((column_name.clone(), rule.clone()));
+      }
+      if let Some(ref rule) = column_access_rule.write_rule {
+        // Only applies if the request actually sets the column.
+        column_write_clauses.push(format!(
+          "('{name}' NOT IN _REQ_FIELDS_ OR ({rule}))",
+          name = column_name.replace('\'', "''"),
+        ));
+      }
+    }
+
+    let with_column_write_rules = |rule: &Option<String>| -> Option<String> {
+      if column_write_clauses.is_empty() {
+        return rule.clone();
+      }
+      let clauses = column_write_clauses.join(" AND ");
+      return Some(match rule {
+        Some(rule) => format!("({rule}) AND {clauses}"),
+        None => clauses,
+      });
+    };
+    let create_access_rule = with_column_write_rules(&config.create_access_rule);
+    let update_access_rule = with_column_write_rules(&config.update_access_rule);
+
+    let subscription_column_read_access_query = if column_read_rules.is_empty() || !schema.is_table
+    {
+      None
+    } else {
+      Some(
+        SubscriptionRecordReadTemplate {
+          read_access_rules: column_read_rules.iter().map(|(_, r)| r.as_str()).collect(),
+          column_names: schema.columns.iter().map(|c| c.name.as_str()).collect(),
+        }
+        .render()
+        .map_err(|err| err.to_string())?,
+      )
+    };
+
     let (read_access_query, subscription_read_access_query) = match &config.read_access_rule {
       Some(rule) => {
         let read_access_query =
@@ -228,7 +324,7 @@ impl RecordApi {
         let subscription_read_access_query = if schema.is_table {
           Some(
             SubscriptionRecordReadTemplate {
-              read_access_rule: rule,
+              read_access_rules: vec![rule],
               column_names: schema.columns.iter().map(|c| c.name.as_str()).collect(),
             }
             .render()
@@ -251,7 +347,7 @@ impl RecordApi {
       build_read_delete_schema_query(&schema.table_name, &schema.record_pk_column.1.name, rule)
     });
 
-    let create_access_query = match &config.create_access_rule {
+    let create_access_query = match &create_access_rule {
       Some(rule) => {
         if schema.is_table {
           Some(build_create_access_query(&schema.columns, rule)?)
@@ -262,7 +358,7 @@ impl RecordApi {
       None => None,
     };
 
-    let update_access_query = match &config.update_access_rule {
+    let update_access_query = match &update_access_rule {
       Some(rule) => {
         if schema.is_table {
           Some(build_update_access_query(
@@ -295,23 +391,44 @@ impl RecordApi {
           .unwrap_or(false),
         enable_subscriptions: config.enable_subscriptions.unwrap_or(false),
 
-        expand: if config.expand.is_empty() {
-          None
-        } else {
-          Some(
-            config
-              .expand
-              .iter()
-              .map(|col_name| (col_name.to_string(), serde_json::Value::Null))
-              .collect(),
-          )
+        expand: {
+          // Only the first segments of forward expansions are foreign key columns on this table,
+          // e.g. `author` for `author.org` but not reverse expansions like `comment!post`.
+          let foreign_key_columns: HashMap<String, serde_json::Value> = config
+            .expand
+            .iter()
+            .filter_map(|path| {
+              let col_name = path.split('.').next().unwrap_or(path);
+              if col_name.contains('!') {
+                return None;
+              }
+              return Some((col_name.to_string(), serde_json::Value::Null));
+            })
+            .collect();
+
+          if foreign_key_columns.is_empty() {
+            None
+          } else {
+            Some(foreign_key_columns)
+          }
         },
+        expand_paths: config.expand,
+        related_apis: config
+          .related_apis
+          .iter()
+          .filter_map(|related| Some((related.table_name.clone()?, related.api_name.clone()?)))
+          .collect(),
 
         // Access control lists.
         acl: [
           convert_acl(&config.acl_world),
           convert_acl(&config.acl_authenticated),
         ],
+        role_acls: config
+          .acl_roles
+          .iter()
+          .filter_map(|role_acl| Some((role_acl.role.clone()?, convert_acl(&role_acl.acl))))
+          .collect(),
         // Access rules.
         //
         // Create:
@@ -325,6 +442,16 @@ impl RecordApi {
         update_access_query,
         delete_access_query,
         schema_access_query,
+
+        update_access_rule,
+        delete_access_rule: config.delete_access_rule,
+
+        column_read_rules,
+        subscription_column_read_access_query,
+
+        version_column: config.version_column,
+        soft_delete_column: config.soft_delete_column,
+        enable_history: config.enable_history.unwrap_or(false),
       }),
     });
   }
@@ -359,6 +486,21 @@ impl RecordApi {
     return self.state.expand.as_ref();
   }
 
+  #[inline]
+  pub(crate) fn expand_paths(&self) -> &[String] {
+    return &self.state.expand_paths;
+  }
+
+  /// Name of the API configured to govern access to records of the given related table, if any.
+  pub(crate) fn related_api_name(&self, table_name: &str) -> Option<&str> {
+    return self
+      .state
+      .related_apis
+      .iter()
+      .find(|(name, _)| name == table_name)
+      .map(|(_, api_name)| api_name.as_str());
+  }
+
   #[inline]
   pub fn record_pk_column(&self) -> &(usize, Column) {
     return &self.state.schema.record_pk_column;
@@ -384,6 +526,26 @@ impl RecordApi {
     return self.state.schema.column_name_to_index.get(key).copied();
   }
 
+  /// Whether the given columns, in any order, are covered by a uniqueness constraint.
+  pub fn is_unique_column_set(&self, columns: &[String]) -> bool {
+    return self
+      .state
+      .schema
+      .unique_column_sets
+      .iter()
+      .any(|set| set.len() == columns.len() && set.iter().all(|c| columns.contains(c)));
+  }
+
+  #[inline]
+  pub fn fts(&self) -> Option<&FtsMetadata> {
+    return self.state.schema.fts.as_ref();
+  }
+
+  #[inline]
+  pub fn spatial_index(&self) -> Option<&SpatialIndexMetadata> {
+    return self.state.schema.spatial_index.as_ref();
+  }
+
   pub fn id_to_sql(&self, id: &str) -> Result<Value, RecordError> {
     return match self.state.schema.record_pk_column.1.data_type {
       ColumnDataType::Blob => {
@@ -412,6 +574,202 @@ impl RecordApi {
     return self.state.read_access_rule.as_deref();
   }
 
+  #[inline]
+  pub fn update_access_rule(&self) -> Option<&str> {
+    return self.state.update_access_rule.as_deref();
+  }
+
+  #[inline]
+  pub fn delete_access_rule(&self) -> Option<&str> {
+    return self.state.delete_access_rule.as_deref();
+  }
+
+  /// Column-level read rule of the given column, if any.
+  pub(crate) fn column_read_rule(&self, column_name: &str) -> Option<&str> {
+    return self
+      .state
+      .column_read_rules
+      .iter()
+      .find(|(name, _)| name == column_name)
+      .map(|(_, rule)| rule.as_str());
+  }
+
+  /// SQL expression selecting the given column of `_ROW_`, which evaluates to NULL unless the
+  /// column's read rule, if any, holds. Requires `_USER_` to be in scope.
+  pub(crate) fn column_read_expr(&self, column_name: &str) -> String {
+    return match self.column_read_rule(column_name) {
+      Some(rule) => format!(r#"(CASE WHEN ({rule}) THEN _ROW_."{column_name}" END)"#),
+      None => format!(r#"_ROW_."{column_name}""#),
+    };
+  }
+
+  /// Returns the names of columns, whose values are withheld from the given user for the given
+  /// record observed by subscriptions, i.e. whose column-level read rules don't hold.
+  pub(crate) fn withheld_columns_for_subscriptions(
+    &self,
+    conn: &rusqlite::Connection,
+    record: &[(&str, &rusqlite::types::Value)],
+    user: Option<&User>,
+  ) -> Vec<&str> {
+    let Some(ref query) = self.state.subscription_column_read_access_query else {
+      return vec![];
+    };
+
+    let mut params = Vec::<NamedParamRef<'_>>::with_capacity(record.len() + 2);
+    params.push((
+      Cow::Borrowed(":__user_id"),
+      user.map_or_else(
+        || ToSqlOutput::Owned(Value::Null),
+        |u| ToSqlOutput::Owned(Value::Blob(u.uuid.into())),
+      ),
+    ));
+    params.push((
+      Cow::Borrowed(":__user_roles"),
+      ToSqlOutput::Owned(user.map_or(Value::Null, |u| Value::Text(u.roles_json()))),
+    ));
+    params.extend(record.iter().map(|(name, value)| {
+      (
+        Cow::Owned(prefix_colon(name)),
+        ToSqlOutput::Borrowed((*value).into()),
+      )
+    }));
+
+    let num_rules = self.state.column_read_rules.len();
+    let allowed = (|| -> Result<Option<Vec<Option<bool>>>, rusqlite::Error> {
+      let mut stmt = conn.prepare_cached(query)?;
+      params.bind(&mut stmt)?;
+      let mut rows = stmt.raw_query();
+      return match rows.next()? {
+        Some(row) => Ok(Some(
+          (0..num_rules)
+            .map(|i| row.get(i))
+            .collect::<Result<_, _>>()?,
+        )),
+        None => Ok(None),
+      };
+    })()
+    .unwrap_or_else(|err| {
+      warn!("Column access query failed: {err}");
+      None
+    });
+
+    return withheld(&self.state.column_read_rules, allowed);
+  }
+
+  /// Builds a query evaluating the given filter clause against records observed by table
+  /// subscriptions, see `record_matches_subscription_filter`.
+  pub(crate) fn build_subscription_filter_query(
+    &self,
+    filter_clause: &str,
+  ) -> Result<String, RecordError> {
+    return SubscriptionRecordReadTemplate {
+      read_access_rules: vec![filter_clause],
+      column_names: self.columns().iter().map(|c| c.name.as_str()).collect(),
+    }
+    .render()
+    .map_err(|err| RecordError::Internal(err.into()));
+  }
+
+  /// Checks whether the given record matches a subscription's filter. Records fail to match if
+  /// the filter cannot be evaluated, e.g. because the schema has changed since subscribing.
+  pub(crate) fn record_matches_subscription_filter(
+    &self,
+    conn: &rusqlite::Connection,
+    filter_query: &str,
+    filter_params: &[(Cow<'static, str>, Value)],
+    record: &[(&str, &rusqlite::types::Value)],
+    user: Option<&User>,
+  ) -> bool {
+    let mut params =
+      Vec::<NamedParamRef<'_>>::with_capacity(record.len() + filter_params.len() + 2);
+    params.push((
+      Cow::Borrowed(":__user_id"),
+      user.map_or_else(
+        || ToSqlOutput::Owned(Value::Null),
+        |u| ToSqlOutput::Owned(Value::Blob(u.uuid.into())),
+      ),
+    ));
+    params.push((
+      Cow::Borrowed(":__user_roles"),
+      ToSqlOutput::Owned(user.map_or(Value::Null, |u| Value::Text(u.roles_json()))),
+    ));
+    params.extend(record.iter().map(|(name, value)| {
+      (
+        Cow::Owned(prefix_colon(name)),
+        ToSqlOutput::Borrowed((*value).into()),
+      )
+    }));
+    params.extend(
+      filter_params
+        .iter()
+        .map(|(name, value)| (name.clone(), ToSqlOutput::Borrowed(value.into()))),
+    );
+
+    return (|| -> Result<bool, rusqlite::Error> {
+      let mut stmt = conn.prepare_cached(filter_query)?;
+      params.bind(&mut stmt)?;
+      let mut rows = stmt.raw_query();
+      return match rows.next()? {
+        Some(row) => Ok(row.get::<_, Option<bool>>(0)?.unwrap_or(false)),
+        None => Ok(false),
+      };
+    })()
+    .unwrap_or_else(|err| {
+      warn!("Subscription filter query failed: {err}");
+      false
+    });
+  }
+
+  #[inline]
+  pub fn version_column(&self) -> Option<&str> {
+    return self.state.version_column.as_deref();
+  }
+
+  /// SQL expression yielding a textual representation of `_ROW_`'s version, i.e. either the
+  /// version column or all of the API's columns, followed by those of the given columns withheld
+  /// by column-level read rules, since they change the representation. Requires `_USER_` to be in
+  /// scope. The ETag is derived from it, see `etag_from_value`.
+  pub(crate) fn etag_expr(&self, column_names: &[&str]) -> String {
+    let version = match self.state.version_column {
+      Some(ref column) => format!(r#"quote(_ROW_."{column}")"#),
+      None => self
+        .columns()
+        .iter()
+        .map(|c| format!(r#"quote(_ROW_."{name}")"#, name = c.name))
+        .join(" || ',' || "),
+    };
+
+    let withheld: Vec<String> = column_names
+      .iter()
+      .filter_map(|name| {
+        let rule = self.column_read_rule(name)?;
+        return Some(format!(
+          "IIF(({rule}) IS TRUE, '', '{name},')",
+          name = name.replace('\'', "''")
+        ));
+      })
+      .collect();
+    if withheld.is_empty() {
+      return version;
+    }
+    return format!("{version} || ';' || {}", withheld.join(" || "));
+  }
+
+  #[inline]
+  pub fn soft_delete_column(&self) -> Option<&str> {
+    return self.state.soft_delete_column.as_deref();
+  }
+
+  /// Condition excluding soft-deleted records for the given table alias, if soft-deletes are
+  /// enabled, e.g. `_ROW_."deleted_at" IS NULL`.
+  pub(crate) fn soft_delete_filter(&self, alias: &str) -> Option<String> {
+    return self
+      .state
+      .soft_delete_column
+      .as_ref()
+      .map(|column| format!(r#"{alias}."{column}" IS NULL"#));
+  }
+
   #[inline]
   pub fn insert_autofill_missing_user_id_columns(&self) -> bool {
     return self.state.insert_autofill_missing_user_id_columns;
@@ -422,6 +780,21 @@ impl RecordApi {
     return self.state.enable_subscriptions;
   }
 
+  #[inline]
+  pub fn enable_history(&self) -> bool {
+    return self.state.enable_history;
+  }
+
+  /// The user to attribute recorded history to, if history is enabled.
+  #[inline]
+  pub(crate) fn history_actor<'a>(&self, user: Option<&'a User>) -> Option<&'a User> {
+    return if self.state.enable_history {
+      user
+    } else {
+      None
+    };
+  }
+
   #[inline]
   pub fn insert_conflict_resolution_strategy(&self) -> Option<ConflictResolutionStrategy> {
     return self.state.insert_conflict_resolution_strategy;
@@ -442,6 +815,14 @@ impl RecordApi {
       return Ok(());
     };
 
+    let request_params = match request_params {
+      Some(lazy_params) => Some(
+        lazy_params
+          .params()
+          .map_err(|err| RecordError::Internal(err.into()))?,
+      ),
+      None => None,
+    };
     let params = self.build_named_params(p, record_id, request_params, user)?;
 
     // NOTE: Avoid slushing between sqlite threads with regard to an allowed follow-on action.
@@ -484,6 +865,52 @@ impl RecordApi {
     return Err(RecordError::Forbidden);
   }
 
+  /// Check if the given user (if any) can access a record given the request and the operation.
+  ///
+  /// Unlike `check_record_level_access`, this runs synchronously on the given connection, which
+  /// allows evaluating access rules within an open transaction, e.g. to observe records created
+  /// earlier in the same batch.
+  pub(crate) fn check_record_level_access_with_conn(
+    &self,
+    conn: &rusqlite::Connection,
+    p: Permission,
+    record_id: Option<&Value>,
+    request_params: Option<&Params>,
+    user: Option<&User>,
+  ) -> Result<(), RecordError> {
+    self.check_table_level_access(p, user)?;
+
+    let Some(access_query) = self.state.cached_access_query(p) else {
+      return Ok(());
+    };
+
+    let params = self.build_named_params(p, record_id, request_params, user)?;
+
+    let mut stmt = conn
+      .prepare_cached(&access_query)
+      .map_err(|err| RecordError::Internal(err.into()))?;
+    params
+      .bind(&mut stmt)
+      .map_err(|err| RecordError::Internal(err.into()))?;
+
+    match stmt.raw_query().next() {
+      Ok(Some(row)) => {
+        if row.get(0).unwrap_or(false) {
+          return Ok(());
+        }
+      }
+      Ok(None) => {}
+      Err(err) => {
+        warn!("RLA query failed: {err}");
+
+        #[cfg(test)]
+        panic!("RLA query failed: {err}");
+      }
+    }
+
+    return Err(RecordError::Forbidden);
+  }
+
   /// Check if the given user (if any) can access a record given the request and the operation.
   ///
   /// NOTE: We could inline this in `SubscriptionManager::broker_subscriptions` and reduce some
@@ -503,7 +930,7 @@ impl RecordApi {
     };
 
     let params = {
-      let mut params = Vec::<NamedParamRef<'_>>::with_capacity(record.len() + 1);
+      let mut params = Vec::<NamedParamRef<'_>>::with_capacity(record.len() + 2);
       params.push((
         Cow::Borrowed(":__user_id"),
         user.map_or_else(
@@ -511,6 +938,10 @@ impl RecordApi {
           |u| ToSqlOutput::Owned(Value::Blob(u.uuid.into())),
         ),
       ));
+      params.push((
+        Cow::Borrowed(":__user_roles"),
+        ToSqlOutput::Owned(user.map_or(Value::Null, |u| Value::Text(u.roles_json()))),
+      ));
 
       params.extend(record.iter().map(|(name, value)| {
         (
@@ -555,6 +986,7 @@ impl RecordApi {
   ) -> Result<(), RecordError> {
     if (user.is_some() && self.has_access(Entity::Authenticated, p))
       || self.has_access(Entity::World, p)
+      || user.is_some_and(|user| self.has_role_access(&user.roles, p))
     {
       return Ok(());
     }
@@ -562,6 +994,15 @@ impl RecordApi {
     return Err(RecordError::Forbidden);
   }
 
+  #[inline]
+  fn has_role_access(&self, roles: &[String], p: Permission) -> bool {
+    return self
+      .state
+      .role_acls
+      .iter()
+      .any(|(role, acl)| (acl & (p as u8)) > 0 && roles.contains(role));
+  }
+
   #[inline]
   fn has_access(&self, e: Entity, p: Permission) -> bool {
     return (self.state.acl[e as usize] & (p as u8)) > 0;
@@ -570,11 +1011,11 @@ impl RecordApi {
   // TODO: We should probably break this up into separate functions for CRUD, to only do and inject
   // what's actually needed. Maybe even break up the entire check_access_and_rls_then. It's pretty
   // winding right now.
-  fn build_named_params(
+  pub(crate) fn build_named_params(
     &self,
     p: Permission,
     record_id: Option<&Value>,
-    request_params: Option<&mut LazyParams<'_, RecordApi>>,
+    request_params: Option<&Params>,
     user: Option<&User>,
   ) -> Result<NamedParams, RecordError> {
     // We need to inject context like: record id, user, request, and row into the access
@@ -586,10 +1027,8 @@ impl RecordApi {
           return Err(RecordError::ApiRequiresTable);
         };
 
-        let request_params = request_params
-          .ok_or_else(|| RecordError::Internal("missing req params".into()))?
-          .params()
-          .map_err(|err| RecordError::Internal(err.into()))?;
+        let request_params =
+          request_params.ok_or_else(|| RecordError::Internal("missing req params".into()))?;
 
         // NOTE: We cannot have access queries access missing _REQ_.props. So we need to inject an
         // explicit NULL value for all missing fields on the request. Can we make this cheaper,
@@ -621,13 +1060,17 @@ impl RecordApi {
 
         named_params
       }
-      Permission::Read | Permission::Delete | Permission::Schema => NamedParams::with_capacity(2),
+      Permission::Read | Permission::Delete | Permission::Schema => NamedParams::with_capacity(3),
     };
 
     params.push((
       Cow::Borrowed(":__user_id"),
       user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
     ));
+    params.push((
+      Cow::Borrowed(":__user_roles"),
+      user.map_or(Value::Null, |u| Value::Text(u.roles_json())),
+    ));
     params.push((
       Cow::Borrowed(":__record_id"),
       record_id.map_or(Value::Null, |id| id.clone()),
@@ -701,10 +1144,39 @@ fn validate_expr_recursively(expr: &sqlite3_parser::ast::Expr) -> Result<(), Str
   path = "subscription_record_read.sql"
 )]
 struct SubscriptionRecordReadTemplate<'a> {
-  read_access_rule: &'a str,
+  read_access_rules: Vec<&'a str>,
   column_names: Vec<&'a str>,
 }
 
+/// Returns the columns, whose read rules didn't evaluate to true. All are withheld if the rules
+/// couldn't be evaluated.
+fn withheld<'a>(
+  column_read_rules: &'a [(String, String)],
+  allowed: Option<Vec<Option<bool>>>,
+) -> Vec<&'a str> {
+  return column_read_rules
+    .iter()
+    .enumerate()
+    .filter(|(i, _)| {
+      !allowed
+        .as_ref()
+        .is_some_and(|allowed| allowed.get(*i).copied().flatten().unwrap_or(false))
+    })
+    .map(|(_, (name, _))| name.as_str())
+    .collect();
+}
+
+/// Sets the values of the given columns to NULL, i.e. withholds them.
+pub(crate) fn withhold_columns(record: &mut serde_json::Value, columns: &[&str]) {
+  if let serde_json::Value::Object(record) = record {
+    for column in columns {
+      if let Some(value) = record.get_mut(*column) {
+        *value = serde_json::Value::Null;
+      }
+    }
+  }
+}
+
 /// Build access query for record reads, deletes and query access.
 ///
 /// Assumes access_rule is an expression: https://www.sqlite.org/syntax/expr.html
@@ -718,7 +1190,7 @@ fn build_read_delete_schema_query(
       SELECT
         CAST(({access_rule}) AS INTEGER)
       FROM
-        (SELECT :__user_id AS id) AS _USER_,
+        (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
         (SELECT * FROM {table_name} WHERE "{pk_column_name}" = :__record_id) AS _ROW_
     "#,
   )
@@ -910,7 +1382,7 @@ mod tests {
   fn test_subscription_record_read_template() {
     {
       let query = SubscriptionRecordReadTemplate {
-        read_access_rule: "TRUE",
+        read_access_rules: vec!["TRUE"],
         column_names: vec![],
       }
       .render()
@@ -921,7 +1393,7 @@ mod tests {
 
     {
       let query = SubscriptionRecordReadTemplate {
-        read_access_rule: r#"_USER_.id = X'05' AND "index" = 'secret'"#,
+        read_access_rules: vec![r#"_USER_.id = X'05' AND "index" = 'secret'"#, "TRUE"],
         column_names: vec!["index"],
       }
       .render()
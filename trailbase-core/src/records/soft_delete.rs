@@ -0,0 +1,258 @@
+use axum::{
+  extract::{Path, State},
+  http::StatusCode,
+  response::{IntoResponse, Response},
+};
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::auth::util::is_admin;
+use crate::records::query_builder::{DeleteQueryBuilder, QueryError};
+use crate::records::{Permission, RecordApi, RecordError};
+
+/// Restore soft-deleted record.
+///
+/// Requires the API to be configured with a `soft_delete_column` and the same permissions as
+/// deleting the record.
+#[utoipa::path(
+  post,
+  path = "/:name/:record/restore",
+  responses(
+    (status = 200, description = "Successful restoration.")
+  )
+)]
+pub async fn restore_record_handler(
+  State(state): State<AppState>,
+  Path((api_name, record)): Path<(String, String)>,
+  user: Option<User>,
+) -> Result<Response, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  if !api.is_table() {
+    return Err(RecordError::ApiRequiresTable);
+  }
+
+  let Some(soft_delete_column) = api.soft_delete_column() else {
+    return Err(RecordError::BadRequest("Soft deletion not enabled"));
+  };
+
+  let record_id = api.id_to_sql(&record)?;
+
+  api
+    .check_record_level_access(Permission::Delete, Some(&record_id), None, user.as_ref())
+    .await?;
+
+  let (_index, pk_column) = api.record_pk_column();
+
+  DeleteQueryBuilder::run_restore(
+    &state,
+    api.table_name(),
+    &pk_column.name,
+    record_id,
+    soft_delete_column,
+    api.history_actor(user.as_ref()),
+  )
+  .await
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    err => RecordError::Internal(err.into()),
+  })?;
+
+  return Ok((StatusCode::OK, "restored").into_response());
+}
+
+/// Checks whether soft-deleted records should be included in reads. Only admins may opt into
+/// seeing soft-deleted records by passing `include_deleted`.
+pub(crate) async fn include_deleted(
+  state: &AppState,
+  user: Option<&User>,
+  include_deleted: Option<bool>,
+) -> Result<bool, RecordError> {
+  if include_deleted != Some(true) {
+    return Ok(false);
+  }
+
+  return match user {
+    Some(user) if is_admin(state, user).await => Ok(true),
+    _ => Err(RecordError::Forbidden),
+  };
+}
+
+/// Returns the filter hiding soft-deleted records from listings, if soft deletion is enabled and
+/// soft-deleted records weren't explicitly requested.
+pub(crate) async fn soft_delete_filter(
+  state: &AppState,
+  api: &RecordApi,
+  user: Option<&User>,
+  include_deleted_param: Option<bool>,
+) -> Result<Option<String>, RecordError> {
+  if include_deleted(state, user, include_deleted_param).await? {
+    return Ok(None);
+  }
+  return Ok(api.soft_delete_filter("_ROW_"));
+}
+
+#[cfg(test)]
+mod test {
+  use axum::extract::{Query, RawQuery};
+  use axum::http::HeaderMap;
+
+  use super::*;
+  use crate::app_state::*;
+  use crate::config::proto::{PermissionFlag, RecordApiConfig};
+  use crate::records::delete_record::delete_record_handler;
+  use crate::records::image_transform::ImageTransformQuery;
+  use crate::records::list_records::{ListResponse, list_records_handler};
+  use crate::records::read_record::{
+    ReadRecordQuery, get_uploaded_file_from_record_handler, read_record_handler,
+  };
+  use crate::records::test_utils::*;
+  use crate::test::unpack_json_response;
+
+  #[tokio::test]
+  async fn test_record_api_soft_delete_and_restore() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+        CREATE TABLE data (
+          id INTEGER PRIMARY KEY,
+          text TEXT NOT NULL,
+          file TEXT CHECK(jsonschema('std.FileUpload', file)),
+          deleted_at INTEGER
+        );
+        INSERT INTO data (id, text, file) VALUES
+          (1, 'first', '{"id": "00000000-0000-0000-0000-000000000000"}'),
+          (2, 'second', NULL);
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("data".to_string()),
+        acl_world: [PermissionFlag::Read as i32, PermissionFlag::Delete as i32].into(),
+        soft_delete_column: Some("deleted_at".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let read = async |include_deleted: Option<bool>| {
+      return read_record_handler(
+        State(state.clone()),
+        Path(("api".to_string(), "1".to_string())),
+        Query(ReadRecordQuery {
+          include_deleted,
+          ..Default::default()
+        }),
+        None,
+        HeaderMap::new(),
+      )
+      .await;
+    };
+    let list = async |query: Option<&str>| {
+      return list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(query.map(|q| q.to_string())),
+        None,
+        HeaderMap::new(),
+      )
+      .await;
+    };
+
+    delete_record_handler(
+      State(state.clone()),
+      Path(("api".to_string(), "1".to_string())),
+      None,
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+
+    // The record is retained but hidden.
+    let deleted_at: Option<i64> = conn
+      .read_query_row_f("SELECT deleted_at FROM data WHERE id = 1", (), |row| {
+        row.get(0)
+      })
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(deleted_at.is_some());
+
+    assert!(matches!(read(None).await, Err(RecordError::RecordNotFound)));
+    // Files of soft-deleted records cannot be downloaded either.
+    assert!(matches!(
+      get_uploaded_file_from_record_handler(
+        State(state.clone()),
+        Path(("api".to_string(), "1".to_string(), "file".to_string())),
+        Query(ImageTransformQuery::default()),
+        None,
+      )
+      .await,
+      Err(RecordError::RecordNotFound)
+    ));
+    let response: ListResponse = unpack_json_response(list(None).await.unwrap())
+      .await
+      .unwrap();
+    assert_eq!(1, response.records.len());
+    assert_eq!(2, response.records[0]["id"]);
+
+    // Only admins may include soft-deleted records.
+    assert!(matches!(
+      read(Some(true)).await,
+      Err(RecordError::Forbidden)
+    ));
+    assert!(matches!(
+      list(Some("include_deleted=true")).await,
+      Err(RecordError::Forbidden)
+    ));
+
+    // Soft-deleted records cannot be deleted again.
+    assert!(matches!(
+      delete_record_handler(
+        State(state.clone()),
+        Path(("api".to_string(), "1".to_string())),
+        None,
+        HeaderMap::new(),
+      )
+      .await,
+      Err(RecordError::RecordNotFound)
+    ));
+
+    restore_record_handler(
+      State(state.clone()),
+      Path(("api".to_string(), "1".to_string())),
+      None,
+    )
+    .await
+    .unwrap();
+
+    assert!(read(None).await.is_ok());
+    let response: ListResponse = unpack_json_response(list(None).await.unwrap())
+      .await
+      .unwrap();
+    assert_eq!(2, response.records.len());
+
+    // Only soft-deleted records can be restored.
+    assert!(matches!(
+      restore_record_handler(
+        State(state.clone()),
+        Path(("api".to_string(), "1".to_string())),
+        None,
+      )
+      .await,
+      Err(RecordError::RecordNotFound)
+    ));
+  }
+}
@@ -197,7 +197,7 @@ mod tests {
     let table = lookup_and_parse_table_schema(conn, "test_table", Some("main"))
       .await
       .unwrap();
-    let metadata = TableMetadata::new(table.clone(), &[table], USER_TABLE);
+    let metadata = TableMetadata::new(table.clone(), &[table], &[], USER_TABLE);
 
     let insert = |json: serde_json::Value| async move {
       conn
@@ -1,34 +1,51 @@
 use async_channel::WeakReceiver;
 use axum::{
-  extract::{Path, State},
-  response::sse::{Event, KeepAlive, Sse},
+  extract::{
+    Path, RawQuery, State,
+    ws::{Message, WebSocket, WebSocketUpgrade},
+  },
+  http::HeaderMap,
+  response::{
+    Response,
+    sse::{Event, KeepAlive, Sse},
+  },
 };
-use futures_util::Stream;
+use futures_util::{Stream, StreamExt};
+use itertools::Itertools;
 use log::*;
 use parking_lot::RwLock;
 use pin_project_lite::pin_project;
 use rusqlite::hooks::{Action, PreUpdateCase};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
 use std::sync::{
   Arc,
-  atomic::{AtomicI64, Ordering},
+  atomic::{AtomicBool, AtomicI64, Ordering},
 };
 use std::task::{Context, Poll};
+use trailbase_qs::Query;
 use trailbase_schema::QualifiedName;
-use trailbase_sqlite::connection::{extract_record_values, extract_row_id};
+use trailbase_sqlite::connection::extract_row_id;
 use trailbase_sqlite::rows::value_to_json;
+use trailbase_sqlite::{NamedParams, Params as _, Value};
 
 use crate::AppState;
 use crate::auth::user::User;
+use crate::listing::{WhereClause, build_filter_where_clause_with_fts, limit_or_default};
 use crate::records::RecordApi;
+use crate::records::change_log::{self, ChangeLogEntry};
+use crate::records::record_api::withhold_columns;
 use crate::records::{Permission, RecordError};
-use crate::schema_metadata::{SchemaMetadataCache, TableMetadata};
+use crate::schema_metadata::SchemaMetadataCache;
 use crate::value_notifier::Computed;
 
 static SUBSCRIPTION_COUNTER: AtomicI64 = AtomicI64::new(0);
 
+/// Number of live events buffered per subscription before events get dropped.
+const SUBSCRIPTION_CHANNEL_CAPACITY: usize = 16;
+
 type SseEvent = Result<axum::response::sse::Event, axum::Error>;
 
 /// Composite id uniquely identifying a subscription.
@@ -44,7 +61,7 @@ struct SubscriptionId {
 /// RAII type for automatically cleaning up subscriptions when the receiving side gets dropped,
 /// e.g. client disconnects.
 struct CleanupSubscription {
-  receiver: WeakReceiver<Event>,
+  receiver: WeakReceiver<SubscriptionEvent>,
   state: AppState,
   id: SubscriptionId,
 }
@@ -71,22 +88,28 @@ pin_project! {
   struct AutoCleanupEventStream {
     cleanup: CleanupSubscription,
 
+    /// Snapshotted records and replayed changes, which precede the received live changes.
+    backlog: VecDeque<SubscriptionEvent>,
     #[pin]
-    receiver: async_channel::Receiver<Event>,
+    receiver: async_channel::Receiver<SubscriptionEvent>,
   }
 }
 
 impl Stream for AutoCleanupEventStream {
-  type Item = Result<Event, axum::Error>;
+  type Item = SubscriptionEvent;
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
     let mut this = self.project();
-    let res = futures_util::ready!(this.receiver.as_mut().poll_next(cx));
-    Poll::Ready(res.map(Ok))
+    if let Some(event) = this.backlog.pop_front() {
+      return Poll::Ready(Some(event));
+    }
+    this.receiver.as_mut().poll_next(cx)
   }
 
   fn size_hint(&self) -> (usize, Option<usize>) {
-    self.receiver.size_hint()
+    let (lower, upper) = self.receiver.size_hint();
+    let backlog = self.backlog.len();
+    (lower + backlog, upper.map(|upper| upper + backlog))
   }
 }
 
@@ -113,7 +136,20 @@ pub enum DbEvent {
   Update(Option<serde_json::Value>),
   Insert(Option<serde_json::Value>),
   Delete(Option<serde_json::Value>),
+  /// Record matching at the time of subscribing, sent ahead of changes when requested.
+  Snapshot(Option<serde_json::Value>),
   Error(String),
+  /// Changes since the subscriber's `Last-Event-ID` can no longer be replayed, e.g. the change log
+  /// has been truncated, and records should be refetched.
+  Reset(String),
+}
+
+/// Event sent to subscribers. Events for logged changes carry the change's sequence number, which
+/// is used as SSE event id.
+#[derive(Clone, Debug)]
+struct SubscriptionEvent {
+  seq: Option<i64>,
+  event: Arc<DbEvent>,
 }
 
 pub struct Subscription {
@@ -126,8 +162,40 @@ pub struct Subscription {
   /// Record id present for subscriptions to specific records.
   // record_id: Option<trailbase_sqlite::Value>,
   user: Option<User>,
-  /// Channel for sending events to the SSE handler.
-  sender: async_channel::Sender<Event>,
+  /// Filter narrowing table subscriptions down to matching records.
+  filter: Option<SubscriptionFilter>,
+  /// Channel for sending events to the SSE or WebSocket handler. Events are shared between
+  /// subscribers and only encoded by the respective handler.
+  sender: async_channel::Sender<SubscriptionEvent>,
+}
+
+/// Filter query and its parameters, evaluated against every changed record.
+struct SubscriptionFilter {
+  query: String,
+  /// The filter's where clause on `_ROW_`, e.g. for reading snapshots.
+  clause: String,
+  params: Vec<(Cow<'static, str>, trailbase_sqlite::Value)>,
+}
+
+/// What a new subscription receives ahead of live changes.
+#[derive(Clone, Copy, Debug, Default)]
+enum SubscriptionStart {
+  /// Nothing, i.e. only changes from now on.
+  #[default]
+  Live,
+  /// Changes logged since the given sequence number, i.e. the client's `Last-Event-ID`.
+  Resume(i64),
+  /// Up to the given number of currently matching records.
+  Snapshot(usize),
+}
+
+/// Outcome of brokering a change to a single subscription.
+enum Delivery {
+  /// The change isn't visible to the subscriber.
+  Skip,
+  Send(SubscriptionEvent),
+  /// The subscription has ended, optionally with a final event to be sent.
+  End(Option<SubscriptionEvent>),
 }
 
 /// Internal, shareable state of the cloneable SubscriptionManager.
@@ -145,6 +213,12 @@ struct ManagerState {
 
   /// Map from table name to table subscriptions.
   table_subscriptions: RwLock<HashMap<QualifiedName, Vec<Subscription>>>,
+
+  /// Sequence number of the most recent change log entry brokered to subscribers. Only accessed
+  /// from the writer.
+  change_log_cursor: AtomicI64,
+  /// Whether brokering of newly logged changes has already been scheduled.
+  pump_scheduled: AtomicBool,
 }
 
 impl ManagerState {
@@ -206,15 +280,6 @@ pub struct SubscriptionManager {
   state: Arc<ManagerState>,
 }
 
-struct ContinuationState {
-  state: Arc<ManagerState>,
-  schema_metadata: Option<Arc<TableMetadata>>,
-  action: RecordAction,
-  table_name: QualifiedName,
-  rowid: i64,
-  record_values: Vec<rusqlite::types::Value>,
-}
-
 impl SubscriptionManager {
   pub fn new(
     conn: trailbase_sqlite::Connection,
@@ -229,6 +294,9 @@ impl SubscriptionManager {
 
         record_subscriptions: RwLock::new(HashMap::new()),
         table_subscriptions: RwLock::new(HashMap::new()),
+
+        change_log_cursor: AtomicI64::new(0),
+        pump_scheduled: AtomicBool::new(false),
       }),
     };
   }
@@ -258,33 +326,36 @@ impl SubscriptionManager {
     conn: &rusqlite::Connection,
     subs: &[Subscription],
     record_subscriptions: bool,
+    action: RecordAction,
     record: &[(&str, &rusqlite::types::Value)],
-    event: &Event,
+    event: &SubscriptionEvent,
   ) -> Vec<usize> {
     let mut dead_subscriptions: Vec<usize> = vec![];
+    let mut soft_deleted_event: Option<SubscriptionEvent> = None;
     for (idx, sub) in subs.iter().enumerate() {
-      let Some(api) = s.lookup_record_api(&sub.record_api_name) else {
-        dead_subscriptions.push(idx);
-        sub.sender.close();
-        continue;
-      };
-
-      if let Err(_err) =
-        api.check_record_level_read_access_for_subscriptions(conn, record, sub.user.as_ref())
-      {
-        if record_subscriptions {
-          // This can happen if the record api configuration has changed since originally
-          // subscribed. In this case we just send and error and cancel the subscription.
-          if let Ok(ev) = Event::default().json_data(DbEvent::Error("Access denied".into())) {
-            let _ = sub.sender.try_send(ev);
+      let event = match Self::deliver(
+        s,
+        conn,
+        sub,
+        record_subscriptions,
+        action,
+        record,
+        event,
+        &mut soft_deleted_event,
+      ) {
+        Delivery::Skip => continue,
+        Delivery::Send(event) => event,
+        Delivery::End(event) => {
+          if let Some(event) = event {
+            let _ = sub.sender.try_send(event);
           }
           dead_subscriptions.push(idx);
           sub.sender.close();
+          continue;
         }
-        continue;
-      }
+      };
 
-      match sub.sender.try_send(event.clone()) {
+      match sub.sender.try_send(event) {
         Ok(_) => {}
         Err(async_channel::TrySendError::Full(ev)) => {
           warn!("Channel full, dropping event: {ev:?}");
@@ -299,20 +370,145 @@ impl SubscriptionManager {
     return dead_subscriptions;
   }
 
-  /// Continuation of the preupdate hook being scheduled on the executor.
-  fn hook_continuation(conn: &rusqlite::Connection, state: ContinuationState) {
-    let ContinuationState {
-      state,
-      schema_metadata,
+  /// Determines what, if anything, the given subscription receives for a change. The event of
+  /// soft-deleting a record is shared between subscriptions via `soft_deleted_event`.
+  #[allow(clippy::too_many_arguments)]
+  fn deliver(
+    s: &ManagerState,
+    conn: &rusqlite::Connection,
+    sub: &Subscription,
+    record_subscription: bool,
+    action: RecordAction,
+    record: &[(&str, &rusqlite::types::Value)],
+    event: &SubscriptionEvent,
+    soft_deleted_event: &mut Option<SubscriptionEvent>,
+  ) -> Delivery {
+    let Some(api) = s.lookup_record_api(&sub.record_api_name) else {
+      return Delivery::End(None);
+    };
+
+    // Soft-deleted records are invisible to subscribers. Soft-deleting a record, i.e. updating
+    // its soft-delete column, is observed as a deletion.
+    let soft_deleted = api.soft_delete_column().is_some_and(|column| {
+      return record
+        .iter()
+        .any(|(name, value)| *name == column && !matches!(value, rusqlite::types::Value::Null));
+    });
+    let event = match (soft_deleted, action) {
+      (false, _) => event,
+      (true, RecordAction::Update) => {
+        &*soft_deleted_event.get_or_insert_with(|| SubscriptionEvent {
+          seq: event.seq,
+          event: Arc::new(DbEvent::Delete(Some(record_to_json(record)))),
+        })
+      }
+      (true, RecordAction::Insert | RecordAction::Delete) => {
+        return Delivery::Skip;
+      }
+    };
+
+    if let Err(_err) =
+      api.check_record_level_read_access_for_subscriptions(conn, record, sub.user.as_ref())
+    {
+      if record_subscription {
+        // This can happen if the record api configuration has changed since originally
+        // subscribed. In this case we just send and error and cancel the subscription.
+        return Delivery::End(Some(SubscriptionEvent {
+          seq: None,
+          event: Arc::new(DbEvent::Error("Access denied".into())),
+        }));
+      }
+      return Delivery::Skip;
+    }
+
+    // Skip records not matching the subscription's filter. Deletions are matched against the
+    // deleted record.
+    let filtered_out = sub.filter.as_ref().is_some_and(|filter| {
+      return !api.record_matches_subscription_filter(
+        conn,
+        &filter.query,
+        &filter.params,
+        record,
+        sub.user.as_ref(),
+      );
+    });
+    if filtered_out {
+      return Delivery::Skip;
+    }
+
+    // Mask values of columns withheld from this subscriber by column-level read rules.
+    let withheld = api.withheld_columns_for_subscriptions(conn, record, sub.user.as_ref());
+    if withheld.is_empty() {
+      return Delivery::Send(event.clone());
+    }
+
+    let mut json_value = record_to_json(record);
+    withhold_columns(&mut json_value, &withheld);
+    // Preserve the kind of event, e.g. deletions of soft-deleted records.
+    let db_event = match &*event.event {
+      DbEvent::Insert(_) => DbEvent::Insert(Some(json_value)),
+      DbEvent::Update(_) => DbEvent::Update(Some(json_value)),
+      DbEvent::Snapshot(_) => DbEvent::Snapshot(Some(json_value)),
+      _ => DbEvent::Delete(Some(json_value)),
+    };
+
+    return Delivery::Send(SubscriptionEvent {
+      seq: event.seq,
+      event: Arc::new(db_event),
+    });
+  }
+
+  /// Brokers changes logged since the last run to subscribers.
+  fn pump_change_log(conn: &rusqlite::Connection, s: &ManagerState) {
+    s.pump_scheduled.store(false, Ordering::SeqCst);
+
+    let cursor = s.change_log_cursor.load(Ordering::SeqCst);
+    let read = || -> Result<(i64, Vec<ChangeLogEntry>), rusqlite::Error> {
+      let last_seq = change_log::last_seq(conn)?;
+      return Ok((
+        last_seq,
+        change_log::read_change_log(conn, cursor, last_seq, None)?,
+      ));
+    };
+    let (last_seq, entries) = match read() {
+      Ok(result) => result,
+      Err(err) => {
+        warn!("Failed to read change log: {err}");
+        return;
+      }
+    };
+    s.change_log_cursor.store(last_seq, Ordering::SeqCst);
+
+    for entry in entries {
+      Self::broker_change(conn, s, entry);
+    }
+  }
+
+  /// Brokers a logged change to the subscriptions of the changed record and table.
+  fn broker_change(conn: &rusqlite::Connection, s: &ManagerState, entry: ChangeLogEntry) {
+    let ChangeLogEntry {
+      seq,
       table_name,
+      row_id: rowid,
       action,
-      rowid,
-      record_values,
-    } = state;
-    let s = &state;
+      record: snapshot,
+    } = entry;
+
+    // If there are no subscriptions, do nothing.
+    let record_subs_candidate = s
+      .record_subscriptions
+      .read()
+      .get(&table_name)
+      .and_then(|m| m.get(&rowid))
+      .is_some();
+    let table_subs_candidate = s.table_subscriptions.read().get(&table_name).is_some();
+    if !record_subs_candidate && !table_subs_candidate {
+      return;
+    }
+
     // If schema_metadata is missing, the config/schema must have changed, thus removing the
     // subscriptions.
-    let Some(schema_metadata) = schema_metadata else {
+    let Some(schema_metadata) = s.schema_metadata.get_table(&table_name) else {
       warn!("Table not found: {table_name:?}. Removing subscriptions");
 
       let mut record_subs = s.record_subscriptions.write();
@@ -328,6 +524,19 @@ impl SubscriptionManager {
       return;
     };
 
+    // Events carry the record's values as logged with the change.
+    let Some(snapshot) = snapshot else {
+      warn!("Missing values of record for change {seq}");
+      return;
+    };
+    let record_values = match change_log::snapshot_values(conn, &schema_metadata, &snapshot) {
+      Ok(record_values) => record_values,
+      Err(err) => {
+        warn!("Failed to decode record for change {seq}: {err}");
+        return;
+      }
+    };
+
     // Join values with column names.
     let record: Vec<(&str, &rusqlite::types::Value)> = record_values
       .iter()
@@ -335,32 +544,7 @@ impl SubscriptionManager {
       .map(|(idx, v)| (schema_metadata.schema.columns[idx].name.as_str(), v))
       .collect();
 
-    // Build a JSON-encoded SQLite event (insert, update, delete).
-    let event = {
-      let json_value = serde_json::Value::Object(
-        record
-          .iter()
-          .filter_map(|(name, value)| {
-            if let Ok(v) = value_to_json(value) {
-              return Some(((*name).to_string(), v));
-            };
-            return None;
-          })
-          .collect(),
-      );
-
-      let db_event = match action {
-        RecordAction::Delete => DbEvent::Delete(Some(json_value)),
-        RecordAction::Insert => DbEvent::Insert(Some(json_value)),
-        RecordAction::Update => DbEvent::Update(Some(json_value)),
-      };
-
-      let Ok(event) = Event::default().json_data(db_event) else {
-        return;
-      };
-
-      event
-    };
+    let event = change_event(seq, action, &record);
 
     'record_subs: {
       let mut read_lock = s.record_subscriptions.upgradable_read();
@@ -368,7 +552,8 @@ impl SubscriptionManager {
         break 'record_subs;
       };
 
-      let dead_subscriptions = Self::broker_subscriptions(s, conn, subs, true, &record, &event);
+      let dead_subscriptions =
+        Self::broker_subscriptions(s, conn, subs, true, action, &record, &event);
       if dead_subscriptions.is_empty() && action != RecordAction::Delete {
         // No cleanup needed.
         break 'record_subs;
@@ -418,7 +603,8 @@ impl SubscriptionManager {
         break 'table_subs;
       };
 
-      let dead_subscriptions = Self::broker_subscriptions(s, conn, subs, false, &record, &event);
+      let dead_subscriptions =
+        Self::broker_subscriptions(s, conn, subs, false, action, &record, &event);
       if dead_subscriptions.is_empty() && action != RecordAction::Delete {
         // No cleanup needed.
         break 'table_subs;
@@ -444,72 +630,324 @@ impl SubscriptionManager {
     }
   }
 
-  async fn add_hook(&self) -> trailbase_sqlite::connection::Result<()> {
-    let state = &self.state;
-    let conn = state.conn.clone();
-    let s = state.clone();
+  /// Builds the preupdate hook scheduling the brokering of changes with potential subscribers.
+  /// Since the hook runs before the change is applied and logged, brokering is deferred until the
+  /// writer has completed the current write.
+  fn build_hook(
+    s: Arc<ManagerState>,
+  ) -> impl Fn(Action, &str, &str, &PreUpdateCase) + Send + Sync + 'static {
+    return move |action: Action, db: &str, table_name: &str, case: &PreUpdateCase| {
+      match action {
+        Action::SQLITE_UPDATE | Action::SQLITE_INSERT | Action::SQLITE_DELETE => {}
+        a => {
+          error!("Unknown action: {a:?}");
+          return;
+        }
+      };
+
+      let Some(rowid) = extract_row_id(case) else {
+        error!("Failed to extract row id");
+        return;
+      };
 
-    return state
-      .conn
-      .add_preupdate_hook(Some(
-        move |action: Action, db: &str, table_name: &str, case: &PreUpdateCase| {
-          let action: RecordAction = match action {
-            Action::SQLITE_UPDATE | Action::SQLITE_INSERT | Action::SQLITE_DELETE => action.into(),
-            a => {
-              error!("Unknown action: {a:?}");
-              return;
-            }
-          };
+      let qualified_table_name = QualifiedName {
+        name: table_name.to_string(),
+        database_schema: Some(db.to_string()),
+      };
 
-          let Some(rowid) = extract_row_id(case) else {
-            error!("Failed to extract row id");
-            return;
-          };
+      // If there are no subscriptions, do nothing.
+      let record_subs_candidate = s
+        .record_subscriptions
+        .read()
+        .get(&qualified_table_name)
+        .and_then(|m| m.get(&rowid))
+        .is_some();
+      let table_subs_candidate = s
+        .table_subscriptions
+        .read()
+        .get(&qualified_table_name)
+        .is_some();
+      if !record_subs_candidate && !table_subs_candidate {
+        return;
+      }
 
-          let qualified_table_name = QualifiedName {
-            name: table_name.to_string(),
-            database_schema: Some(db.to_string()),
-          };
+      // A single run brokers all changes logged since, thus schedule at most one.
+      if !s.pump_scheduled.swap(true, Ordering::SeqCst) {
+        let state = s.clone();
+        s.conn.call_and_forget(move |conn| {
+          Self::pump_change_log(conn, &state);
+        });
+      }
+    };
+  }
+
+  /// Adds a subscription to the given table or, with `row_id`, a specific record.
+  ///
+  /// Subscriptions are registered on the writer, i.e. without racing the brokering of changes.
+  /// Snapshots are read from a reader together with the change log's position, changes after
+  /// which are then replayed on the writer. Thus, snapshotted records and replayed changes
+  /// seamlessly transition into live changes without gaps or duplicates. If replaying is no
+  /// longer possible, a reset event is sent.
+  async fn add_subscription(
+    &self,
+    app_state: AppState,
+    api: RecordApi,
+    row_id: Option<i64>,
+    user: Option<User>,
+    filter: Option<SubscriptionFilter>,
+    start: SubscriptionStart,
+  ) -> Result<AutoCleanupEventStream, RecordError> {
+    let (resume_after, snapshot) = match start {
+      SubscriptionStart::Live => (None, vec![]),
+      SubscriptionStart::Resume(last_seq) => (Some(last_seq), vec![]),
+      SubscriptionStart::Snapshot(limit) => {
+        let (seq, snapshot) = self
+          .read_snapshot(&api, row_id, user.as_ref(), filter.as_ref(), limit)
+          .await?;
+        (Some(seq), snapshot)
+      }
+    };
+
+    let s = self.state.clone();
+    let table_name = api.qualified_name().clone();
+    let record_api_name = api.api_name().to_string();
+    let subscription_id = SUBSCRIPTION_COUNTER.fetch_add(1, Ordering::SeqCst);
+
+    let (receiver, backlog) = self
+      .state
+      .conn
+      .call(move |conn| {
+        let empty =
+          s.record_subscriptions.read().is_empty() && s.table_subscriptions.read().is_empty();
+        if empty {
+          // Changes logged while nobody was subscribed haven't been brokered and are skipped.
+          s.change_log_cursor
+            .store(change_log::last_seq(conn)?, Ordering::SeqCst);
+        } else {
+          // Broker pending changes first, so the new subscription only receives later ones.
+          Self::pump_change_log(conn, &s);
+        }
+        let cursor = s.change_log_cursor.load(Ordering::SeqCst);
+
+        let (sender, receiver) =
+          async_channel::bounded::<SubscriptionEvent>(SUBSCRIPTION_CHANNEL_CAPACITY);
+        let subscription = Subscription {
+          subscription_id,
+          record_api_name,
+          user,
+          filter,
+          sender,
+        };
+
+        // Events to send ahead of live changes.
+        let mut backlog = VecDeque::from(snapshot);
+        match resume_after {
+          Some(last_seq) if last_seq != cursor => {
+            if change_log::truncated_after(conn, last_seq, cursor)? {
+              backlog.push_back(SubscriptionEvent {
+                seq: Some(cursor),
+                event: Arc::new(DbEvent::Reset("Change log truncated".into())),
+              });
+            } else {
+              let Some(metadata) = s.schema_metadata.get_table(&table_name) else {
+                return Err(trailbase_sqlite::Error::Other("Table not found".into()));
+              };
+
+              for entry in change_log::read_change_log(conn, last_seq, cursor, Some(&table_name))? {
+                if row_id.is_some_and(|row_id| entry.row_id != row_id) {
+                  continue;
+                }
+
+                // Like live changes, replayed changes carry the record's values as logged with the
+                // change.
+                let Some(ref snapshot) = entry.record else {
+                  backlog.push_back(SubscriptionEvent {
+                    seq: Some(cursor),
+                    event: Arc::new(DbEvent::Reset("Change cannot be replayed".into())),
+                  });
+                  if row_id.is_some() {
+                    subscription.sender.close();
+                    return Ok((receiver, backlog));
+                  }
+                  break;
+                };
+                let record_values = change_log::snapshot_values(conn, &metadata, snapshot)?;
+                let record: Vec<(&str, &rusqlite::types::Value)> = record_values
+                  .iter()
+                  .enumerate()
+                  .map(|(idx, v)| (metadata.schema.columns[idx].name.as_str(), v))
+                  .collect();
+
+                // Replayed changes are subject to the same checks as live changes.
+                match Self::deliver(
+                  &s,
+                  conn,
+                  &subscription,
+                  row_id.is_some(),
+                  entry.action,
+                  &record,
+                  &change_event(entry.seq, entry.action, &record),
+                  &mut None,
+                ) {
+                  Delivery::Skip => {}
+                  Delivery::Send(event) => backlog.push_back(event),
+                  Delivery::End(event) => {
+                    backlog.extend(event);
+                    subscription.sender.close();
+                    return Ok((receiver, backlog));
+                  }
+                }
+
+                if row_id.is_some() && entry.action == RecordAction::Delete {
+                  // The subscription has already ended, i.e. the record got deleted.
+                  subscription.sender.close();
+                  return Ok((receiver, backlog));
+                }
+              }
+            }
+          }
+          _ => {}
+        };
 
-          // If there are no subscriptions, do nothing.
-          let record_subs_candidate = s
+        match row_id {
+          Some(row_id) => s
             .record_subscriptions
-            .read()
-            .get(&qualified_table_name)
-            .and_then(|m| m.get(&rowid))
-            .is_some();
-          let table_subs_candidate = s
+            .write()
+            .entry(table_name)
+            .or_default()
+            .entry(row_id)
+            .or_default()
+            .push(subscription),
+          None => s
             .table_subscriptions
-            .read()
-            .get(&qualified_table_name)
-            .is_some();
-          if !record_subs_candidate && !table_subs_candidate {
-            return;
-          }
+            .write()
+            .entry(table_name)
+            .or_default()
+            .push(subscription),
+        };
 
-          let Some(record_values) = extract_record_values(case) else {
-            error!("Failed to extract values");
-            return;
-          };
+        if empty {
+          conn.preupdate_hook(Some(Self::build_hook(s.clone())));
+        }
 
-          let state = ContinuationState {
-            state: s.clone(),
-            schema_metadata: s.schema_metadata.get_table(&qualified_table_name),
-            action,
-            table_name: qualified_table_name,
-            rowid,
-            record_values,
-          };
+        return Ok((receiver, backlog));
+      })
+      .await
+      .map_err(|err| RecordError::Internal(err.into()))?;
 
-          // TODO: Optimization: in cases where there's only table-level access restrictions, we
-          // could avoid the continuation and even dispatch the subscription handling to a
-          // different thread entirely to take more work off the SQLite thread.
-          conn.call_and_forget(move |conn| {
-            Self::hook_continuation(conn, state);
-          });
+    return Ok(AutoCleanupEventStream {
+      cleanup: CleanupSubscription {
+        receiver: receiver.downgrade(),
+        state: app_state,
+        id: SubscriptionId {
+          table_name: api.qualified_name().clone(),
+          row_id,
+          sub_id: subscription_id,
         },
-      ))
-      .await;
+      },
+      backlog,
+      receiver,
+    });
+  }
+
+  /// Reads up to `limit` of the most recent records visible to the user and matching the filter
+  /// or, with `row_id`, a specific record. Also returns the sequence number of the most recent
+  /// change reflected by the snapshot.
+  ///
+  /// NOTE: Reads happen on a reader, i.e. large snapshots don't block writes. Access rules and
+  /// filters are applied in SQL rather than per record.
+  async fn read_snapshot(
+    &self,
+    api: &RecordApi,
+    row_id: Option<i64>,
+    user: Option<&User>,
+    filter: Option<&SubscriptionFilter>,
+    limit: usize,
+  ) -> Result<(i64, Vec<SubscriptionEvent>), RecordError> {
+    let Some(metadata) = self.state.schema_metadata.get_table(api.qualified_name()) else {
+      return Err(RecordError::ApiRequiresTable);
+    };
+
+    let mut clauses: Vec<String> = vec![format!("({})", api.read_access_rule().unwrap_or("TRUE"))];
+    if let Some(filter) = filter {
+      clauses.push(format!("({})", filter.clause));
+    }
+    if let Some(soft_delete_filter) = api.soft_delete_filter("_ROW_") {
+      clauses.push(soft_delete_filter);
+    }
+    clauses.push("(:__row_id IS NULL OR _ROW_._rowid_ = :__row_id)".to_string());
+
+    let sql = format!(
+      "SELECT {columns} FROM (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_, {table_name} AS _ROW_ WHERE {where_clause} ORDER BY _ROW_._rowid_ DESC LIMIT :__limit",
+      columns = metadata
+        .schema
+        .columns
+        .iter()
+        .map(|c| format!(r#"_ROW_."{}""#, c.name))
+        .join(", "),
+      table_name = api.table_name(),
+      where_clause = clauses.join(" AND "),
+    );
+
+    let mut params: NamedParams = filter.map(|f| f.params.clone()).unwrap_or_default();
+    params.extend([
+      (
+        Cow::Borrowed(":__user_id"),
+        user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+      ),
+      (
+        Cow::Borrowed(":__user_roles"),
+        user.map_or(Value::Null, |u| Value::Text(u.roles_json())),
+      ),
+      (
+        Cow::Borrowed(":__row_id"),
+        row_id.map_or(Value::Null, Value::Integer),
+      ),
+      (Cow::Borrowed(":__limit"), Value::Integer(limit as i64)),
+    ]);
+
+    let api = api.clone();
+    let user = user.cloned();
+    return Ok(
+      self
+        .state
+        .conn
+        .call_reader(move |conn| {
+          // Read the records and the change log's position from the same database snapshot.
+          let tx = conn.unchecked_transaction()?;
+          let seq = change_log::last_seq(&tx)?;
+
+          let columns = &metadata.schema.columns;
+          let mut stmt = tx.prepare(&sql)?;
+          params.bind(&mut stmt)?;
+
+          let mut events: Vec<SubscriptionEvent> = vec![];
+          let mut rows = stmt.raw_query();
+          while let Some(row) = rows.next()? {
+            let record_values: Vec<Value> = (0..columns.len())
+              .map(|idx| row.get(idx))
+              .collect::<Result<_, _>>()?;
+            let record: Vec<(&str, &Value)> = record_values
+              .iter()
+              .enumerate()
+              .map(|(idx, v)| (columns[idx].name.as_str(), v))
+              .collect();
+
+            let mut json_value = record_to_json(&record);
+            withhold_columns(
+              &mut json_value,
+              &api.withheld_columns_for_subscriptions(&tx, &record, user.as_ref()),
+            );
+            events.push(SubscriptionEvent {
+              seq: Some(seq),
+              event: Arc::new(DbEvent::Snapshot(Some(json_value))),
+            });
+          }
+
+          return Ok((seq, events));
+        })
+        .await?,
+    );
   }
 
   async fn add_record_subscription(
@@ -518,6 +956,7 @@ impl SubscriptionManager {
     api: RecordApi,
     record: trailbase_sqlite::Value,
     user: Option<User>,
+    start: SubscriptionStart,
   ) -> Result<AutoCleanupEventStream, RecordError> {
     let table_name = api.table_name();
     let pk_column = &api.record_pk_column().1.name;
@@ -526,7 +965,12 @@ impl SubscriptionManager {
       .state
       .conn
       .read_query_row_f(
-        format!(r#"SELECT _rowid_ FROM {table_name} WHERE "{pk_column}" = $1"#),
+        match api.soft_delete_column() {
+          Some(column) => format!(
+            r#"SELECT _rowid_ FROM {table_name} WHERE "{pk_column}" = $1 AND "{column}" IS NULL"#
+          ),
+          None => format!(r#"SELECT _rowid_ FROM {table_name} WHERE "{pk_column}" = $1"#),
+        },
         [record],
         |row| row.get(0),
       )
@@ -535,45 +979,9 @@ impl SubscriptionManager {
       return Err(RecordError::RecordNotFound);
     };
 
-    let (sender, receiver) = async_channel::bounded::<Event>(16);
-
-    let subscription_id = SUBSCRIPTION_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let empty = {
-      let mut lock = self.state.record_subscriptions.write();
-      let empty = lock.is_empty();
-      let m: &mut HashMap<i64, Vec<Subscription>> =
-        lock.entry(api.qualified_name().clone()).or_default();
-
-      m.entry(row_id).or_default().push(Subscription {
-        subscription_id,
-        record_api_name: api.api_name().to_string(),
-        // record_id: Some(record),
-        user,
-        sender,
-      });
-
-      empty
-    };
-
-    if empty {
-      self
-        .add_hook()
-        .await
-        .map_err(|err| RecordError::Internal(err.into()))?;
-    }
-
-    return Ok(AutoCleanupEventStream {
-      cleanup: CleanupSubscription {
-        receiver: receiver.downgrade(),
-        state: app_state,
-        id: SubscriptionId {
-          table_name: api.qualified_name().clone(),
-          row_id: Some(row_id),
-          sub_id: subscription_id,
-        },
-      },
-      receiver,
-    });
+    return self
+      .add_subscription(app_state, api, Some(row_id), user, None, start)
+      .await;
   }
 
   async fn add_table_subscription(
@@ -581,54 +989,72 @@ impl SubscriptionManager {
     app_state: AppState,
     api: RecordApi,
     user: Option<User>,
+    filter: Option<SubscriptionFilter>,
+    start: SubscriptionStart,
   ) -> Result<AutoCleanupEventStream, RecordError> {
-    let state = &self.state;
-
-    let (sender, receiver) = async_channel::bounded::<Event>(16);
-    let subscription_id = SUBSCRIPTION_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let empty = {
-      let mut lock = state.table_subscriptions.write();
-      let empty = lock.is_empty() && state.record_subscriptions.read().is_empty();
-      let m: &mut Vec<Subscription> = lock.entry(api.qualified_name().clone()).or_default();
-
-      m.push(Subscription {
-        subscription_id,
-        record_api_name: api.api_name().to_string(),
-        user,
-        sender,
-      });
-
-      empty
-    };
-
-    if empty {
-      self
-        .add_hook()
-        .await
-        .map_err(|err| RecordError::Internal(err.into()))?;
-    }
-
-    return Ok(AutoCleanupEventStream {
-      cleanup: CleanupSubscription {
-        receiver: receiver.downgrade(),
-        state: app_state,
-        id: SubscriptionId {
-          table_name: api.qualified_name().clone(),
-          row_id: None,
-          sub_id: subscription_id,
-        },
-      },
-      receiver,
-    });
+    return self
+      .add_subscription(app_state, api, None, user, filter, start)
+      .await;
   }
 }
 
+/// Subscribes to changes of a specific record or, with `record` being "*", the entire table.
+///
+/// Table subscriptions accept the same `filter` parameters as listing to only receive changes
+/// to matching records.
+///
+/// Events of changes carry ids. Reconnecting clients sending a `Last-Event-ID` header receive the
+/// changes they missed or a `Reset` event, if these are no longer available.
 pub async fn add_subscription_sse_handler(
   State(state): State<AppState>,
   Path((api_name, record)): Path<(String, String)>,
+  RawQuery(raw_url_query): RawQuery,
   user: Option<User>,
+  headers: HeaderMap,
 ) -> Result<Sse<impl Stream<Item = SseEvent>>, RecordError> {
-  let Some(api) = state.lookup_record_api(&api_name) else {
+  let last_seq = match headers.get("Last-Event-ID") {
+    Some(value) => Some(
+      value
+        .to_str()
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or(RecordError::BadRequest("Invalid Last-Event-ID"))?,
+    ),
+    None => None,
+  };
+
+  let receiver = subscribe(
+    &state,
+    &api_name,
+    &record,
+    raw_url_query.as_deref(),
+    user,
+    last_seq,
+  )
+  .await?;
+
+  return Ok(
+    Sse::new(receiver.map(|event| -> SseEvent {
+      let sse_event = Event::default().json_data(&*event.event)?;
+      return Ok(match event.seq {
+        Some(seq) => sse_event.id(seq.to_string()),
+        None => sse_event,
+      });
+    }))
+    .keep_alive(KeepAlive::default()),
+  );
+}
+
+/// Adds a subscription after checking access, shared by the SSE and WebSocket transports.
+async fn subscribe(
+  state: &AppState,
+  api_name: &str,
+  record: &str,
+  raw_url_query: Option<&str>,
+  user: Option<User>,
+  last_seq: Option<i64>,
+) -> Result<AutoCleanupEventStream, RecordError> {
+  let Some(api) = state.lookup_record_api(api_name) else {
     return Err(RecordError::ApiNotFound);
   };
 
@@ -636,30 +1062,344 @@ pub async fn add_subscription_sse_handler(
     return Err(RecordError::Forbidden);
   }
 
+  let Query {
+    filter,
+    snapshot,
+    limit,
+    ..
+  } = raw_url_query
+    .map_or_else(|| Ok(Query::default()), Query::parse)
+    .map_err(|_err| RecordError::BadRequest("Invalid query"))?;
+
+  // A snapshot supersedes replaying missed changes.
+  let start = match (snapshot, last_seq) {
+    (Some(true), _) => {
+      SubscriptionStart::Snapshot(limit_or_default(limit).map_err(RecordError::BadRequest)?)
+    }
+    (_, Some(last_seq)) => SubscriptionStart::Resume(last_seq),
+    _ => SubscriptionStart::Live,
+  };
+
   if record == "*" {
     api.check_table_level_access(Permission::Read, user.as_ref())?;
 
-    let receiver = state
+    let filter = filter
+      .map(|filter| build_subscription_filter(&api, filter))
+      .transpose()?;
+
+    return state
       .subscription_manager()
-      .add_table_subscription(state.clone(), api, user)
-      .await?;
+      .add_table_subscription(state.clone(), api, user, filter, start)
+      .await;
+  }
 
-    return Ok(Sse::new(receiver).keep_alive(KeepAlive::default()));
-  } else {
-    let record_id = api.id_to_sql(&record)?;
-    api
-      .check_record_level_access(Permission::Read, Some(&record_id), None, user.as_ref())
-      .await?;
+  if filter.is_some() {
+    return Err(RecordError::BadRequest(
+      "Filters are only supported for table subscriptions",
+    ));
+  }
 
-    let receiver = state
-      .subscription_manager()
-      .add_record_subscription(state.clone(), api, record_id, user)
-      .await?;
+  let record_id = api.id_to_sql(record)?;
+  api
+    .check_record_level_access(Permission::Read, Some(&record_id), None, user.as_ref())
+    .await?;
+
+  return state
+    .subscription_manager()
+    .add_record_subscription(state.clone(), api, record_id, user, start)
+    .await;
+}
+
+/// Command sent by clients over a multiplexed WebSocket subscription connection.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubscriptionRequest {
+  /// Subscribes to changes of a specific record or, with `record` being "*", the entire table.
+  /// `query` optionally holds URL-encoded `filter` parameters for table subscriptions. The `id` is
+  /// chosen by the client and must be unique per connection. With `last_seq`, the `seq` of the
+  /// last received event, missed changes are replayed first.
+  Subscribe {
+    id: u64,
+    api: String,
+    record: String,
+    #[serde(default)]
+    query: Option<String>,
+    #[serde(default)]
+    last_seq: Option<i64>,
+  },
+  Unsubscribe {
+    id: u64,
+  },
+}
+
+/// Message sent by the server over a multiplexed WebSocket subscription connection.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubscriptionResponse<'a> {
+  Subscribed {
+    id: u64,
+  },
+  /// Change event for the subscription with the given `id`. `seq` is the sequence number of the
+  /// change or, for snapshots, of the most recent change reflected by the snapshot. It's absent
+  /// for errors.
+  Event {
+    id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seq: Option<i64>,
+    event: &'a DbEvent,
+  },
+  /// Sent on explicit unsubscribes and when the server ends a subscription, e.g. when the
+  /// subscribed record got deleted.
+  Unsubscribed {
+    id: u64,
+  },
+  /// Failed command, e.g. due to missing access. `id` is absent for malformed commands.
+  Error {
+    id: Option<u64>,
+    message: String,
+  },
+}
+
+/// Upper bound for concurrent subscriptions per WebSocket connection.
+const MAX_SOCKET_SUBSCRIPTIONS: usize = 256;
+
+/// Multiplexes many subscriptions over a single WebSocket connection.
+///
+/// Clients send JSON-encoded `SubscriptionRequest`s as text messages and receive
+/// `SubscriptionResponse`s. Subscriptions are subject to the same access checks as their SSE
+/// counterparts and end when the connection closes. Connections are limited to
+/// `MAX_SOCKET_SUBSCRIPTIONS` concurrent subscriptions. Upgrades from foreign origins are rejected
+/// to prevent cross-site WebSocket hijacking.
+pub async fn subscription_ws_handler(
+  State(state): State<AppState>,
+  user: Option<User>,
+  headers: HeaderMap,
+  ws: WebSocketUpgrade,
+) -> Result<Response, RecordError> {
+  if !state.is_allowed_origin(&headers) {
+    return Err(RecordError::Forbidden);
+  }
 
-    return Ok(Sse::new(receiver).keep_alive(KeepAlive::default()));
+  return Ok(ws.on_upgrade(move |socket| handle_subscription_socket(state, user, socket)));
+}
+
+async fn handle_subscription_socket(state: AppState, user: Option<User>, mut socket: WebSocket) {
+  let (sender, receiver) = async_channel::bounded::<String>(64);
+  let mut subscriptions = SocketSubscriptions::new(state, user, sender);
+
+  loop {
+    tokio::select! {
+      msg = socket.recv() => {
+        let text = match msg {
+          Some(Ok(Message::Text(text))) => text,
+          Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+          // Pings are answered automatically.
+          Some(Ok(_)) => continue,
+        };
+
+        let response = match serde_json::from_str::<SubscriptionRequest>(text.as_str()) {
+          Ok(request) => subscriptions.handle(request).await,
+          Err(_err) => encode_response(&SubscriptionResponse::Error {
+            id: None,
+            message: "Invalid request".to_string(),
+          }),
+        };
+
+        let Some(response) = response else {
+          continue;
+        };
+        if socket.send(Message::Text(response.into())).await.is_err() {
+          break;
+        }
+      }
+      Ok(response) = receiver.recv() => {
+        if socket.send(Message::Text(response.into())).await.is_err() {
+          break;
+        }
+      }
+    }
   }
 }
 
+/// Subscriptions of a single WebSocket connection. Each subscription is forwarded by its own task,
+/// which, once aborted, drops the underlying subscription.
+struct SocketSubscriptions {
+  state: AppState,
+  user: Option<User>,
+  sender: async_channel::Sender<String>,
+  tasks: HashMap<u64, tokio::task::AbortHandle>,
+}
+
+impl SocketSubscriptions {
+  fn new(state: AppState, user: Option<User>, sender: async_channel::Sender<String>) -> Self {
+    return Self {
+      state,
+      user,
+      sender,
+      tasks: HashMap::new(),
+    };
+  }
+
+  /// Handles the given command and returns the encoded response.
+  async fn handle(&mut self, request: SubscriptionRequest) -> Option<String> {
+    return match request {
+      SubscriptionRequest::Subscribe {
+        id,
+        api,
+        record,
+        query,
+        last_seq,
+      } => {
+        // Subscriptions ended by the server no longer count.
+        self.tasks.retain(|_, task| !task.is_finished());
+
+        if self.tasks.contains_key(&id) {
+          return encode_response(&SubscriptionResponse::Error {
+            id: Some(id),
+            message: "Duplicate subscription id".to_string(),
+          });
+        }
+        if self.tasks.len() >= MAX_SOCKET_SUBSCRIPTIONS {
+          return encode_response(&SubscriptionResponse::Error {
+            id: Some(id),
+            message: "Too many subscriptions".to_string(),
+          });
+        }
+
+        let stream = match subscribe(
+          &self.state,
+          &api,
+          &record,
+          query.as_deref(),
+          self.user.clone(),
+          last_seq,
+        )
+        .await
+        {
+          Ok(stream) => stream,
+          Err(err) => {
+            return encode_response(&SubscriptionResponse::Error {
+              id: Some(id),
+              message: error_message(&err),
+            });
+          }
+        };
+
+        let sender = self.sender.clone();
+        let task = tokio::spawn(async move {
+          let mut stream = std::pin::pin!(stream);
+          while let Some(event) = stream.next().await {
+            let Some(response) = encode_response(&SubscriptionResponse::Event {
+              id,
+              seq: event.seq,
+              event: &event.event,
+            }) else {
+              continue;
+            };
+            if sender.send(response).await.is_err() {
+              return;
+            }
+          }
+
+          // The subscription was ended by the server.
+          if let Some(response) = encode_response(&SubscriptionResponse::Unsubscribed { id }) {
+            let _ = sender.send(response).await;
+          }
+        });
+        self.tasks.insert(id, task.abort_handle());
+
+        encode_response(&SubscriptionResponse::Subscribed { id })
+      }
+      SubscriptionRequest::Unsubscribe { id } => {
+        if let Some(task) = self.tasks.remove(&id) {
+          task.abort();
+        }
+        encode_response(&SubscriptionResponse::Unsubscribed { id })
+      }
+    };
+  }
+}
+
+impl Drop for SocketSubscriptions {
+  fn drop(&mut self) {
+    for task in self.tasks.values() {
+      task.abort();
+    }
+  }
+}
+
+fn encode_response(response: &SubscriptionResponse<'_>) -> Option<String> {
+  return serde_json::to_string(response)
+    .map_err(|err| warn!("Failed to encode subscription response: {err}"))
+    .ok();
+}
+
+/// Client-facing message for the given error, keeping internals private.
+fn error_message(err: &RecordError) -> String {
+  return match err {
+    RecordError::Internal(_) => "Internal".to_string(),
+    err => err.to_string(),
+  };
+}
+
+fn build_subscription_filter(
+  api: &RecordApi,
+  filter: trailbase_qs::ValueOrComposite,
+) -> Result<SubscriptionFilter, RecordError> {
+  // Filters on values withheld by column-level read rules never match.
+  let guards = |column_name: &str| api.column_read_rule(column_name).map(|r| format!("({r})"));
+  // NOTE: This will also drop any filters for unknown columns, thus avoiding SQL injections.
+  let (WhereClause { clause, params }, _matches) = build_filter_where_clause_with_fts(
+    "_ROW_",
+    api.columns(),
+    None,
+    None,
+    None,
+    Some(&guards),
+    Some(filter),
+  )
+  .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
+
+  return Ok(SubscriptionFilter {
+    query: api.build_subscription_filter_query(&clause)?,
+    clause,
+    params,
+  });
+}
+
+/// Builds the event for a logged change (insert, update, delete) with a JSON-encoded record.
+fn change_event(
+  seq: i64,
+  action: RecordAction,
+  record: &[(&str, &rusqlite::types::Value)],
+) -> SubscriptionEvent {
+  let json_value = record_to_json(record);
+
+  return SubscriptionEvent {
+    seq: Some(seq),
+    event: Arc::new(match action {
+      RecordAction::Delete => DbEvent::Delete(Some(json_value)),
+      RecordAction::Insert => DbEvent::Insert(Some(json_value)),
+      RecordAction::Update => DbEvent::Update(Some(json_value)),
+    }),
+  };
+}
+
+/// JSON-encodes the given record, skipping values that cannot be represented.
+fn record_to_json(record: &[(&str, &rusqlite::types::Value)]) -> serde_json::Value {
+  return serde_json::Value::Object(
+    record
+      .iter()
+      .filter_map(|(name, value)| {
+        if let Ok(v) = value_to_json(value) {
+          return Some(((*name).to_string(), v));
+        };
+        return None;
+      })
+      .collect(),
+  );
+}
+
 #[cfg(test)]
 async fn decode_sse_json_event(event: Event) -> serde_json::Value {
   use axum::response::IntoResponse;
@@ -688,7 +1428,7 @@ async fn decode_sse_json_event(event: Event) -> serde_json::Value {
 #[cfg(test)]
 mod tests {
   use async_channel::TryRecvError;
-  use futures_util::StreamExt;
+  use futures_util::{FutureExt, StreamExt};
   use trailbase_sqlite::params;
 
   use super::DbEvent;
@@ -702,8 +1442,8 @@ mod tests {
   use crate::records::test_utils::add_record_api_config;
   use crate::util::uuid_to_b64;
 
-  async fn decode_db_event(event: Event) -> DbEvent {
-    let json = decode_sse_json_event(event).await;
+  async fn decode_db_event(event: SubscriptionEvent) -> DbEvent {
+    let json = decode_sse_json_event(Event::default().json_data(&*event.event).unwrap()).await;
     return serde_json::from_value(json).unwrap();
   }
 
@@ -714,9 +1454,15 @@ mod tests {
       "b": "text",
     });
     let db_event = DbEvent::Delete(Some(json));
-    let event = Event::default().json_data(db_event.clone()).unwrap();
 
-    assert_eq!(decode_db_event(event).await, db_event);
+    assert_eq!(
+      decode_db_event(SubscriptionEvent {
+        seq: None,
+        event: Arc::new(db_event.clone()),
+      })
+      .await,
+      db_event
+    );
   }
 
   async fn setup_world_readable() -> AppState {
@@ -777,6 +1523,7 @@ mod tests {
         api,
         trailbase_sqlite::Value::Integer(0),
         None,
+        SubscriptionStart::Live,
       )
       .await
       .unwrap();
@@ -846,7 +1593,7 @@ mod tests {
 
     {
       let stream = manager
-        .add_table_subscription(state.clone(), api, None)
+        .add_table_subscription(state.clone(), api, None, None, SubscriptionStart::Live)
         .await
         .unwrap();
 
@@ -919,6 +1666,444 @@ mod tests {
     assert_eq!(0, manager.num_table_subscriptions());
   }
 
+  #[tokio::test]
+  async fn subscribe_to_table_with_filter_test() {
+    let state = setup_world_readable().await;
+    let conn = state.conn().clone();
+
+    let manager = state.subscription_manager();
+    let api = state.lookup_record_api("api_name").unwrap();
+
+    let Query { filter, .. } = Query::parse("filter[text][$ne]=foo").unwrap();
+    let filter = build_subscription_filter(&api, filter.unwrap()).unwrap();
+
+    let stream = manager
+      .add_table_subscription(
+        state.clone(),
+        api,
+        None,
+        Some(filter),
+        SubscriptionStart::Live,
+      )
+      .await
+      .unwrap();
+
+    conn
+      .execute(
+        "INSERT INTO test (id, text) VALUES (0, 'foo'), (1, 'bar')",
+        (),
+      )
+      .await
+      .unwrap();
+
+    let expected = serde_json::json!({
+      "id": 1,
+      "text": "bar",
+    });
+    match decode_db_event(stream.receiver.recv().await.unwrap()).await {
+      DbEvent::Insert(Some(value)) => {
+        assert_eq!(value, expected);
+      }
+      x => {
+        assert!(false, "Expected insert, got: {x:?}");
+      }
+    };
+
+    // Deletions are matched against the deleted record.
+    conn
+      .execute("DELETE FROM test WHERE id = 0", ())
+      .await
+      .unwrap();
+    conn
+      .execute("DELETE FROM test WHERE id = 1", ())
+      .await
+      .unwrap();
+
+    match decode_db_event(stream.receiver.recv().await.unwrap()).await {
+      DbEvent::Delete(Some(value)) => {
+        assert_eq!(value, expected);
+      }
+      x => {
+        assert!(false, "Expected delete, got: {x:?}");
+      }
+    };
+    assert_eq!(
+      TryRecvError::Empty,
+      stream.receiver.try_recv().err().unwrap()
+    );
+
+    // Filters are only supported for table subscriptions.
+    let sse_or = add_subscription_sse_handler(
+      State(state.clone()),
+      Path(("api_name".to_string(), "0".to_string())),
+      RawQuery(Some("filter[text]=foo".to_string())),
+      None,
+      HeaderMap::new(),
+    )
+    .await;
+    assert!(matches!(sse_or, Err(RecordError::BadRequest(_))));
+  }
+
+  #[tokio::test]
+  async fn resume_subscription_test() {
+    let state = setup_world_readable().await;
+    let conn = state.conn().clone();
+
+    let manager = state.subscription_manager();
+    let api = state.lookup_record_api("api_name").unwrap();
+
+    let last_seq = {
+      let stream = manager
+        .add_table_subscription(
+          state.clone(),
+          api.clone(),
+          None,
+          None,
+          SubscriptionStart::Live,
+        )
+        .await
+        .unwrap();
+
+      conn
+        .execute("INSERT INTO test (id, text) VALUES (0, 'foo')", ())
+        .await
+        .unwrap();
+
+      let event = stream.receiver.recv().await.unwrap();
+      assert_eq!(
+        DbEvent::Insert(Some(serde_json::json!({"id": 0, "text": "foo"}))),
+        *event.event
+      );
+      event.seq.unwrap()
+    };
+
+    // Changes while disconnected.
+    conn
+      .execute("INSERT INTO test (id, text) VALUES (1, 'bar')", ())
+      .await
+      .unwrap();
+    conn
+      .execute("UPDATE test SET text = 'baz' WHERE id = 0", ())
+      .await
+      .unwrap();
+
+    {
+      let stream = manager
+        .add_table_subscription(
+          state.clone(),
+          api.clone(),
+          None,
+          None,
+          SubscriptionStart::Resume(last_seq),
+        )
+        .await
+        .unwrap();
+      let mut stream = std::pin::pin!(stream);
+
+      // Missed changes are replayed in order followed by live changes.
+      conn
+        .execute("DELETE FROM test WHERE id = 1", ())
+        .await
+        .unwrap();
+
+      let mut seq = last_seq;
+      for expected in [
+        DbEvent::Insert(Some(serde_json::json!({"id": 1, "text": "bar"}))),
+        DbEvent::Update(Some(serde_json::json!({"id": 0, "text": "baz"}))),
+        DbEvent::Delete(Some(serde_json::json!({"id": 1, "text": "bar"}))),
+      ] {
+        let event = stream.next().await.unwrap();
+        assert_eq!(expected, *event.event);
+        assert!(event.seq.unwrap() > seq);
+        seq = event.seq.unwrap();
+      }
+    }
+
+    {
+      // Changes are replayed with the logged values, i.e. including changes to records deleted
+      // since.
+      let stream = manager
+        .add_table_subscription(
+          state.clone(),
+          api.clone(),
+          None,
+          None,
+          SubscriptionStart::Resume(last_seq),
+        )
+        .await
+        .unwrap();
+      let mut stream = std::pin::pin!(stream);
+
+      for expected in [
+        DbEvent::Insert(Some(serde_json::json!({"id": 1, "text": "bar"}))),
+        DbEvent::Update(Some(serde_json::json!({"id": 0, "text": "baz"}))),
+        DbEvent::Delete(Some(serde_json::json!({"id": 1, "text": "bar"}))),
+      ] {
+        assert_eq!(expected, *stream.next().await.unwrap().event);
+      }
+    }
+
+    // Changes can no longer be replayed once the log has been truncated.
+    conn
+      .execute(format!("DELETE FROM {}", change_log::CHANGE_LOG_TABLE), ())
+      .await
+      .unwrap();
+
+    let stream = manager
+      .add_table_subscription(
+        state.clone(),
+        api,
+        None,
+        None,
+        SubscriptionStart::Resume(last_seq),
+      )
+      .await
+      .unwrap();
+    let mut stream = std::pin::pin!(stream);
+    assert!(matches!(
+      *stream.next().await.unwrap().event,
+      DbEvent::Reset(_)
+    ));
+  }
+
+  #[tokio::test]
+  async fn subscribe_with_snapshot_test() {
+    let state = setup_world_readable().await;
+    let conn = state.conn().clone();
+
+    conn
+      .execute(
+        "INSERT INTO test (id, text) VALUES (0, 'foo'), (1, 'bar'), (2, 'baz')",
+        (),
+      )
+      .await
+      .unwrap();
+
+    let manager = state.subscription_manager();
+    let api = state.lookup_record_api("api_name").unwrap();
+
+    let Query { filter, .. } = Query::parse("filter[text][$ne]=foo").unwrap();
+    let filter = build_subscription_filter(&api, filter.unwrap()).unwrap();
+
+    let stream = manager
+      .add_table_subscription(
+        state.clone(),
+        api.clone(),
+        None,
+        Some(filter),
+        SubscriptionStart::Snapshot(10),
+      )
+      .await
+      .unwrap();
+    let mut stream = std::pin::pin!(stream);
+
+    conn
+      .execute("UPDATE test SET text = 'qux' WHERE id = 2", ())
+      .await
+      .unwrap();
+
+    // Matching records, most recent first, are followed by live changes.
+    for expected in [
+      DbEvent::Snapshot(Some(serde_json::json!({"id": 2, "text": "baz"}))),
+      DbEvent::Snapshot(Some(serde_json::json!({"id": 1, "text": "bar"}))),
+      DbEvent::Update(Some(serde_json::json!({"id": 2, "text": "qux"}))),
+    ] {
+      assert_eq!(
+        expected,
+        decode_db_event(stream.next().await.unwrap()).await
+      );
+    }
+    assert!(stream.next().now_or_never().is_none());
+
+    // Snapshots are limited.
+    let stream = manager
+      .add_table_subscription(
+        state.clone(),
+        api.clone(),
+        None,
+        None,
+        SubscriptionStart::Snapshot(1),
+      )
+      .await
+      .unwrap();
+    let mut stream = std::pin::pin!(stream);
+    assert_eq!(
+      DbEvent::Snapshot(Some(serde_json::json!({"id": 2, "text": "qux"}))),
+      decode_db_event(stream.next().await.unwrap()).await
+    );
+    assert!(stream.next().now_or_never().is_none());
+
+    // Record subscriptions receive a snapshot of the record.
+    let stream = manager
+      .add_record_subscription(
+        state.clone(),
+        api,
+        trailbase_sqlite::Value::Integer(0),
+        None,
+        SubscriptionStart::Snapshot(10),
+      )
+      .await
+      .unwrap();
+    let mut stream = std::pin::pin!(stream);
+    assert_eq!(
+      DbEvent::Snapshot(Some(serde_json::json!({"id": 0, "text": "foo"}))),
+      decode_db_event(stream.next().await.unwrap()).await
+    );
+  }
+
+  #[tokio::test]
+  async fn multiplexed_subscriptions_test() {
+    let state = setup_world_readable().await;
+    let conn = state.conn().clone();
+    let manager = state.subscription_manager();
+
+    let (sender, receiver) = async_channel::unbounded::<String>();
+    let mut subscriptions = SocketSubscriptions::new(state.clone(), None, sender);
+
+    let decode = |response: Option<String>| -> serde_json::Value {
+      return serde_json::from_str(&response.unwrap()).unwrap();
+    };
+    let table_subscription =
+      |id: u64, api: &str, query: Option<&str>| SubscriptionRequest::Subscribe {
+        id,
+        api: api.to_string(),
+        record: "*".to_string(),
+        query: query.map(|q| q.to_string()),
+        last_seq: None,
+      };
+
+    assert_eq!(
+      serde_json::json!({"type": "subscribed", "id": 0}),
+      decode(
+        subscriptions
+          .handle(table_subscription(0, "api_name", None))
+          .await
+      )
+    );
+    assert_eq!(
+      serde_json::json!({"type": "subscribed", "id": 1}),
+      decode(
+        subscriptions
+          .handle(table_subscription(1, "api_name", Some("filter[text]=bar")))
+          .await
+      )
+    );
+    assert_eq!(
+      serde_json::json!({"type": "error", "id": 0, "message": "Duplicate subscription id"}),
+      decode(
+        subscriptions
+          .handle(table_subscription(0, "api_name", None))
+          .await
+      )
+    );
+    assert_eq!(
+      serde_json::json!({"type": "error", "id": 2, "message": "Api Not Found"}),
+      decode(
+        subscriptions
+          .handle(table_subscription(2, "unknown", None))
+          .await
+      )
+    );
+
+    assert_eq!(2, manager.num_table_subscriptions());
+
+    conn
+      .execute("INSERT INTO test (id, text) VALUES (0, 'foo')", ())
+      .await
+      .unwrap();
+    conn
+      .execute("INSERT INTO test (id, text) VALUES (1, 'bar')", ())
+      .await
+      .unwrap();
+
+    // Events of different subscriptions may interleave arbitrarily.
+    let mut events: Vec<serde_json::Value> = vec![];
+    let mut last_seq: i64 = 0;
+    for _ in 0..3 {
+      let mut event: serde_json::Value =
+        serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+      let seq = event.as_object_mut().unwrap().remove("seq").unwrap();
+      last_seq = last_seq.max(seq.as_i64().unwrap());
+      events.push(event);
+    }
+    for expected in [
+      serde_json::json!({"type": "event", "id": 0, "event": {"Insert": {"id": 0, "text": "foo"}}}),
+      serde_json::json!({"type": "event", "id": 0, "event": {"Insert": {"id": 1, "text": "bar"}}}),
+      serde_json::json!({"type": "event", "id": 1, "event": {"Insert": {"id": 1, "text": "bar"}}}),
+    ] {
+      assert!(events.contains(&expected), "{events:?}");
+    }
+
+    assert_eq!(
+      serde_json::json!({"type": "unsubscribed", "id": 0}),
+      decode(
+        subscriptions
+          .handle(SubscriptionRequest::Unsubscribe { id: 0 })
+          .await
+      )
+    );
+
+    // Resuming replays missed changes.
+    conn
+      .execute("UPDATE test SET text = 'baz' WHERE id = 0", ())
+      .await
+      .unwrap();
+    assert_eq!(
+      serde_json::json!({"type": "subscribed", "id": 3}),
+      decode(
+        subscriptions
+          .handle(SubscriptionRequest::Subscribe {
+            id: 3,
+            api: "api_name".to_string(),
+            record: "*".to_string(),
+            query: None,
+            last_seq: Some(last_seq),
+          })
+          .await
+      )
+    );
+    let event: serde_json::Value = serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+    assert_eq!(
+      serde_json::json!({"Update": {"id": 0, "text": "baz"}}),
+      event["event"]
+    );
+    assert_eq!(3, event["id"]);
+    assert!(event["seq"].as_i64().unwrap() > last_seq);
+
+    // Subscriptions per connection are capped.
+    for id in 4..(MAX_SOCKET_SUBSCRIPTIONS as u64 + 2) {
+      decode(
+        subscriptions
+          .handle(table_subscription(id, "api_name", None))
+          .await,
+      );
+    }
+    assert_eq!(
+      serde_json::json!({"type": "error", "id": 1000, "message": "Too many subscriptions"}),
+      decode(
+        subscriptions
+          .handle(table_subscription(1000, "api_name", None))
+          .await
+      )
+    );
+
+    // Closing the connection drops all remaining subscriptions.
+    drop(subscriptions);
+
+    for _ in 0..100 {
+      if manager.num_table_subscriptions() == 0 {
+        break;
+      }
+      tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+      // Implicitly await for scheduled cleanups to go through.
+      conn
+        .read_query_row_f("SELECT 1", (), |row| row.get::<_, i64>(0))
+        .await
+        .unwrap();
+    }
+    assert_eq!(0, manager.num_table_subscriptions());
+  }
+
   #[tokio::test]
   async fn subscription_lifecycle_test() {
     let state = setup_world_readable().await;
@@ -941,7 +2126,9 @@ mod tests {
     let sse = add_subscription_sse_handler(
       State(state.clone()),
       Path(("api_name".to_string(), record_id_raw.to_string())),
+      RawQuery(None),
       None,
+      HeaderMap::new(),
     )
     .await;
 
@@ -1008,7 +2195,9 @@ mod tests {
     let sse_or = add_subscription_sse_handler(
       State(state.clone()),
       Path(("api_name".to_string(), "*".to_string())),
+      RawQuery(None),
       None,
+      HeaderMap::new(),
     )
     .await;
 
@@ -1027,7 +2216,9 @@ mod tests {
       let _ = add_subscription_sse_handler(
         State(state.clone()),
         Path(("api_name".to_string(), "*".to_string())),
+        RawQuery(None),
         User::from_auth_token(&state, &user_x_token.auth_token),
+        HeaderMap::new(),
       )
       .await
       .unwrap();
@@ -1053,7 +2244,9 @@ mod tests {
       let _ = add_subscription_sse_handler(
         State(state.clone()),
         Path(("api_name".to_string(), record_id_raw.to_string())),
+        RawQuery(None),
         User::from_auth_token(&state, &user_x_token.auth_token),
+        HeaderMap::new(),
       )
       .await
       .unwrap();
@@ -1073,7 +2266,9 @@ mod tests {
       let sse_or = add_subscription_sse_handler(
         State(state.clone()),
         Path(("api_name".to_string(), record_id_raw.to_string())),
+        RawQuery(None),
         User::from_auth_token(&state, &user_y_token.auth_token),
+        HeaderMap::new(),
       )
       .await;
 
@@ -1114,6 +2309,8 @@ mod tests {
           state.clone(),
           api.clone(),
           User::from_auth_token(&state, &user_x_token.auth_token),
+          None,
+          SubscriptionStart::Live,
         )
         .await
         .unwrap();
@@ -1123,6 +2320,8 @@ mod tests {
           state.clone(),
           api.clone(),
           User::from_auth_token(&state, &user_y_token.auth_token),
+          None,
+          SubscriptionStart::Live,
         )
         .await
         .unwrap();
@@ -1217,6 +2416,7 @@ mod tests {
         api,
         trailbase_sqlite::Value::Integer(record_id),
         user_x,
+        SubscriptionStart::Live,
       )
       .await
       .unwrap();
@@ -25,6 +25,7 @@ mod tests {
 
       acl_world: acls.world.into_iter().map(|f| f as i32).collect(),
       acl_authenticated: acls.authenticated.into_iter().map(|f| f as i32).collect(),
+      acl_roles: vec![],
       conflict_resolution: None,
       autofill_missing_user_id_columns: None,
       enable_subscriptions: None,
@@ -35,6 +36,12 @@ mod tests {
       delete_access_rule: access_rules.delete,
       schema_access_rule: access_rules.schema,
       expand: vec![],
+      related_apis: vec![],
+      version_column: None,
+      soft_delete_column: None,
+      soft_delete_retention_sec: None,
+      enable_history: None,
+      column_access_rules: vec![],
     });
 
     return state.validate_and_update_config(config, None).await;
@@ -1,10 +1,12 @@
 use axum::extract::{Path, State};
+use axum::http::HeaderMap;
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
 use crate::extract::Either;
+use crate::records::etag::IfMatch;
 use crate::records::params::{JsonRow, LazyParams};
-use crate::records::query_builder::UpdateQueryBuilder;
+use crate::records::query_builder::{QueryError, UpdateQueryBuilder};
 use crate::records::{Permission, RecordError};
 
 /// Update existing record.
@@ -13,13 +15,15 @@ use crate::records::{Permission, RecordError};
   path = "/:name/:record",
   request_body = serde_json::Value,
   responses(
-    (status = 200, description = "Successful update.")
+    (status = 200, description = "Successful update."),
+    (status = 412, description = "Record changed, i.e. `If-Match` precondition failed.")
   )
 )]
 pub async fn update_record_handler(
   State(state): State<AppState>,
   Path((api_name, record)): Path<(String, String)>,
   user: Option<User>,
+  headers: HeaderMap,
   either_request: Either<JsonRow>,
 ) -> Result<(), RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
@@ -62,13 +66,20 @@ pub async fn update_record_handler(
     &state,
     api.table_name(),
     &pk_column.name,
+    api.soft_delete_column(),
     api.has_file_columns(),
     lazy_params
       .consume()
       .map_err(|err| RecordError::Internal(err.into()))?,
+    IfMatch::from_headers(&state, &api, &record_id, user.as_ref(), &headers)?,
+    api.history_actor(user.as_ref()),
   )
   .await
-  .map_err(|err| RecordError::Internal(err.into()))?;
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    QueryError::RecordChanged => RecordError::PreconditionFailed,
+    err => RecordError::Internal(err.into()),
+  })?;
 
   return Ok(());
 }
@@ -76,6 +87,7 @@ pub async fn update_record_handler(
 #[cfg(test)]
 mod test {
   use axum::extract::Query;
+  use axum::http::{HeaderValue, StatusCode, header};
   use trailbase_sqlite::params;
 
   use super::*;
@@ -88,6 +100,7 @@ mod test {
   use crate::records::create_record::{
     CreateRecordQuery, CreateRecordResponse, create_record_handler,
   };
+  use crate::records::read_record::{ReadRecordQuery, read_record_handler};
   use crate::records::test_utils::*;
   use crate::records::*;
   use crate::test::unpack_json_response;
@@ -184,6 +197,7 @@ mod test {
         State(state.clone()),
         Path(("messages_api".to_string(), b64_id.clone())),
         User::from_auth_token(&state, &user_x_token.auth_token),
+        HeaderMap::new(),
         Either::Json(json_row_from_value(update_json).unwrap().into()),
       )
       .await;
@@ -210,11 +224,71 @@ mod test {
         State(state.clone()),
         Path(("messages_api".to_string(), b64_id.clone())),
         User::from_auth_token(&state, &user_y_token.auth_token),
+        HeaderMap::new(),
         Either::Json(json_row_from_value(update_json).unwrap().into()),
       )
       .await;
 
       assert!(update_response.is_err(), "{b64_id} {update_response:?}");
     }
+
+    {
+      // Conditional requests using ETags.
+      let read = async |headers: HeaderMap| {
+        return read_record_handler(
+          State(state.clone()),
+          Path(("messages_api".to_string(), b64_id.clone())),
+          Query(ReadRecordQuery::default()),
+          User::from_auth_token(&state, &user_x_token.auth_token),
+          headers,
+        )
+        .await
+        .unwrap();
+      };
+      let update = async |if_match: &str, data: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_MATCH, HeaderValue::from_str(if_match).unwrap());
+
+        return update_record_handler(
+          State(state.clone()),
+          Path(("messages_api".to_string(), b64_id.clone())),
+          User::from_auth_token(&state, &user_x_token.auth_token),
+          headers,
+          Either::Json(json_row_from_value(serde_json::json!({"data": data})).unwrap()),
+        )
+        .await;
+      };
+
+      let response = read(HeaderMap::new()).await;
+      assert_eq!(StatusCode::OK, response.status());
+      let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+      let mut headers = HeaderMap::new();
+      headers.insert(header::IF_NONE_MATCH, etag.clone());
+      assert_eq!(
+        StatusCode::NOT_MODIFIED,
+        read(headers.clone()).await.status()
+      );
+
+      update(etag.to_str().unwrap(), "etag update").await.unwrap();
+
+      // The record changed, thus the previous ETag is stale.
+      assert_eq!(StatusCode::OK, read(headers).await.status());
+      assert!(matches!(
+        update(etag.to_str().unwrap(), "stale update").await,
+        Err(RecordError::PreconditionFailed)
+      ));
+      update("*", "wildcard update").await.unwrap();
+
+      let message_text: String = conn
+        .read_query_value(
+          "SELECT data FROM message WHERE mid = $1",
+          params!(b64_to_id(&b64_id).unwrap()),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+      assert_eq!("wildcard update", message_text);
+    }
   }
 }
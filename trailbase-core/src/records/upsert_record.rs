@@ -0,0 +1,651 @@
+use axum::extract::{Json, Path, Query, State};
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::{NamedParams, Value};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::extract::Either;
+use crate::records::create_record::extract_record_id;
+use crate::records::files::{FileManager, delete_pending_files};
+use crate::records::history;
+use crate::records::params::{JsonRow, Params};
+use crate::records::query_builder::{UpsertQueryBuilder, query_row, sql_error};
+use crate::records::{Permission, RecordApi, RecordError};
+use crate::util::uuid_to_b64;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct UpsertRecordQuery {
+  /// Comma-separated list of columns to detect conflicts on. The columns must match the primary
+  /// key or a UNIQUE constraint. Defaults to the primary key.
+  pub on_conflict: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpsertRecordResponse {
+  /// Safe-url base64 encoded id of the inserted or updated record.
+  pub id: String,
+  /// Whether a new record was inserted as opposed to an existing one being updated.
+  pub inserted: bool,
+}
+
+/// Insert new record or update existing record conflicting on the given columns.
+///
+/// Unlike `REPLACE` conflict resolution, conflicting records are updated in-place, i.e. they
+/// retain their rowid and foreign key relationships.
+///
+/// Conflicts with soft-deleted records are rejected. They need to be restored first.
+#[utoipa::path(
+  put,
+  path = "/:name",
+  params(UpsertRecordQuery),
+  request_body = serde_json::Value,
+  responses(
+    (status = 200, description = "Id of inserted or updated record.", body = UpsertRecordResponse),
+    (status = 409, description = "Conflicting record is soft-deleted."),
+  )
+)]
+pub async fn upsert_record_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  Query(upsert_record_query): Query<UpsertRecordQuery>,
+  user: Option<User>,
+  either_request: Either<JsonRow>,
+) -> Result<Json<UpsertRecordResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  if !api.is_table() {
+    return Err(RecordError::ApiRequiresTable);
+  }
+
+  // Reject early if the user can neither create nor update. Which of the two applies can only be
+  // determined once we know whether a conflicting record exists.
+  if api
+    .check_table_level_access(Permission::Create, user.as_ref())
+    .is_err()
+    && api
+      .check_table_level_access(Permission::Update, user.as_ref())
+      .is_err()
+  {
+    return Err(RecordError::Forbidden);
+  }
+
+  let (_index, pk_column) = api.record_pk_column();
+  let conflict_columns: Vec<String> = match upsert_record_query.on_conflict {
+    Some(ref columns) => columns.split(',').map(|c| c.trim().to_string()).collect(),
+    None => vec![pk_column.name.clone()],
+  };
+  if !api.is_unique_column_set(&conflict_columns) {
+    return Err(RecordError::BadRequest("Invalid conflict target"));
+  }
+
+  let (mut request, multipart_files) = match either_request {
+    Either::Json(value) => (value, None),
+    Either::Multipart(value, files) => (value, Some(files)),
+    Either::Form(value) => (value, None),
+  };
+
+  // Like for creates, missing user id columns are autofilled. They only apply when inserting,
+  // i.e. they're neither updated on conflict nor seen by the update access check.
+  let mut autofilled_columns: Vec<String> = vec![];
+  if api.insert_autofill_missing_user_id_columns() {
+    if let Some(ref user) = user {
+      for column_index in api.user_id_columns() {
+        let col_name = &api.columns()[*column_index].name;
+        if !request.contains_key(col_name) {
+          request.insert(
+            col_name.to_owned(),
+            serde_json::Value::String(uuid_to_b64(&user.uuid)),
+          );
+          autofilled_columns.push(col_name.to_owned());
+        }
+      }
+    }
+  }
+
+  let mut params = Params::from(&api, request, multipart_files)
+    .map_err(|_| RecordError::BadRequest("Parameter conversion"))?;
+  if params.column_names.is_empty() {
+    return Err(RecordError::BadRequest("no values provided"));
+  }
+
+  let query = UpsertQueryBuilder::build_upsert_query(
+    api.table_name(),
+    &params.column_names,
+    &conflict_columns,
+    &autofilled_columns,
+    &pk_column.name,
+  )
+  .map_err(|err| RecordError::Internal(err.into()))?;
+
+  // If any of the conflict target's columns is missing, there cannot be a conflict since it will
+  // either be defaulted or NULL, which is never equal.
+  let conflict_params: Option<NamedParams> = conflict_columns
+    .iter()
+    .map(|name| {
+      let index = params.column_names.iter().position(|c| c == name)?;
+      return Some(params.named_params[index].clone());
+    })
+    .collect();
+  let conflict_query = conflict_params.map(|conflict_params| {
+    (
+      UpsertQueryBuilder::build_conflict_query(
+        api.table_name(),
+        &conflict_columns,
+        &pk_column.name,
+        api.soft_delete_column(),
+      ),
+      conflict_params,
+    )
+  });
+
+  // We're storing any files to the object store first to make sure the DB entry is valid right
+  // after commit and not racily pointing to soon-to-be-written files.
+  let files = std::mem::take(&mut params.files);
+  let mut file_manager = if files.is_empty() {
+    FileManager::empty()
+  } else {
+    FileManager::write(&state, files)
+      .await
+      .map_err(|err| RecordError::Internal(err.into()))?
+  };
+
+  let tx_api = api.clone();
+  let actor: Option<[u8; 16]> = api
+    .history_actor(user.as_ref())
+    .map(|user| user.uuid.into_bytes());
+  let result = state
+    .conn()
+    .call(move |conn| {
+      // NOTE: Both, the conflict lookup and the access check, need to happen within the same
+      // transaction as the write. Otherwise, a concurrent write could change whether we're
+      // creating or updating a record after the fact.
+      let tx = conn.transaction()?;
+      if actor.is_some() {
+        history::set_actor(&tx, actor.as_ref())?;
+      }
+
+      let result = execute_upsert(
+        &tx,
+        &tx_api,
+        &query,
+        conflict_query,
+        params,
+        &autofilled_columns,
+        user.as_ref(),
+      );
+      if result.is_ok() {
+        if actor.is_some() {
+          history::set_actor(&tx, None)?;
+        }
+        tx.commit()?;
+      }
+
+      return Ok(result);
+    })
+    .await?;
+
+  let (rowid, record_id, inserted) = result?;
+
+  // Successful write, do not cleanup written files.
+  file_manager.release();
+
+  if !inserted && api.has_file_columns() {
+    delete_pending_files(&state, api.table_name(), rowid)
+      .await
+      .map_err(|err| RecordError::Internal(err.into()))?;
+  }
+
+  return Ok(Json(UpsertRecordResponse {
+    id: extract_record_id(record_id)?,
+    inserted,
+  }));
+}
+
+/// Returns the rowid and primary key of the upserted record and whether it was newly inserted.
+fn execute_upsert(
+  conn: &rusqlite::Connection,
+  api: &RecordApi,
+  query: &str,
+  conflict_query: Option<(String, NamedParams)>,
+  params: Params,
+  autofilled_columns: &[String],
+  user: Option<&User>,
+) -> Result<(i64, Value, bool), RecordError> {
+  let existing: Option<(Value, bool)> = match conflict_query {
+    Some((conflict_query, conflict_params)) => {
+      query_row(conn, &conflict_query, conflict_params, |row| {
+        Ok((row.get(0)?, row.get(1)?))
+      })?
+    }
+    None => None,
+  };
+
+  // NOTE: Updating a soft-deleted record in place would leave it hidden, while silently reviving
+  // it would bypass the delete permissions required for restoring.
+  let existing: Option<Value> = match existing {
+    Some((_, true)) => {
+      return Err(RecordError::Conflict("record deleted"));
+    }
+    Some((record_id, false)) => Some(record_id),
+    None => None,
+  };
+
+  match existing {
+    Some(ref record_id) => {
+      let (_index, pk_column) = api.record_pk_column();
+      if let Some(index) = params
+        .column_names
+        .iter()
+        .position(|c| *c == pk_column.name)
+      {
+        if params.named_params[index].1 != *record_id {
+          return Err(RecordError::BadRequest("primary key mismatch"));
+        }
+      }
+
+      // Autofilled columns won't be updated and thus mustn't be visible as `_REQ_`.
+      let mut update_params = Params {
+        named_params: vec![],
+        files: vec![],
+        column_names: vec![],
+        column_indexes: vec![],
+      };
+      for (index, name) in params.column_names.iter().enumerate() {
+        if !autofilled_columns.contains(name) {
+          update_params
+            .named_params
+            .push(params.named_params[index].clone());
+          update_params.column_names.push(name.clone());
+          update_params
+            .column_indexes
+            .push(params.column_indexes[index]);
+        }
+      }
+
+      api.check_record_level_access_with_conn(
+        conn,
+        Permission::Update,
+        Some(record_id),
+        Some(&update_params),
+        user,
+      )?;
+    }
+    None => {
+      api.check_record_level_access_with_conn(
+        conn,
+        Permission::Create,
+        None,
+        Some(&params),
+        user,
+      )?;
+    }
+  };
+
+  let (rowid, record_id): (i64, Value) = query_row(conn, query, params.named_params, |row| {
+    Ok((row.get(0)?, row.get(1)?))
+  })?
+  .ok_or_else(|| sql_error(rusqlite::Error::QueryReturnedNoRows))?;
+
+  return Ok((rowid, record_id, existing.is_none()));
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+
+  use super::*;
+  use crate::admin::user::*;
+  use crate::app_state::*;
+  use crate::auth::api::login::login_with_password;
+  use crate::config::proto::{PermissionFlag, RecordApiConfig};
+  use crate::records::test_utils::*;
+  use crate::util::id_to_b64;
+
+  #[tokio::test]
+  async fn test_record_api_upsert() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE profile (
+            id      INTEGER PRIMARY KEY,
+            owner   BLOB NOT NULL REFERENCES _user(id),
+            handle  TEXT NOT NULL UNIQUE,
+            bio     TEXT
+          ) STRICT;
+
+          CREATE TABLE follower (
+            profile INTEGER NOT NULL REFERENCES profile(id),
+            name    TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("profile_api".to_string()),
+        table_name: Some("profile".to_string()),
+        acl_authenticated: [
+          PermissionFlag::Create as i32,
+          PermissionFlag::Read as i32,
+          PermissionFlag::Update as i32,
+        ]
+        .into(),
+        create_access_rule: Some("_USER_.id = _REQ_.owner".to_string()),
+        update_access_rule: Some("_USER_.id = _ROW_.owner".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let password = "Secret!1!!";
+    let user_x_email = "user_x@test.com";
+    let user_x = create_user_for_test(&state, user_x_email, password)
+      .await
+      .unwrap()
+      .into_bytes();
+    let user_x_token = login_with_password(&state, user_x_email, password)
+      .await
+      .unwrap();
+
+    let user_y_email = "user_y@test.com";
+    let user_y = create_user_for_test(&state, user_y_email, password)
+      .await
+      .unwrap()
+      .into_bytes();
+    let user_y_token = login_with_password(&state, user_y_email, password)
+      .await
+      .unwrap();
+
+    let upsert = async |token: &str, on_conflict: Option<&str>, value: serde_json::Value| {
+      return upsert_record_handler(
+        State(state.clone()),
+        Path("profile_api".to_string()),
+        Query(UpsertRecordQuery {
+          on_conflict: on_conflict.map(|s| s.to_string()),
+        }),
+        User::from_auth_token(&state, token),
+        Either::Json(json_row_from_value(value).unwrap()),
+      )
+      .await;
+    };
+
+    let inserted = upsert(
+      &user_x_token.auth_token,
+      Some("handle"),
+      json!({
+        "owner": id_to_b64(&user_x),
+        "handle": "x",
+        "bio": "first",
+      }),
+    )
+    .await
+    .unwrap()
+    .0;
+    assert!(inserted.inserted);
+
+    conn
+      .execute(
+        "INSERT INTO follower (profile, name) VALUES ($1, 'follower')",
+        [Value::Integer(inserted.id.parse::<i64>().unwrap())],
+      )
+      .await
+      .unwrap();
+
+    let updated = upsert(
+      &user_x_token.auth_token,
+      Some("handle"),
+      json!({
+        "owner": id_to_b64(&user_x),
+        "handle": "x",
+        "bio": "second",
+      }),
+    )
+    .await
+    .unwrap()
+    .0;
+    assert!(!updated.inserted);
+    // The record was updated in place, i.e. it retained its id and is still referenced.
+    assert_eq!(inserted.id, updated.id);
+
+    let (bio, followers): (String, i64) = conn
+      .read_query_row_f(
+        "SELECT bio, (SELECT COUNT(*) FROM follower WHERE profile = profile.id) FROM profile",
+        (),
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!("second", bio);
+    assert_eq!(1, followers);
+
+    // User y can create their own profile but not update user x's.
+    assert!(matches!(
+      upsert(
+        &user_y_token.auth_token,
+        Some("handle"),
+        json!({
+          "owner": id_to_b64(&user_y),
+          "handle": "x",
+          "bio": "hijacked",
+        }),
+      )
+      .await,
+      Err(RecordError::Forbidden)
+    ));
+
+    // Upsert on the primary key by default.
+    let response = upsert(
+      &user_x_token.auth_token,
+      None,
+      json!({
+        "id": inserted.id,
+        "owner": id_to_b64(&user_x),
+        "handle": "x2",
+      }),
+    )
+    .await
+    .unwrap()
+    .0;
+    assert!(!response.inserted);
+
+    // Conflict targets must be unique.
+    assert!(matches!(
+      upsert(
+        &user_x_token.auth_token,
+        Some("bio"),
+        json!({
+          "owner": id_to_b64(&user_x),
+          "handle": "x",
+          "bio": "bio",
+        }),
+      )
+      .await,
+      Err(RecordError::BadRequest(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn test_record_api_upsert_soft_deleted() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE tag (
+            id          INTEGER PRIMARY KEY,
+            name        TEXT NOT NULL UNIQUE,
+            deleted_at  INTEGER
+          ) STRICT;
+
+          INSERT INTO tag (id, name, deleted_at) VALUES (1, 'deleted', UNIXEPOCH());
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("tag_api".to_string()),
+        table_name: Some("tag".to_string()),
+        acl_world: [PermissionFlag::Create as i32, PermissionFlag::Update as i32].into(),
+        soft_delete_column: Some("deleted_at".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let upsert = async |on_conflict: Option<&str>, value: serde_json::Value| {
+      return upsert_record_handler(
+        State(state.clone()),
+        Path("tag_api".to_string()),
+        Query(UpsertRecordQuery {
+          on_conflict: on_conflict.map(|s| s.to_string()),
+        }),
+        None,
+        Either::Json(json_row_from_value(value).unwrap()),
+      )
+      .await;
+    };
+
+    // Conflicts with soft-deleted records are rejected rather than updating hidden records.
+    assert!(matches!(
+      upsert(Some("name"), json!({"name": "deleted"})).await,
+      Err(RecordError::Conflict(_))
+    ));
+    assert!(matches!(
+      upsert(None, json!({"id": 1, "name": "renamed"})).await,
+      Err(RecordError::Conflict(_))
+    ));
+
+    let (name, deleted): (String, bool) = conn
+      .read_query_row_f(
+        "SELECT name, deleted_at IS NOT NULL FROM tag WHERE id = 1",
+        (),
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!("deleted", name);
+    assert!(deleted);
+
+    assert!(
+      upsert(Some("name"), json!({"name": "new"}))
+        .await
+        .unwrap()
+        .0
+        .inserted
+    );
+  }
+
+  #[tokio::test]
+  async fn test_record_api_upsert_autofill() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE note (
+            id      INTEGER PRIMARY KEY,
+            owner   BLOB NOT NULL REFERENCES _user(id),
+            text    TEXT
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("note_api".to_string()),
+        table_name: Some("note".to_string()),
+        acl_authenticated: [PermissionFlag::Create as i32, PermissionFlag::Update as i32].into(),
+        create_access_rule: Some("_USER_.id = _REQ_.owner".to_string()),
+        // Anyone may edit notes but nobody may change their owner.
+        update_access_rule: Some("_REQ_.owner IS NULL".to_string()),
+        autofill_missing_user_id_columns: Some(true),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let password = "Secret!1!!";
+    let user_x_email = "user_x@test.com";
+    let user_x = create_user_for_test(&state, user_x_email, password)
+      .await
+      .unwrap()
+      .into_bytes();
+    let user_x_token = login_with_password(&state, user_x_email, password)
+      .await
+      .unwrap();
+
+    let user_y_email = "user_y@test.com";
+    create_user_for_test(&state, user_y_email, password)
+      .await
+      .unwrap();
+    let user_y_token = login_with_password(&state, user_y_email, password)
+      .await
+      .unwrap();
+
+    let upsert = async |token: &str, value: serde_json::Value| {
+      return upsert_record_handler(
+        State(state.clone()),
+        Path("note_api".to_string()),
+        Query(UpsertRecordQuery { on_conflict: None }),
+        User::from_auth_token(&state, token),
+        Either::Json(json_row_from_value(value).unwrap()),
+      )
+      .await;
+    };
+
+    // The missing owner is autofilled on insert.
+    let inserted = upsert(&user_x_token.auth_token, json!({"text": "first"}))
+      .await
+      .unwrap()
+      .0;
+    assert!(inserted.inserted);
+
+    // On update, the owner is neither autofilled nor overwritten.
+    let updated = upsert(
+      &user_y_token.auth_token,
+      json!({"id": inserted.id, "text": "second"}),
+    )
+    .await
+    .unwrap()
+    .0;
+    assert!(!updated.inserted);
+    assert_eq!(inserted.id, updated.id);
+
+    let (owner, text): (Vec<u8>, String) = conn
+      .read_query_row_f("SELECT owner, text FROM note", (), |row| {
+        Ok((row.get(0)?, row.get(1)?))
+      })
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(user_x.to_vec(), owner);
+    assert_eq!("second", text);
+  }
+}
@@ -1,8 +1,9 @@
 use itertools::Itertools;
 use trailbase_schema::QualifiedName;
-use trailbase_schema::sqlite::ColumnOption;
+use trailbase_schema::sqlite::{Column, ColumnOption};
 
 use crate::config::{ConfigError, proto};
+use crate::records::expand::MAX_EXPANSION_DEPTH;
 use crate::records::record_api::validate_rule;
 use crate::schema_metadata::{SchemaMetadataCache, TableOrViewMetadata};
 
@@ -19,6 +20,13 @@ fn validate_record_api_name(name: &str) -> Result<(), ConfigError> {
     )));
   }
 
+  // Reserved for routes sharing the record API path prefix, e.g. batches.
+  if name == "_batch" {
+    return Err(ConfigError::Invalid(format!(
+      "Invalid api name: {name}. Name is reserved."
+    )));
+  }
+
   Ok(())
 }
 
@@ -56,7 +64,7 @@ pub(crate) fn validate_record_api_config(
     }
   };
 
-  let Some((pk_index, _)) = metadata.record_pk_column() else {
+  let Some((pk_index, pk_column)) = metadata.record_pk_column() else {
     return ierr(&format!(
       "Table for api '{api_name}' is missing valid integer/UUID primary key column."
     ));
@@ -92,62 +100,87 @@ pub(crate) fn validate_record_api_config(
     }
   }
 
-  for expand in &api_config.expand {
-    if expand.starts_with("_") {
-      return ierr(&format!("{api_name} expands hidden column: {expand}"));
+  if let Some(ref version_column) = api_config.version_column {
+    if !columns.iter().any(|col| col.name == *version_column)
+      || api_config.excluded_columns.contains(version_column)
+    {
+      return ierr(&format!(
+        "Version column '{version_column}' in API '{api_name}' not found.",
+      ));
     }
+  }
 
-    let Some(column) = columns.iter().find(|c| c.name == *expand) else {
-      return ierr(&format!("{api_name} expands missing column: {expand}"));
-    };
+  if let Some(ref soft_delete_column) = api_config.soft_delete_column {
+    if schemas
+      .get_table(&QualifiedName::parse(table_name)?)
+      .is_none()
+    {
+      return ierr(&format!(
+        "Soft-deletes in API '{api_name}' require a table.",
+      ));
+    }
 
-    let Some(ColumnOption::ForeignKey {
-      foreign_table: foreign_table_name,
-      referred_columns,
-      ..
-    }) = column
-      .options
+    let Some(index) = columns
       .iter()
-      .find_or_first(|o| matches!(o, ColumnOption::ForeignKey { .. }))
+      .position(|col| col.name == *soft_delete_column)
     else {
       return ierr(&format!(
-        "{api_name} expands non-foreign-key column: {expand}"
+        "Soft-delete column '{soft_delete_column}' in API '{api_name}' not found.",
       ));
     };
 
-    if foreign_table_name.starts_with("_") {
+    if index == pk_index || columns[index].is_not_null() {
       return ierr(&format!(
-        "{api_name} expands reference '{expand}' to hidden table: {foreign_table_name}"
+        "Soft-delete column '{soft_delete_column}' in API '{api_name}' must be nullable.",
       ));
     }
+  }
 
-    let Some(foreign_table) = schemas.get_table(&QualifiedName::parse(foreign_table_name)?) else {
+  if api_config.enable_history == Some(true) {
+    let table_name = QualifiedName::parse(table_name)?;
+    if schemas.get_table(&table_name).is_none() {
+      return ierr(&format!("History in API '{api_name}' requires a table."));
+    }
+
+    // NOTE: Triggers are database-local, i.e. they can only record into the main database's
+    // `_record_history` table.
+    if table_name
+      .database_schema
+      .as_deref()
+      .is_some_and(|db| db != "main")
+    {
       return ierr(&format!(
-        "{api_name} reference missing table: {foreign_table_name}"
+        "History in API '{api_name}' is not supported on attached databases.",
       ));
-    };
+    }
+  }
+
+  for expand in &api_config.expand {
+    validate_expand_path(
+      schemas,
+      api_name,
+      &QualifiedName::parse(table_name)?.name,
+      &pk_column.name,
+      columns,
+      expand,
+    )?;
+  }
 
-    let Some((_idx, foreign_pk_column)) = foreign_table.record_pk_column() else {
+  let mut related_tables: Vec<&str> = vec![];
+  for related_api in &api_config.related_apis {
+    let (Some(related_table), Some(_related_api_name)) =
+      (&related_api.table_name, &related_api.api_name)
+    else {
       return ierr(&format!(
-        "{api_name} references pk-less table: {foreign_table_name}"
+        "Related API in API '{api_name}' misses table or API name."
       ));
     };
-
-    match referred_columns.len() {
-      0 => {}
-      1 => {
-        if referred_columns[0] != foreign_pk_column.name {
-          return ierr(&format!(
-            "{api_name}.{expand} expands non-primary-key reference"
-          ));
-        }
-      }
-      _ => {
-        return ierr(&format!(
-          "Composite keys cannot be expanded for {api_name}.{expand}"
-        ));
-      }
-    };
+    if related_tables.contains(&related_table.as_str()) {
+      return ierr(&format!(
+        "Related API for '{related_table}' in API '{api_name}': duplicate table."
+      ));
+    }
+    related_tables.push(related_table);
   }
 
   let rules = [
@@ -161,5 +194,222 @@ pub(crate) fn validate_record_api_config(
     validate_rule(rule).map_err(ConfigError::Invalid)?;
   }
 
+  let mut roles: Vec<&str> = vec![];
+  for role_acl in &api_config.acl_roles {
+    let Some(role) = role_acl.role.as_deref().filter(|role| !role.is_empty()) else {
+      return ierr(&format!("Role ACL in API '{api_name}' misses role."));
+    };
+    if roles.contains(&role) {
+      return ierr(&format!(
+        "Role ACL for '{role}' in API '{api_name}': duplicate role."
+      ));
+    }
+    roles.push(role);
+  }
+
+  let mut column_access_rule_columns: Vec<&str> = vec![];
+  for column_access_rule in &api_config.column_access_rules {
+    let Some(ref column_name) = column_access_rule.column else {
+      return ierr(&format!(
+        "Column access rule in API '{api_name}' misses column."
+      ));
+    };
+
+    let Some(index) = columns.iter().position(|col| col.name == *column_name) else {
+      return ierr(&format!(
+        "Column access rule for '{column_name}' in API '{api_name}': column not found."
+      ));
+    };
+    if index == pk_index || api_config.excluded_columns.contains(column_name) {
+      return ierr(&format!(
+        "Column access rule for '{column_name}' in API '{api_name}': column is the primary key or excluded."
+      ));
+    }
+    if column_access_rule_columns.contains(&column_name.as_str()) {
+      return ierr(&format!(
+        "Column access rule for '{column_name}' in API '{api_name}': duplicate column."
+      ));
+    }
+    column_access_rule_columns.push(column_name);
+
+    let rules = [
+      &column_access_rule.read_rule,
+      &column_access_rule.write_rule,
+    ];
+    if rules.iter().all(|rule| rule.is_none()) {
+      return ierr(&format!(
+        "Column access rule for '{column_name}' in API '{api_name}' misses rules."
+      ));
+    }
+    for rule in rules.into_iter().flatten() {
+      validate_rule(rule).map_err(ConfigError::Invalid)?;
+    }
+  }
+
   return Ok(api_name.to_owned());
 }
+
+/// Validates an expansion path segment by segment, where each segment is either a foreign key
+/// column, e.g. `author`, or a reverse relation `<table>!<column>`, e.g. `comment!post`.
+fn validate_expand_path(
+  schemas: &SchemaMetadataCache,
+  api_name: &str,
+  table_name: &str,
+  pk_column_name: &str,
+  columns: &[Column],
+  expand: &str,
+) -> Result<(), ConfigError> {
+  let ierr = |msg: &str| Err(ConfigError::Invalid(msg.to_string()));
+
+  if expand.split('.').count() > MAX_EXPANSION_DEPTH {
+    return ierr(&format!(
+      "{api_name} expansion exceeds max depth of {MAX_EXPANSION_DEPTH}: {expand}"
+    ));
+  }
+
+  let mut table_name = table_name.to_string();
+  let mut pk_column_name = pk_column_name.to_string();
+  let mut columns = columns.to_vec();
+
+  for segment in expand.split('.') {
+    if segment.starts_with("_") {
+      return ierr(&format!("{api_name} expands hidden column: {expand}"));
+    }
+
+    let next_table = match segment.split_once('!') {
+      None => {
+        let Some(column) = columns.iter().find(|c| c.name == segment) else {
+          return ierr(&format!("{api_name} expands missing column: {expand}"));
+        };
+
+        let Some(ColumnOption::ForeignKey {
+          foreign_table: foreign_table_name,
+          referred_columns,
+          ..
+        }) = column
+          .options
+          .iter()
+          .find_or_first(|o| matches!(o, ColumnOption::ForeignKey { .. }))
+        else {
+          return ierr(&format!(
+            "{api_name} expands non-foreign-key column: {expand}"
+          ));
+        };
+
+        if foreign_table_name.starts_with("_") {
+          return ierr(&format!(
+            "{api_name} expands reference '{expand}' to hidden table: {foreign_table_name}"
+          ));
+        }
+
+        let Some(foreign_table) = schemas.get_table(&QualifiedName::parse(foreign_table_name)?)
+        else {
+          return ierr(&format!(
+            "{api_name} reference missing table: {foreign_table_name}"
+          ));
+        };
+
+        let Some((_idx, foreign_pk_column)) = foreign_table.record_pk_column() else {
+          return ierr(&format!(
+            "{api_name} references pk-less table: {foreign_table_name}"
+          ));
+        };
+
+        match referred_columns.len() {
+          0 => {}
+          1 => {
+            if referred_columns[0] != foreign_pk_column.name {
+              return ierr(&format!(
+                "{api_name}.{expand} expands non-primary-key reference"
+              ));
+            }
+          }
+          _ => {
+            return ierr(&format!(
+              "Composite keys cannot be expanded for {api_name}.{expand}"
+            ));
+          }
+        };
+
+        foreign_table
+      }
+      Some((referencing_table_name, column_name)) => {
+        if referencing_table_name.starts_with("_") {
+          return ierr(&format!(
+            "{api_name} expands reverse reference '{expand}' from hidden table: {referencing_table_name}"
+          ));
+        }
+
+        let Some(referencing_table) =
+          schemas.get_table(&QualifiedName::parse(referencing_table_name)?)
+        else {
+          return ierr(&format!(
+            "{api_name} reverse reference missing table: {referencing_table_name}"
+          ));
+        };
+
+        if referencing_table.record_pk_column().is_none() {
+          return ierr(&format!(
+            "{api_name} reverse references pk-less table: {referencing_table_name}"
+          ));
+        }
+
+        let Some(column) = referencing_table
+          .schema
+          .columns
+          .iter()
+          .find(|c| c.name == column_name)
+        else {
+          return ierr(&format!("{api_name} expands missing column: {expand}"));
+        };
+
+        let Some(ColumnOption::ForeignKey {
+          foreign_table: foreign_table_name,
+          referred_columns,
+          ..
+        }) = column
+          .options
+          .iter()
+          .find_or_first(|o| matches!(o, ColumnOption::ForeignKey { .. }))
+        else {
+          return ierr(&format!(
+            "{api_name} expands non-foreign-key column: {expand}"
+          ));
+        };
+
+        if *foreign_table_name != table_name {
+          return ierr(&format!(
+            "{api_name}.{expand} expands column not referencing: {table_name}"
+          ));
+        }
+
+        match referred_columns.len() {
+          0 => {}
+          1 => {
+            if referred_columns[0] != pk_column_name {
+              return ierr(&format!(
+                "{api_name}.{expand} expands non-primary-key reference"
+              ));
+            }
+          }
+          _ => {
+            return ierr(&format!(
+              "Composite keys cannot be expanded for {api_name}.{expand}"
+            ));
+          }
+        };
+
+        referencing_table
+      }
+    };
+
+    let Some((_idx, next_pk_column)) = next_table.record_pk_column() else {
+      return ierr(&format!("{api_name} expands pk-less table: {expand}"));
+    };
+    pk_column_name = next_pk_column.name.clone();
+    table_name = next_table.name().name.clone();
+    columns = next_table.schema.columns.clone();
+  }
+
+  return Ok(());
+}
@@ -10,11 +10,15 @@ use std::sync::{
   Arc,
   atomic::{AtomicI32, Ordering},
 };
+use trailbase_schema::QualifiedName;
 use trailbase_sqlite::{Connection, params};
 
 use crate::DataDir;
 use crate::config::proto::{Config, SystemJob, SystemJobId};
-use crate::constants::{DEFAULT_REFRESH_TOKEN_TTL, LOGS_RETENTION_DEFAULT, SESSION_TABLE};
+use crate::constants::{
+  DEFAULT_REFRESH_TOKEN_TTL, LOGS_RETENTION_DEFAULT, SESSION_TABLE, SOFT_DELETE_RETENTION_DEFAULT,
+};
+use crate::records::change_log::prune_change_log;
 use crate::records::files::{FileDeletionsDb, FileError, delete_pending_files_impl};
 
 type CallbackError = Box<dyn std::error::Error + Sync + Send>;
@@ -414,6 +418,85 @@ fn build_job(
         }),
       }
     }
+    SystemJobId::SoftDeletePurge => {
+      let conn = conn.clone();
+      // Tables and retention periods of Record APIs with soft deletion enabled.
+      let purges: Vec<(String, String, Duration)> = config
+        .record_apis
+        .iter()
+        .filter_map(|api| {
+          let column = api.soft_delete_column.as_ref()?;
+          let table_name = match QualifiedName::parse(api.table_name.as_deref()?) {
+            Ok(table_name) => table_name.escaped_string(),
+            Err(err) => {
+              warn!("Invalid table name for soft deletion: {err}");
+              return None;
+            }
+          };
+          let retention = api
+            .soft_delete_retention_sec
+            .map_or(SOFT_DELETE_RETENTION_DEFAULT, Duration::seconds);
+
+          return Some((table_name, column.clone(), retention));
+        })
+        .collect();
+
+      DefaultSystemJob {
+        name: "Soft Delete Purge",
+        default: SystemJob {
+          id: Some(id as i32),
+          schedule: Some("@hourly".into()),
+          disabled: Some(false),
+        },
+        callback: build_callback(move || {
+          let conn = conn.clone();
+          let purges = purges.clone();
+
+          return async move {
+            // NOTE: Hard deletions queue files of the purged records for deletion via triggers.
+            for (table_name, column, retention) in purges {
+              let timestamp = (Utc::now() - retention).timestamp();
+              conn
+                .execute(
+                  format!(r#"DELETE FROM {table_name} WHERE "{column}" < $1"#),
+                  params!(timestamp),
+                )
+                .await
+                .map_err(|err| {
+                  warn!("Periodic soft delete purge failed for {table_name}: {err}");
+                  err
+                })?;
+            }
+
+            Ok::<(), trailbase_sqlite::Error>(())
+          };
+        }),
+      }
+    }
+    SystemJobId::ChangeLogPrune => {
+      let conn = conn.clone();
+
+      DefaultSystemJob {
+        name: "Change Log Prune",
+        default: SystemJob {
+          id: Some(id as i32),
+          schedule: Some("41 * * * * * *".into()),
+          disabled: Some(false),
+        },
+        callback: build_callback(move || {
+          let conn = conn.clone();
+
+          return async move {
+            prune_change_log(&conn).await.map_err(|err| {
+              warn!("Periodic change log pruning failed: {err}");
+              err
+            })?;
+
+            Ok::<(), trailbase_sqlite::Error>(())
+          };
+        }),
+      }
+    }
   };
 }
 
@@ -454,6 +537,8 @@ pub fn build_job_registry_from_config(
     SystemJobId::AuthCleaner,
     SystemJobId::QueryOptimizer,
     SystemJobId::FileDeletions,
+    SystemJobId::SoftDeletePurge,
+    SystemJobId::ChangeLogPrune,
   ];
 
   let jobs = JobRegistry::new();
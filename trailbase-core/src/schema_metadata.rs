@@ -4,7 +4,7 @@ use std::collections::HashSet;
 use std::sync::Arc;
 use thiserror::Error;
 use trailbase_schema::sqlite::{
-  QualifiedName, SchemaError, Table, View, sqlite3_parse_into_statement,
+  QualifiedName, SchemaError, Table, TableIndex, View, sqlite3_parse_into_statement,
 };
 use trailbase_sqlite::params;
 
@@ -28,7 +28,8 @@ pub struct SchemaMetadataCache {
 impl SchemaMetadataCache {
   pub async fn new(conn: trailbase_sqlite::Connection) -> Result<Self, SchemaLookupError> {
     let tables = lookup_and_parse_all_table_schemas(&conn).await?;
-    let table_map = Self::build_tables(&conn, &tables).await?;
+    let indexes = lookup_and_parse_all_index_schemas(&conn).await?;
+    let table_map = Self::build_tables(&conn, &tables, &indexes).await?;
     let views = Self::build_views(&conn, &tables).await?;
 
     return Ok(SchemaMetadataCache {
@@ -43,12 +44,13 @@ impl SchemaMetadataCache {
   async fn build_tables(
     conn: &trailbase_sqlite::Connection,
     tables: &[Table],
+    indexes: &[TableIndex],
   ) -> Result<HashSet<Arc<TableMetadata>>, SchemaLookupError> {
     let schema_metadata_map: HashSet<Arc<TableMetadata>> = tables
       .iter()
       .cloned()
       .map(|t: Table| {
-        return Arc::new(TableMetadata::new(t, tables, USER_TABLE));
+        return Arc::new(TableMetadata::new(t, tables, indexes, USER_TABLE));
       })
       .collect();
 
@@ -142,7 +144,8 @@ impl SchemaMetadataCache {
     let conn = &self.conn;
 
     let tables = lookup_and_parse_all_table_schemas(conn).await?;
-    let table_map = Self::build_tables(conn, &tables).await?;
+    let indexes = lookup_and_parse_all_index_schemas(conn).await?;
+    let table_map = Self::build_tables(conn, &tables, &indexes).await?;
     let views = Self::build_views(conn, &tables).await?;
 
     *self.state.write() = SchemaMetadataCacheState {
@@ -243,6 +246,41 @@ pub async fn lookup_and_parse_all_table_schemas(
   return Ok(tables);
 }
 
+/// Looks up all indexes across databases. Auto-indexes, e.g. backing UNIQUE constraints, have no
+/// SQL and are skipped.
+pub async fn lookup_and_parse_all_index_schemas(
+  conn: &trailbase_sqlite::Connection,
+) -> Result<Vec<TableIndex>, SchemaLookupError> {
+  let databases = conn.list_databases().await?;
+
+  let mut indexes: Vec<TableIndex> = vec![];
+  for db in databases {
+    let rows = conn
+      .read_query_rows(
+        format!(
+          "SELECT sql FROM {db}.{SQLITE_SCHEMA_TABLE} WHERE type = 'index' AND sql IS NOT NULL",
+          db = db.name
+        ),
+        (),
+      )
+      .await?;
+
+    for row in rows.iter() {
+      let sql: String = row.get(0)?;
+      let Some(stmt) = sqlite3_parse_into_statement(&sql)? else {
+        return Err(SchemaLookupError::Missing);
+      };
+      indexes.push({
+        let mut index: TableIndex = stmt.try_into()?;
+        index.name.database_schema = Some(db.name.clone());
+        index
+      });
+    }
+  }
+
+  return Ok(indexes);
+}
+
 fn sqlite3_parse_view(sql: &str, tables: &[Table]) -> Result<View, SchemaLookupError> {
   let mut parser = sqlite3_parser::lexer::sql::Parser::new(sql.as_bytes());
   match parser.next()? {
@@ -288,16 +326,20 @@ pub async fn lookup_and_parse_all_view_schemas(
 
 #[cfg(test)]
 mod tests {
-  use axum::extract::{Json, Path, Query, RawQuery, State};
+  use axum::extract::{Path, Query, RawQuery, State};
+  use axum::http::HeaderMap;
   use serde_json::json;
   use trailbase_schema::QualifiedName;
   use trailbase_schema::json_schema::{Expand, JsonSchemaMode, build_json_schema_expanded};
 
   use crate::app_state::*;
-  use crate::config::proto::{PermissionFlag, RecordApiConfig};
-  use crate::records::list_records::list_records_handler;
+  use crate::config::proto::{PermissionFlag, RecordApiConfig, RelatedRecordApi};
+  use crate::records::RecordError;
+  use crate::records::json_schema::build_api_json_schema_with_fields;
+  use crate::records::list_records::{ListResponse, list_records_handler};
   use crate::records::read_record::{ReadRecordQuery, read_record_handler};
   use crate::records::test_utils::add_record_api_config;
+  use crate::test::unpack_json_response;
 
   #[tokio::test]
   async fn test_expanded_foreign_key() {
@@ -346,6 +388,19 @@ mod tests {
     .await
     .unwrap();
 
+    // Expanded tables must be exposed by a Record API.
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("foreign_table_api".to_string()),
+        table_name: Some("foreign_table".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
     let test_schema_metadata = state.schema_metadata().get_table(&table_name).unwrap();
 
     let (validator, schema) = build_json_schema_expanded(
@@ -354,7 +409,8 @@ mod tests {
       JsonSchemaMode::Select,
       Some(Expand {
         tables: &state.schema_metadata().tables(),
-        foreign_key_columns: vec!["foreign_table"],
+        paths: vec!["fk"],
+        fields: vec![],
       }),
     )
     .unwrap();
@@ -412,8 +468,11 @@ mod tests {
         Path(("test_table_api".to_string(), "1".to_string())),
         Query(ReadRecordQuery {
           expand: Some("UNKNOWN".to_string()),
+          fields: None,
+          include_deleted: None,
         }),
         None,
+        HeaderMap::new(),
       )
       .await;
 
@@ -424,6 +483,7 @@ mod tests {
         Path("test_table_api".to_string()),
         RawQuery(Some("expand=UNKNOWN".to_string())),
         None,
+        HeaderMap::new(),
       )
       .await;
 
@@ -437,11 +497,16 @@ mod tests {
         "fk":{ "id": 1 },
       });
 
-      let Json(value) = read_record_handler(
-        State(state.clone()),
-        Path(("test_table_api".to_string(), "1".to_string())),
-        Query(ReadRecordQuery::default()),
-        None,
+      let value: serde_json::Value = unpack_json_response(
+        read_record_handler(
+          State(state.clone()),
+          Path(("test_table_api".to_string(), "1".to_string())),
+          Query(ReadRecordQuery::default()),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -450,11 +515,16 @@ mod tests {
 
       assert_eq!(expected, value);
 
-      let Json(list_response) = list_records_handler(
-        State(state.clone()),
-        Path("test_table_api".to_string()),
-        RawQuery(None),
-        None,
+      let list_response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("test_table_api".to_string()),
+          RawQuery(None),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -474,13 +544,20 @@ mod tests {
     });
 
     {
-      let Json(value) = read_record_handler(
-        State(state.clone()),
-        Path(("test_table_api".to_string(), "1".to_string())),
-        Query(ReadRecordQuery {
-          expand: Some("fk".to_string()),
-        }),
-        None,
+      let value: serde_json::Value = unpack_json_response(
+        read_record_handler(
+          State(state.clone()),
+          Path(("test_table_api".to_string(), "1".to_string())),
+          Query(ReadRecordQuery {
+            expand: Some("fk".to_string()),
+            fields: None,
+            include_deleted: None,
+          }),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -491,11 +568,16 @@ mod tests {
     }
 
     {
-      let Json(list_response) = list_records_handler(
-        State(state.clone()),
-        Path("test_table_api".to_string()),
-        RawQuery(Some("expand=fk".to_string())),
-        None,
+      let list_response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("test_table_api".to_string()),
+          RawQuery(Some("expand=fk".to_string())),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -505,11 +587,16 @@ mod tests {
     }
 
     {
-      let Json(list_response) = list_records_handler(
-        State(state.clone()),
-        Path("test_table_api".to_string()),
-        RawQuery(Some("count=TRUE&expand=fk".to_string())),
-        None,
+      let list_response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("test_table_api".to_string()),
+          RawQuery(Some("count=TRUE&expand=fk".to_string())),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -567,6 +654,20 @@ mod tests {
     .await
     .unwrap();
 
+    for foreign_table in ["foreign_table0", "foreign_table1"] {
+      add_record_api_config(
+        &state,
+        RecordApiConfig {
+          name: Some(format!("{foreign_table}_api")),
+          table_name: Some(foreign_table.to_string()),
+          acl_world: [PermissionFlag::Read as i32].into(),
+          ..Default::default()
+        },
+      )
+      .await
+      .unwrap();
+    }
+
     exec("INSERT INTO foreign_table0 (id) VALUES (1);")
       .await
       .unwrap();
@@ -582,11 +683,20 @@ mod tests {
 
     // Expand none
     {
-      let Json(value) = read_record_handler(
-        State(state.clone()),
-        Path(("test_table_api".to_string(), "1".to_string())),
-        Query(ReadRecordQuery { expand: None }),
-        None,
+      let value: serde_json::Value = unpack_json_response(
+        read_record_handler(
+          State(state.clone()),
+          Path(("test_table_api".to_string(), "1".to_string())),
+          Query(ReadRecordQuery {
+            expand: None,
+            fields: None,
+            include_deleted: None,
+          }),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -600,11 +710,16 @@ mod tests {
 
       assert_eq!(expected, value);
 
-      let Json(list_response) = list_records_handler(
-        State(state.clone()),
-        Path("test_table_api".to_string()),
-        RawQuery(None),
-        None,
+      let list_response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("test_table_api".to_string()),
+          RawQuery(None),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -626,24 +741,36 @@ mod tests {
         },
       });
 
-      let Json(value) = read_record_handler(
-        State(state.clone()),
-        Path(("test_table_api".to_string(), "1".to_string())),
-        Query(ReadRecordQuery {
-          expand: Some("fk1".to_string()),
-        }),
-        None,
+      let value: serde_json::Value = unpack_json_response(
+        read_record_handler(
+          State(state.clone()),
+          Path(("test_table_api".to_string(), "1".to_string())),
+          Query(ReadRecordQuery {
+            expand: Some("fk1".to_string()),
+            fields: None,
+            include_deleted: None,
+          }),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
 
       assert_eq!(expected, value);
 
-      let Json(list_response) = list_records_handler(
-        State(state.clone()),
-        Path("test_table_api".to_string()),
-        RawQuery(Some("expand=fk1".to_string())),
-        None,
+      let list_response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("test_table_api".to_string()),
+          RawQuery(Some("expand=fk1".to_string())),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -670,13 +797,20 @@ mod tests {
         },
       });
 
-      let Json(value) = read_record_handler(
-        State(state.clone()),
-        Path(("test_table_api".to_string(), "1".to_string())),
-        Query(ReadRecordQuery {
-          expand: Some("fk0,fk1".to_string()),
-        }),
-        None,
+      let value: serde_json::Value = unpack_json_response(
+        read_record_handler(
+          State(state.clone()),
+          Path(("test_table_api".to_string(), "1".to_string())),
+          Query(ReadRecordQuery {
+            expand: Some("fk0,fk1".to_string()),
+            fields: None,
+            include_deleted: None,
+          }),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -687,11 +821,16 @@ mod tests {
         .await
         .unwrap();
 
-      let Json(list_response) = list_records_handler(
-        State(state.clone()),
-        Path("test_table_api".to_string()),
-        RawQuery(Some("expand=fk0,fk1".to_string())),
-        None,
+      let list_response: ListResponse = unpack_json_response(
+        list_records_handler(
+          State(state.clone()),
+          Path("test_table_api".to_string()),
+          RawQuery(Some("expand=fk0,fk1".to_string())),
+          None,
+          HeaderMap::new(),
+        )
+        .await
+        .unwrap(),
       )
       .await
       .unwrap();
@@ -710,4 +849,416 @@ mod tests {
       );
     }
   }
+
+  #[tokio::test]
+  async fn test_nested_and_reverse_expansions() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE org (
+            id    INTEGER PRIMARY KEY,
+            name  TEXT NOT NULL
+          ) STRICT;
+          CREATE TABLE author (
+            id    INTEGER PRIMARY KEY,
+            name  TEXT NOT NULL,
+            org   INTEGER REFERENCES org(id)
+          ) STRICT;
+          CREATE TABLE post (
+            id      INTEGER PRIMARY KEY,
+            author  INTEGER REFERENCES author(id)
+          ) STRICT;
+          CREATE TABLE comment (
+            id    INTEGER PRIMARY KEY,
+            post  INTEGER NOT NULL REFERENCES post(id),
+            body  TEXT NOT NULL
+          ) STRICT;
+
+          INSERT INTO org (id, name) VALUES (1, 'org');
+          INSERT INTO author (id, name, org) VALUES (1, 'public', 1), (2, 'hidden', 1);
+          INSERT INTO post (id, author) VALUES (1, 1), (2, 2);
+          INSERT INTO comment (id, post, body) VALUES
+            (1, 1, 'first'), (2, 1, 'secret'), (3, 1, 'third'), (4, 2, 'other');
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("post_api".to_string()),
+        table_name: Some("post".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        expand: vec!["author.org".to_string(), "comment!post".to_string()],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    // Expanded records must respect the ACLs of the Record APIs exposing their tables.
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("author_api".to_string()),
+        table_name: Some("author".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.name != 'hidden'".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("comment_api".to_string()),
+        table_name: Some("comment".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.body != 'secret'".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let read = async |id: &str, expand: &str| {
+      let response = read_record_handler(
+        State(state.clone()),
+        Path(("post_api".to_string(), id.to_string())),
+        Query(ReadRecordQuery {
+          expand: Some(expand.to_string()),
+          fields: None,
+          include_deleted: None,
+        }),
+        None,
+        HeaderMap::new(),
+      )
+      .await?;
+      return Ok::<_, RecordError>(
+        unpack_json_response::<serde_json::Value>(response)
+          .await
+          .unwrap(),
+      );
+    };
+
+    // Tables not exposed by any Record API cannot be expanded.
+    assert!(matches!(
+      read("1", "author.org").await,
+      Err(RecordError::BadRequest(_))
+    ));
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("org_api".to_string()),
+        table_name: Some("org".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let expected_author = json!({
+      "id": 1,
+      "data": {
+        "id": 1,
+        "name": "public",
+        "org": {
+          "id": 1,
+          "data": { "id": 1, "name": "org" },
+        },
+      },
+    });
+    let expected_comments = json!([
+      { "id": 3, "post": 1, "body": "third" },
+      { "id": 1, "post": 1, "body": "first" },
+    ]);
+
+    assert_eq!(
+      json!({
+        "id": 1,
+        "author": expected_author,
+        "comment!post": expected_comments,
+      }),
+      read("1", "author.org,comment!post").await.unwrap()
+    );
+
+    // Only the first level.
+    assert_eq!(
+      json!({
+        "id": 1,
+        "author": {
+          "id": 1,
+          "data": { "id": 1, "name": "public", "org": { "id": 1 } },
+        },
+      }),
+      read("1", "author").await.unwrap()
+    );
+
+    // The hidden author isn't readable through author_api and therefore not inlined.
+    assert_eq!(
+      json!({
+        "id": 2,
+        "author": { "id": 2 },
+        "comment!post": [{ "id": 4, "post": 2, "body": "other" }],
+      }),
+      read("2", "author.org,comment!post").await.unwrap()
+    );
+
+    // Paths that aren't configured.
+    assert!(read("1", "author.org.id").await.is_err());
+    assert!(read("1", "comment!author").await.is_err());
+    assert!(read("1", "org").await.is_err());
+
+    let list_response: ListResponse = unpack_json_response(
+      list_records_handler(
+        State(state.clone()),
+        Path("post_api".to_string()),
+        RawQuery(Some("expand=author.org,comment!post".to_string())),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+      vec![
+        json!({
+          "id": 2,
+          "author": { "id": 2 },
+          "comment!post": [{ "id": 4, "post": 2, "body": "other" }],
+        }),
+        json!({
+          "id": 1,
+          "author": expected_author,
+          "comment!post": expected_comments,
+        }),
+      ],
+      list_response.records
+    );
+
+    // Tables exposed by multiple Record APIs require configuring which one applies.
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("author_admin_api".to_string()),
+        table_name: Some("author".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    assert!(matches!(
+      read("2", "author").await,
+      Err(RecordError::BadRequest(_))
+    ));
+
+    assert!(
+      add_record_api_config(
+        &state,
+        RecordApiConfig {
+          name: Some("invalid_api".to_string()),
+          table_name: Some("post".to_string()),
+          related_apis: vec![RelatedRecordApi {
+            table_name: Some("author".to_string()),
+            api_name: Some("comment_api".to_string()),
+          }],
+          ..Default::default()
+        },
+      )
+      .await
+      .is_err()
+    );
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("post_admin_api".to_string()),
+        table_name: Some("post".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        expand: vec!["author".to_string()],
+        related_apis: vec![RelatedRecordApi {
+          table_name: Some("author".to_string()),
+          api_name: Some("author_admin_api".to_string()),
+        }],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let response = read_record_handler(
+      State(state.clone()),
+      Path(("post_admin_api".to_string(), "2".to_string())),
+      Query(ReadRecordQuery {
+        expand: Some("author".to_string()),
+        fields: None,
+        include_deleted: None,
+      }),
+      None,
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+      json!({
+        "id": 2,
+        "author": {
+          "id": 2,
+          "data": { "id": 2, "name": "hidden", "org": 1 },
+        },
+      }),
+      unpack_json_response::<serde_json::Value>(response)
+        .await
+        .unwrap()
+    );
+  }
+
+  #[tokio::test]
+  async fn test_field_projection() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE author (
+            id    INTEGER PRIMARY KEY,
+            name  TEXT NOT NULL,
+            bio   TEXT
+          ) STRICT;
+          CREATE TABLE post (
+            id      INTEGER PRIMARY KEY,
+            title   TEXT NOT NULL,
+            body    TEXT NOT NULL,
+            author  INTEGER REFERENCES author(id)
+          ) STRICT;
+
+          INSERT INTO author (id, name, bio) VALUES (1, 'name', 'bio');
+          INSERT INTO post (id, title, body, author) VALUES (1, 'title', 'body', 1);
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.schema_metadata().invalidate_all().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("post_api".to_string()),
+        table_name: Some("post".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        expand: vec!["author".to_string()],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("author_api".to_string()),
+        table_name: Some("author".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let read = async |expand: Option<&str>, fields: &str| {
+      let response = read_record_handler(
+        State(state.clone()),
+        Path(("post_api".to_string(), "1".to_string())),
+        Query(ReadRecordQuery {
+          expand: expand.map(|e| e.to_string()),
+          fields: Some(fields.to_string()),
+          include_deleted: None,
+        }),
+        None,
+        HeaderMap::new(),
+      )
+      .await?;
+      return Ok::<_, RecordError>(
+        unpack_json_response::<serde_json::Value>(response)
+          .await
+          .unwrap(),
+      );
+    };
+
+    // The primary key is always included.
+    assert_eq!(
+      json!({ "id": 1, "title": "title" }),
+      read(None, "title").await.unwrap()
+    );
+
+    // The foreign key of an expansion is always included.
+    assert_eq!(
+      json!({
+        "id": 1,
+        "title": "title",
+        "author": {
+          "id": 1,
+          "data": { "id": 1, "name": "name" },
+        },
+      }),
+      read(Some("author"), "title,author.name").await.unwrap()
+    );
+
+    // Unknown fields, hidden fields and fields of non-expanded relations.
+    assert!(read(None, "UNKNOWN").await.is_err());
+    assert!(read(None, "_rowid_").await.is_err());
+    assert!(read(None, "author.name").await.is_err());
+    assert!(read(Some("author"), "author.UNKNOWN").await.is_err());
+
+    let list_response: ListResponse = unpack_json_response(
+      list_records_handler(
+        State(state.clone()),
+        Path("post_api".to_string()),
+        RawQuery(Some("fields=body,author".to_string())),
+        None,
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+      vec![json!({ "id": 1, "body": "body", "author": 1 })],
+      list_response.records
+    );
+
+    let api = state.lookup_record_api("post_api").unwrap();
+    let schema = build_api_json_schema_with_fields(
+      &state,
+      &api,
+      Some(JsonSchemaMode::Select),
+      &["title", "author.name"],
+    )
+    .unwrap();
+
+    let properties = schema["properties"].as_object().unwrap();
+    assert_eq!(
+      vec!["author", "id", "title"],
+      properties.keys().collect::<Vec<_>>()
+    );
+  }
 }
@@ -45,6 +45,7 @@ pub struct InitArgs {
   pub geoip_db_path: Option<PathBuf>,
 
   pub address: String,
+  pub cors_allowed_origins: Vec<String>,
   pub dev: bool,
   pub demo: bool,
   pub js_runtime_threads: Option<usize>,
@@ -137,6 +138,7 @@ pub async fn init_app_state(args: InitArgs) -> Result<(bool, AppState), InitErro
     data_dir: args.data_dir.clone(),
     public_dir: args.public_dir,
     address: args.address,
+    cors_allowed_origins: args.cors_allowed_origins,
     dev: args.dev,
     demo: args.demo,
     schema_metadata,
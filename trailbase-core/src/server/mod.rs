@@ -27,6 +27,7 @@ use crate::admin;
 use crate::app_state::AppState;
 use crate::auth::util::is_admin;
 use crate::auth::{self, AuthError, User};
+use crate::broadcast;
 use crate::constants::{ADMIN_API_PATH, HEADER_CSRF_TOKEN};
 use crate::data_dir::DataDir;
 use crate::logging;
@@ -122,6 +123,7 @@ impl Server {
       public_dir: opts.public_dir.clone(),
       geoip_db_path: opts.geoip_db_path.clone(),
       address: opts.address.clone(),
+      cors_allowed_origins: opts.cors_allowed_origins.clone(),
       dev: opts.dev,
       demo: opts.demo,
       js_runtime_threads: opts.js_runtime_threads,
@@ -300,6 +302,7 @@ impl Server {
     let mut router = Router::new()
       // Public, stable and versioned APIs.
       .merge(records::router())
+      .merge(broadcast::router())
       .merge(auth::router())
       .route("/api/healthcheck", get(healthcheck_handler));
 
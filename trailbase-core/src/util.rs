@@ -80,6 +80,28 @@ pub(crate) fn get_header_owned(
   return None;
 }
 
+/// Whether a browser's `Origin` is the site itself or one of the allowed CORS origins. Requests
+/// without `Origin`, i.e. from non-browser clients, cannot be hijacked cross-site and pass.
+pub(crate) fn is_allowed_origin(
+  headers: &HeaderMap,
+  site_url: &url::Url,
+  allowed_origins: &[String],
+) -> bool {
+  let Some(origin) = headers.get(axum::http::header::ORIGIN) else {
+    return true;
+  };
+  let Ok(origin) = origin.to_str() else {
+    return false;
+  };
+
+  if origin == site_url.origin().ascii_serialization() {
+    return true;
+  }
+  return allowed_origins
+    .iter()
+    .any(|allowed| allowed == "*" || allowed.trim_end_matches('/') == origin);
+}
+
 pub fn cow_to_string(cow: Cow<'static, [u8]>) -> String {
   match cow {
     Cow::Borrowed(x) => String::from_utf8_lossy(x).to_string(),
@@ -95,4 +117,44 @@ mod tests {
   fn test_urlencode() {
     assert_eq!(urlencode("+col0,-col1"), "%2Bcol0%2C-col1");
   }
+
+  #[test]
+  fn test_is_allowed_origin() {
+    let site_url = url::Url::parse("https://example.com/").unwrap();
+    let headers = |origin: &'static str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(
+        axum::http::header::ORIGIN,
+        axum::http::HeaderValue::from_static(origin),
+      );
+      return headers;
+    };
+
+    assert!(is_allowed_origin(&HeaderMap::new(), &site_url, &[]));
+    assert!(is_allowed_origin(
+      &headers("https://example.com"),
+      &site_url,
+      &[]
+    ));
+    assert!(!is_allowed_origin(
+      &headers("https://evil.com"),
+      &site_url,
+      &[]
+    ));
+    assert!(!is_allowed_origin(
+      &headers("http://example.com"),
+      &site_url,
+      &[]
+    ));
+    assert!(is_allowed_origin(
+      &headers("https://app.example.com"),
+      &site_url,
+      &["https://app.example.com/".to_string()]
+    ));
+    assert!(is_allowed_origin(
+      &headers("https://evil.com"),
+      &site_url,
+      &["*".to_string()]
+    ));
+  }
 }
@@ -0,0 +1,25 @@
+SELECT
+{% for expr in group_by_exprs -%}
+  {%- if !loop.first %},{% endif %}{{ expr }}
+{%- endfor %}
+{%- for aggregation in aggregations -%}
+  {%- if !loop.first || !group_by_exprs.is_empty() %},{% endif %}{{ aggregation }}
+{%- endfor %}
+FROM
+  (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
+  {{ table_name }} AS _ROW_
+WHERE
+  ({{ read_access_clause }})
+  AND ({{ filter_clause }})
+{%- if !group_by_exprs.is_empty() %}
+GROUP BY
+{% for expr in group_by_exprs -%}
+  {%- if !loop.first %},{% endif %}{{ expr }}
+{%- endfor %}
+ORDER BY
+  {{ order_clause }}
+{%- endif %}
+LIMIT :__limit
+{%- if offset %}
+OFFSET :__offset
+{%- endif -%}
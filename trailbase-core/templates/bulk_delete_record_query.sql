@@ -0,0 +1,12 @@
+{% match soft_delete_column -%}
+{%- when Some with (column) -%}
+UPDATE {{ table_name }} SET "{{ column }}" = UNIXEPOCH()
+{%- when None -%}
+DELETE FROM {{ table_name }}
+{%- endmatch %}
+WHERE _rowid_ IN (
+  SELECT _ROW_._rowid_
+  FROM (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_, {{ table_name }} AS _ROW_
+  WHERE ({{ delete_access_clause }}) AND ({{ filter_clause }})
+)
+RETURNING _rowid_
@@ -0,0 +1,18 @@
+WITH _REQ_FIELDS_(_) AS (SELECT value FROM (json_each(:__fields)))
+UPDATE {{ table_name }} SET
+{%- for name in column_names -%}
+  {%- if !loop.first %},{% endif %}"{{ name }}" = :{{ name }}
+{%- endfor %}
+WHERE _rowid_ IN (
+  SELECT _ROW_._rowid_
+  FROM
+  (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
+  (SELECT
+  {%- for name in request_column_names -%}
+    {% if !loop.first %},{% endif %} :{{ name }} AS "{{ name }}"
+  {%- endfor -%}
+  ) AS _REQ_,
+  {{ table_name }} AS _ROW_
+  WHERE ({{ update_access_clause }}) AND ({{ filter_clause }})
+)
+RETURNING _rowid_
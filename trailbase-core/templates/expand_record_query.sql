@@ -0,0 +1,29 @@
+{%- if limit.is_some() -%}
+SELECT * FROM (
+{% endif -%}
+SELECT
+{% for name in column_names -%}
+  {%- if !loop.first %},{% endif -%}
+  {%- if let Some(Some(rule)) = column_read_rules.get(loop.index0) -%}
+    (CASE WHEN ({{ rule }}) THEN _ROW_."{{ name }}" END) AS "{{ name }}"
+  {%- else -%}
+    _ROW_."{{ name }}"
+  {%- endif -%}
+{%- endfor %}
+  , _ROW_."{{ key_column_name }}" AS _key_
+{%- if limit.is_some() %}
+  , ROW_NUMBER() OVER (PARTITION BY _ROW_."{{ key_column_name }}" ORDER BY _ROW_."{{ pk_column_name }}" DESC) AS _index_
+{%- endif %}
+FROM
+  (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
+  {{ table_name }} AS _ROW_
+WHERE
+  ({{ read_access_clause }})
+  AND _ROW_."{{ key_column_name }}" IN (
+{%- for i in 0..num_keys -%}
+  {%- if !loop.first %}, {% endif %}:__key{{ i }}
+{%- endfor -%}
+  )
+{%- if let Some(limit) = limit %}
+) WHERE _index_ <= {{ limit }}
+{%- endif -%}
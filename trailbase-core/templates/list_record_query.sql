@@ -3,7 +3,7 @@ WITH
   total_count AS (
     SELECT COUNT(*) AS _value_
     FROM
-      (SELECT :__user_id AS id) AS _USER_,
+      (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
       {{ table_name }} as _ROW_
     WHERE
       ({{ read_access_clause }})
@@ -13,22 +13,27 @@ WITH
 
 SELECT
 {% for name in column_names -%}
-  {%- if !loop.first %},{% endif %}_ROW_."{{ name }}"
+  {%- if !loop.first %},{% endif -%}
+  {%- if let Some(Some(rule)) = column_read_rules.get(loop.index0) -%}
+    (CASE WHEN ({{ rule }}) THEN _ROW_."{{ name }}" END) AS "{{ name }}"
+  {%- else -%}
+    _ROW_."{{ name }}"
+  {%- endif -%}
 {%- endfor %}
-{%- for expanded in expanded_tables -%}
-  , F{{ loop.index0 }}.*
+{%- for expr in cursor_columns -%}
+  , {{ expr }} AS _cursor{{ loop.index0 }}_
 {%- endfor -%}
+{%- if let Some(fts_clause) = fts_clause -%}
+  , {{ fts_clause }}
+{%- endif -%}
 {% if count -%}, total_count._value_ AS _total_count_{%- endif %}
   , _ROW_._rowid_ AS _rowid_
 FROM
-  (SELECT :__user_id AS id) AS _USER_,
+  (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
 {%- if count %}
   total_count,
 {%- endif %}
   {{ table_name }} AS _ROW_
-{%- for expanded in expanded_tables %}
-    LEFT JOIN "{{ expanded.foreign_table_name }}" AS F{{ loop.index0 }} ON _ROW_."{{ expanded.local_column_name }}" = F{{ loop.index0 }}."{{ expanded.foreign_column_name }}"
-{%- endfor %}
 WHERE
   ({{ read_access_clause }})
   AND ({{ filter_clause }})
@@ -1,6 +1,12 @@
 SELECT
-{% for name in column_names -%}
-  {%- if !loop.first %},{% endif %}MAIN."{{ name }}"
+{% for expr in column_exprs -%}
+  {%- if !loop.first %},{% endif %}{{ expr }}
 {%- endfor %}
-FROM {{ table_name }} as MAIN
-WHERE MAIN."{{ pk_column_name }}" = ?1
+{%- if let Some(etag_expr) = etag_expr -%}
+  , {{ etag_expr }} AS _etag_
+{%- endif %}
+FROM
+  (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
+  {{ table_name }} AS _ROW_
+WHERE _ROW_."{{ pk_column_name }}" = :__record_id
+{%- if let Some(column) = soft_delete_column %} AND _ROW_."{{ column }}" IS NULL{% endif %}
@@ -1,7 +1,9 @@
 SELECT
-  CAST(({{ read_access_rule }}) AS INTEGER)
+  {% for rule in read_access_rules -%}
+    {% if !loop.first %}, {% endif %}CAST(({{ rule }}) AS INTEGER)
+  {%- endfor %}
 FROM
-  (SELECT :__user_id AS id) AS _USER_
+  (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_
   {% if !column_names.is_empty() -%}
   , (SELECT
     {%- for name in column_names -%}
@@ -2,7 +2,7 @@ WITH _REQ_FIELDS_(_) AS (SELECT value FROM (json_each(:__fields)))
 SELECT
   CAST(({{ update_access_rule }}) AS INTEGER)
 FROM
-  (SELECT :__user_id AS id) AS _USER_,
+  (SELECT :__user_id AS id, :__user_roles AS roles) AS _USER_,
   (SELECT * FROM {{ table_name }} WHERE "{{ pk_column_name }}" = :__record_id) AS _ROW_
   {% if !column_names.is_empty() -%}
   , (SELECT
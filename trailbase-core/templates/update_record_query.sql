@@ -3,6 +3,7 @@ UPDATE {{ table_name }} SET
   {%- if !loop.first %},{% endif %}"{{ name }}" = :{{ name }}
 {%- endfor %}
 WHERE "{{ pk_column_name }}" = :{{ pk_column_name }}
+{%- if let Some(column) = soft_delete_column %} AND "{{ column }}" IS NULL{% endif %}
 {%- match returning -%}
   {%- when Some with ("*") %} RETURNING *
   {%- when Some with (value) %} RETURNING "{{ value }}"
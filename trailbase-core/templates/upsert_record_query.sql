@@ -0,0 +1,16 @@
+INSERT INTO {{ table_name }} (
+  {%- for name in column_names -%}
+    {%- if !loop.first %},{% endif %}"{{ name }}"
+  {%- endfor -%}
+  ) VALUES (
+  {%- for name in column_names -%}
+    {%- if !loop.first %},{% endif %}:{{ name }}
+  {%- endfor -%}
+) ON CONFLICT (
+  {%- for name in conflict_columns -%}
+    {%- if !loop.first %},{% endif %}"{{ name }}"
+  {%- endfor -%}
+) DO UPDATE SET
+{%- for name in update_column_names -%}
+  {%- if !loop.first %},{% endif %} "{{ name }}" = excluded."{{ name }}"
+{%- endfor %} RETURNING _rowid_,"{{ pk_column_name }}"
@@ -0,0 +1,226 @@
+use rusqlite::Error;
+use rusqlite::functions::Context;
+use rusqlite::types::ValueRef;
+
+/// Mean earth radius in meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+const GEOHASH_DEFAULT_PRECISION: usize = 9;
+const GEOHASH_MAX_PRECISION: usize = 12;
+
+/// Great-circle distance in meters between two points given as latitude and longitude in
+/// degrees, e.g. `geo_distance(lat, lng, 52.52, 13.40)`.
+pub(super) fn geo_distance(context: &Context) -> Result<Option<f64>, Error> {
+  #[cfg(debug_assertions)]
+  if context.len() != 4 {
+    return Err(Error::InvalidParameterCount(context.len(), 4));
+  }
+
+  let (Some(lat0), Some(lng0), Some(lat1), Some(lng1)) = (
+    get_degrees(context, 0)?,
+    get_degrees(context, 1)?,
+    get_degrees(context, 2)?,
+    get_degrees(context, 3)?,
+  ) else {
+    return Ok(None);
+  };
+
+  return Ok(Some(haversine_distance(lat0, lng0, lat1, lng1)));
+}
+
+/// Geohash of the given point, e.g. `geohash(lat, lng)` or `geohash(lat, lng, 5)` with an
+/// explicit precision of 1 to 12 characters.
+pub(super) fn geohash(context: &Context) -> Result<Option<String>, Error> {
+  #[cfg(debug_assertions)]
+  if context.len() != 2 && context.len() != 3 {
+    return Err(Error::InvalidParameterCount(context.len(), 3));
+  }
+
+  let (Some(lat), Some(lng)) = (get_degrees(context, 0)?, get_degrees(context, 1)?) else {
+    return Ok(None);
+  };
+  let precision = if context.len() > 2 {
+    let precision = context.get::<i64>(2)?;
+    if !(1..=GEOHASH_MAX_PRECISION as i64).contains(&precision) {
+      return Err(Error::UserFunctionError(
+        format!("Geohash precision must be between 1 and {GEOHASH_MAX_PRECISION}").into(),
+      ));
+    }
+    precision as usize
+  } else {
+    GEOHASH_DEFAULT_PRECISION
+  };
+
+  return Ok(Some(geohash_encode(lat, lng, precision)));
+}
+
+/// Whether a point is contained in the bounding box given by its south-west and north-east
+/// corners, e.g. `geo_within(lat, lng, min_lat, min_lng, max_lat, max_lng)`. Boxes with
+/// `min_lng > max_lng` cross the anti-meridian.
+pub(super) fn geo_within(context: &Context) -> Result<Option<bool>, Error> {
+  #[cfg(debug_assertions)]
+  if context.len() != 6 {
+    return Err(Error::InvalidParameterCount(context.len(), 6));
+  }
+
+  let mut values = [0.0; 6];
+  for (index, value) in values.iter_mut().enumerate() {
+    let Some(v) = get_degrees(context, index)? else {
+      return Ok(None);
+    };
+    *value = v;
+  }
+  let [lat, lng, min_lat, min_lng, max_lat, max_lng] = values;
+
+  return Ok(Some(bbox_contains(
+    lat, lng, min_lat, min_lng, max_lat, max_lng,
+  )));
+}
+
+#[inline]
+fn get_degrees(context: &Context, index: usize) -> Result<Option<f64>, Error> {
+  let value = match context.get_raw(index) {
+    ValueRef::Null => return Ok(None),
+    ValueRef::Integer(i) => i as f64,
+    ValueRef::Real(r) => r,
+    _ => {
+      return Err(Error::UserFunctionError(
+        format!("Expected numeric coordinate for argument {index}").into(),
+      ));
+    }
+  };
+
+  if !value.is_finite() {
+    return Err(Error::UserFunctionError(
+      format!("Invalid coordinate for argument {index}").into(),
+    ));
+  }
+  return Ok(Some(value));
+}
+
+fn haversine_distance(lat0: f64, lng0: f64, lat1: f64, lng1: f64) -> f64 {
+  let (phi0, phi1) = (lat0.to_radians(), lat1.to_radians());
+  let d_phi = (lat1 - lat0).to_radians();
+  let d_lambda = (lng1 - lng0).to_radians();
+
+  let a = (d_phi / 2.0).sin().powi(2) + phi0.cos() * phi1.cos() * (d_lambda / 2.0).sin().powi(2);
+  return 2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin();
+}
+
+fn geohash_encode(lat: f64, lng: f64, precision: usize) -> String {
+  let (mut lat_range, mut lng_range) = ((-90.0, 90.0), (-180.0, 180.0));
+
+  let mut hash = String::with_capacity(precision);
+  let mut even = true;
+  let (mut bits, mut bit_count) = (0_usize, 0);
+
+  while hash.len() < precision {
+    let (range, value) = if even {
+      (&mut lng_range, lng)
+    } else {
+      (&mut lat_range, lat)
+    };
+
+    let mid = (range.0 + range.1) / 2.0;
+    bits <<= 1;
+    if value >= mid {
+      bits |= 1;
+      range.0 = mid;
+    } else {
+      range.1 = mid;
+    }
+    even = !even;
+
+    bit_count += 1;
+    if bit_count == 5 {
+      hash.push(GEOHASH_ALPHABET[bits] as char);
+      (bits, bit_count) = (0, 0);
+    }
+  }
+
+  return hash;
+}
+
+fn bbox_contains(
+  lat: f64,
+  lng: f64,
+  min_lat: f64,
+  min_lng: f64,
+  max_lat: f64,
+  max_lng: f64,
+) -> bool {
+  if lat < min_lat || lat > max_lat {
+    return false;
+  }
+
+  return if min_lng <= max_lng {
+    min_lng <= lng && lng <= max_lng
+  } else {
+    lng >= min_lng || lng <= max_lng
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_haversine_distance() {
+    assert_eq!(0.0, haversine_distance(52.52, 13.40, 52.52, 13.40));
+
+    // Berlin to Paris is roughly 878km.
+    let distance = haversine_distance(52.5200, 13.4050, 48.8566, 2.3522);
+    assert!((distance - 877_500.0).abs() < 2_000.0, "{distance}");
+
+    // A degree of latitude is roughly 111km.
+    let distance = haversine_distance(0.0, 0.0, 1.0, 0.0);
+    assert!((distance - 111_195.0).abs() < 100.0, "{distance}");
+  }
+
+  #[test]
+  fn test_geohash_encode() {
+    assert_eq!("u33dc0cpp", geohash_encode(52.5200, 13.4050, 9));
+    assert_eq!("ezs42", geohash_encode(42.6, -5.6, 5));
+    assert_eq!("s", geohash_encode(0.0, 0.0, 1));
+  }
+
+  #[test]
+  fn test_bbox_contains() {
+    assert!(bbox_contains(52.5, 13.4, 52.0, 13.0, 53.0, 14.0));
+    assert!(!bbox_contains(52.5, 14.4, 52.0, 13.0, 53.0, 14.0));
+    assert!(!bbox_contains(51.5, 13.4, 52.0, 13.0, 53.0, 14.0));
+
+    // Crossing the anti-meridian.
+    assert!(bbox_contains(0.0, 179.5, -1.0, 179.0, 1.0, -179.0));
+    assert!(bbox_contains(0.0, -179.5, -1.0, 179.0, 1.0, -179.0));
+    assert!(!bbox_contains(0.0, 0.0, -1.0, 179.0, 1.0, -179.0));
+  }
+
+  #[test]
+  fn test_geo_functions() {
+    let conn = crate::connect_sqlite(None, None).unwrap();
+
+    let distance: f64 = conn
+      .query_row("SELECT geo_distance(0, 0, 1, 0)", (), |row| row.get(0))
+      .unwrap();
+    assert!((distance - 111_195.0).abs() < 100.0, "{distance}");
+
+    let hash: String = conn
+      .query_row("SELECT geohash(42.6, -5.6, 5)", (), |row| row.get(0))
+      .unwrap();
+    assert_eq!("ezs42", hash);
+
+    let within: bool = conn
+      .query_row("SELECT geo_within(52.5, 13.4, 52, 13, 53, 14)", (), |row| {
+        row.get(0)
+      })
+      .unwrap();
+    assert!(within);
+
+    let null: Option<f64> = conn
+      .query_row("SELECT geo_distance(NULL, 0, 1, 0)", (), |row| row.get(0))
+      .unwrap();
+    assert_eq!(None, null);
+  }
+}
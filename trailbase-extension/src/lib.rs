@@ -8,6 +8,7 @@ pub mod geoip;
 pub mod jsonschema;
 pub mod password;
 
+mod geo;
 mod regex;
 mod uuid;
 mod validators;
@@ -222,6 +223,30 @@ pub fn sqlite3_extension_init(
     geoip::geoip_city_json,
   )?;
 
+  // Geospatial helpers for latitude/longitude pairs in degrees.
+  db.create_scalar_function(
+    "geo_distance",
+    4,
+    FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+    geo::geo_distance,
+  )?;
+  db.create_scalar_function(
+    "geo_within",
+    6,
+    FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+    geo::geo_within,
+  )?;
+  for n_args in [2, 3] {
+    db.create_scalar_function(
+      "geohash",
+      n_args,
+      FunctionFlags::SQLITE_UTF8
+        | FunctionFlags::SQLITE_DETERMINISTIC
+        | FunctionFlags::SQLITE_INNOCUOUS,
+      geo::geohash,
+    )?;
+  }
+
   return Ok(db);
 }
 
@@ -288,6 +313,15 @@ mod test {
     conn
       .query_row("SELECT vec_f32('[0, 1, 2, 3]')", (), |_row| Ok(()))
       .unwrap();
+
+    let distance: f64 = conn
+      .query_row(
+        "SELECT vec_distance_l2(vec_f32('[0, 0]'), '[3, 4]')",
+        (),
+        |row| row.get(0),
+      )
+      .unwrap();
+    assert_eq!(distance, 5.0);
   }
 
   #[test]
@@ -22,6 +22,7 @@ export {
   addCronCallback,
   addPeriodicCallback,
   addRoute,
+  broadcast,
   execute,
   htmlHandler,
   jsonHandler,
@@ -714,6 +714,12 @@ export async function execute(sql: string, params: unknown[]): Promise<number> {
   return await rustyscript.async_functions.execute(sql, params);
 }
 
+/// Publishes the given JSON-serializable payload to all current subscribers of
+/// the broadcast channel and returns the number of receivers.
+export function broadcast(channel: string, payload: unknown): number {
+  return rustyscript.functions.broadcast(channel, payload);
+}
+
 export class Transaction {
   finalized: boolean;
 
@@ -11,7 +11,25 @@ pub enum CompareOp {
   LessThanEqual,
   LessThan,
   Like,
+  /// Case-insensitive `Like`.
+  ILike,
   Regexp,
+  /// Full-text search match, see `ValueOrComposite::into_sql_with_match`.
+  Match,
+  /// Set membership, expects a `Value::Array`.
+  In,
+  /// Negated set membership, expects a `Value::Array`.
+  NotIn,
+  /// Inclusive range, expects a `Value::Array` of lower and upper bound.
+  Between,
+  /// `IS NULL` check for `true` and `IS NOT NULL` check for `false`.
+  Null,
+  /// Geospatial proximity on a `[lat,lng]` column pair, expects a `Value::Array` of center
+  /// latitude, longitude and radius in meters.
+  Near,
+  /// Geospatial bounding box on a `[lat,lng]` column pair, expects a `Value::Array` of
+  /// `min_lat, min_lng, max_lat, max_lng`.
+  Within,
 }
 
 impl CompareOp {
@@ -24,7 +42,15 @@ impl CompareOp {
       "$lte" => Some(Self::LessThanEqual),
       "$lt" => Some(Self::LessThan),
       "$like" => Some(Self::Like),
+      "$ilike" => Some(Self::ILike),
       "$re" => Some(Self::Regexp),
+      "$match" => Some(Self::Match),
+      "$in" => Some(Self::In),
+      "$nin" => Some(Self::NotIn),
+      "$between" => Some(Self::Between),
+      "$null" => Some(Self::Null),
+      "$near" => Some(Self::Near),
+      "$within" => Some(Self::Within),
       _ => None,
     };
   }
@@ -36,11 +62,30 @@ impl CompareOp {
       Self::LessThanEqual => "<=",
       Self::LessThan => "<",
       Self::NotEqual => "<>",
-      Self::Like => "LIKE",
+      Self::Like | Self::ILike => "LIKE",
       Self::Regexp => "REGEXP",
+      Self::Match => "MATCH",
+      Self::In => "IN",
+      Self::NotIn => "NOT IN",
+      Self::Between => "BETWEEN",
+      Self::Null => "IS",
+      Self::Near => "<=",
+      Self::Within => "BETWEEN",
       Self::Equal => "=",
     };
   }
+
+  /// Number of values expected by the operator, i.e. `None` for any number of values and
+  /// `Some(0)` for a single, non-array value.
+  fn arity(self) -> Option<usize> {
+    return match self {
+      Self::In | Self::NotIn => None,
+      Self::Between => Some(2),
+      Self::Near => Some(3),
+      Self::Within => Some(4),
+      _ => Some(0),
+    };
+  }
 }
 
 impl<'de> serde::de::Deserialize<'de> for CompareOp {
@@ -68,11 +113,115 @@ impl<'de> serde::de::Deserialize<'de> for CompareOp {
 /// Type to support query of shape: `[column][op]=value`.
 #[derive(Clone, Debug, PartialEq)]
 pub struct ColumnOpValue {
+  /// Column name or, for geospatial filters, a `lat,lng` column pair.
   pub column: String,
+  /// Path into a JSON column, e.g. `["tags", "0"]` for `[metadata.tags.0]` or
+  /// `[metadata->tags->0]`. Empty if the filter applies to the column itself.
+  pub json_path: Vec<String>,
   pub op: CompareOp,
   pub value: Value,
 }
 
+impl ColumnOpValue {
+  /// SQLite JSON path expression, e.g. `$."tags"[0]`, if any.
+  pub fn json_path_expr(&self) -> Option<String> {
+    if self.json_path.is_empty() {
+      return None;
+    }
+
+    let mut expr = "$".to_string();
+    for segment in &self.json_path {
+      if segment.chars().all(|c| c.is_ascii_digit()) {
+        expr.push_str(&format!("[{segment}]"));
+      } else {
+        expr.push_str(&format!(r#"."{segment}""#));
+      }
+    }
+    return Some(expr);
+  }
+
+  /// Latitude and longitude column of geospatial filters, e.g. `[lat,lng][$near]`.
+  pub fn geo_columns(&self) -> Option<(&str, &str)> {
+    if !matches!(self.op, CompareOp::Near | CompareOp::Within) {
+      return None;
+    }
+    return self.column.split_once(',');
+  }
+
+  /// Numeric values of geospatial filters, e.g. center and radius for `$near`.
+  pub(crate) fn geo_coordinates(&self) -> Vec<f64> {
+    let Value::Array(ref values) = self.value else {
+      return vec![];
+    };
+    return values.iter().filter_map(to_f64).collect();
+  }
+}
+
+fn to_f64(value: &Value) -> Option<f64> {
+  return match value {
+    Value::Integer(i) => Some(*i as f64),
+    Value::Double(d) if d.is_finite() => Some(*d),
+    _ => None,
+  };
+}
+
+/// Validates the values of geospatial filters, i.e. coordinates in degrees and a non-negative
+/// radius.
+fn validate_geo_coordinates(op: CompareOp, value: &Value) -> Result<(), &'static str> {
+  let Value::Array(values) = value else {
+    return Err("expected array");
+  };
+  let Some(coordinates) = values.iter().map(to_f64).collect::<Option<Vec<_>>>() else {
+    return Err("expected numeric values");
+  };
+
+  let valid_lat = |lat: f64| (-90.0..=90.0).contains(&lat);
+  let valid_lng = |lng: f64| (-180.0..=180.0).contains(&lng);
+
+  let valid = match (op, coordinates.as_slice()) {
+    (CompareOp::Near, [lat, lng, radius]) => valid_lat(*lat) && valid_lng(*lng) && *radius >= 0.0,
+    (CompareOp::Within, [min_lat, min_lng, max_lat, max_lng]) => {
+      valid_lat(*min_lat)
+        && valid_lat(*max_lat)
+        && min_lat <= max_lat
+        && valid_lng(*min_lng)
+        && valid_lng(*max_lng)
+    }
+    _ => return Err("expected three values for $near or four for $within"),
+  };
+  if !valid {
+    return Err("coordinates out of range");
+  }
+  return Ok(());
+}
+
+/// Splits a geospatial filter key into its latitude and longitude column, e.g. `lat,lng`.
+fn parse_geo_columns(key: &str) -> Option<String> {
+  let (lat, lng) = key.split_once(',')?;
+  let valid = |c: &str| {
+    !c.is_empty() && !c.contains('.') && !c.contains('-') && crate::util::sanitize_column_name(c)
+  };
+  if !valid(lat) || !valid(lng) {
+    return None;
+  }
+  return Some(key.to_string());
+}
+
+/// Splits a filter key into column name and JSON path, where path segments are either separated
+/// by `.` or `->`.
+fn parse_column_and_json_path(key: &str) -> Option<(String, Vec<String>)> {
+  let mut segments = key.split("->").flat_map(|s| s.split('.')).map(|segment| {
+    if segment.is_empty() || !crate::util::sanitize_column_name(segment) {
+      return None;
+    }
+    return Some(segment.to_string());
+  });
+
+  let column = segments.next()??;
+  let json_path = segments.collect::<Option<Vec<_>>>()?;
+  return Some((column, json_path));
+}
+
 pub fn serde_value_to_single_column_rel_value<'de, D>(
   key: String,
   value: serde_value::Value,
@@ -81,17 +230,32 @@ where
   D: Deserializer<'de>,
 {
   use serde_value::Value;
-  if !crate::util::sanitize_column_name(&key) {
+
+  // Geospatial filters apply to a pair of columns, e.g. `[lat,lng][$near]`.
+  if let Value::Map(ref m) = value {
+    let geo_op = m.keys().next().is_some_and(|k| {
+      matches!(
+        k.clone().deserialize_into::<CompareOp>(),
+        Ok(CompareOp::Near | CompareOp::Within)
+      )
+    });
+    if geo_op {
+      return parse_geo_filter::<D>(key, value);
+    }
+  }
+
+  let Some((column, json_path)) = parse_column_and_json_path(&key) else {
     // NOTE: This may trigger if serde_qs parse depth is not enough. In this case, square brakets
     // will end up in the column name.
     return Err(Error::custom(format!(
       "invalid column name for filter: {key}. Nesting too deep?"
     )));
-  }
+  };
 
   return match value {
     Value::String(_) => Ok(ColumnOpValue {
-      column: key,
+      column,
+      json_path,
       op: CompareOp::Equal,
       value: crate::value::serde_value_to_value::<D>(value)?,
     }),
@@ -99,11 +263,53 @@ where
       let (k, v) = m.pop_first().expect("len() == 1");
 
       let op = k.deserialize_into::<CompareOp>().map_err(Error::custom)?;
+      let value = crate::value::serde_value_to_value::<D>(v)?;
+
+      match (op.arity(), &value) {
+        (Some(0), crate::value::Value::Array(_)) => {
+          return Err(Error::custom(format!(
+            "filter on '{key}': array value not supported by operator"
+          )));
+        }
+        (Some(0), _) => {}
+        (arity, crate::value::Value::Array(values)) => {
+          if values.is_empty() || arity.is_some_and(|n| n != values.len()) {
+            return Err(Error::invalid_length(
+              values.len(),
+              &"non-empty array or the operator's number of elements, e.g. two for $between",
+            ));
+          }
+        }
+        (_, _) => {
+          return Err(Error::custom(format!(
+            "filter on '{key}': operator expects array value, e.g. [column][$in][0]=value"
+          )));
+        }
+      };
+
+      if op == CompareOp::Match && !json_path.is_empty() {
+        return Err(Error::custom(format!(
+          "filter on '{key}': $match not supported on JSON paths"
+        )));
+      }
+
+      if matches!(op, CompareOp::Near | CompareOp::Within) {
+        return Err(Error::custom(format!(
+          "filter on '{key}': {op:?} expects a [lat,lng] column pair"
+        )));
+      }
+
+      if op == CompareOp::Null && !matches!(value, crate::value::Value::Bool(_)) {
+        return Err(Error::custom(format!(
+          "filter on '{key}': $null expects true or false"
+        )));
+      }
 
       Ok(ColumnOpValue {
-        column: key,
+        column,
+        json_path,
         op,
-        value: crate::value::serde_value_to_value::<D>(v)?,
+        value,
       })
     }
     v => Err(Error::invalid_type(
@@ -112,3 +318,43 @@ where
     )),
   };
 }
+
+fn parse_geo_filter<'de, D>(
+  key: String,
+  value: serde_value::Value,
+) -> Result<ColumnOpValue, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  use serde_value::Value;
+
+  let Some(column) = parse_geo_columns(&key) else {
+    return Err(Error::custom(format!(
+      "invalid column pair for geospatial filter: {key}. Expected [lat,lng]"
+    )));
+  };
+
+  let Value::Map(mut m) = value else {
+    return Err(Error::custom("expected map"));
+  };
+  let Some((k, v)) = m.pop_first() else {
+    return Err(Error::custom("expected map"));
+  };
+  if !m.is_empty() {
+    return Err(Error::custom(format!(
+      "filter on '{key}': expected a single operator"
+    )));
+  }
+
+  let op = k.deserialize_into::<CompareOp>().map_err(Error::custom)?;
+  let value = crate::value::serde_value_to_value::<D>(v)?;
+  validate_geo_coordinates(op, &value)
+    .map_err(|err| Error::custom(format!("filter on '{key}': {err}")))?;
+
+  return Ok(ColumnOpValue {
+    column,
+    json_path: vec![],
+    op,
+    value,
+  });
+}
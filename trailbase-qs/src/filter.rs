@@ -9,7 +9,7 @@
 /// filters[and][0][or][0][column0]=value0&[and][0][or][1][column1]=value1
 use std::collections::BTreeMap;
 
-use crate::column_rel_value::{ColumnOpValue, serde_value_to_single_column_rel_value};
+use crate::column_rel_value::{ColumnOpValue, CompareOp, serde_value_to_single_column_rel_value};
 use crate::value::Value;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -18,6 +18,68 @@ pub enum Combiner {
   Or,
 }
 
+/// Validates a filter's column name and JSON path, if any.
+type Validator<'a, E> = dyn Fn(&str, &[String]) -> Result<(), E> + 'a;
+
+/// Renders a `$match` filter given the column and parameter name.
+pub type MatchSql<'a, E> = dyn Fn(&str, &str) -> Result<String, E> + 'a;
+
+/// Resolves a filter's column name and path, e.g. `author` and `["org"]`, to a column of a
+/// related table, if any.
+pub type RelationSql<'a, E> = dyn Fn(&str, &[String]) -> Result<Option<RelatedColumn>, E> + 'a;
+
+/// A column of a related table, e.g. referenced through a foreign key. Conditions on `column` are
+/// rendered as `{prefix}{condition}{suffix}`, e.g. within an `EXISTS` sub-query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelatedColumn {
+  pub column: String,
+  pub prefix: String,
+  pub suffix: String,
+}
+
+/// Renders an additional condition for a geospatial filter, e.g. narrowing down candidates using
+/// a spatial index. Returns `None` if there's nothing to add.
+pub type GeoSql<'a, E> = dyn Fn(&GeoFilter<'_>) -> Result<Option<String>, E> + 'a;
+
+/// Returns a condition guarding filters on the given column, if any, e.g. a column-level access
+/// rule. Guarded conditions are rendered as `({guard} AND {condition})`.
+pub type GuardSql<'a, E> = dyn Fn(&str) -> Result<Option<String>, E> + 'a;
+
+/// A geospatial `$near` or `$within` filter on a pair of latitude and longitude columns.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeoFilter<'a> {
+  pub latitude_column: &'a str,
+  pub longitude_column: &'a str,
+  /// Names of the parameters holding latitude and longitude of a `$near` filter's center.
+  pub center: Option<(&'a str, &'a str)>,
+  /// Bounding box containing all matches as `[min_lat, min_lng, max_lat, max_lng]`.
+  pub bbox: [f64; 4],
+}
+
+/// Optional callbacks letting the caller render parts of a filter, see `into_sql_with_hooks`.
+pub struct SqlHooks<'a, E> {
+  /// Renders `$match` filters, e.g. as a sub-query against a full-text index.
+  pub match_sql: Option<&'a MatchSql<'a, E>>,
+  /// Resolves filters on columns of related tables, e.g. `author.org`. Filters resolved this way
+  /// bypass the validator.
+  pub relation_sql: Option<&'a RelationSql<'a, E>>,
+  /// Adds conditions to `$near` and `$within` filters.
+  pub geo_sql: Option<&'a GeoSql<'a, E>>,
+  /// Guards filters on individual columns of the filtered table.
+  pub guard_sql: Option<&'a GuardSql<'a, E>>,
+}
+
+impl<E> Default for SqlHooks<'_, E> {
+  fn default() -> Self {
+    return Self {
+      match_sql: None,
+      relation_sql: None,
+      geo_sql: None,
+      guard_sql: None,
+    };
+  }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ValueOrComposite {
   Value(ColumnOpValue),
@@ -28,39 +90,189 @@ impl ValueOrComposite {
   pub fn into_sql<E>(
     self,
     prefix: Option<&str>,
-    validator: &dyn Fn(&str) -> Result<(), E>,
+    validator: &Validator<'_, E>,
+  ) -> Result<(String, Vec<(String, Value)>), E> {
+    let mut index: usize = 0;
+    return self.into_sql_impl(prefix, validator, &SqlHooks::default(), &mut index);
+  }
+
+  /// Like `into_sql` but lets the caller render `$match` filters, e.g. as a sub-query against a
+  /// full-text index, given the column and parameter name.
+  pub fn into_sql_with_match<E>(
+    self,
+    prefix: Option<&str>,
+    validator: &Validator<'_, E>,
+    match_sql: &MatchSql<'_, E>,
+  ) -> Result<(String, Vec<(String, Value)>), E> {
+    let hooks = SqlHooks {
+      match_sql: Some(match_sql),
+      ..Default::default()
+    };
+    let mut index: usize = 0;
+    return self.into_sql_impl(prefix, validator, &hooks, &mut index);
+  }
+
+  /// Like `into_sql` but lets the caller render parts of the filter using `hooks`, e.g. `$match`
+  /// filters or filters on columns of related tables.
+  pub fn into_sql_with_hooks<E>(
+    self,
+    prefix: Option<&str>,
+    validator: &Validator<'_, E>,
+    hooks: &SqlHooks<'_, E>,
   ) -> Result<(String, Vec<(String, Value)>), E> {
     let mut index: usize = 0;
-    return self.into_sql_impl(prefix, validator, &mut index);
+    return self.into_sql_impl(prefix, validator, hooks, &mut index);
+  }
+
+  /// Names of the parameters compared against values within JSON columns, in the order they're
+  /// assigned by `into_sql`. Unlike other parameters, these are never BLOBs.
+  pub fn json_path_params(&self) -> Vec<String> {
+    return self.params_where(|_column, json_path| !json_path.is_empty());
+  }
+
+  /// Names of the parameters of filters whose column name and path satisfy `predicate`, in the
+  /// order they're assigned by `into_sql`.
+  pub fn params_where(&self, predicate: impl Fn(&str, &[String]) -> bool) -> Vec<String> {
+    fn collect(
+      filter: &ValueOrComposite,
+      predicate: &dyn Fn(&str, &[String]) -> bool,
+      index: &mut usize,
+      params: &mut Vec<String>,
+    ) {
+      match filter {
+        ValueOrComposite::Value(v) => {
+          let count = param_count(v);
+          if predicate(&v.column, &v.json_path) {
+            params.extend((*index..*index + count).map(param_name));
+          }
+          *index += count;
+        }
+        ValueOrComposite::Composite(_combiner, vec) => {
+          for value_or_composite in vec {
+            collect(value_or_composite, predicate, index, params);
+          }
+        }
+      }
+    }
+
+    let mut params: Vec<String> = vec![];
+    collect(self, &predicate, &mut 0, &mut params);
+    return params;
   }
 
   fn into_sql_impl<E>(
     self,
     prefix: Option<&str>,
-    validator: &dyn Fn(&str) -> Result<(), E>,
+    validator: &Validator<'_, E>,
+    hooks: &SqlHooks<'_, E>,
     index: &mut usize,
   ) -> Result<(String, Vec<(String, Value)>), E> {
+    let column_expr = |column: &str| -> String {
+      return match prefix {
+        Some(p) => format!(r#"{p}."{column}""#),
+        None => format!(r#""{column}""#),
+      };
+    };
+    let guard = |columns: &[&str], condition: String| -> Result<String, E> {
+      let Some(guard_sql) = hooks.guard_sql else {
+        return Ok(condition);
+      };
+      let mut guards: Vec<String> = vec![];
+      for column in columns {
+        guards.extend(guard_sql(column)?);
+      }
+      if guards.is_empty() {
+        return Ok(condition);
+      }
+      return Ok(format!("({} AND {condition})", guards.join(" AND ")));
+    };
+
     match self {
       Self::Value(v) => {
-        validator(&v.column)?;
+        if let Some((latitude_column, longitude_column)) = v.geo_columns() {
+          validator(latitude_column, &[])?;
+          validator(longitude_column, &[])?;
 
-        let param = param_name(*index);
-        *index += 1;
+          let (lat, lng) = (column_expr(latitude_column), column_expr(longitude_column));
+          let coordinates = v.geo_coordinates();
+          let params: Vec<(String, Value)> = match v.value {
+            Value::Array(ref values) => values
+              .iter()
+              .map(|value| {
+                let param = param_name(*index);
+                *index += 1;
+                return (param, value.clone());
+              })
+              .collect(),
+            _ => vec![],
+          };
+          let names: Vec<&str> = params.iter().map(|(name, _)| name.as_str()).collect();
 
-        return Ok((
-          match prefix {
-            Some(p) => format!(r#"{p}."{c}" {o} {param}"#, c = v.column, o = v.op.to_sql()),
-            None => format!(r#""{c}" {o} {param}"#, c = v.column, o = v.op.to_sql()),
-          },
-          vec![(param, v.value)],
-        ));
+          let (condition, center) = match (v.op, names.as_slice()) {
+            (CompareOp::Near, [center_lat, center_lng, radius]) => (
+              format!("geo_distance({lat}, {lng}, {center_lat}, {center_lng}) <= {radius}"),
+              Some((*center_lat, *center_lng)),
+            ),
+            (CompareOp::Within, [min_lat, min_lng, max_lat, max_lng]) => (
+              format!("geo_within({lat}, {lng}, {min_lat}, {min_lng}, {max_lat}, {max_lng})"),
+              None,
+            ),
+            _ => unreachable!("validated during parsing"),
+          };
+
+          let extra = match hooks.geo_sql {
+            Some(geo_sql) => geo_sql(&GeoFilter {
+              latitude_column,
+              longitude_column,
+              center,
+              bbox: geo_bbox(v.op, &coordinates),
+            })?,
+            None => None,
+          };
+          let fragment = match extra {
+            Some(extra) => format!("({extra} AND {condition})"),
+            None => condition,
+          };
+          return Ok((
+            guard(&[latitude_column, longitude_column], fragment)?,
+            params,
+          ));
+        }
+
+        let related = match hooks.relation_sql {
+          Some(relation_sql) if !v.json_path.is_empty() => relation_sql(&v.column, &v.json_path)?,
+          _ => None,
+        };
+
+        if let Some(related) = related {
+          let (condition, params) = render_condition(&related.column, v, None, index)?;
+          return Ok((
+            format!("{}{condition}{}", related.prefix, related.suffix),
+            params,
+          ));
+        }
+
+        validator(&v.column, &v.json_path)?;
+
+        let column = column_expr(&v.column);
+        let column = match v.json_path_expr() {
+          Some(path) => format!("{column} ->> '{path}'"),
+          None => column,
+        };
+
+        let column_name = v.column.clone();
+        let (condition, params) = render_condition(&column, v, hooks.match_sql, index)?;
+        return Ok((guard(&[&column_name], condition)?, params));
+      }
+      Self::Composite(_combiner, vec) if vec.is_empty() => {
+        return Ok(("TRUE".to_string(), vec![]));
       }
       Self::Composite(combiner, vec) => {
         let mut fragments = Vec::<String>::with_capacity(vec.len());
         let mut params = Vec::<(String, Value)>::with_capacity(vec.len());
 
         for value_or_composite in vec {
-          let (f, p) = value_or_composite.into_sql_impl::<E>(prefix, validator, index)?;
+          let (f, p) = value_or_composite.into_sql_impl::<E>(prefix, validator, hooks, index)?;
           fragments.push(f);
           params.extend(p);
         }
@@ -179,6 +391,99 @@ impl<'de> serde::de::Deserialize<'de> for ValueOrComposite {
   }
 }
 
+/// Renders the condition of a single filter on the given `column` expression.
+fn render_condition<E>(
+  column: &str,
+  v: ColumnOpValue,
+  match_sql: Option<&MatchSql<'_, E>>,
+  index: &mut usize,
+) -> Result<(String, Vec<(String, Value)>), E> {
+  match (v.op, v.value) {
+    (CompareOp::Null, value) => {
+      let check = match value {
+        Value::Bool(false) => "IS NOT NULL",
+        _ => "IS NULL",
+      };
+      return Ok((format!("{column} {check}"), vec![]));
+    }
+    (op @ (CompareOp::In | CompareOp::NotIn | CompareOp::Between), Value::Array(values)) => {
+      let params: Vec<(String, Value)> = values
+        .into_iter()
+        .map(|value| {
+          let param = param_name(*index);
+          *index += 1;
+          return (param, value);
+        })
+        .collect();
+      let names: Vec<&str> = params.iter().map(|(name, _)| name.as_str()).collect();
+
+      let fragment = match op {
+        CompareOp::Between => format!("{column} BETWEEN {}", names.join(" AND ")),
+        op => format!("{column} {o} ({})", names.join(", "), o = op.to_sql()),
+      };
+      return Ok((fragment, params));
+    }
+    (op, value) => {
+      let param = param_name(*index);
+      *index += 1;
+
+      if let (CompareOp::Match, Some(match_sql)) = (op, match_sql) {
+        return Ok((match_sql(&v.column, &param)?, vec![(param, value)]));
+      }
+
+      let fragment = match op {
+        CompareOp::ILike => format!("LOWER({column}) LIKE LOWER({param})"),
+        op => format!("{column} {o} {param}", o = op.to_sql()),
+      };
+      return Ok((fragment, vec![(param, value)]));
+    }
+  };
+}
+
+/// Mean earth radius in meters, see `geo_distance()` SQL function.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Bounding box `[min_lat, min_lng, max_lat, max_lng]` containing all matches of a geospatial
+/// filter. Boxes crossing the poles or the anti-meridian span all longitudes.
+fn geo_bbox(op: CompareOp, coordinates: &[f64]) -> [f64; 4] {
+  return match (op, coordinates) {
+    (CompareOp::Near, [lat, lng, radius]) => {
+      let angle = radius / EARTH_RADIUS;
+      let d_lat = angle.to_degrees();
+      let (min_lat, max_lat) = (lat - d_lat, lat + d_lat);
+      if min_lat <= -90.0 || max_lat >= 90.0 {
+        return [min_lat.max(-90.0), -180.0, max_lat.min(90.0), 180.0];
+      }
+
+      let d_lng = (angle.sin() / lat.to_radians().cos())
+        .min(1.0)
+        .asin()
+        .to_degrees();
+      let (min_lng, max_lng) = (lng - d_lng, lng + d_lng);
+      if min_lng < -180.0 || max_lng > 180.0 {
+        return [min_lat, -180.0, max_lat, 180.0];
+      }
+      [min_lat, min_lng, max_lat, max_lng]
+    }
+    (CompareOp::Within, [min_lat, min_lng, max_lat, max_lng]) => {
+      if min_lng > max_lng {
+        return [*min_lat, -180.0, *max_lat, 180.0];
+      }
+      [*min_lat, *min_lng, *max_lat, *max_lng]
+    }
+    _ => [-90.0, -180.0, 90.0, 180.0],
+  };
+}
+
+/// Number of parameters bound by the given filter.
+fn param_count(v: &ColumnOpValue) -> usize {
+  return match (v.op, &v.value) {
+    (CompareOp::Null, _) => 0,
+    (CompareOp::In | CompareOp::NotIn | CompareOp::Between, Value::Array(values)) => values.len(),
+    _ => 1,
+  };
+}
+
 #[inline]
 fn param_name(index: usize) -> String {
   let mut s = String::with_capacity(10);
@@ -194,7 +499,6 @@ mod tests {
   use serde::Deserialize;
   use serde_qs::Config;
 
-  use crate::column_rel_value::{ColumnOpValue, CompareOp};
   use crate::value::Value;
 
   #[derive(Clone, Debug, Default, Deserialize)]
@@ -222,11 +526,13 @@ mod tests {
         vec![
           ValueOrComposite::Value(ColumnOpValue {
             column: "col0".to_string(),
+            json_path: vec![],
             op: CompareOp::Equal,
             value: Value::String("val0".to_string()),
           }),
           ValueOrComposite::Value(ColumnOpValue {
             column: "col1".to_string(),
+            json_path: vec![],
             op: CompareOp::Equal,
             value: Value::String("val1".to_string()),
           }),
@@ -249,11 +555,13 @@ mod tests {
         vec![
           ValueOrComposite::Value(ColumnOpValue {
             column: "col0".to_string(),
+            json_path: vec![],
             op: CompareOp::Equal,
             value: Value::String("val0".to_string()),
           }),
           ValueOrComposite::Value(ColumnOpValue {
             column: "col1".to_string(),
+            json_path: vec![],
             op: CompareOp::Equal,
             value: Value::String("val1".to_string()),
           }),
@@ -265,9 +573,324 @@ mod tests {
     let m4: Result<Query, _> = qs.deserialize_str("filter[$and][0][col0]=val0");
     assert!(m4.is_err(), "{m4:?}");
 
+    let m5: Query = qs
+      .deserialize_str("filter[col0][$match]=quick+fox&filter[col1]=val1")
+      .unwrap();
+    let match_sql = |column: &str, param: &str| -> Result<String, String> {
+      return Ok(format!(
+        r#"_ROW_._rowid_ IN (SELECT rowid FROM fts WHERE fts."{column}" MATCH {param})"#
+      ));
+    };
+    let (sql, params) = m5
+      .filter
+      .clone()
+      .unwrap()
+      .into_sql_with_match(Some("_ROW_"), &|_, _| Ok(()), &match_sql)
+      .unwrap();
+    assert_eq!(
+      sql,
+      r#"(_ROW_._rowid_ IN (SELECT rowid FROM fts WHERE fts."col0" MATCH :__p0) AND _ROW_."col1" = :__p1)"#
+    );
+    assert_eq!(params[0].1, Value::String("quick fox".to_string()));
+
+    // Without custom mapping, $match is applied to the column directly.
+    let (sql, _) = m5
+      .filter
+      .unwrap()
+      .into_sql(None, &|_, _| Ok::<(), String>(()))
+      .unwrap();
+    assert_eq!(sql, r#"("col0" MATCH :__p0 AND "col1" = :__p1)"#);
+
     // Too few elements
     let m3: Result<Query, _> =
       qs.deserialize_str("filter[col0]=val0&filter[$and][0][col0]=val0&filter[col1]=val1");
     assert!(m3.is_err(), "{m3:?}");
   }
+
+  #[test]
+  fn test_filter_operators() {
+    let qs = Config::new(5, false);
+    let to_sql = |query: &str| -> (String, Vec<(String, Value)>) {
+      let query: Query = qs.deserialize_str(query).unwrap();
+      return query
+        .filter
+        .unwrap()
+        .into_sql(Some("_ROW_"), &|_, _| Ok::<(), String>(()))
+        .unwrap();
+    };
+
+    let m0: Query = qs
+      .deserialize_str("filter[col0][$in][0]=a&filter[col0][$in][1]=2")
+      .unwrap();
+    assert_eq!(
+      m0.filter.unwrap(),
+      ValueOrComposite::Value(ColumnOpValue {
+        column: "col0".to_string(),
+        json_path: vec![],
+        op: CompareOp::In,
+        value: Value::Array(vec![Value::String("a".to_string()), Value::Integer(2)]),
+      })
+    );
+
+    let (sql, params) = to_sql("filter[col0][$in][0]=a&filter[col0][$in][1]=2&filter[col1]=x");
+    assert_eq!(
+      sql,
+      r#"(_ROW_."col0" IN (:__p0, :__p1) AND _ROW_."col1" = :__p2)"#
+    );
+    assert_eq!(
+      params.into_iter().map(|(_, v)| v).collect::<Vec<_>>(),
+      vec![
+        Value::String("a".to_string()),
+        Value::Integer(2),
+        Value::String("x".to_string())
+      ]
+    );
+
+    let (sql, params) = to_sql("filter[col0][$nin][0]=a");
+    assert_eq!(sql, r#"_ROW_."col0" NOT IN (:__p0)"#);
+    assert_eq!(params.len(), 1);
+
+    let (sql, params) = to_sql("filter[col0][$between][0]=1&filter[col0][$between][1]=10");
+    assert_eq!(sql, r#"_ROW_."col0" BETWEEN :__p0 AND :__p1"#);
+    assert_eq!(params.len(), 2);
+
+    let (sql, params) = to_sql("filter[col0][$null]=true&filter[col1][$null]=false");
+    assert_eq!(
+      sql,
+      r#"(_ROW_."col0" IS NULL AND _ROW_."col1" IS NOT NULL)"#
+    );
+    assert!(params.is_empty());
+
+    let (sql, _) = to_sql("filter[col0][$ilike]=%25Foo%25");
+    assert_eq!(sql, r#"LOWER(_ROW_."col0") LIKE LOWER(:__p0)"#);
+
+    // Operators and values must match up.
+    for invalid in [
+      "filter[col0][$in]=a",
+      "filter[col0][$eq][0]=a",
+      "filter[col0][$between][0]=1",
+      "filter[col0][$between][0]=1&filter[col0][$between][1]=2&filter[col0][$between][2]=3",
+      "filter[col0][$null]=a",
+      "filter[col0][$in][0][0]=a",
+    ] {
+      let result: Result<Query, _> = qs.deserialize_str(invalid);
+      assert!(result.is_err(), "{invalid}: {result:?}");
+    }
+  }
+
+  #[test]
+  fn test_json_path_filters() {
+    let qs = Config::new(5, false);
+
+    let m0: Query = qs.deserialize_str("filter[metadata.tags.0]=x").unwrap();
+    let filter = m0.filter.unwrap();
+    assert_eq!(
+      filter,
+      ValueOrComposite::Value(ColumnOpValue {
+        column: "metadata".to_string(),
+        json_path: vec!["tags".to_string(), "0".to_string()],
+        op: CompareOp::Equal,
+        value: Value::String("x".to_string()),
+      })
+    );
+
+    // Numbers are retained as such to compare numerically against JSON values.
+    let numeric: Query = qs.deserialize_str("filter[data.n][$gt]=5").unwrap();
+    let (sql, params) = numeric
+      .filter
+      .unwrap()
+      .into_sql(None, &|_, _| Ok::<(), String>(()))
+      .unwrap();
+    assert_eq!(sql, r#""data" ->> '$."n"' > :__p0"#);
+    assert_eq!(params, vec![(":__p0".to_string(), Value::Integer(5))]);
+
+    let m1: Query = qs
+      .deserialize_str("filter[col0][$in][0]=a&filter[col0][$in][1]=b&filter[profile->age][$gt]=18")
+      .unwrap();
+    let filter = m1.filter.unwrap();
+    assert_eq!(filter.json_path_params(), vec![":__p2".to_string()]);
+
+    let validated = std::cell::RefCell::new(Vec::<(String, Vec<String>)>::new());
+    let (sql, params) = filter
+      .into_sql(Some("_ROW_"), &|column, path| {
+        validated
+          .borrow_mut()
+          .push((column.to_string(), path.to_vec()));
+        return Ok::<(), String>(());
+      })
+      .unwrap();
+    assert_eq!(
+      sql,
+      r#"(_ROW_."col0" IN (:__p0, :__p1) AND _ROW_."profile" ->> '$."age"' > :__p2)"#
+    );
+    assert_eq!(params[2], (":__p2".to_string(), Value::Integer(18)));
+    assert_eq!(
+      validated.into_inner(),
+      vec![
+        ("col0".to_string(), vec![]),
+        ("profile".to_string(), vec!["age".to_string()])
+      ]
+    );
+
+    for invalid in [
+      "filter[metadata.]=x",
+      "filter[->tags]=x",
+      "filter[metadata->>tags]=x",
+      "filter[metadata.tags][$match]=x",
+    ] {
+      let result: Result<Query, _> = qs.deserialize_str(invalid);
+      assert!(result.is_err(), "{invalid}: {result:?}");
+    }
+  }
+
+  #[test]
+  fn test_related_column_filters() {
+    let qs = Config::new(5, false);
+
+    let m0: Query = qs
+      .deserialize_str("filter[author.org]=acme&filter[metadata.tags]=x&filter[col0]=y")
+      .unwrap();
+    let filter = m0.filter.unwrap();
+    assert_eq!(
+      filter.params_where(|column, _path| column == "author"),
+      vec![":__p0".to_string()]
+    );
+
+    let relation_sql = |column: &str, path: &[String]| {
+      if column != "author" {
+        return Ok(None);
+      }
+      return Ok(Some(RelatedColumn {
+        column: format!(r#"_REL_."{}""#, path[0]),
+        prefix: r#"EXISTS (SELECT 1 FROM author AS _REL_ WHERE _REL_.id = _ROW_."author" AND "#
+          .to_string(),
+        suffix: ")".to_string(),
+      }));
+    };
+    let (sql, params) = filter
+      .into_sql_with_hooks(
+        Some("_ROW_"),
+        &|column, _path| {
+          assert_ne!(column, "author");
+          return Ok::<(), String>(());
+        },
+        &SqlHooks {
+          relation_sql: Some(&relation_sql),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    assert_eq!(
+      sql,
+      r#"(EXISTS (SELECT 1 FROM author AS _REL_ WHERE _REL_.id = _ROW_."author" AND _REL_."org" = :__p0) AND _ROW_."col0" = :__p1 AND _ROW_."metadata" ->> '$."tags"' = :__p2)"#
+    );
+    assert_eq!(params.len(), 3);
+  }
+
+  #[test]
+  fn test_guarded_filters() {
+    let qs = Config::new(5, false);
+
+    let m0: Query = qs
+      .deserialize_str("filter[salary][$gt]=10&filter[name]=x")
+      .unwrap();
+    let guard_sql = |column: &str| {
+      return Ok::<_, String>((column == "salary").then(|| "_ROW_.owner = _USER_.id".to_string()));
+    };
+    let (sql, _params) = m0
+      .filter
+      .unwrap()
+      .into_sql_with_hooks(
+        Some("_ROW_"),
+        &|_column, _path| Ok::<(), String>(()),
+        &SqlHooks {
+          guard_sql: Some(&guard_sql),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    assert_eq!(
+      sql,
+      r#"(_ROW_."name" = :__p0 AND (_ROW_.owner = _USER_.id AND _ROW_."salary" > :__p1))"#
+    );
+  }
+
+  #[test]
+  fn test_geo_filters() {
+    let qs = Config::new(5, false);
+
+    let m0: Query = qs
+      .deserialize_str("filter[lat,lng][$near][0]=52.5&filter[lat,lng][$near][1]=13.4&filter[lat,lng][$near][2]=1000")
+      .unwrap();
+    let filter = m0.filter.unwrap();
+
+    let geo_filters =
+      std::cell::RefCell::new(Vec::<(String, String, Option<(String, String)>, [f64; 4])>::new());
+    let geo_sql = |f: &GeoFilter<'_>| {
+      geo_filters.borrow_mut().push((
+        f.latitude_column.to_string(),
+        f.longitude_column.to_string(),
+        f.center
+          .map(|(lat, lng)| (lat.to_string(), lng.to_string())),
+        f.bbox,
+      ));
+      return Ok::<_, String>(Some("INDEX".to_string()));
+    };
+    let (sql, params) = filter
+      .into_sql_with_hooks(
+        Some("_ROW_"),
+        &|_, _| Ok(()),
+        &SqlHooks {
+          geo_sql: Some(&geo_sql),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    assert_eq!(
+      sql,
+      r#"(INDEX AND geo_distance(_ROW_."lat", _ROW_."lng", :__p0, :__p1) <= :__p2)"#
+    );
+    assert_eq!(params[2], (":__p2".to_string(), Value::Integer(1000)));
+
+    let (lat, lng, center, bbox) = geo_filters.into_inner().pop().unwrap();
+    assert_eq!(("lat", "lng"), (lat.as_str(), lng.as_str()));
+    assert_eq!(Some((":__p0".to_string(), ":__p1".to_string())), center);
+    // 1km are roughly 0.009 degrees latitude and 0.015 degrees longitude at 52.5 degrees.
+    assert!((bbox[0] - 52.491).abs() < 0.001, "{bbox:?}");
+    assert!((bbox[1] - 13.385).abs() < 0.001, "{bbox:?}");
+    assert!((bbox[2] - 52.509).abs() < 0.001, "{bbox:?}");
+    assert!((bbox[3] - 13.415).abs() < 0.001, "{bbox:?}");
+
+    let m1: Query = qs
+      .deserialize_str("filter[lat,lng][$within][0]=52&filter[lat,lng][$within][1]=13&filter[lat,lng][$within][2]=53&filter[lat,lng][$within][3]=14")
+      .unwrap();
+    let (sql, params) = m1
+      .filter
+      .unwrap()
+      .into_sql(None, &|_, _| Ok::<(), String>(()))
+      .unwrap();
+    assert_eq!(
+      sql,
+      r#"geo_within("lat", "lng", :__p0, :__p1, :__p2, :__p3)"#
+    );
+    assert_eq!(params.len(), 4);
+
+    for invalid in [
+      // Single column.
+      "filter[lat][$near][0]=1&filter[lat][$near][1]=2&filter[lat][$near][2]=3",
+      // Wrong number of values.
+      "filter[lat,lng][$near][0]=1&filter[lat,lng][$near][1]=2",
+      // Out of range.
+      "filter[lat,lng][$near][0]=91&filter[lat,lng][$near][1]=2&filter[lat,lng][$near][2]=3",
+      "filter[lat,lng][$near][0]=1&filter[lat,lng][$near][1]=2&filter[lat,lng][$near][2]=-3",
+      // Not a number.
+      "filter[lat,lng][$near][0]=1&filter[lat,lng][$near][1]=x&filter[lat,lng][$near][2]=3",
+      // Column pairs are only valid for geospatial filters.
+      "filter[lat,lng][$eq]=1",
+      "filter[lat.x,lng][$near][0]=1&filter[lat.x,lng][$near][1]=2&filter[lat.x,lng][$near][2]=3",
+    ] {
+      let result: Result<Query, _> = qs.deserialize_str(invalid);
+      assert!(result.is_err(), "{invalid}: {result:?}");
+    }
+  }
 }
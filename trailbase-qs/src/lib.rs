@@ -8,6 +8,12 @@ mod query;
 mod util;
 mod value;
 
-pub use filter::{Combiner, ValueOrComposite};
-pub use query::{Cursor, CursorType, Expand, Order, OrderPrecedent, Query};
+pub use filter::{
+  Combiner, GeoFilter, GeoSql, GuardSql, MatchSql, RelatedColumn, RelationSql, SqlHooks,
+  ValueOrComposite,
+};
+pub use query::{
+  Aggregate, AggregateFunction, Aggregation, Cursor, CursorType, Expand, Fields, Format, GroupBy,
+  Nearest, Order, OrderPrecedent, Query, VectorMetric,
+};
 pub use value::Value;
@@ -126,12 +126,257 @@ impl<'de> serde::de::Deserialize<'de> for Expand {
       ));
     };
 
+    let columns = str
+      .split(",")
+      .map(|path| {
+        // Paths are '.'-separated, e.g. `author.org`, where each segment is either a foreign-key
+        // column or a reverse relation `<table>!<column>`, i.e. records referencing the parent.
+        let segments: Vec<&str> = path.split('.').collect();
+        if segments.len() > 3 {
+          return Err(Error::custom(format!(
+            "expand path exceeds max depth of 3: {path}",
+          )));
+        }
+
+        for segment in segments {
+          let valid = match segment.split_once('!') {
+            Some((table_name, column_name)) => {
+              !table_name.is_empty()
+                && !column_name.is_empty()
+                && crate::util::sanitize_column_name(table_name)
+                && crate::util::sanitize_column_name(column_name)
+            }
+            None => !segment.is_empty() && crate::util::sanitize_column_name(segment),
+          };
+
+          if !valid {
+            return Err(Error::custom(format!("invalid expand path: {path}")));
+          }
+        }
+
+        return Ok(path.to_string());
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.len() > 5 {
+      return Err(Error::invalid_length(
+        5,
+        &"more more than 5 expand dimension",
+      ));
+    }
+
+    return Ok(Expand { columns });
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fields {
+  pub columns: Vec<String>,
+}
+
+impl<'de> serde::de::Deserialize<'de> for Fields {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::de::Deserializer<'de>,
+  {
+    use serde::de::Error;
+    use serde_value::Value;
+
+    let value = Value::deserialize(deserializer)?;
+    let Value::String(str) = value else {
+      return Err(Error::invalid_type(
+        crate::util::unexpected(&value),
+        &"comma separated column names to select",
+      ));
+    };
+
+    let columns = str
+      .split(",")
+      .map(|field| {
+        let field = field.trim();
+
+        // Fields of expanded records are prefixed with their expansion path, e.g. `author.name`.
+        let mut segments: Vec<&str> = field.split('.').collect();
+        let valid = match segments.pop() {
+          Some(column_name) => {
+            !column_name.is_empty()
+              && crate::util::sanitize_column_name(column_name)
+              && segments.iter().all(|segment| {
+                return !segment.is_empty()
+                  && segment
+                    .split('!')
+                    .all(|s| !s.is_empty() && crate::util::sanitize_column_name(s));
+              })
+          }
+          None => false,
+        };
+
+        if !valid {
+          return Err(Error::custom(format!("invalid field: {field}")));
+        }
+
+        return Ok(field.to_string());
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.len() > 64 {
+      return Err(Error::invalid_length(64, &"more than 64 fields"));
+    }
+
+    return Ok(Fields { columns });
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregateFunction {
+  Count,
+  Sum,
+  Avg,
+  Min,
+  Max,
+}
+
+impl AggregateFunction {
+  pub fn from(name: &str) -> Option<Self> {
+    return match name {
+      "count" => Some(Self::Count),
+      "sum" => Some(Self::Sum),
+      "avg" => Some(Self::Avg),
+      "min" => Some(Self::Min),
+      "max" => Some(Self::Max),
+      _ => None,
+    };
+  }
+
+  pub fn to_sql(self) -> &'static str {
+    return match self {
+      Self::Count => "COUNT",
+      Self::Sum => "SUM",
+      Self::Avg => "AVG",
+      Self::Min => "MIN",
+      Self::Max => "MAX",
+    };
+  }
+
+  fn name(self) -> &'static str {
+    return match self {
+      Self::Count => "count",
+      Self::Sum => "sum",
+      Self::Avg => "avg",
+      Self::Min => "min",
+      Self::Max => "max",
+    };
+  }
+}
+
+/// A single aggregation, e.g. `count`, `count(col)` or `sum(col)`.
+///
+/// Only `count` may omit the column, in which case all rows are counted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregation {
+  pub function: AggregateFunction,
+  pub column: Option<String>,
+}
+
+impl Aggregation {
+  fn parse(s: &str) -> Option<Self> {
+    let s = s.trim();
+    let Some((function, rest)) = s.split_once('(') else {
+      return match AggregateFunction::from(s)? {
+        AggregateFunction::Count => Some(Aggregation {
+          function: AggregateFunction::Count,
+          column: None,
+        }),
+        _ => None,
+      };
+    };
+
+    let function = AggregateFunction::from(function.trim())?;
+    let column = rest.strip_suffix(')')?.trim();
+    if column.is_empty() || !crate::util::sanitize_column_name(column) {
+      return None;
+    }
+
+    return Some(Aggregation {
+      function,
+      column: Some(column.to_string()),
+    });
+  }
+
+  /// Name identifying the aggregation in results, e.g. "count" or "sum(col)".
+  pub fn name(&self) -> String {
+    return match self.column {
+      Some(ref column) => format!("{}({column})", self.function.name()),
+      None => self.function.name().to_string(),
+    };
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregate {
+  pub aggregations: Vec<Aggregation>,
+}
+
+impl<'de> serde::de::Deserialize<'de> for Aggregate {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::de::Deserializer<'de>,
+  {
+    use serde::de::Error;
+    use serde_value::Value;
+
+    let value = Value::deserialize(deserializer)?;
+    let Value::String(str) = value else {
+      return Err(Error::invalid_type(
+        crate::util::unexpected(&value),
+        &"comma separated aggregations, e.g. count,sum(col)",
+      ));
+    };
+
+    let aggregations = str
+      .split(",")
+      .map(|v| {
+        return Aggregation::parse(v)
+          .ok_or_else(|| Error::custom(format!("invalid aggregation: {v}")));
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if aggregations.len() > 10 {
+      return Err(Error::invalid_length(10, &"more more than 10 aggregations"));
+    }
+
+    return Ok(Aggregate { aggregations });
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupBy {
+  pub columns: Vec<String>,
+}
+
+impl<'de> serde::de::Deserialize<'de> for GroupBy {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::de::Deserializer<'de>,
+  {
+    use serde::de::Error;
+    use serde_value::Value;
+
+    let value = Value::deserialize(deserializer)?;
+    let Value::String(str) = value else {
+      return Err(Error::invalid_type(
+        crate::util::unexpected(&value),
+        &"comma separated column names to group by",
+      ));
+    };
+
     let columns = str
       .split(",")
       .map(|column_name| {
+        let column_name = column_name.trim();
         if !crate::util::sanitize_column_name(column_name) {
           return Err(Error::custom(format!(
-            "invalid column name for expand: {column_name}",
+            "invalid column name for group_by: {column_name}",
           )));
         }
 
@@ -142,14 +387,125 @@ impl<'de> serde::de::Deserialize<'de> for Expand {
     if columns.len() > 5 {
       return Err(Error::invalid_length(
         5,
-        &"more more than 5 expand dimension",
+        &"more more than 5 group_by dimension",
       ));
     }
 
-    return Ok(Expand { columns });
+    return Ok(GroupBy { columns });
+  }
+}
+
+/// Distance metrics for vector similarity searches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorMetric {
+  #[default]
+  Cosine,
+  L2,
+  L1,
+}
+
+/// Max number of dimensions of query vectors, same as sqlite-vec's limit.
+const MAX_VECTOR_DIMENSIONS: usize = 8192;
+
+/// A k-nearest-neighbour search for records similar to a query vector, e.g.
+/// `nearest[column]=embedding&nearest[vector]=[0.1,0.2,0.3]&nearest[metric]=l2`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nearest {
+  pub column: String,
+  pub vector: Vec<f32>,
+  pub metric: VectorMetric,
+}
+
+impl<'de> serde::de::Deserialize<'de> for Nearest {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::de::Deserializer<'de>,
+  {
+    use serde::de::Error;
+    use serde_value::Value;
+
+    let value = Value::deserialize(deserializer)?;
+    let Value::Map(map) = value else {
+      return Err(Error::invalid_type(
+        crate::util::unexpected(&value),
+        &"nearest[column] and nearest[vector]",
+      ));
+    };
+
+    let (mut column, mut vector, mut metric) = (None, None, VectorMetric::default());
+    for (key, value) in map {
+      let (Value::String(key), Value::String(value)) = (key, value) else {
+        return Err(Error::custom("expected string key and value"));
+      };
+
+      match key.as_str() {
+        "column" => {
+          if !crate::util::sanitize_column_name(&value) {
+            return Err(Error::custom(format!(
+              "invalid column name for nearest: {value}"
+            )));
+          }
+          column = Some(value);
+        }
+        "vector" => {
+          let Some(elements) = value
+            .trim()
+            .strip_prefix('[')
+            .and_then(|v| v.strip_suffix(']'))
+          else {
+            return Err(Error::custom("invalid vector: expected [x0, x1, ...]"));
+          };
+          let v = elements
+            .split(',')
+            .filter(|_| !elements.trim().is_empty())
+            .map(|x| x.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::custom(format!("invalid vector: {err}")))?;
+          if v.is_empty() || v.len() > MAX_VECTOR_DIMENSIONS {
+            return Err(Error::invalid_length(
+              v.len(),
+              &"vector of 1 to 8192 dimensions",
+            ));
+          }
+          if v.iter().any(|x| !x.is_finite()) {
+            return Err(Error::custom("invalid vector: non-finite value"));
+          }
+          vector = Some(v);
+        }
+        "metric" => {
+          metric = match value.as_str() {
+            "cosine" => VectorMetric::Cosine,
+            "l2" => VectorMetric::L2,
+            "l1" => VectorMetric::L1,
+            x => {
+              return Err(Error::unknown_variant(x, &["cosine", "l2", "l1"]));
+            }
+          };
+        }
+        x => {
+          return Err(Error::unknown_field(x, &["column", "vector", "metric"]));
+        }
+      }
+    }
+
+    return Ok(Nearest {
+      column: column.ok_or_else(|| Error::missing_field("column"))?,
+      vector: vector.ok_or_else(|| Error::missing_field("vector"))?,
+      metric,
+    });
   }
 }
 
+/// Response formats for listing records besides the default JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+  Json,
+  Csv,
+  NdJson,
+}
+
 #[derive(Clone, Default, Debug, PartialEq, Deserialize)]
 pub struct Query {
   /// Pagination parameters:
@@ -165,15 +521,40 @@ pub struct Query {
   #[serde(default, deserialize_with = "deserialize_bool")]
   pub count: Option<bool>,
 
-  /// Which foreign key columns to expand (only when allowed by configuration).
+  /// Which foreign key columns or reverse relations to expand (only when allowed by
+  /// configuration). Paths may be nested, e.g. `author.org` or `comment!post`.
   pub expand: Option<Expand>,
 
+  /// Which columns to select, e.g. `fields=id,title`. Fields of expanded records are prefixed by
+  /// their expansion path, e.g. `author.name`.
+  pub fields: Option<Fields>,
+
   /// Ordering. It's a vector for &order=-col0,+col1,col2
   pub order: Option<Order>,
 
   /// Map from filter params to filter value. It's a vector in cases like:
   ///   `col0[$gte]=2&col0[$lte]=10`.
   pub filter: Option<ValueOrComposite>,
+
+  /// Order records by their similarity to a query vector, i.e. k-nearest-neighbours.
+  pub nearest: Option<Nearest>,
+
+  /// Aggregations to compute over matching records, e.g. `aggregate=count,avg(col0)`.
+  pub aggregate: Option<Aggregate>,
+
+  /// Columns to group aggregations by, e.g. `group_by=col0,col1`.
+  pub group_by: Option<GroupBy>,
+
+  /// Response format, e.g. `format=csv`. Takes precedence over the "Accept" header.
+  pub format: Option<Format>,
+
+  /// Include soft-deleted records (admin only).
+  #[serde(default, deserialize_with = "deserialize_bool")]
+  pub include_deleted: Option<bool>,
+
+  /// Stream currently matching records ahead of live changes (subscriptions only).
+  #[serde(default, deserialize_with = "deserialize_bool")]
+  pub snapshot: Option<bool>,
 }
 
 impl Query {
@@ -198,6 +579,7 @@ mod tests {
   fn test_query_basic_parsing() {
     assert_eq!(Query::parse("").unwrap(), Query::default());
     assert_eq!(Query::parse("unknown=foo").unwrap(), Query::default());
+    assert_eq!(Some(true), Query::parse("snapshot=true").unwrap().snapshot);
 
     // NOTE: The filter value contains a '&', which will not parse in serde_qs strict-mode. Test
     // explicitly that we properly allow '&'s.
@@ -208,6 +590,7 @@ mod tests {
         .unwrap(),
       ValueOrComposite::Value(ColumnOpValue {
         column: "text_not_null".to_string(),
+        json_path: vec![],
         op: CompareOp::Equal,
         value: Value::String("rust client test 0: =?&1747466199".to_string()),
       })
@@ -221,11 +604,13 @@ mod tests {
           vec![
             ValueOrComposite::Value(ColumnOpValue {
               column: "latency".to_string(),
+              json_path: vec![],
               op: CompareOp::GreaterThan,
               value: Value::Integer(2),
             }),
             ValueOrComposite::Value(ColumnOpValue {
               column: "status".to_string(),
+              json_path: vec![],
               op: CompareOp::GreaterThanEqual,
               value: Value::Integer(400),
             }),
@@ -233,6 +618,7 @@ mod tests {
         ),
         ValueOrComposite::Value(ColumnOpValue {
           column: "latency".to_string(),
+          json_path: vec![],
           op: CompareOp::GreaterThan,
           value: Value::Integer(2),
         }),
@@ -258,6 +644,16 @@ mod tests {
         ..Default::default()
       }
     );
+    assert_eq!(
+      Query::parse("format=ndjson").unwrap().format,
+      Some(Format::NdJson)
+    );
+    assert_eq!(
+      Query::parse("format=csv").unwrap().format,
+      Some(Format::Csv)
+    );
+    assert!(Query::parse("format=xml").is_err());
+
     assert_eq!(
       Query::parse("count=FALSE").unwrap(),
       Query {
@@ -314,6 +710,147 @@ mod tests {
     assert!(qs.deserialize_str::<Query>("expand=$").is_err());
     assert!(qs.deserialize_str::<Query>("expand=a,b,c,d,e").is_ok());
     assert!(qs.deserialize_str::<Query>("expand=a,b,c,d,e,f").is_err());
+
+    assert_eq!(
+      qs.deserialize_str::<Query>("expand=author.org,comment!post")
+        .unwrap(),
+      Query {
+        expand: Some(Expand {
+          columns: vec!["author.org".to_string(), "comment!post".to_string()],
+        }),
+        ..Default::default()
+      },
+    );
+    assert!(qs.deserialize_str::<Query>("expand=a.b.c").is_ok());
+    assert!(qs.deserialize_str::<Query>("expand=a.b.c.d").is_err());
+    assert!(qs.deserialize_str::<Query>("expand=a..b").is_err());
+    assert!(qs.deserialize_str::<Query>("expand=comment!").is_err());
+    assert!(qs.deserialize_str::<Query>("expand=a!b!c").is_err());
+  }
+
+  #[test]
+  fn test_query_fields_parsing() {
+    let qs = Config::new(5, false);
+
+    assert_eq!(
+      qs.deserialize_str::<Query>("fields=id,title,author.name,comment!post.body")
+        .unwrap(),
+      Query {
+        fields: Some(Fields {
+          columns: vec![
+            "id".to_string(),
+            "title".to_string(),
+            "author.name".to_string(),
+            "comment!post.body".to_string(),
+          ],
+        }),
+        ..Default::default()
+      },
+    );
+
+    assert!(qs.deserialize_str::<Query>("fields=$").is_err());
+    assert!(qs.deserialize_str::<Query>("fields=a,,b").is_err());
+    assert!(qs.deserialize_str::<Query>("fields=author.").is_err());
+    assert!(qs.deserialize_str::<Query>("fields=comment!.body").is_err());
+  }
+
+  #[test]
+  fn test_query_nearest_parsing() {
+    assert_eq!(
+      Query::parse("nearest[column]=embedding&nearest[vector]=%5B0.5%2C1%2C-2%5D")
+        .unwrap()
+        .nearest,
+      Some(Nearest {
+        column: "embedding".to_string(),
+        vector: vec![0.5, 1.0, -2.0],
+        metric: VectorMetric::Cosine,
+      })
+    );
+    assert_eq!(
+      Query::parse("nearest[column]=e&nearest[vector]=[1,2]&nearest[metric]=l2")
+        .unwrap()
+        .nearest,
+      Some(Nearest {
+        column: "e".to_string(),
+        vector: vec![1.0, 2.0],
+        metric: VectorMetric::L2,
+      })
+    );
+
+    assert!(Query::parse("nearest[column]=e").is_err());
+    assert!(Query::parse("nearest[vector]=[1]").is_err());
+    assert!(Query::parse("nearest[column]=e&nearest[vector]=[]").is_err());
+    assert!(Query::parse("nearest[column]=e&nearest[vector]=[1,\"a\"]").is_err());
+    assert!(Query::parse("nearest[column]=e\"&nearest[vector]=[1]").is_err());
+    assert!(Query::parse("nearest[column]=e&nearest[vector]=[1]&nearest[metric]=dot").is_err());
+  }
+
+  #[test]
+  fn test_query_aggregate_parsing() {
+    let qs = Config::new(5, false);
+
+    assert_eq!(
+      qs.deserialize_str::<Query>("aggregate=").unwrap(),
+      Query {
+        aggregate: None,
+        ..Default::default()
+      },
+    );
+
+    assert_eq!(
+      qs.deserialize_str::<Query>(
+        "aggregate=count,count(a),sum(b),avg( c ),min(d),max(e)&group_by=f,g"
+      )
+      .unwrap(),
+      Query {
+        aggregate: Some(Aggregate {
+          aggregations: vec![
+            Aggregation {
+              function: AggregateFunction::Count,
+              column: None,
+            },
+            Aggregation {
+              function: AggregateFunction::Count,
+              column: Some("a".to_string()),
+            },
+            Aggregation {
+              function: AggregateFunction::Sum,
+              column: Some("b".to_string()),
+            },
+            Aggregation {
+              function: AggregateFunction::Avg,
+              column: Some("c".to_string()),
+            },
+            Aggregation {
+              function: AggregateFunction::Min,
+              column: Some("d".to_string()),
+            },
+            Aggregation {
+              function: AggregateFunction::Max,
+              column: Some("e".to_string()),
+            },
+          ]
+        }),
+        group_by: Some(GroupBy {
+          columns: vec!["f".to_string(), "g".to_string()],
+        }),
+        ..Default::default()
+      }
+    );
+
+    assert_eq!(
+      Aggregation::parse("sum(col)").unwrap().name(),
+      "sum(col)".to_string()
+    );
+
+    // Only count may omit the column.
+    assert!(qs.deserialize_str::<Query>("aggregate=sum").is_err());
+    assert!(qs.deserialize_str::<Query>("aggregate=sum()").is_err());
+    assert!(qs.deserialize_str::<Query>("aggregate=median(a)").is_err());
+    assert!(qs.deserialize_str::<Query>("aggregate=sum(a").is_err());
+    assert!(qs.deserialize_str::<Query>("aggregate=sum(*)").is_err());
+    assert!(qs.deserialize_str::<Query>("group_by=$").is_err());
+    assert!(qs.deserialize_str::<Query>("group_by=a,b,c,d,e,f").is_err());
   }
 
   #[test]
@@ -335,11 +872,13 @@ mod tests {
         vec![
           ValueOrComposite::Value(ColumnOpValue {
             column: "col0".to_string(),
+            json_path: vec![],
             op: CompareOp::GreaterThan,
             value: Value::Integer(0),
           }),
           ValueOrComposite::Value(ColumnOpValue {
             column: "col1".to_string(),
+            json_path: vec![],
             op: CompareOp::Equal,
             value: Value::String("val1".to_string()),
           }),
@@ -361,11 +900,13 @@ mod tests {
             vec![
               ValueOrComposite::Value(ColumnOpValue {
                 column: "col2".to_string(),
+                json_path: vec![],
                 op: CompareOp::Equal,
                 value: Value::String("val2".to_string()),
               }),
               ValueOrComposite::Value(ColumnOpValue {
                 column: "col0".to_string(),
+                json_path: vec![],
                 op: CompareOp::NotEqual,
                 value: Value::String("val0".to_string()),
               }),
@@ -373,6 +914,7 @@ mod tests {
           ),
           ValueOrComposite::Value(ColumnOpValue {
             column: "col1".to_string(),
+            json_path: vec![],
             op: CompareOp::Equal,
             value: Value::Integer(1),
           }),
@@ -380,7 +922,7 @@ mod tests {
       )
     );
 
-    let filter = |_: &str| -> Result<(), String> {
+    let filter = |_: &str, _: &[String]| -> Result<(), String> {
       return Ok(());
     };
     let (sql, params) = q1.filter.clone().unwrap().into_sql(None, &filter).unwrap();
@@ -410,6 +952,7 @@ mod tests {
       q2.filter.unwrap(),
       ValueOrComposite::Value(ColumnOpValue {
         column: "col".to_string(),
+        json_path: vec![],
         op: CompareOp::Equal,
         value: Value::String("with white spaces".to_string()),
       }),
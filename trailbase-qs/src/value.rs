@@ -10,6 +10,8 @@ pub enum Value {
   Integer(i64),
   Double(f64),
   Bool(bool),
+  /// List of scalar values, e.g. for `$in` or `$between` filters.
+  Array(Vec<Value>),
 }
 
 impl Value {
@@ -43,6 +45,14 @@ impl Value {
         true => "TRUE".to_string(),
         false => "false".to_string(),
       },
+      Self::Array(values) => format!(
+        "({})",
+        values
+          .iter()
+          .map(|v| v.to_sql())
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
     };
   }
 }
@@ -54,6 +64,15 @@ impl std::fmt::Display for Value {
       Self::Integer(i) => i.fmt(f),
       Self::Double(d) => d.fmt(f),
       Self::Bool(b) => b.fmt(f),
+      Self::Array(values) => {
+        for (index, value) in values.iter().enumerate() {
+          if index > 0 {
+            f.write_str(",")?;
+          }
+          value.fmt(f)?;
+        }
+        Ok(())
+      }
     };
   }
 }
@@ -74,9 +93,21 @@ where
     serde_value::Value::U16(i) => Ok(Value::Integer(i as i64)),
     serde_value::Value::U8(i) => Ok(Value::Integer(i as i64)),
     serde_value::Value::Bool(b) => Ok(Value::Bool(b)),
+    serde_value::Value::Seq(values) => Ok(Value::Array(
+      values
+        .into_iter()
+        .map(|v| match v {
+          serde_value::Value::Seq(_) => Err(Error::invalid_type(
+            crate::util::unexpected(&v),
+            &"scalar array element",
+          )),
+          v => serde_value_to_value::<D>(v),
+        })
+        .collect::<Result<Vec<_>, _>>()?,
+    )),
     _ => Err(Error::invalid_type(
       crate::util::unexpected(&value),
-      &"trailbase_qs::Value, i.e. string, integer, double or bool",
+      &"trailbase_qs::Value, i.e. string, integer, double, bool or array thereof",
     )),
   };
 }
@@ -114,6 +145,7 @@ mod tests {
       v0.filter.unwrap(),
       ValueOrComposite::Value(ColumnOpValue {
         column: "col0".to_string(),
+        json_path: vec![],
         op: CompareOp::Equal,
         value: Value::String("val0".to_string()),
       })
@@ -123,6 +155,7 @@ mod tests {
       v1.filter.unwrap(),
       ValueOrComposite::Value(ColumnOpValue {
         column: "col0".to_string(),
+        json_path: vec![],
         op: CompareOp::NotEqual,
         value: Value::Bool(true),
       })
@@ -133,6 +166,7 @@ mod tests {
       v2.filter.unwrap(),
       ValueOrComposite::Value(ColumnOpValue {
         column: "col0".to_string(),
+        json_path: vec![],
         op: CompareOp::NotEqual,
         value: Value::Integer(0),
       })
@@ -143,6 +177,7 @@ mod tests {
       v3.filter.unwrap(),
       ValueOrComposite::Value(ColumnOpValue {
         column: "col0".to_string(),
+        json_path: vec![],
         op: CompareOp::NotEqual,
         value: Value::Double(0.0),
       })
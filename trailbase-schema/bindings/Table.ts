@@ -3,5 +3,10 @@ import type { Check } from "./Check";
 import type { Column } from "./Column";
 import type { ForeignKey } from "./ForeignKey";
 import type { UniqueConstraint } from "./UniqueConstraint";
+import type { VirtualTableModule } from "./VirtualTableModule";
 
-export type Table = { name: string, strict: boolean, columns: Array<Column>, foreign_keys: Array<ForeignKey>, unique: Array<UniqueConstraint>, checks: Array<Check>, virtual_table: boolean, temporary: boolean, };
+export type Table = { name: string, strict: boolean, columns: Array<Column>, foreign_keys: Array<ForeignKey>, unique: Array<UniqueConstraint>, checks: Array<Check>, virtual_table: boolean, temporary: boolean, 
+/**
+ * Module and arguments for virtual tables, e.g. `USING fts5(body, content='post')`.
+ */
+virtual_table_module?: VirtualTableModule, };
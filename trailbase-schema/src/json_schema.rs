@@ -37,7 +37,98 @@ pub fn build_json_schema(
 #[derive(Debug)]
 pub struct Expand<'a> {
   pub tables: &'a [TableMetadata],
-  pub foreign_key_columns: Vec<&'a str>,
+  /// Expansion paths, i.e. foreign key columns, e.g. `author`, reverse relations
+  /// `<table>!<column>`, e.g. `comment!post`, or nested paths thereof, e.g. `author.org`.
+  pub paths: Vec<&'a str>,
+  /// Projected fields of expanded records prefixed by their expansion path, e.g. `author.name`.
+  pub fields: Vec<&'a str>,
+}
+
+impl<'a> Expand<'a> {
+  /// Returns the nested paths below `segment` if `segment` is expanded, e.g. `["org"]` for
+  /// `author` given `author.org`.
+  fn nested(&self, segment: &str) -> Option<Vec<&'a str>> {
+    let mut expanded = false;
+    let mut nested: Vec<&'a str> = vec![];
+    for path in &self.paths {
+      match path.split_once('.') {
+        Some((first, rest)) if first == segment => {
+          expanded = true;
+          nested.push(rest);
+        }
+        None if *path == segment => {
+          expanded = true;
+        }
+        _ => {}
+      }
+    }
+    return expanded.then_some(nested);
+  }
+
+  fn build_schema(
+    &self,
+    segment: &str,
+    title: &str,
+    table: &TableMetadata,
+    nested: Vec<&'a str>,
+    mode: JsonSchemaMode,
+    defs: &mut serde_json::Map<String, Value>,
+  ) -> Result<Value, JsonSchemaError> {
+    let fields: Vec<&'a str> = self
+      .fields
+      .iter()
+      .filter_map(|field| {
+        return field
+          .split_once('.')
+          .and_then(|(first, rest)| (first == segment).then_some(rest));
+      })
+      .collect();
+
+    // Project onto the selected columns, if any, always including the primary key.
+    let selected: Vec<&str> = fields
+      .iter()
+      .filter(|f| !f.contains('.'))
+      .copied()
+      .collect();
+    let columns: Vec<Column> = if selected.is_empty() {
+      table.schema.columns.clone()
+    } else {
+      let pk_index = crate::metadata::find_pk_column_index(&table.schema.columns);
+      table
+        .schema
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(idx, c)| Some(*idx) == pk_index || selected.contains(&c.name.as_str()))
+        .map(|(_, c)| c.clone())
+        .collect()
+    };
+
+    let (_validator, mut schema) = build_json_schema_expanded(
+      title,
+      &columns,
+      mode,
+      if nested.is_empty() {
+        None
+      } else {
+        Some(Expand {
+          tables: self.tables,
+          paths: nested,
+          fields,
+        })
+      },
+    )?;
+
+    // References are resolved relative to the root schema, thus hoist nested definitions.
+    if let Some(Value::Object(nested_defs)) = schema
+      .as_object_mut()
+      .and_then(|schema| schema.remove("$defs"))
+    {
+      defs.extend(nested_defs);
+    }
+
+    return Ok(schema);
+  }
 }
 
 /// NOTE: Foreign keys can only reference tables not view, so the inline schemas don't need to be
@@ -100,13 +191,9 @@ pub fn build_json_schema_expanded(
           ..
         } => {
           if let (Some(expand), JsonSchemaMode::Select) = (&expand, mode) {
-            let column_is_expanded = expand
-              .foreign_key_columns
-              .iter()
-              .any(|column_name| *column_name != col.name);
-            if !column_is_expanded {
+            let Some(nested) = expand.nested(&col.name) else {
               continue;
-            }
+            };
 
             // NOTE: Foreign keys cannot cross database boundaries, we can therefore compare by
             // unqualified name.
@@ -136,8 +223,8 @@ pub fn build_json_schema_expanded(
               continue;
             };
 
-            let (_validator, schema) =
-              build_json_schema(foreign_table, &table.schema.columns, mode)?;
+            let schema =
+              expand.build_schema(&col.name, foreign_table, table, nested, mode, &mut defs)?;
 
             let new_def_name = foreign_table.clone();
             defs.insert(
@@ -188,6 +275,39 @@ pub fn build_json_schema_expanded(
     );
   }
 
+  // Reverse expansions, i.e. lists of records referencing this one, e.g. `comment!post`.
+  if let (Some(expand), JsonSchemaMode::Select) = (&expand, mode) {
+    let mut segments: Vec<&str> = vec![];
+    for path in &expand.paths {
+      let segment = path.split('.').next().unwrap_or(path);
+      if segment.contains('!') && !segments.contains(&segment) {
+        segments.push(segment);
+      }
+    }
+
+    for segment in segments {
+      let Some((table_name, _column_name)) = segment.split_once('!') else {
+        continue;
+      };
+      let Some(table) = expand.tables.iter().find(|t| t.name().name == table_name) else {
+        warn!("Failed to find table: {table_name}");
+        continue;
+      };
+      let Some(nested) = expand.nested(segment) else {
+        continue;
+      };
+
+      let schema = expand.build_schema(segment, table_name, table, nested, mode, &mut defs)?;
+      properties.insert(
+        segment.to_string(),
+        serde_json::json!({
+          "type": "array",
+          "items": schema,
+        }),
+      );
+    }
+  }
+
   let schema = if defs.is_empty() {
     serde_json::json!({
       "title": title,
@@ -388,7 +508,7 @@ mod tests {
       .collect::<Vec<_>>()[0];
 
     assert_eq!(check_expr, check);
-    let table_metadata = TableMetadata::new(table.clone(), &[table], "_user");
+    let table_metadata = TableMetadata::new(table.clone(), &[table], &[], "_user");
 
     let (schema, _) = build_json_schema(
       &table_metadata.name().name,
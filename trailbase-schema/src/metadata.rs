@@ -8,7 +8,7 @@ use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use thiserror::Error;
 
-use crate::sqlite::{Column, ColumnDataType, ColumnOption, QualifiedName, Table, View};
+use crate::sqlite::{Column, ColumnDataType, ColumnOption, QualifiedName, Table, TableIndex, View};
 
 // TODO: Can we merge this with crate::sqlite::SchemaError?
 #[derive(Debug, Clone, Error)]
@@ -92,6 +92,136 @@ impl JsonMetadata {
   }
 }
 
+/// A FTS5 full-text index associated with a table.
+///
+/// A FTS5 virtual table is considered associated if it either uses the table as its external
+/// content, i.e. `content='<table>'`, or is named `<table>_fts`. In the latter case, the index'
+/// rowids are expected to match the table's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FtsMetadata {
+  pub name: QualifiedName,
+  /// Columns of the FTS5 table in order.
+  pub columns: Vec<String>,
+  /// Column of the content table corresponding to the index' rowid.
+  pub content_rowid: String,
+}
+
+impl FtsMetadata {
+  fn find(table: &Table, tables: &[Table]) -> Option<Self> {
+    if table.virtual_table {
+      return None;
+    }
+
+    let fts_tables = tables.iter().filter_map(|t| {
+      let module = t.virtual_table_module.as_ref()?;
+      if !module.name.eq_ignore_ascii_case("fts5")
+        || t.name.database_schema != table.name.database_schema
+      {
+        return None;
+      }
+      return Some((t, module));
+    });
+
+    let mut by_convention: Option<Self> = None;
+    for (fts_table, module) in fts_tables {
+      // NOTE: Strip column options such as "UNINDEXED".
+      let columns: Vec<String> = module
+        .positional_args()
+        .filter_map(|arg| {
+          Some(crate::sqlite::unquote_string(
+            arg.split_whitespace().next()?,
+          ))
+        })
+        .collect();
+
+      match module.option("content") {
+        Some(content) if content == table.name.name => {
+          return Some(FtsMetadata {
+            name: fts_table.name.clone(),
+            columns,
+            content_rowid: module
+              .option("content_rowid")
+              .unwrap_or_else(|| "_rowid_".to_string()),
+          });
+        }
+        Some(_) => {}
+        None => {
+          if fts_table.name.name == format!("{}_fts", table.name.name) {
+            by_convention = Some(FtsMetadata {
+              name: fts_table.name.clone(),
+              columns,
+              content_rowid: "_rowid_".to_string(),
+            });
+          }
+        }
+      }
+    }
+
+    return by_convention;
+  }
+}
+
+/// A 2D R*Tree spatial index associated with a table.
+///
+/// A R*Tree virtual table is considered associated if it's named `<table>_rtree` and has the shape
+/// `rtree(id, min_<lat>, max_<lat>, min_<lng>, max_<lng>)`, where `<lat>` and `<lng>` are the
+/// table's latitude and longitude columns. The index' ids are expected to match the table's rowids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpatialIndexMetadata {
+  pub name: QualifiedName,
+  /// Name of the R*Tree's id column.
+  pub id_column: String,
+  /// Indexed latitude column of the table.
+  pub latitude_column: String,
+  /// Indexed longitude column of the table.
+  pub longitude_column: String,
+}
+
+impl SpatialIndexMetadata {
+  fn find(table: &Table, tables: &[Table]) -> Option<Self> {
+    if table.virtual_table {
+      return None;
+    }
+
+    let rtree_name = format!("{}_rtree", table.name.name);
+    let rtree = tables.iter().find(|t| {
+      return t.name.name == rtree_name && t.name.database_schema == table.name.database_schema;
+    })?;
+    let module = rtree.virtual_table_module.as_ref()?;
+    if !module.name.eq_ignore_ascii_case("rtree") {
+      return None;
+    }
+
+    let columns: Vec<String> = module
+      .positional_args()
+      .map(crate::sqlite::unquote_string)
+      .collect();
+    let [id_column, min_lat, max_lat, min_lng, max_lng] = columns.as_slice() else {
+      return None;
+    };
+
+    let latitude_column = min_lat.strip_prefix("min_")?;
+    let longitude_column = min_lng.strip_prefix("min_")?;
+    if max_lat.strip_prefix("max_") != Some(latitude_column)
+      || max_lng.strip_prefix("max_") != Some(longitude_column)
+    {
+      return None;
+    }
+
+    let has_column = |name: &str| table.columns.iter().any(|c| c.name == name);
+    if !has_column(latitude_column) || !has_column(longitude_column) {
+      return None;
+    }
+
+    return Some(SpatialIndexMetadata {
+      name: rtree.name.clone(),
+      id_column: id_column.clone(),
+      latitude_column: latitude_column.to_string(),
+      longitude_column: longitude_column.to_string(),
+    });
+  }
+}
+
 /// A data class describing a sqlite Table and additional meta data useful for TrailBase.
 ///
 /// An example of TrailBase idiosyncrasies are UUIDv7 columns, which are a bespoke concept.
@@ -105,6 +235,12 @@ pub struct TableMetadata {
   pub user_id_columns: Vec<usize>,
   /// Metadata for CHECK(json_schema()) columns.
   pub json_metadata: JsonMetadata,
+  /// Associated FTS5 full-text index if any.
+  pub fts: Option<FtsMetadata>,
+  /// Associated R*Tree spatial index if any.
+  pub spatial_index: Option<SpatialIndexMetadata>,
+  /// Indexes on this table, i.e. created separately using "CREATE INDEX".
+  pub indexes: Vec<TableIndex>,
 
   name_to_index: HashMap<String, usize>,
   // TODO: Add triggers once sqlparser supports a sqlite "CREATE TRIGGER" statements.
@@ -114,8 +250,13 @@ impl TableMetadata {
   /// Build a new TableMetadata instance containing TrailBase/RecordApi specific information.
   ///
   /// NOTE: The list of all tables is needed only to extract interger/UUIDv7 pk columns for foreign
-  /// key relationships.
-  pub fn new(table: Table, tables: &[Table], user_table_name: &str) -> Self {
+  /// key relationships. Indexes of other tables are ignored.
+  pub fn new(
+    table: Table,
+    tables: &[Table],
+    indexes: &[TableIndex],
+    user_table_name: &str,
+  ) -> Self {
     let name_to_index = HashMap::<String, usize>::from_iter(
       table
         .columns
@@ -127,6 +268,17 @@ impl TableMetadata {
     let record_pk_column = find_record_pk_column_index(&table.columns, tables);
     let user_id_columns = find_user_id_foreign_key_columns(&table.columns, user_table_name);
     let json_metadata = JsonMetadata::from_table(&table);
+    let fts = FtsMetadata::find(&table, tables);
+    let spatial_index = SpatialIndexMetadata::find(&table, tables);
+    let indexes: Vec<TableIndex> = indexes
+      .iter()
+      .filter(|index| {
+        return index.table_name == table.name.name
+          && index.name.database_schema.as_deref().unwrap_or("main")
+            == table.name.database_schema.as_deref().unwrap_or("main");
+      })
+      .cloned()
+      .collect();
 
     return TableMetadata {
       schema: table,
@@ -134,6 +286,9 @@ impl TableMetadata {
       record_pk_column,
       user_id_columns,
       json_metadata,
+      fts,
+      spatial_index,
+      indexes,
     };
   }
 
@@ -152,6 +307,51 @@ impl TableMetadata {
     let index = self.column_index_by_name(key)?;
     return Some((index, &self.schema.columns[index]));
   }
+
+  /// Sets of columns with uniqueness guarantees, i.e. the primary key, column-level and
+  /// table-level UNIQUE constraints as well as unique indexes.
+  ///
+  /// NOTE: Partial indexes and indexes on expressions are not included, since they cannot serve as
+  /// plain conflict targets.
+  pub fn unique_column_sets(&self) -> Vec<Vec<String>> {
+    let mut sets: Vec<Vec<String>> = self
+      .schema
+      .columns
+      .iter()
+      .filter(|col| {
+        col
+          .options
+          .iter()
+          .any(|opt| matches!(opt, ColumnOption::Unique { .. }))
+      })
+      .map(|col| vec![col.name.clone()])
+      .collect();
+
+    sets.extend(self.schema.unique.iter().map(|u| u.columns.clone()));
+
+    sets.extend(
+      self
+        .indexes
+        .iter()
+        .filter(|index| {
+          return index.unique
+            && index.predicate.is_none()
+            && index
+              .columns
+              .iter()
+              .all(|c| self.name_to_index.contains_key(&c.column_name));
+        })
+        .map(|index| {
+          index
+            .columns
+            .iter()
+            .map(|c| c.column_name.clone())
+            .collect()
+        }),
+    );
+
+    return sets;
+  }
 }
 
 // Implement `PartialEq`, `Hash`, and `Borrow` for TableMetadata based on fully qualified name for
@@ -538,7 +738,7 @@ mod tests {
     let table: Table = create_table_statement.try_into().unwrap();
 
     {
-      let metadata = TableMetadata::new(table.clone(), &[table.clone()], "_user");
+      let metadata = TableMetadata::new(table.clone(), &[table.clone()], &[], "_user");
 
       assert_eq!(table_name, *metadata.name());
       assert_eq!("col1", metadata.columns().unwrap()[2].name);
@@ -602,6 +802,144 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_unique_column_sets() {
+    let table_sql = r#"
+      CREATE TABLE test (
+          id           INTEGER PRIMARY KEY,
+          email        TEXT NOT NULL UNIQUE,
+          org          TEXT NOT NULL,
+          handle       TEXT NOT NULL,
+          other        TEXT,
+
+          UNIQUE (org, handle)
+      ) STRICT;"#;
+
+    let create_table_statement = sqlite3_parse_into_statement(table_sql).unwrap().unwrap();
+    let table: Table = create_table_statement.try_into().unwrap();
+
+    let index = |sql: &str| -> TableIndex {
+      return sqlite3_parse_into_statement(sql)
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    };
+    let indexes = [
+      index("CREATE UNIQUE INDEX test__other_handle ON test (other, handle)"),
+      index("CREATE INDEX test__org ON test (org)"),
+      index("CREATE UNIQUE INDEX test__other ON test (other) WHERE other IS NOT NULL"),
+      index("CREATE UNIQUE INDEX test__lower_email ON test (lower(email))"),
+      index("CREATE UNIQUE INDEX other__other ON other (other)"),
+    ];
+    let metadata = TableMetadata::new(table.clone(), &[table], &indexes, "_user");
+
+    assert_eq!(
+      metadata.unique_column_sets(),
+      vec![
+        vec!["id".to_string()],
+        vec!["email".to_string()],
+        vec!["org".to_string(), "handle".to_string()],
+        vec!["other".to_string(), "handle".to_string()],
+      ]
+    );
+  }
+
+  #[test]
+  fn test_fts_metadata() {
+    let parse = |sql: &str| -> Table {
+      return sqlite3_parse_into_statement(sql)
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    };
+
+    let post = parse("CREATE TABLE post (id INTEGER PRIMARY KEY, title TEXT, body TEXT) STRICT");
+    let post_fts = parse(
+      "CREATE VIRTUAL TABLE post_fts USING fts5(title, body, tag UNINDEXED, content='post', content_rowid='id')",
+    );
+    let note = parse("CREATE TABLE note (body TEXT) STRICT");
+    let note_fts = parse("CREATE VIRTUAL TABLE note_fts USING fts5(body)");
+
+    let module = post_fts.virtual_table_module.as_ref().unwrap();
+    assert_eq!("fts5", module.name);
+    assert_eq!(Some("post".to_string()), module.option("content"));
+
+    let tables = [
+      post.clone(),
+      post_fts.clone(),
+      note.clone(),
+      note_fts.clone(),
+    ];
+
+    assert_eq!(
+      TableMetadata::new(post, &tables, &[], "_user").fts,
+      Some(FtsMetadata {
+        name: post_fts.name.clone(),
+        columns: vec!["title".to_string(), "body".to_string(), "tag".to_string()],
+        content_rowid: "id".to_string(),
+      })
+    );
+    assert_eq!(
+      TableMetadata::new(note, &tables, &[], "_user").fts,
+      Some(FtsMetadata {
+        name: note_fts.name.clone(),
+        columns: vec!["body".to_string()],
+        content_rowid: "_rowid_".to_string(),
+      })
+    );
+    assert_eq!(
+      TableMetadata::new(post_fts, &tables, &[], "_user").fts,
+      None
+    );
+  }
+
+  #[test]
+  fn test_spatial_index_metadata() {
+    let parse = |sql: &str| -> Table {
+      return sqlite3_parse_into_statement(sql)
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    };
+
+    let place =
+      parse("CREATE TABLE place (id INTEGER PRIMARY KEY, lat REAL, lng REAL, name TEXT) STRICT");
+    let place_rtree =
+      parse("CREATE VIRTUAL TABLE place_rtree USING rtree(id, min_lat, max_lat, min_lng, max_lng)");
+    let shop = parse("CREATE TABLE shop (id INTEGER PRIMARY KEY, lat REAL, lng REAL) STRICT");
+    let shop_rtree =
+      parse("CREATE VIRTUAL TABLE shop_rtree USING rtree(id, min_x, max_x, min_y, max_y)");
+
+    let tables = [
+      place.clone(),
+      place_rtree.clone(),
+      shop.clone(),
+      shop_rtree.clone(),
+    ];
+
+    assert_eq!(
+      TableMetadata::new(place, &tables, &[], "_user").spatial_index,
+      Some(SpatialIndexMetadata {
+        name: place_rtree.name.clone(),
+        id_column: "id".to_string(),
+        latitude_column: "lat".to_string(),
+        longitude_column: "lng".to_string(),
+      })
+    );
+    // Index columns don't match the table's columns.
+    assert_eq!(
+      TableMetadata::new(shop, &tables, &[], "_user").spatial_index,
+      None
+    );
+    assert_eq!(
+      TableMetadata::new(place_rtree, &tables, &[], "_user").spatial_index,
+      None
+    );
+  }
+
   #[test]
   fn test_metadata_hash_set_by_name() {
     let table_name = QualifiedName {
@@ -614,7 +952,7 @@ mod tests {
     );
     let create_table_statement = sqlite3_parse_into_statement(&table_sql).unwrap().unwrap();
     let table: Table = create_table_statement.try_into().unwrap();
-    let table_metadata = TableMetadata::new(table.clone(), &[table.clone()], "_user");
+    let table_metadata = TableMetadata::new(table.clone(), &[table.clone()], &[], "_user");
 
     let mut table_set = HashSet::<TableMetadata>::new();
 
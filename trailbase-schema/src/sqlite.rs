@@ -594,6 +594,40 @@ pub struct Table {
   // NOTE: consider parsing "CREATE VIRTUAL TABLE" into a separate struct.
   pub virtual_table: bool,
   pub temporary: bool,
+
+  /// Module and arguments for virtual tables, e.g. `USING fts5(body, content='post')`.
+  #[ts(optional)]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub virtual_table_module: Option<VirtualTableModule>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, TS, PartialEq)]
+#[ts(export)]
+pub struct VirtualTableModule {
+  pub name: String,
+  pub args: Vec<String>,
+}
+
+impl VirtualTableModule {
+  /// Looks up `key=value` arguments, e.g. FTS5's `content='table'`, with quotes stripped.
+  pub fn option(&self, key: &str) -> Option<String> {
+    return self.args.iter().find_map(|arg| {
+      let (k, v) = arg.split_once('=')?;
+      if k.trim() != key {
+        return None;
+      }
+      return Some(unquote_string(v.trim()));
+    });
+  }
+
+  /// Positional arguments, i.e. arguments that aren't `key=value` options.
+  pub fn positional_args(&self) -> impl Iterator<Item = &str> {
+    return self
+      .args
+      .iter()
+      .filter(|arg| !arg.contains('='))
+      .map(|arg| arg.trim());
+  }
 }
 
 impl Table {
@@ -796,11 +830,13 @@ impl TryFrom<sqlite3_parser::ast::Stmt> for Table {
           checks,
           virtual_table: false,
           temporary,
+          virtual_table_module: None,
         })
       }
       Stmt::CreateVirtualTable {
         tbl_name,
-        args: _args,
+        module_name,
+        args,
         ..
       } => Ok(Table {
         name: tbl_name.into(),
@@ -811,6 +847,12 @@ impl TryFrom<sqlite3_parser::ast::Stmt> for Table {
         checks: vec![],
         virtual_table: true,
         temporary: false,
+        virtual_table_module: Some(VirtualTableModule {
+          name: unquote_name(module_name),
+          args: args.map_or_else(Vec::new, |args| {
+            args.into_iter().map(|arg| arg.trim().to_string()).collect()
+          }),
+        }),
       }),
       _ => Err(SchemaError::Precondition(
         format!("expected 'CREATE [VIRTUAL] TABLE', got: {value:?}").into(),
@@ -1309,7 +1351,7 @@ pub(crate) fn quote(column_names: &[String]) -> String {
 }
 
 #[inline]
-fn unquote_string(s: &str) -> String {
+pub(crate) fn unquote_string(s: &str) -> String {
   let n = s.as_bytes();
   if n.is_empty() {
     return String::new();
@@ -1577,6 +1619,7 @@ mod tests {
         checks: vec![],
         virtual_table: false,
         temporary: false,
+        virtual_table_module: None,
       },
       Table {
         name: QualifiedName {
@@ -1614,6 +1657,7 @@ mod tests {
         checks: vec![],
         virtual_table: false,
         temporary: false,
+        virtual_table_module: None,
       },
     ];
 
@@ -241,8 +241,10 @@ impl Connection {
       .send(Message::RunMut(Box::new(move |conn| function(conn))));
   }
 
+  /// Call a function on a reader connection in a background thread and get the result
+  /// asynchronously.
   #[inline]
-  async fn call_reader<F, R>(&self, function: F) -> Result<R>
+  pub async fn call_reader<F, R>(&self, function: F) -> Result<R>
   where
     F: FnOnce(&rusqlite::Connection) -> Result<R> + Send + 'static,
     R: Send + 'static,